@@ -0,0 +1,94 @@
+//! compare connectors against loopback echo server, run with
+//! `cargo bench --features bench,rust_tls --bench connector`
+use std::time::Duration;
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+use fluvio_future::bench::connect_rate;
+use fluvio_future::bench::first_byte_latency;
+use fluvio_future::bench::throughput;
+use fluvio_future::bench::EchoServer;
+use fluvio_future::net::DefaultTcpDomainConnector;
+use fluvio_future::net::TcpDomainConnector;
+use fluvio_future::task::run_block_on;
+
+const STREAMED: usize = 16 * 1024 * 1024;
+
+/// connect, first byte and throughput of connector, timed by bench helpers
+fn bench_connector<C: TcpDomainConnector>(
+    c: &mut Criterion,
+    name: &str,
+    connector: &C,
+    target: &str,
+) {
+    let mut group = c.benchmark_group(name);
+    group.bench_function("connect", |b| {
+        b.iter_custom(|iters| {
+            run_block_on(connect_rate(name, connector, target, iters as usize))
+                .expect("connect")
+                .total()
+        })
+    });
+    group.bench_function("first byte", |b| {
+        b.iter_custom(|iters| {
+            run_block_on(first_byte_latency(name, connector, target, iters as usize))
+                .expect("first byte")
+                .total()
+        })
+    });
+    group.throughput(Throughput::Bytes(STREAMED as u64));
+    group.sample_size(10);
+    group.bench_function("throughput", |b| {
+        b.iter_custom(|iters| {
+            run_block_on(async {
+                let mut total = Duration::default();
+                for _ in 0..iters {
+                    total += throughput(name, connector, target, STREAMED)
+                        .await
+                        .expect("throughput")
+                        .total();
+                }
+                total
+            })
+        })
+    });
+    group.finish();
+}
+
+fn tcp(c: &mut Criterion) {
+    let server = EchoServer::start("127.0.0.1:0").expect("echo server");
+    let target = server.local_addr().to_string();
+    bench_connector(c, "tcp", &DefaultTcpDomainConnector::new(), &target);
+}
+
+#[cfg(feature = "rust_tls")]
+fn tls(c: &mut Criterion) {
+    use fluvio_future::tls::AcceptorBuilder;
+    use fluvio_future::tls::ConnectorBuilder;
+
+    let acceptor = AcceptorBuilder::new_no_client_authentication()
+        .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")
+        .expect("server certs")
+        .build();
+    let server = EchoServer::start_with("127.0.0.1:0", acceptor).expect("echo server");
+    // tls server name can't be ip address
+    let target = format!("localhost:{}", server.local_addr().port());
+
+    let anonymous = ConnectorBuilder::new()
+        .danger_accept_invalid_certs()
+        .build_anonymous();
+    bench_connector(c, "tls anonymous", &anonymous, &target);
+
+    let domain = ConnectorBuilder::new()
+        .danger_accept_invalid_certs()
+        .build_domain("localhost");
+    bench_connector(c, "tls domain", &domain, &target);
+}
+
+#[cfg(not(feature = "rust_tls"))]
+fn tls(_c: &mut Criterion) {
+    println!("tls connectors skipped, enable rust_tls feature");
+}
+
+criterion_group!(benches, tcp, tls);
+criterion_main!(benches);
@@ -0,0 +1,299 @@
+//! harness for benchmarking connectors against loopback echo server.
+//!
+//! `EchoServer` accepts thru any acceptor, such as tls acceptor, and echoes bytes back.
+//! measurements cover connect rate, latency until first byte is echoed and streaming throughput,
+//! so connectors can be compared by numbers. `benches/connector.rs` runs them for tcp and tls
+use std::fmt;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_io::block_on;
+use futures_lite::future::zip;
+use futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use log::debug;
+
+use crate::net::DefaultTcpDomainAcceptor;
+use crate::net::TcpDomainAcceptor;
+use crate::net::TcpDomainConnector;
+use crate::net::TcpListener;
+
+const ECHO_BUFFER: usize = 64 * 1024;
+
+/// echo server on own threads, so it doesn't compete with measured tasks for executor.
+/// each connection is served by its own thread. server stops when dropped
+pub struct EchoServer {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl EchoServer {
+    /// plain tcp echo server, `127.0.0.1:0` picks free port
+    pub fn start(addr: &str) -> Result<Self, IoError> {
+        Self::start_with(addr, DefaultTcpDomainAcceptor::new())
+    }
+
+    pub fn start_with<A>(addr: &str, acceptor: A) -> Result<Self, IoError>
+    where
+        A: TcpDomainAcceptor + Clone + Send + Sync + 'static,
+    {
+        let listener = block_on(TcpListener::bind(addr))?;
+        let addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let accept_stopped = stopped.clone();
+        thread::spawn(move || {
+            block_on(async {
+                while !accept_stopped.load(Ordering::SeqCst) {
+                    let stream = match listener.accept().await {
+                        Ok((stream, _)) => stream,
+                        Err(err) => {
+                            debug!("echo server accept failed: {}", err);
+                            continue;
+                        }
+                    };
+                    let acceptor = acceptor.clone();
+                    thread::spawn(move || {
+                        block_on(async {
+                            let result = match acceptor.accept(stream).await {
+                                Ok(stream) => echo(stream).await,
+                                Err(err) => Err(err),
+                            };
+                            if let Err(err) = result {
+                                debug!("echo connection failed: {}", err);
+                            }
+                        })
+                    });
+                }
+            })
+        });
+        Ok(Self { addr, stopped })
+    }
+
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for EchoServer {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+        // wake accept loop so it sees flag
+        let _ = std::net::TcpStream::connect(self.addr);
+    }
+}
+
+async fn echo<S: AsyncRead + AsyncWrite + Unpin>(mut stream: S) -> Result<(), IoError> {
+    let mut buf = vec![0; ECHO_BUFFER];
+    loop {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 {
+            return Ok(());
+        }
+        stream.write_all(&buf[..n]).await?;
+        stream.flush().await?;
+    }
+}
+
+/// durations of iterations, and bytes moved when measuring throughput
+#[derive(Debug, Clone)]
+pub struct Measurement {
+    name: String,
+    samples: Vec<Duration>,
+    bytes: u64,
+}
+
+impl Measurement {
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            samples: vec![],
+            bytes: 0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn samples(&self) -> &[Duration] {
+        &self.samples
+    }
+
+    pub fn total(&self) -> Duration {
+        self.samples.iter().sum()
+    }
+
+    pub fn mean(&self) -> Duration {
+        if self.samples.is_empty() {
+            Duration::default()
+        } else {
+            self.total() / self.samples.len() as u32
+        }
+    }
+
+    /// sample at `percentile`, between 0 and 100
+    pub fn percentile(&self, percentile: f64) -> Duration {
+        let mut sorted = self.samples.clone();
+        sorted.sort();
+        match sorted.len() {
+            0 => Duration::default(),
+            len => {
+                let rank = (percentile.clamp(0.0, 100.0) / 100.0 * (len - 1) as f64).round();
+                sorted[rank as usize]
+            }
+        }
+    }
+
+    /// iterations per second
+    pub fn rate(&self) -> f64 {
+        self.samples.len() as f64 / self.total().as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// bytes per second, none if no bytes were moved
+    pub fn throughput(&self) -> Option<f64> {
+        if self.bytes == 0 {
+            None
+        } else {
+            Some(self.bytes as f64 / self.total().as_secs_f64().max(f64::EPSILON))
+        }
+    }
+}
+
+impl fmt::Display for Measurement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}: {} iterations, {:.1}/s, mean {:?}, p50 {:?}, p99 {:?}",
+            self.name,
+            self.samples.len(),
+            self.rate(),
+            self.mean(),
+            self.percentile(50.0),
+            self.percentile(99.0)
+        )?;
+        if let Some(throughput) = self.throughput() {
+            write!(f, ", {:.1} MiB/s", throughput / (1024.0 * 1024.0))?;
+        }
+        Ok(())
+    }
+}
+
+/// time of each connect, including handshake of connector
+pub async fn connect_rate<C: TcpDomainConnector>(
+    name: &str,
+    connector: &C,
+    target: &str,
+    iterations: usize,
+) -> Result<Measurement, IoError> {
+    let mut measurement = Measurement::new(name);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let (stream, _) = connector.connect(target).await?;
+        measurement.samples.push(start.elapsed());
+        drop(stream);
+    }
+    Ok(measurement)
+}
+
+/// time from connect until first byte is echoed, which covers handshake
+/// even if server completes it only once data arrives
+pub async fn first_byte_latency<C: TcpDomainConnector>(
+    name: &str,
+    connector: &C,
+    target: &str,
+    iterations: usize,
+) -> Result<Measurement, IoError> {
+    let mut measurement = Measurement::new(name);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let (mut stream, _) = connector.connect(target).await?;
+        stream.write_all(&[1]).await?;
+        stream.flush().await?;
+        let mut byte = [0; 1];
+        stream.read_exact(&mut byte).await?;
+        measurement.samples.push(start.elapsed());
+    }
+    Ok(measurement)
+}
+
+/// time to stream `total_bytes` thru echo server and receive them back, on one connection
+pub async fn throughput<C: TcpDomainConnector>(
+    name: &str,
+    connector: &C,
+    target: &str,
+    total_bytes: usize,
+) -> Result<Measurement, IoError> {
+    let (stream, _) = connector.connect(target).await?;
+    let (mut reader, mut writer) = futures_lite::io::split(stream);
+    let chunk = vec![0xA5; ECHO_BUFFER];
+
+    let start = Instant::now();
+    let write_ft = async {
+        let mut written = 0;
+        while written < total_bytes {
+            let n = chunk.len().min(total_bytes - written);
+            writer.write_all(&chunk[..n]).await?;
+            written += n;
+        }
+        writer.flush().await
+    };
+    let read_ft = async {
+        let mut buf = vec![0; ECHO_BUFFER];
+        let mut received = 0;
+        while received < total_bytes {
+            match reader.read(&mut buf).await? {
+                0 => return Err(ErrorKind::UnexpectedEof.into()),
+                n => received += n,
+            }
+        }
+        Ok(()) as Result<(), IoError>
+    };
+    let (write_result, read_result) = zip(write_ft, read_ft).await;
+    write_result?;
+    read_result?;
+
+    let mut measurement = Measurement::new(name);
+    measurement.samples.push(start.elapsed());
+    measurement.bytes = total_bytes as u64;
+    Ok(measurement)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::test_async;
+
+    use super::connect_rate;
+    use super::first_byte_latency;
+    use super::throughput;
+    use super::EchoServer;
+
+    #[test_async]
+    async fn test_bench_harness() -> Result<(), IoError> {
+        let server = EchoServer::start("127.0.0.1:0")?;
+        let target = server.local_addr().to_string();
+        let connector = DefaultTcpDomainConnector::new();
+
+        let connects = connect_rate("tcp", &connector, &target, 5).await?;
+        assert_eq!(connects.samples().len(), 5);
+        assert!(connects.percentile(0.0) <= connects.percentile(100.0));
+        assert!(connects.throughput().is_none());
+
+        let latency = first_byte_latency("tcp", &connector, &target, 3).await?;
+        assert_eq!(latency.samples().len(), 3);
+
+        let streamed = throughput("tcp", &connector, &target, 1024 * 1024).await?;
+        assert!(streamed.throughput().expect("bytes") > 0.0);
+        assert!(streamed.to_string().contains("MiB/s"));
+        Ok(())
+    }
+}
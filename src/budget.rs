@@ -0,0 +1,102 @@
+//! cooperative scheduling for loops which may never hit pending, such as copy into fast socket.
+//!
+//! each step of such loop consumes unit of budget. once budget is used up, task yields to executor
+//! and budget is refilled. budget is per thread and shared by tasks polled on it, so it needs no
+//! support from executor
+use std::cell::Cell;
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+/// steps allowed before yielding
+pub const BUDGET: u32 = 128;
+
+thread_local! {
+    static REMAINING: Cell<u32> = const { Cell::new(BUDGET) };
+}
+
+/// consume unit of budget, pending with task woken once budget is used up
+pub fn poll_budget(cx: &mut Context<'_>) -> Poll<()> {
+    let exhausted = REMAINING.with(|remaining| match remaining.get() {
+        0 => {
+            remaining.set(BUDGET);
+            true
+        }
+        left => {
+            remaining.set(left - 1);
+            false
+        }
+    });
+    if exhausted {
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    } else {
+        Poll::Ready(())
+    }
+}
+
+/// consume unit of budget, yielding once budget is used up
+pub fn consume_budget() -> ConsumeBudget {
+    ConsumeBudget {}
+}
+
+pub struct ConsumeBudget {}
+
+impl Future for ConsumeBudget {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        poll_budget(cx)
+    }
+}
+
+/// yield to executor once, so other tasks can run
+pub fn yield_now() -> YieldNow {
+    YieldNow(false)
+}
+
+pub struct YieldNow(bool);
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.0 {
+            return Poll::Ready(());
+        }
+        self.0 = true;
+        REMAINING.with(|remaining| remaining.set(BUDGET));
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error;
+
+    use futures_lite::future::poll_once;
+
+    use crate::test_async;
+
+    use super::consume_budget;
+    use super::yield_now;
+    use super::BUDGET;
+
+    #[test_async]
+    async fn test_consume_budget() -> Result<(), Error> {
+        assert!(poll_once(yield_now()).await.is_none());
+        // budget is full after yield
+        for _ in 0..BUDGET {
+            assert!(poll_once(consume_budget()).await.is_some());
+        }
+        assert!(poll_once(consume_budget()).await.is_none());
+        assert!(poll_once(consume_budget()).await.is_some());
+
+        let mut yield_ft = yield_now();
+        assert!(poll_once(&mut yield_ft).await.is_none());
+        assert!(poll_once(&mut yield_ft).await.is_some());
+        Ok(())
+    }
+}
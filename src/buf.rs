@@ -0,0 +1,231 @@
+//! pooled buffers, so steady state copying doesn't go thru global allocator for every chunk.
+//!
+//! pool is made of slabs, each holding free buffers of a fixed capacity.
+//! buffer is taken from the smallest slab that fits and returned to it on drop.
+use std::fmt;
+use std::ops::Deref;
+use std::ops::DerefMut;
+use std::sync::Arc;
+use std::sync::OnceLock;
+
+use bytes::BytesMut;
+use concurrent_queue::ConcurrentQueue;
+use log::trace;
+
+pub const DEFAULT_SLAB_SIZES: [usize; 3] = [4 * 1024, 64 * 1024, 1024 * 1024];
+pub const DEFAULT_BUFFERS_PER_SLAB: usize = 16;
+
+#[derive(Debug, Clone)]
+pub struct BufPoolConfig {
+    /// capacity of buffers in each slab
+    pub slab_sizes: Vec<usize>,
+    /// max free buffers kept in each slab, extra buffers are released
+    pub buffers_per_slab: usize,
+}
+
+impl Default for BufPoolConfig {
+    fn default() -> Self {
+        Self {
+            slab_sizes: DEFAULT_SLAB_SIZES.to_vec(),
+            buffers_per_slab: DEFAULT_BUFFERS_PER_SLAB,
+        }
+    }
+}
+
+struct Slab {
+    size: usize,
+    free: ConcurrentQueue<BytesMut>,
+}
+
+/// lock free pool of `BytesMut`. cloning shares same pool
+#[derive(Clone)]
+pub struct BufPool {
+    slabs: Arc<Vec<Slab>>,
+}
+
+impl fmt::Debug for BufPool {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("BufPool")
+            .field("slab_sizes", &self.slab_sizes())
+            .field("free", &self.free())
+            .finish()
+    }
+}
+
+impl Default for BufPool {
+    fn default() -> Self {
+        Self::new(BufPoolConfig::default())
+    }
+}
+
+impl BufPool {
+    pub fn new(config: BufPoolConfig) -> Self {
+        let buffers_per_slab = config.buffers_per_slab.max(1);
+        let mut sizes = config.slab_sizes;
+        sizes.sort_unstable();
+        sizes.dedup();
+        let slabs = sizes
+            .into_iter()
+            .map(|size| Slab {
+                size,
+                free: ConcurrentQueue::bounded(buffers_per_slab),
+            })
+            .collect();
+        Self {
+            slabs: Arc::new(slabs),
+        }
+    }
+
+    /// pool shared by crate, with default config
+    pub fn global() -> &'static BufPool {
+        static GLOBAL: OnceLock<BufPool> = OnceLock::new();
+        GLOBAL.get_or_init(BufPool::default)
+    }
+
+    pub fn slab_sizes(&self) -> Vec<usize> {
+        self.slabs.iter().map(|slab| slab.size).collect()
+    }
+
+    /// number of free buffers held by pool
+    pub fn free(&self) -> usize {
+        self.slabs.iter().map(|slab| slab.free.len()).sum()
+    }
+
+    /// get empty buffer with capacity at least `min_capacity`.
+    /// if it is larger than biggest slab, buffer is allocated and not pooled
+    pub fn get(&self, min_capacity: usize) -> PooledBuf {
+        match self.slabs.iter().position(|slab| slab.size >= min_capacity) {
+            Some(index) => {
+                let slab = &self.slabs[index];
+                let buf = slab
+                    .free
+                    .pop()
+                    .unwrap_or_else(|_| BytesMut::with_capacity(slab.size));
+                PooledBuf {
+                    buf,
+                    home: Some((self.slabs.clone(), index)),
+                }
+            }
+            None => {
+                trace!("buffer: {} larger than slabs, not pooled", min_capacity);
+                PooledBuf {
+                    buf: BytesMut::with_capacity(min_capacity),
+                    home: None,
+                }
+            }
+        }
+    }
+
+    /// get buffer filled with `len` zeros, ready to be read into
+    pub fn get_zeroed(&self, len: usize) -> PooledBuf {
+        let mut buf = self.get(len);
+        buf.resize(len, 0);
+        buf
+    }
+}
+
+/// buffer borrowed from pool, returned to its slab on drop
+pub struct PooledBuf {
+    buf: BytesMut,
+    home: Option<(Arc<Vec<Slab>>, usize)>,
+}
+
+impl PooledBuf {
+    /// detach from pool
+    pub fn into_inner(mut self) -> BytesMut {
+        self.home = None;
+        std::mem::take(&mut self.buf)
+    }
+}
+
+impl fmt::Debug for PooledBuf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("PooledBuf")
+            .field("len", &self.buf.len())
+            .field("capacity", &self.buf.capacity())
+            .finish()
+    }
+}
+
+impl Deref for PooledBuf {
+    type Target = BytesMut;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buf
+    }
+}
+
+impl DerefMut for PooledBuf {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buf
+    }
+}
+
+impl Drop for PooledBuf {
+    fn drop(&mut self) {
+        if let Some((slabs, index)) = self.home.take() {
+            let slab = &slabs[index];
+            let mut buf = std::mem::take(&mut self.buf);
+            // buffer may have been split, only keep it if it still has full capacity.
+            // buffer grown well past its slab is released, so slab doesn't hold on to it
+            if buf.capacity() >= slab.size && buf.capacity() < 2 * slab.size {
+                buf.clear();
+                let _ = slab.free.push(buf);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::BufPool;
+    use super::BufPoolConfig;
+
+    fn test_pool() -> BufPool {
+        BufPool::new(BufPoolConfig {
+            slab_sizes: vec![1024, 16],
+            buffers_per_slab: 2,
+        })
+    }
+
+    #[test]
+    fn test_buf_reuse() {
+        let pool = test_pool();
+        assert_eq!(pool.slab_sizes(), vec![16, 1024]);
+
+        let mut buf = pool.get(100);
+        assert!(buf.capacity() >= 1024);
+        buf.extend_from_slice(b"hello");
+        let ptr = buf.as_ptr();
+        drop(buf);
+        assert_eq!(pool.free(), 1);
+
+        let buf = pool.get(1000);
+        assert!(buf.is_empty());
+        assert_eq!(buf.as_ptr(), ptr);
+        assert_eq!(pool.free(), 0);
+    }
+
+    #[test]
+    fn test_buf_not_pooled() {
+        let pool = test_pool();
+
+        // larger than any slab
+        let buf = pool.get(4096);
+        assert!(buf.capacity() >= 4096);
+        drop(buf);
+        assert_eq!(pool.free(), 0);
+
+        // detached from pool
+        let buf = pool.get(10).into_inner();
+        assert!(buf.capacity() >= 16);
+        assert_eq!(pool.free(), 0);
+
+        // slab is bounded
+        let buffers: Vec<_> = (0..3).map(|_| pool.get_zeroed(10)).collect();
+        assert_eq!(buffers[0].len(), 10);
+        drop(buffers);
+        assert_eq!(pool.free(), 2);
+    }
+}
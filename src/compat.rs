@@ -0,0 +1,179 @@
+//! adapters between streams of this crate, which implement `futures` io traits, and
+//! tokio io traits, so libraries built on tokio can be driven over `TlsStream` or
+//! `AllTcpStream` and tokio streams can be used where this crate expects its own.
+//!
+//! adapters only translate calls, nothing is buffered or copied in between
+use std::io::Error as IoError;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_lite::io::{AsyncRead, AsyncWrite};
+use pin_project::pin_project;
+
+/// stream of this crate as `tokio::io::AsyncRead` and `tokio::io::AsyncWrite`
+#[pin_project]
+#[derive(Debug)]
+pub struct TokioCompat<T>(#[pin] T);
+
+impl<T> TokioCompat<T> {
+    pub fn new(inner: T) -> Self {
+        Self(inner)
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.0
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: AsyncRead> tokio::io::AsyncRead for TokioCompat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        self.project().0.poll_read(cx, buf)
+    }
+}
+
+impl<T: AsyncWrite> tokio::io::AsyncWrite for TokioCompat<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        self.project().0.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.project().0.poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.project().0.poll_close(cx)
+    }
+}
+
+/// tokio stream as `futures` `AsyncRead` and `AsyncWrite`
+#[pin_project]
+#[derive(Debug)]
+pub struct FuturesCompat<T>(#[pin] T);
+
+impl<T> FuturesCompat<T> {
+    pub fn new(inner: T) -> Self {
+        Self(inner)
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.0
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.0
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<T: tokio::io::AsyncRead> AsyncRead for FuturesCompat<T> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        self.project().0.poll_read(cx, buf)
+    }
+}
+
+impl<T: tokio::io::AsyncWrite> AsyncWrite for FuturesCompat<T> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        self.project().0.poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.project().0.poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.project().0.poll_shutdown(cx)
+    }
+}
+
+/// wrap streams in compat adapters
+pub trait CompatExt: Sized {
+    /// use stream of this crate as tokio stream
+    fn tokio_compat(self) -> TokioCompat<Self> {
+        TokioCompat::new(self)
+    }
+
+    /// use tokio stream as stream of this crate
+    fn futures_compat(self) -> FuturesCompat<Self> {
+        FuturesCompat::new(self)
+    }
+}
+
+impl<T> CompatExt for T {}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::future::zip;
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
+    use tokio::io::{AsyncReadExt as TokioReadExt, AsyncWriteExt as TokioWriteExt};
+
+    use crate::net::TcpListener;
+    use crate::net::TcpStream;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::CompatExt;
+
+    const ADDR: &str = "127.0.0.1:8914";
+
+    #[test_async]
+    async fn test_compat() -> Result<(), IoError> {
+        let listener = TcpListener::bind(ADDR).await?;
+        let server = async {
+            let (stream, _) = listener.accept().await?;
+            // tokio driven side over crate's stream
+            let mut stream = stream.tokio_compat();
+            let mut request = [0; 5];
+            TokioReadExt::read_exact(&mut stream, &mut request).await?;
+            TokioWriteExt::write_all(&mut stream, &request).await?;
+            TokioWriteExt::shutdown(&mut stream).await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client = async {
+            sleep(Duration::from_millis(10)).await;
+            // round trip back thru both adapters
+            let mut stream = TcpStream::connect(ADDR)
+                .await?
+                .tokio_compat()
+                .futures_compat();
+            stream.write_all(b"hello").await?;
+            let mut response = vec![];
+            stream.read_to_end(&mut response).await?;
+            Ok(response) as Result<Vec<u8>, IoError>
+        };
+        let (served, response) = zip(server, client).await;
+        served?;
+        assert_eq!(response?, b"hello");
+        Ok(())
+    }
+}
@@ -23,6 +23,10 @@ impl AsyncFileSlice {
         self.len
     }
 
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
     pub fn fd(&self) -> RawFd {
         self.fd
     }
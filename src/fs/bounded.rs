@@ -4,6 +4,8 @@ use core::task::Poll;
 
 use std::io;
 use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::IoSlice;
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -21,6 +23,7 @@ use crate::file_slice::AsyncFileSlice;
 
 use super::AsyncFileExtension;
 use futures_lite::AsyncWrite;
+use futures_lite::AsyncWriteExt;
 
 #[derive(Debug)]
 pub enum BoundedFileSinkError {
@@ -54,6 +57,12 @@ pub struct BoundedFileOption {
 pub struct BoundedFileSink {
     option: BoundedFileOption,
     current_len: u64,
+    /// len as of last completed flush
+    flushed_len: u64,
+    /// len as of last completed barrier
+    durable_len: u64,
+    /// entry of file in parent directory is durable
+    dir_synced: bool,
     writer: File,
     path: PathBuf,
 }
@@ -75,6 +84,9 @@ impl BoundedFileSink {
             writer,
             path: inner_path.to_owned(),
             current_len: 0,
+            flushed_len: 0,
+            durable_len: 0,
+            dir_synced: false,
             option,
         })
     }
@@ -93,6 +105,9 @@ impl BoundedFileSink {
             writer,
             path: file_path.to_owned(),
             current_len: len,
+            flushed_len: len,
+            durable_len: 0,
+            dir_synced: false,
             option,
         })
     }
@@ -112,6 +127,9 @@ impl BoundedFileSink {
             writer,
             path: file_path.to_owned(),
             current_len: len,
+            flushed_len: len,
+            durable_len: 0,
+            dir_synced: false,
             option,
         })
     }
@@ -121,6 +139,38 @@ impl BoundedFileSink {
         self.current_len
     }
 
+    /// len of file as of last completed flush
+    pub fn get_flushed_len(&self) -> u64 {
+        self.flushed_len
+    }
+
+    /// len of file as of last completed barrier, 0 before first one
+    pub fn get_durable_len(&self) -> u64 {
+        self.durable_len
+    }
+
+    /// make all writes so far durable before returning: flush, fdatasync file, and fsync
+    /// parent directory once so file itself survives crash. since sink is borrowed
+    /// until barrier completes, no later write can be ordered before it
+    pub async fn barrier(&mut self) -> Result<(), IoError> {
+        self.flush().await?;
+        self.writer.sync_data().await?;
+        if !self.dir_synced {
+            if let Some(parent) = self.path.parent() {
+                let parent = if parent.as_os_str().is_empty() {
+                    Path::new(".")
+                } else {
+                    parent
+                };
+                super::sync_dir(parent).await?;
+            }
+            self.dir_synced = true;
+        }
+        self.durable_len = self.flushed_len;
+        trace!("barrier at len: {}", self.durable_len);
+        Ok(())
+    }
+
     /// check if buf_len can be written
     pub fn can_be_appended(&self, buf_len: u64) -> bool {
         match self.option.max_len {
@@ -141,10 +191,66 @@ impl BoundedFileSink {
         &mut self.writer
     }
 
+    /// write all of `bufs` as one contiguous region, without flattening them first.
+    /// fails with `MaxLenReached` before writing if they don't fit
+    pub async fn write_vectored_all(
+        &mut self,
+        bufs: &[IoSlice<'_>],
+    ) -> Result<(), BoundedFileSinkError> {
+        let total: usize = bufs.iter().map(|buf| buf.len()).sum();
+        if !self.can_be_appended(total as u64) {
+            return Err(BoundedFileSinkError::MaxLenReached);
+        }
+        let mut slices: Vec<IoSlice<'_>> = bufs.to_vec();
+        let mut remaining = &mut slices[..];
+        // drop leading empty slices, so zero length write means failure
+        IoSlice::advance_slices(&mut remaining, 0);
+        while !remaining.is_empty() {
+            let n = self.write_vectored(remaining).await?;
+            if n == 0 {
+                return Err(IoError::from(ErrorKind::WriteZero).into());
+            }
+            IoSlice::advance_slices(&mut remaining, n);
+        }
+        Ok(())
+    }
+
+    /// write owned buffer, fails with `MaxLenReached` before writing if it doesn't fit
+    #[cfg(feature = "bytes")]
+    pub async fn write_bytes(&mut self, bytes: bytes::Bytes) -> Result<(), BoundedFileSinkError> {
+        if !self.can_be_appended(bytes.len() as u64) {
+            return Err(BoundedFileSinkError::MaxLenReached);
+        }
+        self.write_all(&bytes).await?;
+        Ok(())
+    }
+
     #[cfg(unix)]
     pub fn slice_from(&self, position: u64, len: u64) -> Result<AsyncFileSlice, IoError> {
         Ok(self.writer.raw_slice(position, len))
     }
+
+    /// slice of flushed bytes from `position`, which doesn't grow with writes after it
+    /// and so never covers partially written data. valid while sink is open
+    #[cfg(unix)]
+    pub fn snapshot_from(&self, position: u64) -> Result<AsyncFileSlice, IoError> {
+        if position > self.flushed_len {
+            return Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "position {} is past flushed len {}",
+                    position, self.flushed_len
+                ),
+            ));
+        }
+        self.slice_from(position, self.flushed_len - position)
+    }
+
+    /// slice of all flushed bytes
+    #[cfg(unix)]
+    pub fn snapshot(&self) -> AsyncFileSlice {
+        self.writer.raw_slice(0, self.flushed_len)
+    }
 }
 
 impl AsyncWrite for BoundedFileSink {
@@ -171,16 +277,39 @@ impl AsyncWrite for BoundedFileSink {
         }
     }
 
-    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.writer().poll_flush(cx)
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let size = futures_lite::ready!(self.as_mut().writer().poll_write_vectored(cx, bufs))?;
+        self.current_len += size as u64;
+        trace!(
+            "success vectored write: {}, current len: {}",
+            size,
+            self.current_len
+        );
+        Poll::Ready(Ok(size))
     }
 
-    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
-        self.writer().poll_close(cx)
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        // only bytes written before flush started are known to be flushed
+        let len = self.current_len;
+        futures_lite::ready!(self.as_mut().writer().poll_flush(cx))?;
+        self.flushed_len = len;
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let len = self.current_len;
+        futures_lite::ready!(self.as_mut().writer().poll_close(cx))?;
+        self.flushed_len = len;
+        Poll::Ready(Ok(()))
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::redundant_pattern_matching, clippy::unused_io_amount)]
 mod tests {
 
     use std::env::temp_dir;
@@ -317,6 +446,87 @@ mod tests {
         Ok(())
     }
 
+    #[test_async]
+    async fn test_sink_file_write_vectored() -> Result<(), BoundedFileSinkError> {
+        use std::io::IoSlice;
+
+        let test_file = temp_dir().join("file_test_vectored");
+        ensure_clean_file(&test_file);
+
+        let option = BoundedFileOption { max_len: Some(12) };
+        let mut f_sink = BoundedFileSink::create(&test_file, option).await?;
+        let header = [0x01; 3];
+        let body = [0x02; 5];
+        f_sink
+            .write_vectored_all(&[
+                IoSlice::new(&header),
+                IoSlice::new(&[]),
+                IoSlice::new(&body),
+            ])
+            .await?;
+        assert_eq!(f_sink.get_current_len(), 8);
+        f_sink
+            .write_bytes(bytes::Bytes::from_static(&[0x03; 4]))
+            .await?;
+        assert!(matches!(
+            f_sink.write_vectored_all(&[IoSlice::new(&header)]).await,
+            Err(BoundedFileSinkError::MaxLenReached)
+        ));
+        f_sink.flush().await?;
+
+        let mut buffer = vec![];
+        StdFile::open(test_file)?.read_to_end(&mut buffer)?;
+        assert_eq!(buffer, [1, 1, 1, 2, 2, 2, 2, 2, 3, 3, 3, 3]);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_sink_file_snapshot() -> Result<(), BoundedFileSinkError> {
+        let test_file = temp_dir().join("file_test_snapshot");
+        ensure_clean_file(&test_file);
+
+        let mut f_sink = BoundedFileSink::create(&test_file, BoundedFileOption::default()).await?;
+        f_sink.write_all(&[0x01; 3]).await?;
+        assert!(f_sink.snapshot().is_empty());
+        f_sink.flush().await?;
+        f_sink.write_all(&[0x02; 5]).await?;
+        // unflushed write is not part of snapshot
+        let snapshot = f_sink.snapshot();
+        assert_eq!(snapshot.position(), 0);
+        assert_eq!(snapshot.len(), 3);
+        assert_eq!(f_sink.get_flushed_len(), 3);
+        assert_eq!(f_sink.snapshot_from(1)?.len(), 2);
+        assert!(f_sink.snapshot_from(4).is_err());
+
+        f_sink.flush().await?;
+        assert_eq!(f_sink.snapshot().len(), 8);
+        // snapshot taken before keeps its len
+        assert_eq!(snapshot.len(), 3);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_sink_file_barrier() -> Result<(), BoundedFileSinkError> {
+        let test_file = temp_dir().join("file_test_barrier");
+        ensure_clean_file(&test_file);
+
+        let mut f_sink = BoundedFileSink::create(&test_file, BoundedFileOption::default()).await?;
+        f_sink.write_all(&[0x01; 3]).await?;
+        assert_eq!(f_sink.get_durable_len(), 0);
+        f_sink.barrier().await?;
+        assert_eq!(f_sink.get_durable_len(), 3);
+        assert_eq!(f_sink.get_flushed_len(), 3);
+        f_sink.write_all(&[0x02; 2]).await?;
+        f_sink.barrier().await?;
+        assert_eq!(f_sink.get_durable_len(), 5);
+
+        let mut buffer = vec![];
+        StdFile::open(&test_file)?.read_to_end(&mut buffer)?;
+        assert_eq!(buffer, [1, 1, 1, 2, 2]);
+        crate::fs::sync_dir(temp_dir()).await?;
+        Ok(())
+    }
+
     mod inner {
 
         use std::io::Error as IoError;
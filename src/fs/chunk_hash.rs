@@ -0,0 +1,121 @@
+use std::fs::File as StdFile;
+use std::io::Error as IoError;
+use std::mem::ManuallyDrop;
+use std::os::unix::io::FromRawFd;
+use std::path::Path;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_lite::Stream;
+use ring::digest::{digest, SHA256, SHA256_OUTPUT_LEN};
+
+use super::Prefetcher;
+use crate::file_slice::AsyncFileSlice;
+
+/// depth of read ahead, memory is bounded by this many chunks
+const HASH_DEPTH: usize = 2;
+
+/// SHA-256 of `len` bytes at `offset`, offset is relative to start of file or slice
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ChunkHash {
+    pub offset: u64,
+    pub len: usize,
+    pub hash: [u8; SHA256_OUTPUT_LEN],
+}
+
+/// stream of hashes of fixed size chunks, last chunk may be shorter
+pub struct ChunkHasher {
+    chunks: Prefetcher,
+    offset: u64,
+}
+
+impl ChunkHasher {
+    /// hash whole file
+    pub async fn open<P: AsRef<Path>>(path: P, chunk_size: usize) -> Result<Self, IoError> {
+        let chunks = Prefetcher::open(path).await?;
+        Ok(Self::new(chunks, chunk_size))
+    }
+
+    /// hash bytes of slice, slice keeps ownership of its file
+    pub fn slice(slice: &AsyncFileSlice, chunk_size: usize) -> Result<Self, IoError> {
+        let file = ManuallyDrop::new(unsafe { StdFile::from_raw_fd(slice.fd()) });
+        let chunks = Prefetcher::new(file.try_clone()?, slice.position(), slice.len());
+        Ok(Self::new(chunks, chunk_size))
+    }
+
+    fn new(chunks: Prefetcher, chunk_size: usize) -> Self {
+        Self {
+            chunks: chunks.chunk_size(chunk_size).depth(HASH_DEPTH),
+            offset: 0,
+        }
+    }
+}
+
+impl Stream for ChunkHasher {
+    type Item = Result<ChunkHash, IoError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let chunk = match futures_lite::ready!(Pin::new(&mut this.chunks).poll_next(cx)) {
+            Some(Ok(chunk)) => chunk,
+            Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+            None => return Poll::Ready(None),
+        };
+        let mut hash = [0; SHA256_OUTPUT_LEN];
+        hash.copy_from_slice(digest(&SHA256, &chunk).as_ref());
+        let chunk_hash = ChunkHash {
+            offset: this.offset,
+            len: chunk.len(),
+            hash,
+        };
+        this.offset += chunk.len() as u64;
+        Poll::Ready(Some(Ok(chunk_hash)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+
+    use futures_lite::StreamExt;
+    use ring::digest::{digest, SHA256};
+
+    use crate::fs::util as file_util;
+    use crate::fs::AsyncFileExtension;
+    use crate::test_async;
+
+    use super::ChunkHash;
+    use super::ChunkHasher;
+
+    #[test_async]
+    async fn test_chunk_hasher() -> Result<(), IoError> {
+        let path = temp_dir().join("file_test_chunk_hash");
+        let content: Vec<u8> = (0..10_000u32).map(|i| (i % 253) as u8).collect();
+        std::fs::write(&path, &content)?;
+
+        let hashes: Vec<ChunkHash> = ChunkHasher::open(&path, 4096).await?.try_collect().await?;
+        assert_eq!(hashes.len(), 3);
+        assert_eq!(hashes[2].offset, 8192);
+        assert_eq!(hashes[2].len, 10_000 - 8192);
+        for chunk in &hashes {
+            let start = chunk.offset as usize;
+            let expected = digest(&SHA256, &content[start..start + chunk.len]);
+            assert_eq!(&chunk.hash[..], expected.as_ref());
+        }
+
+        // offsets are relative to slice
+        let file = file_util::open(&path).await?;
+        let hashes: Vec<ChunkHash> = ChunkHasher::slice(&file.raw_slice(100, 300), 200)?
+            .try_collect()
+            .await?;
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[1].offset, 200);
+        assert_eq!(
+            &hashes[1].hash[..],
+            digest(&SHA256, &content[300..400]).as_ref()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! exclusive lock of data directory by one process.
+//!
+//! lock is `flock` of sentinel file in directory, so it is released by kernel when
+//! holder dies and never goes stale. owner pid and boot id are written to sentinel
+//! for error reporting, and decide staleness where filesystem doesn't support `flock`.
+//! sentinel is never removed, since unlinking it lets two processes lock different files
+use std::fs::File as StdFile;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Seek;
+use std::io::SeekFrom;
+use std::io::Write;
+use std::os::unix::io::AsRawFd;
+use std::path::Path;
+use std::path::PathBuf;
+
+use blocking::unblock;
+use log::debug;
+
+/// name of sentinel file in directory
+pub const LOCK_FILE_NAME: &str = ".lock";
+
+/// holder of sentinel, as written to it
+#[derive(Debug, PartialEq, Eq)]
+struct LockOwner {
+    pid: u32,
+    boot_id: String,
+}
+
+impl LockOwner {
+    fn current() -> Self {
+        Self {
+            pid: std::process::id(),
+            boot_id: boot_id(),
+        }
+    }
+
+    fn parse(content: &str) -> Option<Self> {
+        let mut lines = content.lines();
+        let pid = lines.next()?.trim().parse().ok()?;
+        let boot_id = lines.next().unwrap_or_default().trim().to_owned();
+        Some(Self { pid, boot_id })
+    }
+
+    /// owner is process still running in this boot
+    fn is_alive(&self) -> bool {
+        if self.boot_id != boot_id() {
+            return false;
+        }
+        let result = unsafe { libc::kill(self.pid as libc::pid_t, 0) };
+        result == 0 || IoError::last_os_error().raw_os_error() == Some(libc::EPERM)
+    }
+}
+
+/// changes on each boot, so pid from before reboot isn't mistaken for live process
+fn boot_id() -> String {
+    std::fs::read_to_string("/proc/sys/kernel/random/boot_id")
+        .map(|id| id.trim().to_owned())
+        .unwrap_or_default()
+}
+
+/// held for as long as lock is kept, dropping it releases lock
+#[derive(Debug)]
+pub struct DirLock {
+    file: StdFile,
+    path: PathBuf,
+}
+
+impl DirLock {
+    /// lock directory, creating it if needed. fails with `WouldBlock` kind of error
+    /// while another process, or other `DirLock` of this one, holds it
+    pub async fn acquire<P: AsRef<Path>>(dir: P) -> Result<Self, IoError> {
+        let dir = dir.as_ref().to_owned();
+        unblock(move || Self::acquire_blocking(&dir)).await
+    }
+
+    fn acquire_blocking(dir: &Path) -> Result<Self, IoError> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(LOCK_FILE_NAME);
+        let mut file = std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+        let mut content = String::new();
+        file.read_to_string(&mut content)?;
+        let previous = LockOwner::parse(&content);
+
+        if unsafe { libc::flock(file.as_raw_fd(), libc::LOCK_EX | libc::LOCK_NB) } < 0 {
+            let err = IoError::last_os_error();
+            match err.raw_os_error() {
+                Some(libc::EWOULDBLOCK) => return Err(locked(dir, previous)),
+                Some(libc::ENOLCK) | Some(libc::EOPNOTSUPP) => {
+                    // only sentinel content tells whether lock is held
+                    if let Some(owner) = previous.as_ref().filter(|owner| owner.is_alive()) {
+                        if owner.pid != std::process::id() {
+                            return Err(locked(dir, previous));
+                        }
+                    }
+                }
+                _ => return Err(err),
+            }
+        }
+        if let Some(owner) = previous {
+            debug!("took over stale lock of pid: {}", owner.pid);
+        }
+
+        let owner = LockOwner::current();
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        write!(file, "{}\n{}\n", owner.pid, owner.boot_id)?;
+        file.sync_all()?;
+        debug!("locked dir: {}", dir.display());
+        Ok(Self {
+            file,
+            path: dir.to_owned(),
+        })
+    }
+
+    /// locked directory
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// clear owner and release lock
+    pub fn release(self) -> Result<(), IoError> {
+        self.file.set_len(0)?;
+        debug!("released dir lock: {}", self.path.display());
+        Ok(())
+    }
+}
+
+fn locked(dir: &Path, owner: Option<LockOwner>) -> IoError {
+    let holder = match owner {
+        Some(owner) => format!("pid {}", owner.pid),
+        None => "another process".to_owned(),
+    };
+    IoError::new(
+        ErrorKind::WouldBlock,
+        format!("dir {} is locked by {}", dir.display(), holder),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use crate::test_async;
+
+    use super::DirLock;
+    use super::LockOwner;
+    use super::LOCK_FILE_NAME;
+
+    #[test_async]
+    async fn test_dir_lock() -> Result<(), IoError> {
+        let dir = temp_dir().join("fs_test_dir_lock");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let lock = DirLock::acquire(&dir).await?;
+        let owner = std::fs::read_to_string(dir.join(LOCK_FILE_NAME))?;
+        assert_eq!(LockOwner::parse(&owner), Some(LockOwner::current()));
+        let err = DirLock::acquire(&dir).await.expect_err("locked");
+        assert_eq!(err.kind(), ErrorKind::WouldBlock);
+        drop(lock);
+
+        // owner left by crashed process doesn't block
+        std::fs::write(dir.join(LOCK_FILE_NAME), "4194304\nold-boot\n")?;
+        assert!(!LockOwner::parse("4194304\nold-boot\n")
+            .expect("owner")
+            .is_alive());
+        let lock = DirLock::acquire(&dir).await?;
+        lock.release()?;
+        assert!(std::fs::read_to_string(dir.join(LOCK_FILE_NAME))?.is_empty());
+        Ok(())
+    }
+}
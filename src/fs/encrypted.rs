@@ -0,0 +1,354 @@
+//! encryption at rest for files written thru `BoundedFileSink`.
+//!
+//! plaintext is split in chunks, each sealed with AES-256-GCM under fresh random nonce.
+//! chunk on disk is header of ciphertext len (u32 big endian), key id (u32 big endian)
+//! and nonce, followed by ciphertext with tag. file offset of chunk is authenticated too,
+//! so chunks can't be reordered. reader decrypts chunks of slice which starts at chunk
+use std::fs::File as StdFile;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::mem::ManuallyDrop;
+use std::os::unix::fs::FileExt;
+use std::os::unix::io::FromRawFd;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use blocking::unblock;
+use futures_lite::future::poll_fn;
+use futures_lite::AsyncWrite;
+use log::trace;
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+use super::BoundedFileSink;
+use crate::file_slice::AsyncFileSlice;
+
+pub const KEY_LEN: usize = 32;
+
+/// plaintext bytes of each chunk, unless flushed earlier
+pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+
+/// ciphertext len, key id, nonce
+const HEADER_LEN: usize = 4 + 4 + NONCE_LEN;
+const TAG_LEN: usize = 16;
+/// reader refuses longer chunks rather than allocate for corrupt header
+const MAX_CHUNK_LEN: usize = 16 * 1024 * 1024;
+
+/// keys by id, so keys can be rotated while older chunks stay readable
+pub trait KeyProvider: Send + Sync {
+    /// id and key for new chunks
+    fn current_key(&self) -> Result<(u32, [u8; KEY_LEN]), IoError>;
+
+    /// key with id, for decrypt
+    fn key(&self, id: u32) -> Result<[u8; KEY_LEN], IoError>;
+}
+
+/// single key which is never rotated
+pub struct StaticKey {
+    id: u32,
+    key: [u8; KEY_LEN],
+}
+
+impl StaticKey {
+    pub fn new(id: u32, key: [u8; KEY_LEN]) -> Self {
+        Self { id, key }
+    }
+}
+
+impl KeyProvider for StaticKey {
+    fn current_key(&self) -> Result<(u32, [u8; KEY_LEN]), IoError> {
+        Ok((self.id, self.key))
+    }
+
+    fn key(&self, id: u32) -> Result<[u8; KEY_LEN], IoError> {
+        if id == self.id {
+            Ok(self.key)
+        } else {
+            Err(IoError::new(
+                ErrorKind::NotFound,
+                format!("key {} not found", id),
+            ))
+        }
+    }
+}
+
+fn aead_key(key: &[u8; KEY_LEN]) -> Result<LessSafeKey, IoError> {
+    UnboundKey::new(&AES_256_GCM, key)
+        .map(LessSafeKey::new)
+        .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid key"))
+}
+
+/// encrypts everything written before passing it to `BoundedFileSink`.
+/// chunk is sealed once it is full or on flush, so flush often leads to small chunks
+pub struct EncryptedSink {
+    inner: BoundedFileSink,
+    keys: Arc<dyn KeyProvider>,
+    rng: SystemRandom,
+    chunk_size: usize,
+    /// plaintext of chunk being filled
+    plain: Vec<u8>,
+    /// sealed chunks not yet written
+    sealed: Vec<u8>,
+    written: usize,
+}
+
+impl EncryptedSink {
+    pub fn new(inner: BoundedFileSink, keys: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            inner,
+            keys,
+            rng: SystemRandom::new(),
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            plain: vec![],
+            sealed: vec![],
+            written: 0,
+        }
+    }
+
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.clamp(1, MAX_CHUNK_LEN - TAG_LEN);
+        self
+    }
+
+    pub fn get_ref(&self) -> &BoundedFileSink {
+        &self.inner
+    }
+
+    /// buffered plaintext which isn't flushed is dropped
+    pub fn into_inner(self) -> BoundedFileSink {
+        self.inner
+    }
+
+    /// seal buffered plaintext as chunk at end of file
+    fn seal(&mut self) -> Result<(), IoError> {
+        if self.plain.is_empty() {
+            return Ok(());
+        }
+        let offset = self.inner.get_current_len() + (self.sealed.len() - self.written) as u64;
+        let (key_id, key) = self.keys.current_key()?;
+        let mut nonce = [0; NONCE_LEN];
+        self.rng
+            .fill(&mut nonce)
+            .map_err(|_| IoError::other("nonce generation failed"))?;
+        let mut chunk = std::mem::take(&mut self.plain);
+        aead_key(&key)?
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(offset.to_be_bytes()),
+                &mut chunk,
+            )
+            .map_err(|_| IoError::other("encryption failed"))?;
+        trace!("sealed chunk of {} bytes at: {}", chunk.len(), offset);
+        self.sealed
+            .extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+        self.sealed.extend_from_slice(&key_id.to_be_bytes());
+        self.sealed.extend_from_slice(&nonce);
+        self.sealed.extend_from_slice(&chunk);
+        Ok(())
+    }
+
+    fn poll_write_sealed(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        while self.written < self.sealed.len() {
+            let n = futures_lite::ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.sealed[self.written..])
+            )?;
+            if n == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            self.written += n;
+        }
+        self.sealed.clear();
+        self.written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// flush and make durable, see `BoundedFileSink::barrier`
+    pub async fn barrier(&mut self) -> Result<(), IoError> {
+        self.seal()?;
+        poll_fn(|cx| self.poll_write_sealed(cx)).await?;
+        self.inner.barrier().await
+    }
+}
+
+impl AsyncWrite for EncryptedSink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        futures_lite::ready!(this.poll_write_sealed(cx))?;
+        let n = buf.len().min(this.chunk_size - this.plain.len());
+        this.plain.extend_from_slice(&buf[..n]);
+        if this.plain.len() == this.chunk_size {
+            this.seal()?;
+        }
+        Poll::Ready(Ok(n))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        this.seal()?;
+        futures_lite::ready!(this.poll_write_sealed(cx))?;
+        Pin::new(&mut this.inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        this.seal()?;
+        futures_lite::ready!(this.poll_write_sealed(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+/// decrypts chunks of slice written by `EncryptedSink`, slice must start at chunk
+pub struct DecryptingReader {
+    slice: AsyncFileSlice,
+    keys: Arc<dyn KeyProvider>,
+    /// offset in slice of next chunk
+    next: u64,
+}
+
+impl DecryptingReader {
+    pub fn new(slice: AsyncFileSlice, keys: Arc<dyn KeyProvider>) -> Self {
+        Self {
+            slice,
+            keys,
+            next: 0,
+        }
+    }
+
+    /// read `len` bytes at `position` of file
+    async fn read_at(&self, position: u64, len: usize) -> Result<Vec<u8>, IoError> {
+        let fd = self.slice.fd();
+        unblock(move || {
+            // fd stays owned by slice
+            let file = ManuallyDrop::new(unsafe { StdFile::from_raw_fd(fd) });
+            let mut buf = vec![0; len];
+            file.read_exact_at(&mut buf, position)?;
+            Ok(buf)
+        })
+        .await
+    }
+
+    /// plaintext of next chunk, none once slice is read
+    pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, IoError> {
+        let remaining = self.slice.len() - self.next;
+        if remaining == 0 {
+            return Ok(None);
+        }
+        if remaining < (HEADER_LEN + TAG_LEN) as u64 {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let offset = self.slice.position() + self.next;
+        let header = self.read_at(offset, HEADER_LEN).await?;
+        let len = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+        let key_id = u32::from_be_bytes([header[4], header[5], header[6], header[7]]);
+        if !(TAG_LEN..=MAX_CHUNK_LEN).contains(&len) {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                format!("invalid chunk len {} at: {}", len, offset),
+            ));
+        }
+        if (HEADER_LEN + len) as u64 > remaining {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        let mut nonce = [0; NONCE_LEN];
+        nonce.copy_from_slice(&header[8..]);
+        let mut chunk = self.read_at(offset + HEADER_LEN as u64, len).await?;
+        let plain_len = aead_key(&self.keys.key(key_id)?)?
+            .open_in_place(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::from(offset.to_be_bytes()),
+                &mut chunk,
+            )
+            .map_err(|_| {
+                IoError::new(
+                    ErrorKind::InvalidData,
+                    format!("chunk at {} failed authentication", offset),
+                )
+            })?
+            .len();
+        chunk.truncate(plain_len);
+        self.next += (HEADER_LEN + len) as u64;
+        Ok(Some(chunk))
+    }
+
+    /// plaintext of all remaining chunks
+    pub async fn read_to_end(&mut self) -> Result<Vec<u8>, IoError> {
+        let mut plain = vec![];
+        while let Some(chunk) = self.next_chunk().await? {
+            plain.extend_from_slice(&chunk);
+        }
+        Ok(plain)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::ErrorKind;
+    use std::os::unix::fs::FileExt;
+    use std::sync::Arc;
+
+    use futures_lite::AsyncWriteExt;
+
+    use crate::fs::BoundedFileOption;
+    use crate::fs::BoundedFileSink;
+    use crate::fs::BoundedFileSinkError;
+    use crate::test_async;
+
+    use super::DecryptingReader;
+    use super::EncryptedSink;
+    use super::KeyProvider;
+    use super::StaticKey;
+
+    #[test_async]
+    async fn test_encrypted_sink() -> Result<(), BoundedFileSinkError> {
+        let path = temp_dir().join("file_test_encrypted");
+        let _ = std::fs::remove_file(&path);
+        let keys: Arc<dyn KeyProvider> = Arc::new(StaticKey::new(1, [7; 32]));
+
+        // opened for read too, so snapshot can be read
+        let inner = BoundedFileSink::open_append(&path, BoundedFileOption::default()).await?;
+        let mut sink = EncryptedSink::new(inner, keys.clone()).chunk_size(10);
+        let plain: Vec<u8> = (0..25).collect();
+        sink.write_all(&plain).await?;
+        sink.flush().await?;
+        let written = sink.get_ref().get_flushed_len();
+        // 3 chunks of 10, 10 and 5 bytes
+        assert_eq!(written, 25 + 3 * (20 + 16));
+        let on_disk = std::fs::read(&path)?;
+        assert!(!on_disk.windows(10).any(|window| window == &plain[..10]));
+
+        let slice = sink.get_ref().snapshot();
+        assert_eq!(
+            DecryptingReader::new(slice, keys.clone())
+                .read_to_end()
+                .await?,
+            plain
+        );
+
+        // key not known
+        let other: Arc<dyn KeyProvider> = Arc::new(StaticKey::new(2, [7; 32]));
+        let slice = sink.get_ref().snapshot();
+        let err = DecryptingReader::new(slice, other)
+            .read_to_end()
+            .await
+            .expect_err("unknown key");
+        assert_eq!(err.kind(), ErrorKind::NotFound);
+
+        // tampered ciphertext, flipped since byte is random
+        std::fs::OpenOptions::new()
+            .write(true)
+            .open(&path)?
+            .write_all_at(&[!on_disk[30]], 30)?;
+        let slice = sink.get_ref().snapshot();
+        let mut reader = DecryptingReader::new(slice, keys);
+        let err = reader.next_chunk().await.expect_err("tampered");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        Ok(())
+    }
+}
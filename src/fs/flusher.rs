@@ -0,0 +1,215 @@
+//! coalesced fsync of many files.
+//!
+//! sync requests are collected over window on flusher thread, then served together:
+//! requests for same file share one `fdatasync`, which touches only that file.
+//! each request completes once its file's data is durable
+use std::collections::HashMap;
+use std::fs::File as StdFile;
+use std::future::Future;
+use std::io::Error as IoError;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use log::debug;
+use log::trace;
+
+use super::File;
+
+/// default time requests are collected before they are synced together
+pub const DEFAULT_FLUSH_WINDOW: Duration = Duration::from_millis(2);
+
+#[derive(Default)]
+struct CompletionState {
+    result: Option<Result<(), IoError>>,
+    waker: Option<Waker>,
+}
+
+type Completion = Arc<Mutex<CompletionState>>;
+
+fn complete(completion: &Completion, result: Result<(), IoError>) {
+    let mut state = completion.lock().unwrap();
+    state.result = Some(result);
+    if let Some(waker) = state.waker.take() {
+        waker.wake();
+    }
+}
+
+/// completes once file of request is synced
+pub struct SyncRequest {
+    completion: Completion,
+}
+
+impl Future for SyncRequest {
+    type Output = Result<(), IoError>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut state = self.completion.lock().unwrap();
+        match state.result.take() {
+            Some(result) => Poll::Ready(result),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+struct Request {
+    file: StdFile,
+    completion: Completion,
+}
+
+/// handle to flusher thread, thread stops once all handles are dropped
+#[derive(Clone)]
+pub struct Flusher {
+    sender: mpsc::Sender<Request>,
+    syncs: Arc<AtomicU64>,
+}
+
+impl Default for Flusher {
+    fn default() -> Self {
+        Self::new(DEFAULT_FLUSH_WINDOW)
+    }
+}
+
+impl Flusher {
+    /// start flusher thread which collects requests for `window` after first one
+    pub fn new(window: Duration) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let syncs = Arc::new(AtomicU64::new(0));
+        let thread_syncs = syncs.clone();
+        thread::Builder::new()
+            .name("fluvio-flusher".to_owned())
+            .spawn(move || run(receiver, window, thread_syncs))
+            .expect("flusher thread");
+        Self { sender, syncs }
+    }
+
+    /// make data written to file so far durable, together with other pending requests.
+    /// file still has to be flushed first if it is buffered
+    pub fn sync(&self, file: &File) -> SyncRequest {
+        let completion: Completion = Arc::default();
+        let request = SyncRequest {
+            completion: completion.clone(),
+        };
+        // own fd, so file can be closed while request is pending
+        let fd = unsafe { libc::dup(file.as_raw_fd()) };
+        if fd < 0 {
+            complete(&completion, Err(IoError::last_os_error()));
+            return request;
+        }
+        let file = unsafe { StdFile::from_raw_fd(fd) };
+        if self.sender.send(Request { file, completion }).is_err() {
+            complete(
+                &request.completion,
+                Err(IoError::other("flusher thread stopped")),
+            );
+        }
+        request
+    }
+
+    /// sync calls issued so far, fewer than requests when they are coalesced
+    pub fn sync_count(&self) -> u64 {
+        self.syncs.load(Ordering::Relaxed)
+    }
+}
+
+fn run(receiver: mpsc::Receiver<Request>, window: Duration, syncs: Arc<AtomicU64>) {
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        let deadline = Instant::now() + window;
+        loop {
+            let now = Instant::now();
+            if now >= deadline {
+                break;
+            }
+            match receiver.recv_timeout(deadline - now) {
+                Ok(request) => batch.push(request),
+                Err(_) => break,
+            }
+        }
+        sync_batch(batch, &syncs);
+    }
+    debug!("flusher stopped");
+}
+
+/// same result for each request of batch
+fn copy_result(result: &Result<(), IoError>) -> Result<(), IoError> {
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) => Err(IoError::new(err.kind(), err.to_string())),
+    }
+}
+
+/// requests by file, identified by device and inode
+fn sync_batch(batch: Vec<Request>, syncs: &AtomicU64) {
+    let count = batch.len();
+    let mut files: HashMap<(u64, u64), Vec<Request>> = HashMap::new();
+    for request in batch {
+        match request.file.metadata() {
+            Ok(metadata) => files
+                .entry((metadata.dev(), metadata.ino()))
+                .or_default()
+                .push(request),
+            Err(err) => complete(&request.completion, Err(err)),
+        }
+    }
+    for requests in files.into_values() {
+        syncs.fetch_add(1, Ordering::Relaxed);
+        let result = requests[0].file.sync_data();
+        for request in &requests {
+            complete(&request.completion, copy_result(&result));
+        }
+    }
+    trace!("synced batch of {} requests", count);
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::AsyncWriteExt;
+    use futures_util::future::join_all;
+
+    use crate::fs::util as file_util;
+    use crate::test_async;
+
+    use super::Flusher;
+
+    #[test_async]
+    async fn test_flusher() -> Result<(), IoError> {
+        let dir = temp_dir().join("fs_test_flusher");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let mut files = vec![];
+        for i in 0..5 {
+            let mut file = file_util::create(dir.join(format!("{}.log", i))).await?;
+            file.write_all(b"record").await?;
+            file.flush().await?;
+            files.push(file);
+        }
+
+        let flusher = Flusher::new(Duration::from_millis(50));
+        // same file twice
+        let mut requests: Vec<_> = files.iter().map(|file| flusher.sync(file)).collect();
+        requests.push(flusher.sync(&files[0]));
+        for result in join_all(requests).await {
+            result?;
+        }
+        // one sync per file, duplicate request shares it
+        assert_eq!(flusher.sync_count(), 5);
+        flusher.sync(&files[1]).await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,243 @@
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use futures_lite::AsyncWrite;
+use log::debug;
+
+use super::BoundedFileOption;
+use super::BoundedFileSink;
+
+/// what write does once group budget is used up
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Admission {
+    /// fail write with `QuotaExceeded` kind of error
+    #[default]
+    Error,
+    /// wait until bytes are released, such as by `GroupSink::remove`
+    Block,
+}
+
+struct GroupState {
+    used: u64,
+    /// writers blocked on budget
+    waiters: Vec<Waker>,
+}
+
+struct GroupInner {
+    budget: u64,
+    admission: Admission,
+    state: Mutex<GroupState>,
+}
+
+impl GroupInner {
+    /// reserve up to `len` bytes, none if budget is used up
+    fn reserve(&self, len: u64, waker: &Waker) -> Poll<Result<u64, IoError>> {
+        let mut state = self.state.lock().unwrap();
+        let available = self.budget.saturating_sub(state.used);
+        if available == 0 && len > 0 {
+            return match self.admission {
+                Admission::Error => Poll::Ready(Err(IoError::new(
+                    ErrorKind::QuotaExceeded,
+                    format!("sink group budget of {} bytes used up", self.budget),
+                ))),
+                Admission::Block => {
+                    state.waiters.push(waker.clone());
+                    Poll::Pending
+                }
+            };
+        }
+        let reserved = len.min(available);
+        state.used += reserved;
+        Poll::Ready(Ok(reserved))
+    }
+
+    fn release(&self, len: u64) {
+        if len == 0 {
+            return;
+        }
+        let waiters = {
+            let mut state = self.state.lock().unwrap();
+            state.used = state.used.saturating_sub(len);
+            std::mem::take(&mut state.waiters)
+        };
+        for waiter in waiters {
+            waiter.wake();
+        }
+    }
+}
+
+/// sinks sharing single byte budget, such as topics stored on same volume.
+/// bytes already in file count against budget once it is opened in group
+#[derive(Clone)]
+pub struct SinkGroup {
+    inner: Arc<GroupInner>,
+}
+
+impl SinkGroup {
+    pub fn new(budget: u64, admission: Admission) -> Self {
+        Self {
+            inner: Arc::new(GroupInner {
+                budget,
+                admission,
+                state: Mutex::new(GroupState {
+                    used: 0,
+                    waiters: vec![],
+                }),
+            }),
+        }
+    }
+
+    pub fn budget(&self) -> u64 {
+        self.inner.budget
+    }
+
+    /// bytes of all sinks in group
+    pub fn used(&self) -> u64 {
+        self.inner.state.lock().unwrap().used
+    }
+
+    pub fn available(&self) -> u64 {
+        self.inner.budget.saturating_sub(self.used())
+    }
+
+    /// give back bytes freed outside of group, such as by retention of old files
+    pub fn release(&self, len: u64) {
+        self.inner.release(len)
+    }
+
+    /// open sink in group, its existing len is added to usage even if over budget
+    pub async fn open_append<P: AsRef<Path>>(
+        &self,
+        path: P,
+        option: BoundedFileOption,
+    ) -> Result<GroupSink, IoError> {
+        let sink = BoundedFileSink::open_append(path, option).await?;
+        self.inner.state.lock().unwrap().used += sink.get_current_len();
+        debug!(
+            "opened sink: {} in group, used: {}",
+            sink.get_path().display(),
+            self.used()
+        );
+        Ok(GroupSink {
+            sink,
+            group: self.inner.clone(),
+        })
+    }
+}
+
+/// sink whose writes are admitted by its group. write may be partial when budget is
+/// nearly used up, `write_all` fails or waits for rest as group admission says
+pub struct GroupSink {
+    sink: BoundedFileSink,
+    group: Arc<GroupInner>,
+}
+
+impl GroupSink {
+    pub fn get_ref(&self) -> &BoundedFileSink {
+        &self.sink
+    }
+
+    pub fn get_mut(&mut self) -> &mut BoundedFileSink {
+        &mut self.sink
+    }
+
+    /// delete file and release its bytes to group
+    pub async fn remove(self) -> Result<(), IoError> {
+        let len = self.sink.get_current_len();
+        let path = self.sink.get_path().to_owned();
+        drop(self.sink);
+        super::remove_file(&path).await?;
+        self.group.release(len);
+        Ok(())
+    }
+}
+
+impl AsyncWrite for GroupSink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        let reserved = futures_lite::ready!(this.group.reserve(buf.len() as u64, cx.waker()))?;
+        let result = Pin::new(&mut this.sink).poll_write(cx, &buf[..reserved as usize]);
+        let written = match &result {
+            Poll::Ready(Ok(n)) => *n as u64,
+            _ => 0,
+        };
+        this.group.release(reserved - written);
+        result
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().sink).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().sink).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::fs::BoundedFileOption;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::Admission;
+    use super::SinkGroup;
+
+    #[test_async]
+    async fn test_sink_group() -> Result<(), IoError> {
+        let dir = temp_dir().join("fs_test_sink_group");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+
+        let group = SinkGroup::new(10, Admission::Error);
+        let mut first = group
+            .open_append(dir.join("first"), BoundedFileOption::default())
+            .await?;
+        let mut second = group
+            .open_append(dir.join("second"), BoundedFileOption::default())
+            .await?;
+        first.write_all(&[1; 6]).await?;
+        second.write_all(&[2; 4]).await?;
+        assert_eq!(group.available(), 0);
+        let err = second.write_all(&[2]).await.expect_err("over budget");
+        assert_eq!(err.kind(), ErrorKind::QuotaExceeded);
+
+        // blocked writer resumes once bytes are released
+        let group = SinkGroup::new(10, Admission::Block);
+        let first = group
+            .open_append(dir.join("first"), BoundedFileOption::default())
+            .await?;
+        let mut third = group
+            .open_append(dir.join("third"), BoundedFileOption::default())
+            .await?;
+        assert_eq!(group.used(), 6);
+        let (written, removed) = zip(third.write_all(&[3; 8]), async {
+            sleep(Duration::from_millis(20)).await;
+            first.remove().await
+        })
+        .await;
+        written?;
+        removed?;
+        assert_eq!(group.used(), 8);
+        assert!(!dir.join("first").exists());
+        Ok(())
+    }
+}
@@ -0,0 +1,133 @@
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+
+use blocking::unblock;
+use log::debug;
+
+use super::File;
+use crate::file_slice::AsyncFileSlice;
+
+fn fallocate_punch(fd: RawFd, offset: u64, len: u64) -> Result<(), IoError> {
+    let result = unsafe {
+        libc::fallocate(
+            fd,
+            libc::FALLOC_FL_PUNCH_HOLE | libc::FALLOC_FL_KEEP_SIZE,
+            offset as libc::off_t,
+            len as libc::off_t,
+        )
+    };
+    if result < 0 {
+        Err(IoError::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// deallocate `len` bytes at `offset`, so retention can reclaim head of file without
+/// rewriting it. file len is kept. only whole filesystem blocks are freed, rest of range
+/// is zeroed. fails with `Unsupported` kind of error on filesystems without hole support
+pub async fn punch_hole(file: &File, offset: u64, len: u64) -> Result<(), IoError> {
+    let fd = file.as_raw_fd();
+    // file is borrowed until punch finishes
+    unblock(move || fallocate_punch(fd, offset, len)).await?;
+    debug!("punched hole of {} bytes at: {}", len, offset);
+    Ok(())
+}
+
+impl AsyncFileSlice {
+    /// fails with `InvalidData` if part of slice has been deallocated with `punch_hole`,
+    /// or with `UnexpectedEof` if slice goes past end of file.
+    /// holes are found by whole blocks, like they are freed
+    pub fn ensure_allocated(&self) -> Result<(), IoError> {
+        if self.is_empty() {
+            return Ok(());
+        }
+        // own open file, so seek doesn't move offset shared with file of slice
+        let file = std::fs::File::open(format!("/proc/self/fd/{}", self.fd()))?;
+        let end = self.position() + self.len();
+        let hole = unsafe {
+            libc::lseek(
+                file.as_raw_fd(),
+                self.position() as libc::off_t,
+                libc::SEEK_HOLE,
+            )
+        };
+        if hole < 0 {
+            let err = IoError::last_os_error();
+            if err.raw_os_error() == Some(libc::ENXIO) {
+                return Err(IoError::new(
+                    ErrorKind::UnexpectedEof,
+                    "slice starts past end of file",
+                ));
+            }
+            return Err(err);
+        }
+        let hole = hole as u64;
+        if hole >= end {
+            return Ok(());
+        }
+        // end of file counts as hole
+        if hole >= file.metadata()?.len() {
+            return Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                "slice goes past end of file",
+            ));
+        }
+        Err(IoError::new(
+            ErrorKind::InvalidData,
+            format!(
+                "slice {}..{} is deallocated from: {}",
+                self.position(),
+                end,
+                hole
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::io::Write;
+
+    use log::debug;
+
+    use crate::fs::util as file_util;
+    use crate::fs::AsyncFileExtension;
+    use crate::test_async;
+
+    use super::punch_hole;
+
+    const BLOCK: u64 = 64 * 1024;
+
+    #[test_async]
+    async fn test_punch_hole() -> Result<(), IoError> {
+        let path = temp_dir().join("file_test_punch_hole");
+        std::fs::File::create(&path)?.write_all(&vec![0x01; 4 * BLOCK as usize])?;
+        let file = file_util::open_read_write(&path).await?;
+
+        if let Err(err) = punch_hole(&file, BLOCK, 2 * BLOCK).await {
+            debug!("hole punching not supported: {}", err);
+            return Ok(());
+        }
+        assert_eq!(file.metadata().await?.len(), 4 * BLOCK);
+        file.raw_slice(0, BLOCK).ensure_allocated()?;
+        file.raw_slice(3 * BLOCK, BLOCK).ensure_allocated()?;
+        let err = file
+            .raw_slice(BLOCK / 2, BLOCK)
+            .ensure_allocated()
+            .expect_err("punched");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = file
+            .raw_slice(3 * BLOCK, 2 * BLOCK)
+            .ensure_allocated()
+            .expect_err("past end");
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+}
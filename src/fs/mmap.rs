@@ -128,6 +128,7 @@ impl MemoryMappedFile {
 }
 
 #[cfg(test)]
+#[allow(clippy::unused_io_amount)]
 mod tests {
 
     use std::env::temp_dir;
@@ -1,14 +1,57 @@
 mod bounded;
+#[cfg(all(unix, feature = "hash"))]
+mod chunk_hash;
+#[cfg(unix)]
+mod dir_lock;
 mod extension;
+#[cfg(unix)]
+mod flusher;
+mod group;
+#[cfg(target_os = "linux")]
+mod hole;
+#[cfg(feature = "task")]
+mod periodic;
+#[cfg(unix)]
+mod prefetch;
+mod reflink;
+mod stat;
+mod sync;
+#[cfg(all(unix, feature = "timer"))]
+mod tail;
 
 pub use extension::*;
 
 pub use self::bounded::BoundedFileOption;
 pub use self::bounded::BoundedFileSink;
 pub use self::bounded::BoundedFileSinkError;
+#[cfg(all(unix, feature = "hash"))]
+pub use self::chunk_hash::{ChunkHash, ChunkHasher};
+#[cfg(unix)]
+pub use self::dir_lock::{DirLock, LOCK_FILE_NAME};
+#[cfg(unix)]
+pub use self::flusher::{Flusher, SyncRequest, DEFAULT_FLUSH_WINDOW};
+pub use self::group::{Admission, GroupSink, SinkGroup};
+#[cfg(target_os = "linux")]
+pub use self::hole::punch_hole;
+#[cfg(feature = "task")]
+pub use self::periodic::FlushingSink;
+#[cfg(unix)]
+pub use self::prefetch::Prefetcher;
+pub use self::reflink::{reflink, CloneMethod};
+pub use self::stat::stat_many;
+pub use self::stat::stat_many_bounded;
+pub use self::stat::try_exists;
+pub use self::stat::DEFAULT_STAT_CONCURRENCY;
+pub use self::sync::sync_dir;
+#[cfg(all(unix, feature = "timer"))]
+pub use self::tail::{tail, TailReader, DEFAULT_TAIL_POLL_INTERVAL};
 
+#[cfg(all(unix, feature = "encryption"))]
+pub mod encrypted;
 #[cfg(feature = "mmap")]
 pub mod mmap;
+#[cfg(unix)]
+pub mod segments;
 
 pub use async_fs::*;
 
@@ -19,13 +62,15 @@ pub mod util {
 
     use super::File;
     use super::OpenOptions;
+    use crate::instrument::instrument_fs;
 
     /// open for write only
     pub async fn create<P>(path: P) -> Result<File, IoError>
     where
         P: AsRef<Path>,
     {
-        File::create(path.as_ref()).await
+        let file_path = path.as_ref();
+        instrument_fs("create", file_path, File::create(file_path)).await
     }
 
     /// open for only read
@@ -34,7 +79,7 @@ pub mod util {
         P: AsRef<Path>,
     {
         let file_path = path.as_ref();
-        File::open(file_path).await
+        instrument_fs("open", file_path, File::open(file_path)).await
     }
 
     /// open for read and write
@@ -46,7 +91,7 @@ pub mod util {
         let mut option = OpenOptions::new();
         option.read(true).write(true).create(true).append(false);
 
-        option.open(file_path).await
+        instrument_fs("open_read_write", file_path, option.open(file_path)).await
     }
 
     pub async fn open_read_append<P>(path: P) -> Result<File, IoError>
@@ -57,6 +102,6 @@ pub mod util {
         let mut option = OpenOptions::new();
         option.read(true).create(true).append(true);
 
-        option.open(file_path).await
+        instrument_fs("open_read_append", file_path, option.open(file_path)).await
     }
 }
@@ -0,0 +1,122 @@
+use std::io::Error as IoError;
+use std::sync::Arc;
+use std::sync::Weak;
+use std::time::Duration;
+
+use async_std::sync::Mutex;
+use async_std::sync::MutexGuard;
+use futures_lite::AsyncWriteExt;
+use log::debug;
+use log::trace;
+
+use super::BoundedFileSink;
+use super::BoundedFileSinkError;
+use crate::task::spawn_task;
+use crate::task::Task;
+use crate::timer::sleep;
+
+/// sink flushed every interval by task spawned with `spawn_task`.
+/// task is cancelled once sink is dropped, and holds only weak reference meanwhile
+pub struct FlushingSink {
+    inner: Arc<Mutex<BoundedFileSink>>,
+    interval: Duration,
+    task: Task<()>,
+}
+
+impl BoundedFileSink {
+    /// flush every `interval` from spawned task, instead of loop around sink
+    pub fn flush_every(self, interval: Duration) -> FlushingSink {
+        let inner = Arc::new(Mutex::new(self));
+        let task = spawn_task(flush_loop(Arc::downgrade(&inner), interval));
+        FlushingSink {
+            inner,
+            interval,
+            task,
+        }
+    }
+}
+
+impl Drop for FlushingSink {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn flush_loop(sink: Weak<Mutex<BoundedFileSink>>, interval: Duration) {
+    loop {
+        sleep(interval).await;
+        let sink = match sink.upgrade() {
+            Some(sink) => sink,
+            None => break,
+        };
+        let mut sink = sink.lock().await;
+        if sink.get_flushed_len() == sink.get_current_len() {
+            continue;
+        }
+        match sink.flush().await {
+            Ok(()) => trace!("periodic flush at len: {}", sink.get_flushed_len()),
+            // writer sees error again on its own flush
+            Err(err) => debug!(
+                "periodic flush of {} failed: {}",
+                sink.get_path().display(),
+                err
+            ),
+        }
+    }
+    trace!("periodic flush stopped");
+}
+
+impl FlushingSink {
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// exclusive access to sink, periodic flush waits while guard is held
+    pub async fn lock(&self) -> MutexGuard<'_, BoundedFileSink> {
+        self.inner.lock().await
+    }
+
+    /// write all of `buf`, fails with `MaxLenReached` before writing if it doesn't fit
+    pub async fn write_all(&self, buf: &[u8]) -> Result<(), BoundedFileSinkError> {
+        let mut sink = self.inner.lock().await;
+        if !sink.can_be_appended(buf.len() as u64) {
+            return Err(BoundedFileSinkError::MaxLenReached);
+        }
+        sink.write_all(buf).await?;
+        Ok(())
+    }
+
+    /// flush now, without waiting for next tick
+    pub async fn flush(&self) -> Result<(), IoError> {
+        self.inner.lock().await.flush().await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::time::Duration;
+
+    use crate::fs::BoundedFileOption;
+    use crate::fs::BoundedFileSink;
+    use crate::fs::BoundedFileSinkError;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    #[test_async]
+    async fn test_flush_every() -> Result<(), BoundedFileSinkError> {
+        let path = temp_dir().join("file_test_flush_every");
+        let _ = std::fs::remove_file(&path);
+        let sink = BoundedFileSink::create(&path, BoundedFileOption::default())
+            .await?
+            .flush_every(Duration::from_millis(50));
+        sink.write_all(b"record").await?;
+        assert_eq!(sink.lock().await.get_flushed_len(), 0);
+
+        sleep(Duration::from_millis(200)).await;
+        assert_eq!(sink.lock().await.get_flushed_len(), 6);
+        assert_eq!(std::fs::read(&path)?, b"record");
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::VecDeque;
+use std::fs::File as StdFile;
+use std::io::Error as IoError;
+use std::os::unix::fs::FileExt;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use blocking::unblock;
+use blocking::Task;
+use futures_lite::Future;
+use futures_lite::Stream;
+use log::trace;
+
+const DEFAULT_DEPTH: usize = 4;
+
+/// read file sequentially, keeping up to `depth` chunks read ahead of consumer.
+/// reads run on blocking threads whether or not stream is polled, so disk latency
+/// overlaps with consumer such as network send of previous chunk
+pub struct Prefetcher {
+    file: Arc<StdFile>,
+    next_read: u64,
+    end: u64,
+    chunk_size: usize,
+    depth: usize,
+    in_flight: VecDeque<Task<Result<Vec<u8>, IoError>>>,
+}
+
+impl Prefetcher {
+    /// prefetch whole file
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        let path = path.as_ref().to_owned();
+        let file = unblock(move || StdFile::open(path)).await?;
+        let len = file.metadata()?.len();
+        Ok(Self::new(file, 0, len))
+    }
+
+    /// prefetch `len` bytes from `position`
+    pub fn new(file: StdFile, position: u64, len: u64) -> Self {
+        let prefetcher = Self {
+            file: Arc::new(file),
+            next_read: position,
+            end: position + len,
+            chunk_size: crate::Runtime::current().file_chunk_size(),
+            depth: DEFAULT_DEPTH,
+            in_flight: VecDeque::new(),
+        };
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        prefetcher.advise(libc::POSIX_FADV_SEQUENTIAL, position, len);
+        prefetcher
+    }
+
+    /// bytes of each chunk, last one may be shorter
+    pub fn chunk_size(mut self, chunk_size: usize) -> Self {
+        self.chunk_size = chunk_size.max(1);
+        self
+    }
+
+    /// chunks read ahead of consumer
+    pub fn depth(mut self, depth: usize) -> Self {
+        self.depth = depth.max(1);
+        self
+    }
+
+    /// hint kernel of access pattern, failure only loses hint
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn advise(&self, advice: libc::c_int, position: u64, len: u64) {
+        use std::os::unix::io::AsRawFd;
+
+        let result = unsafe {
+            libc::posix_fadvise(
+                self.file.as_raw_fd(),
+                position as libc::off_t,
+                len as libc::off_t,
+                advice,
+            )
+        };
+        if result != 0 {
+            trace!("fadvise failed: {}", IoError::from_raw_os_error(result));
+        }
+    }
+
+    /// start reads until `depth` chunks are in flight
+    fn fill(&mut self) {
+        while self.in_flight.len() < self.depth && self.next_read < self.end {
+            let position = self.next_read;
+            let len = (self.end - position).min(self.chunk_size as u64) as usize;
+            self.next_read += len as u64;
+            trace!("prefetching {} bytes at: {}", len, position);
+            let file = self.file.clone();
+            self.in_flight.push_back(unblock(move || {
+                let mut chunk = vec![0; len];
+                file.read_exact_at(&mut chunk, position)?;
+                Ok(chunk)
+            }));
+        }
+        // kernel can start on chunk which is read next, while queued reads are served
+        #[cfg(any(target_os = "linux", target_os = "android"))]
+        if self.next_read < self.end {
+            let len = (self.end - self.next_read).min(self.chunk_size as u64);
+            self.advise(libc::POSIX_FADV_WILLNEED, self.next_read, len);
+        }
+    }
+}
+
+impl Stream for Prefetcher {
+    type Item = Result<Vec<u8>, IoError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        this.fill();
+        let chunk = match this.in_flight.front_mut() {
+            Some(task) => futures_lite::ready!(Pin::new(task).poll(cx)),
+            None => return Poll::Ready(None),
+        };
+        this.in_flight.pop_front();
+        if chunk.is_err() {
+            // rest of file can't be read in order
+            this.in_flight.clear();
+            this.next_read = this.end;
+        } else {
+            this.fill();
+        }
+        Poll::Ready(Some(chunk))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::fs::File as StdFile;
+    use std::io::Error as IoError;
+    use std::io::Write;
+
+    use futures_lite::StreamExt;
+
+    use crate::test_async;
+
+    use super::Prefetcher;
+
+    #[test_async]
+    async fn test_prefetcher() -> Result<(), IoError> {
+        let path = temp_dir().join("file_test_prefetch");
+        let content: Vec<u8> = (0..100_000u32).map(|i| (i % 251) as u8).collect();
+        StdFile::create(&path)?.write_all(&content)?;
+
+        let chunks: Vec<Vec<u8>> = Prefetcher::open(&path)
+            .await?
+            .chunk_size(4096)
+            .depth(3)
+            .try_collect()
+            .await?;
+        assert_eq!(chunks.len(), 25);
+        assert!(chunks[..24].iter().all(|chunk| chunk.len() == 4096));
+        assert_eq!(chunks.concat(), content);
+
+        // range of file
+        let chunks: Vec<Vec<u8>> = Prefetcher::new(StdFile::open(&path)?, 10, 1000)
+            .chunk_size(300)
+            .try_collect()
+            .await?;
+        assert_eq!(chunks.len(), 4);
+        assert_eq!(chunks.concat(), &content[10..1010]);
+
+        // range past end of file fails
+        let mut past_end = Prefetcher::new(StdFile::open(&path)?, 99_000, 2000);
+        assert!(past_end.next().await.expect("chunk").is_err());
+        assert!(past_end.next().await.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,140 @@
+use std::io::Error as IoError;
+use std::path::Path;
+
+use blocking::unblock;
+use log::debug;
+
+/// how `reflink` created destination
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CloneMethod {
+    /// shares extents with source until either is written, instant regardless of size
+    Reflink,
+    /// filesystem can't clone, data was copied
+    Copy,
+}
+
+/// clone `src` to new file `dst`, with copy fallback where filesystem doesn't support
+/// clone, such as ext4 or across filesystems. fails if `dst` already exists
+pub async fn reflink<P, Q>(src: P, dst: Q) -> Result<CloneMethod, IoError>
+where
+    P: AsRef<Path>,
+    Q: AsRef<Path>,
+{
+    let src = src.as_ref().to_owned();
+    let dst = dst.as_ref().to_owned();
+    let method = unblock(move || reflink_blocking(&src, &dst)).await?;
+    debug!("cloned file by: {:?}", method);
+    Ok(method)
+}
+
+/// errors meaning clone isn't possible, rather than failure of filesystem
+#[cfg(any(target_os = "linux", target_os = "macos"))]
+fn clone_unsupported(err: &IoError) -> bool {
+    matches!(
+        err.raw_os_error(),
+        Some(libc::EOPNOTSUPP) | Some(libc::EXDEV) | Some(libc::EINVAL) | Some(libc::ENOTTY)
+    )
+}
+
+#[cfg(target_os = "linux")]
+fn reflink_blocking(src: &Path, dst: &Path) -> Result<CloneMethod, IoError> {
+    use std::os::unix::fs::OpenOptionsExt;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::io::AsRawFd;
+
+    // _IOW(0x94, 9, int)
+    const FICLONE: libc::c_ulong = 0x4004_9409;
+
+    let mut src_file = std::fs::File::open(src)?;
+    let mode = src_file.metadata()?.permissions().mode();
+    let mut dst_file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .mode(mode)
+        .open(dst)?;
+    if unsafe { libc::ioctl(dst_file.as_raw_fd(), FICLONE as _, src_file.as_raw_fd()) } == 0 {
+        return Ok(CloneMethod::Reflink);
+    }
+    let err = IoError::last_os_error();
+    if !clone_unsupported(&err) {
+        drop(dst_file);
+        let _ = std::fs::remove_file(dst);
+        return Err(err);
+    }
+    if let Err(err) = std::io::copy(&mut src_file, &mut dst_file) {
+        drop(dst_file);
+        let _ = std::fs::remove_file(dst);
+        return Err(err);
+    }
+    Ok(CloneMethod::Copy)
+}
+
+#[cfg(target_os = "macos")]
+fn reflink_blocking(src: &Path, dst: &Path) -> Result<CloneMethod, IoError> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let c_src = CString::new(src.as_os_str().as_bytes())?;
+    let c_dst = CString::new(dst.as_os_str().as_bytes())?;
+    if unsafe { libc::clonefile(c_src.as_ptr(), c_dst.as_ptr(), 0) } == 0 {
+        return Ok(CloneMethod::Reflink);
+    }
+    let err = IoError::last_os_error();
+    if !clone_unsupported(&err) {
+        return Err(err);
+    }
+    copy_new(src, dst)
+}
+
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn reflink_blocking(src: &Path, dst: &Path) -> Result<CloneMethod, IoError> {
+    copy_new(src, dst)
+}
+
+/// copy to `dst` which must not exist, like clone
+#[cfg(not(target_os = "linux"))]
+fn copy_new(src: &Path, dst: &Path) -> Result<CloneMethod, IoError> {
+    let mut src_file = std::fs::File::open(src)?;
+    let mut dst_file = std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(dst)?;
+    std::io::copy(&mut src_file, &mut dst_file)?;
+    dst_file.set_permissions(src_file.metadata()?.permissions())?;
+    Ok(CloneMethod::Copy)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use crate::test_async;
+
+    use super::reflink;
+
+    #[test_async]
+    async fn test_reflink() -> Result<(), IoError> {
+        let dir = temp_dir().join("fs_test_reflink");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let src = dir.join("src");
+        let dst = dir.join("dst");
+        let content: Vec<u8> = (0..100_000u32).map(|i| (i % 127) as u8).collect();
+        std::fs::write(&src, &content)?;
+
+        // clone or copy depending on filesystem of temp dir
+        reflink(&src, &dst).await?;
+        assert_eq!(std::fs::read(&dst)?, content);
+
+        // clone is independent of source
+        std::fs::write(&src, b"changed")?;
+        assert_eq!(std::fs::read(&dst)?, content);
+
+        let err = reflink(&src, &dst).await.expect_err("exists");
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+//! numbered segment files in one directory, appended thru `BoundedFileSink`.
+//!
+//! writer rolls to next segment once record doesn't fit in max segment size, and deletes
+//! old segments by retention. reader exposes records as slices by segment and offset,
+//! for zero copy send
+use std::collections::BTreeMap;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use futures_lite::AsyncWriteExt;
+use futures_lite::StreamExt;
+use log::debug;
+
+use super::util as file_util;
+use super::AsyncFileExtension;
+use super::BoundedFileOption;
+use super::BoundedFileSink;
+use super::BoundedFileSinkError;
+use super::File;
+use crate::file_slice::AsyncFileSlice;
+
+const SEGMENT_EXTENSION: &str = "segment";
+
+/// location of record, segment id and byte offset within segment
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct SegmentPosition {
+    pub segment: u64,
+    pub offset: u64,
+}
+
+/// segment files are deleted once either limit is exceeded, active segment is always kept
+#[derive(Debug, Clone, Default)]
+pub struct Retention {
+    /// total bytes of all segments
+    pub max_bytes: Option<u64>,
+    /// since segment was last written
+    pub max_age: Option<Duration>,
+}
+
+pub fn segment_path(dir: &Path, segment: u64) -> PathBuf {
+    dir.join(format!("{:020}.{}", segment, SEGMENT_EXTENSION))
+}
+
+/// ids and lengths of segments in `dir`, other files are ignored
+pub async fn list_segments(dir: &Path) -> Result<BTreeMap<u64, u64>, IoError> {
+    let mut ids = vec![];
+    let mut paths = vec![];
+    let mut entries = super::read_dir(dir).await?;
+    while let Some(entry) = entries.next().await {
+        let path = entry?.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some(SEGMENT_EXTENSION) {
+            continue;
+        }
+        let id = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.parse::<u64>().ok());
+        if let Some(id) = id {
+            ids.push(id);
+            paths.push(path);
+        }
+    }
+    let mut segments = BTreeMap::new();
+    for (id, metadata) in ids.into_iter().zip(super::stat_many(&paths).await) {
+        segments.insert(id, metadata?.len());
+    }
+    Ok(segments)
+}
+
+/// appends records to last segment, rolling to new one once it is full
+pub struct SegmentWriter {
+    dir: PathBuf,
+    max_segment_size: u64,
+    /// lengths of closed segments
+    closed: BTreeMap<u64, u64>,
+    active_id: u64,
+    active: BoundedFileSink,
+}
+
+impl SegmentWriter {
+    /// continue after last segment in `dir`, or start with segment 0
+    pub async fn open<P: AsRef<Path>>(dir: P, max_segment_size: u64) -> Result<Self, IoError> {
+        let dir = dir.as_ref().to_owned();
+        super::create_dir_all(&dir).await?;
+        let mut closed = list_segments(&dir).await?;
+        let active_id = match closed.keys().next_back() {
+            Some(id) => *id,
+            None => 0,
+        };
+        closed.remove(&active_id);
+        let active = Self::open_segment(&dir, active_id, max_segment_size).await?;
+        debug!(
+            "opened segments in: {}, active: {}",
+            dir.display(),
+            active_id
+        );
+        Ok(Self {
+            dir,
+            max_segment_size,
+            closed,
+            active_id,
+            active,
+        })
+    }
+
+    async fn open_segment(
+        dir: &Path,
+        id: u64,
+        max_segment_size: u64,
+    ) -> Result<BoundedFileSink, IoError> {
+        let option = BoundedFileOption {
+            max_len: Some(max_segment_size),
+        };
+        BoundedFileSink::open_append(segment_path(dir, id), option).await
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn active_segment(&self) -> u64 {
+        self.active_id
+    }
+
+    /// ids and lengths of all segments, including active one
+    pub fn segments(&self) -> BTreeMap<u64, u64> {
+        let mut segments = self.closed.clone();
+        segments.insert(self.active_id, self.active.get_current_len());
+        segments
+    }
+
+    /// append record, rolling first if it doesn't fit in active segment.
+    /// record larger than max segment size fails with `MaxLenReached`
+    pub async fn append(&mut self, record: &[u8]) -> Result<SegmentPosition, BoundedFileSinkError> {
+        let len = record.len() as u64;
+        if len > self.max_segment_size {
+            return Err(BoundedFileSinkError::MaxLenReached);
+        }
+        if !self.active.can_be_appended(len) {
+            self.roll().await?;
+        }
+        let offset = self.active.get_current_len();
+        self.active.write_all(record).await?;
+        Ok(SegmentPosition {
+            segment: self.active_id,
+            offset,
+        })
+    }
+
+    /// close active segment and start next one
+    pub async fn roll(&mut self) -> Result<(), IoError> {
+        self.active.flush().await?;
+        let next_id = self.active_id + 1;
+        let next = Self::open_segment(&self.dir, next_id, self.max_segment_size).await?;
+        let closed = std::mem::replace(&mut self.active, next);
+        self.closed.insert(self.active_id, closed.get_current_len());
+        debug!("rolled segment {} to {}", self.active_id, next_id);
+        self.active_id = next_id;
+        Ok(())
+    }
+
+    pub async fn flush(&mut self) -> Result<(), IoError> {
+        self.active.flush().await
+    }
+
+    /// delete oldest closed segments beyond retention, returns deleted ids
+    pub async fn enforce_retention(&mut self, retention: &Retention) -> Result<Vec<u64>, IoError> {
+        let mut total: u64 = self.active.get_current_len() + self.closed.values().sum::<u64>();
+        let now = SystemTime::now();
+        let mut deleted = vec![];
+        for (id, len) in self.closed.clone() {
+            let path = segment_path(&self.dir, id);
+            let over_size = retention.max_bytes.is_some_and(|max| total > max);
+            let expired = match retention.max_age {
+                Some(max_age) => {
+                    let modified = super::metadata(&path).await?.modified()?;
+                    now.duration_since(modified).unwrap_or_default() > max_age
+                }
+                None => false,
+            };
+            if !over_size && !expired {
+                // segments are ordered by age, newer ones are kept too
+                break;
+            }
+            super::remove_file(&path).await?;
+            self.closed.remove(&id);
+            total -= len;
+            deleted.push(id);
+        }
+        if !deleted.is_empty() {
+            debug!("deleted segments by retention: {:?}", deleted);
+        }
+        Ok(deleted)
+    }
+}
+
+/// reads segments of directory written by `SegmentWriter`, files are opened once and kept
+pub struct SegmentReader {
+    dir: PathBuf,
+    files: BTreeMap<u64, File>,
+}
+
+impl SegmentReader {
+    pub async fn open<P: AsRef<Path>>(dir: P) -> Result<Self, IoError> {
+        let mut reader = Self {
+            dir: dir.as_ref().to_owned(),
+            files: BTreeMap::new(),
+        };
+        reader.refresh().await?;
+        Ok(reader)
+    }
+
+    /// pick up segments created and forget ones deleted since last refresh
+    pub async fn refresh(&mut self) -> Result<(), IoError> {
+        let segments = list_segments(&self.dir).await?;
+        self.files.retain(|id, _| segments.contains_key(id));
+        for id in segments.keys() {
+            if !self.files.contains_key(id) {
+                let file = file_util::open(segment_path(&self.dir, *id)).await?;
+                self.files.insert(*id, file);
+            }
+        }
+        Ok(())
+    }
+
+    /// ids of known segments, oldest first
+    pub fn segments(&self) -> Vec<u64> {
+        self.files.keys().copied().collect()
+    }
+
+    /// slice of `len` bytes at position, or to end of segment if len is none.
+    /// slice is valid while reader keeps segment open, punched range fails with `InvalidData`
+    pub async fn slice(
+        &self,
+        position: SegmentPosition,
+        len: Option<u64>,
+    ) -> Result<AsyncFileSlice, IoError> {
+        let file = self.files.get(&position.segment).ok_or_else(|| {
+            IoError::new(
+                ErrorKind::NotFound,
+                format!("segment {} not found", position.segment),
+            )
+        })?;
+        let segment_len = file.metadata().await?.len();
+        let available = segment_len.saturating_sub(position.offset);
+        let len = len.unwrap_or(available);
+        if position.offset > segment_len || len > available {
+            return Err(IoError::new(
+                ErrorKind::UnexpectedEof,
+                format!(
+                    "{} bytes at {} exceed segment {} of {} bytes",
+                    len, position.offset, position.segment, segment_len
+                ),
+            ));
+        }
+        let slice = file.raw_slice(position.offset, len);
+        // head of segment may have been reclaimed with `punch_hole`
+        #[cfg(target_os = "linux")]
+        slice.ensure_allocated()?;
+        Ok(slice)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::os::unix::fs::FileExt;
+    use std::os::unix::io::FromRawFd;
+
+    use crate::fs::BoundedFileSinkError;
+    use crate::test_async;
+
+    use super::Retention;
+    use super::SegmentPosition;
+    use super::SegmentReader;
+    use super::SegmentWriter;
+
+    fn read_slice(slice: &crate::file_slice::AsyncFileSlice) -> Vec<u8> {
+        // fd stays owned by reader
+        let file = std::mem::ManuallyDrop::new(unsafe { std::fs::File::from_raw_fd(slice.fd()) });
+        let mut buf = vec![0; slice.len() as usize];
+        file.read_exact_at(&mut buf, slice.position())
+            .expect("read slice");
+        buf
+    }
+
+    #[test_async]
+    async fn test_segments() -> Result<(), BoundedFileSinkError> {
+        let dir = temp_dir().join("segments_test");
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let mut writer = SegmentWriter::open(&dir, 10).await?;
+        let first = writer.append(b"aaaa").await?;
+        let second = writer.append(b"bbbbbb").await?;
+        // doesn't fit in 10 bytes with previous ones
+        let third = writer.append(b"cc").await?;
+        assert_eq!(
+            first,
+            SegmentPosition {
+                segment: 0,
+                offset: 0
+            }
+        );
+        assert_eq!(
+            second,
+            SegmentPosition {
+                segment: 0,
+                offset: 4
+            }
+        );
+        assert_eq!(
+            third,
+            SegmentPosition {
+                segment: 1,
+                offset: 0
+            }
+        );
+        assert!(matches!(
+            writer.append(&[0; 11]).await,
+            Err(BoundedFileSinkError::MaxLenReached)
+        ));
+        writer.flush().await?;
+
+        // writer continues with last segment
+        drop(writer);
+        let mut writer = SegmentWriter::open(&dir, 10).await?;
+        assert_eq!(writer.active_segment(), 1);
+        assert_eq!(writer.append(b"dd").await?.offset, 2);
+        writer.flush().await?;
+
+        let reader = SegmentReader::open(&dir).await?;
+        assert_eq!(reader.segments(), vec![0, 1]);
+        let slice = reader.slice(second, Some(6)).await?;
+        assert_eq!(read_slice(&slice), b"bbbbbb");
+        let slice = reader.slice(third, None).await?;
+        assert_eq!(read_slice(&slice), b"ccdd");
+        assert!(reader.slice(third, Some(5)).await.is_err());
+
+        let retention = Retention {
+            max_bytes: Some(5),
+            max_age: None,
+        };
+        assert_eq!(writer.enforce_retention(&retention).await?, vec![0]);
+        assert_eq!(
+            writer.segments().keys().copied().collect::<Vec<_>>(),
+            vec![1]
+        );
+        let mut reader = reader;
+        reader.refresh().await?;
+        assert_eq!(reader.segments(), vec![1]);
+        Ok(())
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::VecDeque;
+use std::fs::Metadata;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+use blocking::unblock;
+use log::trace;
+
+/// stats run at once by `stat_many`
+pub const DEFAULT_STAT_CONCURRENCY: usize = 16;
+
+/// true if path exists, false if it doesn't. other errors, such as permission denied,
+/// are returned rather than read as missing. symlinks are followed
+pub async fn try_exists<P: AsRef<Path>>(path: P) -> Result<bool, IoError> {
+    match super::metadata(path).await {
+        Ok(_) => Ok(true),
+        Err(err) if err.kind() == ErrorKind::NotFound => Ok(false),
+        Err(err) => Err(err),
+    }
+}
+
+/// metadata for each path in same order, with `DEFAULT_STAT_CONCURRENCY` stats on blocking pool
+pub async fn stat_many<I, P>(paths: I) -> Vec<Result<Metadata, IoError>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    stat_many_bounded(paths, DEFAULT_STAT_CONCURRENCY).await
+}
+
+/// like `stat_many` with up to `concurrency` stats at once
+pub async fn stat_many_bounded<I, P>(paths: I, concurrency: usize) -> Vec<Result<Metadata, IoError>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let concurrency = concurrency.max(1);
+    let mut paths = paths.into_iter();
+    let mut in_flight = VecDeque::with_capacity(concurrency);
+    let mut results = vec![];
+    loop {
+        while in_flight.len() < concurrency {
+            match paths.next() {
+                Some(path) => {
+                    let path: PathBuf = path.as_ref().to_owned();
+                    in_flight.push_back(unblock(move || std::fs::metadata(path)));
+                }
+                None => break,
+            }
+        }
+        // results are awaited in order, later stats keep running meanwhile
+        match in_flight.pop_front() {
+            Some(task) => results.push(task.await),
+            None => break,
+        }
+    }
+    trace!("stat {} paths", results.len());
+    results
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use crate::test_async;
+
+    use super::stat_many_bounded;
+    use super::try_exists;
+
+    #[test_async]
+    async fn test_stat_many() -> Result<(), IoError> {
+        let dir = temp_dir().join("fs_test_stat_many");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let mut paths = vec![];
+        for i in 0..20 {
+            let path = dir.join(format!("{}.txt", i));
+            if i != 7 {
+                std::fs::write(&path, vec![0; i])?;
+            }
+            paths.push(path);
+        }
+
+        assert!(try_exists(&paths[0]).await?);
+        assert!(!try_exists(&paths[7]).await?);
+
+        let results = stat_many_bounded(&paths, 3).await;
+        assert_eq!(results.len(), 20);
+        for (i, result) in results.into_iter().enumerate() {
+            match result {
+                Ok(metadata) => assert_eq!(metadata.len(), i as u64),
+                Err(err) => {
+                    assert_eq!(i, 7);
+                    assert_eq!(err.kind(), ErrorKind::NotFound);
+                }
+            }
+        }
+        Ok(())
+    }
+}
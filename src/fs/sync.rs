@@ -0,0 +1,24 @@
+use std::io::Error as IoError;
+use std::path::Path;
+
+use blocking::unblock;
+use log::trace;
+
+/// fsync directory, so entries created, renamed or removed in it survive crash.
+/// fsync of file only covers its content, not its entry in directory.
+/// no op where directories can't be synced
+pub async fn sync_dir<P: AsRef<Path>>(path: P) -> Result<(), IoError> {
+    let path = path.as_ref().to_owned();
+    trace!("sync dir: {}", path.display());
+    unblock(move || sync_dir_blocking(&path)).await
+}
+
+#[cfg(unix)]
+fn sync_dir_blocking(path: &Path) -> Result<(), IoError> {
+    std::fs::File::open(path)?.sync_all()
+}
+
+#[cfg(not(unix))]
+fn sync_dir_blocking(_path: &Path) -> Result<(), IoError> {
+    Ok(())
+}
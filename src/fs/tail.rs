@@ -0,0 +1,189 @@
+//! follow file as it is appended, like `tail -F`.
+//!
+//! there is no directory watcher to build on, so path is polled once end of file is
+//! reached. file renamed away and recreated, as by log rotation, is read to end before
+//! new file is opened from start. truncated file is read again from start
+use std::fs::File as StdFile;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::fs::FileExt;
+use std::os::unix::fs::MetadataExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use blocking::unblock;
+use futures_lite::stream;
+use futures_lite::Stream;
+use log::debug;
+
+use crate::timer::sleep;
+
+pub const DEFAULT_TAIL_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const READ_SIZE: usize = 64 * 1024;
+
+/// bytes appended to file from where it was opened, surviving rotation
+pub struct TailReader {
+    path: PathBuf,
+    /// read at position, since buffered file would keep end of file it read ahead
+    file: Arc<StdFile>,
+    ino: u64,
+    position: u64,
+    poll_interval: Duration,
+}
+
+impl TailReader {
+    /// follow from current end of file
+    pub async fn open<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        let mut reader = Self::open_from_start(path).await?;
+        reader.position = reader.file.metadata()?.len();
+        Ok(reader)
+    }
+
+    /// follow from start of file, so existing content is read first
+    pub async fn open_from_start<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        let path = path.as_ref().to_owned();
+        let (file, ino) = open(&path).await?;
+        Ok(Self {
+            path,
+            file,
+            ino,
+            position: 0,
+            poll_interval: DEFAULT_TAIL_POLL_INTERVAL,
+        })
+    }
+
+    /// how often path is checked for appends and rotation once end is reached
+    pub fn poll_interval(mut self, poll_interval: Duration) -> Self {
+        self.poll_interval = poll_interval;
+        self
+    }
+
+    /// position in current file
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    /// read at position of current file, empty at end
+    async fn read_chunk(&mut self) -> Result<Vec<u8>, IoError> {
+        let file = self.file.clone();
+        let position = self.position;
+        let chunk = unblock(move || {
+            let mut buf = vec![0; READ_SIZE];
+            let n = file.read_at(&mut buf, position)?;
+            buf.truncate(n);
+            Ok(buf) as Result<Vec<u8>, IoError>
+        })
+        .await?;
+        self.position += chunk.len() as u64;
+        Ok(chunk)
+    }
+
+    /// wait for bytes appended
+    pub async fn next_chunk(&mut self) -> Result<Vec<u8>, IoError> {
+        loop {
+            let chunk = self.read_chunk().await?;
+            if !chunk.is_empty() {
+                return Ok(chunk);
+            }
+            // at end of current file, which may no longer be at path
+            match super::metadata(&self.path).await {
+                Ok(metadata) if metadata.ino() != self.ino => {
+                    // writer may have appended to old file right before rotating
+                    let chunk = self.read_chunk().await?;
+                    if !chunk.is_empty() {
+                        return Ok(chunk);
+                    }
+                    debug!("{} was rotated, reopening", self.path.display());
+                    let (file, ino) = open(&self.path).await?;
+                    self.file = file;
+                    self.ino = ino;
+                    self.position = 0;
+                    continue;
+                }
+                Ok(metadata) if metadata.len() < self.position => {
+                    debug!("{} was truncated, reading from start", self.path.display());
+                    self.position = 0;
+                    continue;
+                }
+                Ok(_) => {}
+                // renamed away and not created again yet
+                Err(err) if err.kind() == ErrorKind::NotFound => {}
+                Err(err) => return Err(err),
+            }
+            sleep(self.poll_interval).await;
+        }
+    }
+
+    /// stream of appended bytes, read errors are yielded and following continues
+    pub fn into_stream(self) -> impl Stream<Item = Result<Vec<u8>, IoError>> {
+        stream::unfold(self, |mut reader| async move {
+            let chunk = reader.next_chunk().await;
+            Some((chunk, reader))
+        })
+    }
+}
+
+async fn open(path: &Path) -> Result<(Arc<StdFile>, u64), IoError> {
+    let path = path.to_owned();
+    let file = unblock(move || StdFile::open(path)).await?;
+    let ino = file.metadata()?.ino();
+    Ok((Arc::new(file), ino))
+}
+
+/// stream of bytes appended to file from now on
+pub async fn tail<P: AsRef<Path>>(
+    path: P,
+) -> Result<impl Stream<Item = Result<Vec<u8>, IoError>>, IoError> {
+    Ok(TailReader::open(path).await?.into_stream())
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::io::Write;
+    use std::time::Duration;
+
+    use crate::test_async;
+
+    use super::TailReader;
+
+    fn append(path: &std::path::Path, data: &[u8]) -> Result<(), IoError> {
+        std::fs::OpenOptions::new()
+            .append(true)
+            .create(true)
+            .open(path)?
+            .write_all(data)
+    }
+
+    #[test_async]
+    async fn test_tail() -> Result<(), IoError> {
+        let dir = temp_dir().join("fs_test_tail");
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join("app.log");
+        append(&path, b"before\n")?;
+
+        let mut reader = TailReader::open(&path)
+            .await?
+            .poll_interval(Duration::from_millis(10));
+        append(&path, b"first\n")?;
+        assert_eq!(reader.next_chunk().await?, b"first\n");
+
+        // rest of rotated file is read before new one
+        append(&path, b"last\n")?;
+        std::fs::rename(&path, dir.join("app.log.1"))?;
+        append(&path, b"rotated\n")?;
+        assert_eq!(reader.next_chunk().await?, b"last\n");
+        assert_eq!(reader.next_chunk().await?, b"rotated\n");
+
+        std::fs::write(&path, b"")?;
+        append(&path, b"new\n")?;
+        assert_eq!(reader.next_chunk().await?, b"new\n");
+        Ok(())
+    }
+}
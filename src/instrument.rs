@@ -0,0 +1,199 @@
+//! tracing spans and events for connection setup, streams and fs.
+//! only emitted with `instrument` feature, otherwise spans are disabled and events are skipped.
+//! with `metrics` feature, same hooks also update `crate::metrics`.
+//! handshake is also reported to event listener of connect in progress
+#[cfg(feature = "net")]
+pub(crate) use connect::*;
+
+#[cfg(feature = "net")]
+mod connect {
+    use std::future::Future;
+    use std::net::SocketAddr;
+    use std::time::Instant;
+
+    use tracing::Instrument;
+    use tracing::Span;
+
+    use crate::net::ConnectorError;
+    use crate::net::TcpStream;
+
+    /// span covering one connect call, peer is recorded once tcp is connected.
+    /// with `task` feature, request id and other values of task context are recorded too
+    pub(crate) fn connect_span(connector: &'static str, target: &str) -> Span {
+        #[cfg(feature = "instrument")]
+        {
+            let span = tracing::info_span!(
+                "connect",
+                connector,
+                target,
+                peer = tracing::field::Empty,
+                request_id = tracing::field::Empty,
+                context = tracing::field::Empty
+            );
+            #[cfg(feature = "task")]
+            if let Some(context) = crate::task::context::TaskContext::current() {
+                if let Some(request_id) = context.request_id() {
+                    span.record("request_id", request_id);
+                }
+                span.record("context", tracing::field::display(&context));
+            }
+            span
+        }
+        #[cfg(not(feature = "instrument"))]
+        {
+            let _ = (connector, target);
+            Span::none()
+        }
+    }
+
+    /// run connect inside its span, counting attempts and failures.
+    /// failure gets logical domain and elapsed time attached
+    pub(crate) async fn instrument_connect<F, T>(
+        connector: &'static str,
+        target: &str,
+        domain: &str,
+        connect: F,
+    ) -> Result<T, ConnectorError>
+    where
+        F: Future<Output = Result<T, ConnectorError>>,
+    {
+        #[cfg(feature = "metrics")]
+        crate::metrics::connect_attempted(connector);
+        let start = Instant::now();
+        let result = connect.instrument(connect_span(connector, target)).await;
+        result.map_err(|err| {
+            #[cfg(feature = "metrics")]
+            crate::metrics::connect_failed(connector);
+            err.with_domain(domain).with_elapsed(start.elapsed())
+        })
+    }
+
+    /// record resolved address on current connect span
+    pub(crate) fn record_peer(stream: &TcpStream) -> Option<SocketAddr> {
+        let peer = stream.peer_addr();
+        #[cfg(feature = "instrument")]
+        match &peer {
+            Ok(addr) => {
+                Span::current().record("peer", tracing::field::display(addr));
+                tracing::debug!(%addr, "tcp connected");
+            }
+            Err(err) => tracing::debug!(%err, "tcp connected, peer unknown"),
+        }
+        peer.ok()
+    }
+
+    /// start timing tls handshake
+    #[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+    pub(crate) fn handshake_start(domain: &str) -> Instant {
+        #[cfg(feature = "instrument")]
+        tracing::debug!(domain, "tls handshake start");
+        #[cfg(not(feature = "instrument"))]
+        let _ = domain;
+        Instant::now()
+    }
+
+    #[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+    pub(crate) fn handshake_done<T, E: std::fmt::Display>(
+        connector: &'static str,
+        start: Instant,
+        result: &Result<T, E>,
+    ) {
+        crate::net::handshake_done(start.elapsed());
+        #[cfg(feature = "metrics")]
+        crate::metrics::handshake_duration(connector, start.elapsed());
+        #[cfg(feature = "instrument")]
+        {
+            let elapsed_ms = start.elapsed().as_millis() as u64;
+            match result {
+                Ok(_) => tracing::info!(connector, elapsed_ms, "tls handshake done"),
+                Err(err) => tracing::info!(connector, elapsed_ms, %err, "tls handshake failed"),
+            }
+        }
+        #[cfg(not(feature = "instrument"))]
+        let _ = (connector, start, result);
+    }
+
+    /// bytes read from stream
+    #[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+    pub(crate) fn stream_read(stream: &'static str, len: usize) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::bytes_read(stream, len);
+        #[cfg(feature = "instrument")]
+        tracing::trace!(stream, len, "stream read");
+        #[cfg(not(feature = "instrument"))]
+        let _ = (stream, len);
+    }
+
+    /// bytes written to stream
+    #[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+    pub(crate) fn stream_write(stream: &'static str, len: usize) {
+        #[cfg(feature = "metrics")]
+        crate::metrics::bytes_written(stream, len);
+        #[cfg(feature = "instrument")]
+        tracing::trace!(stream, len, "stream write");
+        #[cfg(not(feature = "instrument"))]
+        let _ = (stream, len);
+    }
+}
+
+#[cfg(feature = "fs")]
+pub(crate) use fs::*;
+
+#[cfg(feature = "fs")]
+mod fs {
+    use std::future::Future;
+    use std::io::Error as IoError;
+    use std::path::Path;
+
+    use tracing::Instrument;
+    use tracing::Span;
+
+    /// span covering file open
+    fn fs_span(op: &'static str, path: &Path) -> Span {
+        #[cfg(feature = "instrument")]
+        {
+            tracing::debug_span!("fs", op, path = %path.display())
+        }
+        #[cfg(not(feature = "instrument"))]
+        {
+            let _ = (op, path);
+            Span::none()
+        }
+    }
+
+    /// run file open inside its span
+    pub(crate) async fn instrument_fs<F, T>(
+        op: &'static str,
+        path: &Path,
+        open: F,
+    ) -> Result<T, IoError>
+    where
+        F: Future<Output = Result<T, IoError>>,
+    {
+        let result = open.instrument(fs_span(op, path)).await;
+        #[cfg(feature = "metrics")]
+        if result.is_ok() {
+            crate::metrics::file_opened();
+        }
+        result
+    }
+}
+
+#[cfg(all(test, feature = "instrument", feature = "net"))]
+mod test {
+
+    use tracing::subscriber::with_default;
+    use tracing_subscriber::fmt;
+
+    use super::connect_span;
+
+    #[test]
+    fn test_connect_span_enabled() {
+        let subscriber = fmt().with_max_level(tracing::Level::TRACE).finish();
+        with_default(subscriber, || {
+            let span = connect_span("tcp", "localhost:9092");
+            assert!(!span.is_disabled());
+            assert!(span.has_field("peer"));
+        });
+    }
+}
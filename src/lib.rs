@@ -1,20 +1,54 @@
+#[cfg(feature = "buf")]
+pub mod buf;
+
+#[cfg(any(feature = "task", feature = "sink"))]
+mod budget;
+mod instrument;
+
+#[cfg(feature = "tokio_compat")]
+pub mod compat;
+
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
 #[cfg(unix)]
 pub mod file_slice;
 
+#[cfg(all(unix, feature = "bench"))]
+pub mod bench;
+
 #[cfg(feature = "fs")]
 pub mod fs;
 
 #[cfg(feature = "io")]
 pub mod io;
 
+#[cfg(feature = "process")]
+pub mod process;
+
+pub mod runtime;
+pub use runtime::Runtime;
+
+#[cfg(all(unix, feature = "signal"))]
+pub mod signal;
+
+#[cfg(feature = "shutdown")]
+pub mod shutdown;
+
+#[cfg(feature = "sync")]
+pub mod sync;
+
 #[cfg(feature = "task")]
 pub mod task;
 
 #[cfg(feature = "timer")]
 pub mod timer;
 
+#[cfg(feature = "sink")]
+pub mod sink;
+
 #[cfg(any(test, feature = "fixture"))]
-mod test_util;
+pub mod test_util;
 
 #[cfg(any(test, feature = "fixture"))]
 pub use fluvio_test_derive::test_async;
@@ -0,0 +1,656 @@
+//! process wide metrics for connectors, streams and fs.
+//!
+//! metrics are updated by crate internally, `gather` renders them in prometheus text exposition
+//! format so they can be served from embedding service's metrics endpoint.
+//! counting can be disabled with `Runtime::builder().metrics(false)`.
+//!
+//! bytes of `AllTcpStream` are counted by stream kind. bytes of any connector's streams are
+//! counted by connector type once connector is wrapped in `MeteredConnector`
+use std::fmt::Write;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+
+const PREFIX: &str = "fluvio_future";
+
+/// connector labels, connectors with other labels are counted under "other"
+const CONNECTORS: [&str; 7] = [
+    "tcp",
+    "unix",
+    "vsock",
+    "tls_domain",
+    "tls_anonymous",
+    "tls_transport",
+    "other",
+];
+
+/// stream labels, by how stream is wrapped
+const STREAMS: [&str; 3] = ["tcp", "unix", "tls"];
+
+/// upper bounds of handshake duration buckets, in seconds
+const HANDSHAKE_BUCKETS: [f64; 11] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+pub struct Counter(AtomicU64);
+
+impl Counter {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: Counter = Counter(AtomicU64::new(0));
+
+    pub fn add(&self, value: u64) {
+        self.0.fetch_add(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+pub struct Gauge(AtomicU64);
+
+impl Gauge {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const ZERO: Gauge = Gauge(AtomicU64::new(0));
+
+    pub fn set(&self, value: u64) {
+        self.0.store(value, Ordering::Relaxed);
+    }
+
+    /// add one, returning new value
+    #[cfg(all(unix, feature = "net"))]
+    fn increment(&self) -> u64 {
+        self.0.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    #[cfg(all(unix, feature = "net"))]
+    fn decrement(&self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    #[cfg(all(unix, feature = "net"))]
+    fn raise_to(&self, value: u64) {
+        self.0.fetch_max(value, Ordering::Relaxed);
+    }
+
+    pub fn get(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// histogram with fixed buckets, sum is kept in micro seconds
+pub struct Histogram {
+    bounds: &'static [f64],
+    buckets: [Counter; HANDSHAKE_BUCKETS.len()],
+    count: Counter,
+    sum_micros: Counter,
+}
+
+impl Histogram {
+    #[allow(clippy::declare_interior_mutable_const)]
+    const HANDSHAKE: Histogram = Histogram {
+        bounds: &HANDSHAKE_BUCKETS,
+        buckets: [Counter::ZERO; HANDSHAKE_BUCKETS.len()],
+        count: Counter::ZERO,
+        sum_micros: Counter::ZERO,
+    };
+
+    pub fn observe(&self, duration: Duration) {
+        let seconds = duration.as_secs_f64();
+        for (bound, bucket) in self.bounds.iter().zip(self.buckets.iter()) {
+            if seconds <= *bound {
+                bucket.add(1);
+            }
+        }
+        self.count.add(1);
+        self.sum_micros.add(duration.as_micros() as u64);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count.get()
+    }
+
+    pub fn sum(&self) -> Duration {
+        Duration::from_micros(self.sum_micros.get())
+    }
+}
+
+pub struct Metrics {
+    connects_attempted: [Counter; CONNECTORS.len()],
+    connects_failed: [Counter; CONNECTORS.len()],
+    handshake_duration: [Histogram; CONNECTORS.len()],
+    bytes_read: [Counter; STREAMS.len()],
+    bytes_written: [Counter; STREAMS.len()],
+    connector_bytes_read: [Counter; CONNECTORS.len()],
+    connector_bytes_written: [Counter; CONNECTORS.len()],
+    files_opened: Counter,
+    certs_expiring: Gauge,
+    connections_active: Gauge,
+    connections_peak: Gauge,
+}
+
+static METRICS: Metrics = Metrics {
+    connects_attempted: [Counter::ZERO; CONNECTORS.len()],
+    connects_failed: [Counter::ZERO; CONNECTORS.len()],
+    handshake_duration: [Histogram::HANDSHAKE; CONNECTORS.len()],
+    bytes_read: [Counter::ZERO; STREAMS.len()],
+    bytes_written: [Counter::ZERO; STREAMS.len()],
+    connector_bytes_read: [Counter::ZERO; CONNECTORS.len()],
+    connector_bytes_written: [Counter::ZERO; CONNECTORS.len()],
+    files_opened: Counter::ZERO,
+    certs_expiring: Gauge::ZERO,
+    connections_active: Gauge::ZERO,
+    connections_peak: Gauge::ZERO,
+};
+
+/// metrics registry for this process
+pub fn global() -> &'static Metrics {
+    &METRICS
+}
+
+/// render all metrics in prometheus text format
+pub fn gather() -> String {
+    METRICS.gather()
+}
+
+fn label_index(labels: &[&str], label: &str) -> Option<usize> {
+    labels.iter().position(|l| *l == label)
+}
+
+/// index of connector label, unknown labels fall back to "other"
+fn connector_index(connector: &str) -> usize {
+    label_index(&CONNECTORS, connector).unwrap_or(CONNECTORS.len() - 1)
+}
+
+impl Metrics {
+    pub fn connects_attempted(&self, connector: &str) -> u64 {
+        self.connects_attempted[connector_index(connector)].get()
+    }
+
+    pub fn connects_failed(&self, connector: &str) -> u64 {
+        self.connects_failed[connector_index(connector)].get()
+    }
+
+    pub fn handshake_duration(&self, connector: &str) -> Option<&Histogram> {
+        Some(&self.handshake_duration[connector_index(connector)])
+    }
+
+    pub fn bytes_read(&self, stream: &str) -> u64 {
+        label_index(&STREAMS, stream).map_or(0, |i| self.bytes_read[i].get())
+    }
+
+    pub fn bytes_written(&self, stream: &str) -> u64 {
+        label_index(&STREAMS, stream).map_or(0, |i| self.bytes_written[i].get())
+    }
+
+    /// bytes read from streams of `MeteredConnector` with this connector label
+    pub fn connector_bytes_read(&self, connector: &str) -> u64 {
+        self.connector_bytes_read[connector_index(connector)].get()
+    }
+
+    pub fn connector_bytes_written(&self, connector: &str) -> u64 {
+        self.connector_bytes_written[connector_index(connector)].get()
+    }
+
+    pub fn files_opened(&self) -> u64 {
+        self.files_opened.get()
+    }
+
+    /// certificates expired or about to expire at last expiry check
+    pub fn certs_expiring(&self) -> u64 {
+        self.certs_expiring.get()
+    }
+
+    /// connections open on all `LimitedListener`
+    pub fn connections_active(&self) -> u64 {
+        self.connections_active.get()
+    }
+
+    /// most connections open on all `LimitedListener` at same time
+    pub fn connections_peak(&self) -> u64 {
+        self.connections_peak.get()
+    }
+
+    pub fn gather(&self) -> String {
+        let mut out = String::new();
+
+        write_counters(
+            &mut out,
+            "connects_attempted_total",
+            "connect attempts",
+            "connector",
+            &CONNECTORS,
+            &self.connects_attempted,
+        );
+        write_counters(
+            &mut out,
+            "connects_failed_total",
+            "failed connect attempts",
+            "connector",
+            &CONNECTORS,
+            &self.connects_failed,
+        );
+
+        let name = format!("{}_tls_handshake_seconds", PREFIX);
+        let _ = writeln!(out, "# HELP {} tls handshake duration", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for (connector, histogram) in CONNECTORS.iter().zip(self.handshake_duration.iter()) {
+            for (bound, bucket) in histogram.bounds.iter().zip(histogram.buckets.iter()) {
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{connector=\"{}\",le=\"{}\"}} {}",
+                    name,
+                    connector,
+                    bound,
+                    bucket.get()
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{connector=\"{}\",le=\"+Inf\"}} {}",
+                name,
+                connector,
+                histogram.count()
+            );
+            let _ = writeln!(
+                out,
+                "{}_sum{{connector=\"{}\"}} {}",
+                name,
+                connector,
+                histogram.sum().as_secs_f64()
+            );
+            let _ = writeln!(
+                out,
+                "{}_count{{connector=\"{}\"}} {}",
+                name,
+                connector,
+                histogram.count()
+            );
+        }
+
+        write_counters(
+            &mut out,
+            "bytes_read_total",
+            "bytes read from streams",
+            "stream",
+            &STREAMS,
+            &self.bytes_read,
+        );
+        write_counters(
+            &mut out,
+            "bytes_written_total",
+            "bytes written to streams",
+            "stream",
+            &STREAMS,
+            &self.bytes_written,
+        );
+        write_counters(
+            &mut out,
+            "connector_bytes_read_total",
+            "bytes read from streams of metered connectors",
+            "connector",
+            &CONNECTORS,
+            &self.connector_bytes_read,
+        );
+        write_counters(
+            &mut out,
+            "connector_bytes_written_total",
+            "bytes written to streams of metered connectors",
+            "connector",
+            &CONNECTORS,
+            &self.connector_bytes_written,
+        );
+
+        let name = format!("{}_files_opened_total", PREFIX);
+        let _ = writeln!(out, "# HELP {} files opened thru fs module", name);
+        let _ = writeln!(out, "# TYPE {} counter", name);
+        let _ = writeln!(out, "{} {}", name, self.files_opened.get());
+
+        let name = format!("{}_certs_expiring", PREFIX);
+        let _ = writeln!(
+            out,
+            "# HELP {} certificates expired or about to expire",
+            name
+        );
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, self.certs_expiring.get());
+
+        let name = format!("{}_connections_active", PREFIX);
+        let _ = writeln!(out, "# HELP {} connections open on listeners", name);
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, self.connections_active.get());
+
+        let name = format!("{}_connections_peak", PREFIX);
+        let _ = writeln!(
+            out,
+            "# HELP {} most connections open on listeners at same time",
+            name
+        );
+        let _ = writeln!(out, "# TYPE {} gauge", name);
+        let _ = writeln!(out, "{} {}", name, self.connections_peak.get());
+
+        if let Some(open_fds) = open_fds() {
+            let name = format!("{}_open_fds", PREFIX);
+            let _ = writeln!(out, "# HELP {} open file descriptors of process", name);
+            let _ = writeln!(out, "# TYPE {} gauge", name);
+            let _ = writeln!(out, "{} {}", name, open_fds);
+        }
+
+        out
+    }
+}
+
+fn write_counters(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    label: &str,
+    labels: &[&str],
+    counters: &[Counter],
+) {
+    let name = format!("{}_{}", PREFIX, name);
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for (value, counter) in labels.iter().zip(counters.iter()) {
+        let _ = writeln!(out, "{}{{{}=\"{}\"}} {}", name, label, value, counter.get());
+    }
+}
+
+/// number of open fds, only available where /proc is
+fn open_fds() -> Option<usize> {
+    std::fs::read_dir("/proc/self/fd")
+        .ok()
+        .map(|entries| entries.count())
+}
+
+pub(crate) fn connect_attempted(connector: &str) {
+    if !crate::runtime::metrics_enabled() {
+        return;
+    }
+    METRICS.connects_attempted[connector_index(connector)].add(1);
+}
+
+pub(crate) fn connect_failed(connector: &str) {
+    if !crate::runtime::metrics_enabled() {
+        return;
+    }
+    METRICS.connects_failed[connector_index(connector)].add(1);
+}
+
+#[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+pub(crate) fn handshake_duration(connector: &str, duration: Duration) {
+    if !crate::runtime::metrics_enabled() {
+        return;
+    }
+    METRICS.handshake_duration[connector_index(connector)].observe(duration);
+}
+
+#[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+pub(crate) fn bytes_read(stream: &str, len: usize) {
+    if !crate::runtime::metrics_enabled() {
+        return;
+    }
+    if let Some(i) = label_index(&STREAMS, stream) {
+        METRICS.bytes_read[i].add(len as u64);
+    }
+}
+
+#[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+pub(crate) fn bytes_written(stream: &str, len: usize) {
+    if !crate::runtime::metrics_enabled() {
+        return;
+    }
+    if let Some(i) = label_index(&STREAMS, stream) {
+        METRICS.bytes_written[i].add(len as u64);
+    }
+}
+
+#[cfg(all(unix, feature = "net"))]
+pub use metered::*;
+
+#[cfg(all(unix, feature = "net"))]
+mod metered {
+    use std::io::Error as IoError;
+    use std::os::unix::io::RawFd;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use async_trait::async_trait;
+    use futures_lite::{AsyncRead, AsyncWrite};
+
+    use super::connector_index;
+    use super::METRICS;
+    use crate::net::ConnectorError;
+    use crate::net::TcpDomainConnector;
+
+    /// count bytes of streams from connector under its type, such as "tls_domain".
+    /// label which isn't known connector type is counted under "other"
+    pub struct MeteredConnector<C> {
+        inner: C,
+        connector: &'static str,
+    }
+
+    impl<C> MeteredConnector<C> {
+        pub fn new(inner: C, connector: &'static str) -> Self {
+            Self { inner, connector }
+        }
+
+        pub fn get_ref(&self) -> &C {
+            &self.inner
+        }
+    }
+
+    #[async_trait]
+    impl<C> TcpDomainConnector for MeteredConnector<C>
+    where
+        C: TcpDomainConnector + Send + Sync,
+    {
+        type WrapperStream = MeteredStream<C::WrapperStream>;
+
+        async fn connect(
+            &self,
+            domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            let (stream, fd) = self.inner.connect(domain).await?;
+            Ok((
+                MeteredStream {
+                    inner: stream,
+                    index: connector_index(self.connector),
+                },
+                fd,
+            ))
+        }
+    }
+
+    /// stream of `MeteredConnector`
+    pub struct MeteredStream<S> {
+        inner: S,
+        index: usize,
+    }
+
+    impl<S> MeteredStream<S> {
+        pub fn get_ref(&self) -> &S {
+            &self.inner
+        }
+
+        pub fn get_mut(&mut self) -> &mut S {
+            &mut self.inner
+        }
+
+        pub fn into_inner(self) -> S {
+            self.inner
+        }
+    }
+
+    impl<S: AsyncRead + Unpin> AsyncRead for MeteredStream<S> {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize, IoError>> {
+            let this = self.get_mut();
+            let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+            if let Poll::Ready(Ok(len)) = &result {
+                if crate::runtime::metrics_enabled() {
+                    METRICS.connector_bytes_read[this.index].add(*len as u64);
+                }
+            }
+            result
+        }
+    }
+
+    impl<S: AsyncWrite + Unpin> AsyncWrite for MeteredStream<S> {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, IoError>> {
+            let this = self.get_mut();
+            let result = Pin::new(&mut this.inner).poll_write(cx, buf);
+            if let Poll::Ready(Ok(len)) = &result {
+                if crate::runtime::metrics_enabled() {
+                    METRICS.connector_bytes_written[this.index].add(*len as u64);
+                }
+            }
+            result
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            Pin::new(&mut self.get_mut().inner).poll_close(cx)
+        }
+    }
+}
+
+#[cfg(feature = "fs")]
+pub(crate) fn file_opened() {
+    if !crate::runtime::metrics_enabled() {
+        return;
+    }
+    METRICS.files_opened.add(1);
+}
+
+#[cfg(feature = "net")]
+pub(crate) fn certs_expiring(count: u64) {
+    if !crate::runtime::metrics_enabled() {
+        return;
+    }
+    METRICS.certs_expiring.set(count);
+}
+
+/// false if connection isn't counted, then its close must not be counted either
+#[cfg(all(unix, feature = "net"))]
+pub(crate) fn connection_opened() -> bool {
+    if !crate::runtime::metrics_enabled() {
+        return false;
+    }
+    let active = METRICS.connections_active.increment();
+    METRICS.connections_peak.raise_to(active);
+    true
+}
+
+/// only for connection counted by `connection_opened`
+#[cfg(all(unix, feature = "net"))]
+pub(crate) fn connection_closed() {
+    METRICS.connections_active.decrement();
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use super::Histogram;
+
+    #[test]
+    fn test_histogram_buckets() {
+        let histogram = Histogram::HANDSHAKE;
+        histogram.observe(Duration::from_millis(20));
+        histogram.observe(Duration::from_secs(20));
+        assert_eq!(histogram.count(), 2);
+        assert_eq!(histogram.buckets[1].get(), 0);
+        assert_eq!(histogram.buckets[2].get(), 1);
+        assert_eq!(histogram.buckets[10].get(), 1);
+        assert_eq!(histogram.sum(), Duration::from_millis(20020));
+    }
+
+    #[test]
+    fn test_gather() {
+        super::connect_attempted("tcp");
+        super::connect_failed("tls_domain");
+        super::connect_attempted("unknown");
+        super::connect_attempted("vsock");
+
+        let global = super::global();
+        assert!(global.connects_attempted("tcp") >= 1);
+        assert!(global.connects_failed("tls_domain") >= 1);
+        assert!(global.connects_attempted("vsock") >= 1);
+        assert!(global.connects_attempted("other") >= 1);
+
+        let text = super::gather();
+        assert!(text.contains("# TYPE fluvio_future_connects_attempted_total counter"));
+        assert!(text.contains("fluvio_future_connects_failed_total{connector=\"tls_anonymous\"}"));
+        assert!(text.contains(
+            "fluvio_future_tls_handshake_seconds_bucket{connector=\"tls_domain\",le=\"+Inf\"}"
+        ));
+        assert!(text.contains("fluvio_future_bytes_read_total{stream=\"tls\"}"));
+        assert!(text.contains("fluvio_future_connects_attempted_total{connector=\"unix\"}"));
+        assert!(
+            text.contains("fluvio_future_connector_bytes_read_total{connector=\"tls_transport\"}")
+        );
+        assert!(text.contains("# TYPE fluvio_future_connections_peak gauge"));
+        assert!(!text.contains("unknown"));
+    }
+
+    #[cfg(all(unix, feature = "net"))]
+    #[crate::test_async]
+    async fn test_metered_connector() -> Result<(), std::io::Error> {
+        use std::os::unix::io::RawFd;
+        use std::sync::Mutex;
+
+        use async_trait::async_trait;
+        use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+        use crate::net::duplex;
+        use crate::net::ConnectorError;
+        use crate::net::DuplexStream;
+        use crate::net::TcpDomainConnector;
+
+        use super::MeteredConnector;
+
+        /// connector handing out one end of pipe, keeping other end as peer
+        #[derive(Default)]
+        struct PipeConnector(Mutex<Option<DuplexStream>>);
+
+        #[async_trait]
+        impl TcpDomainConnector for PipeConnector {
+            type WrapperStream = DuplexStream;
+
+            async fn connect(
+                &self,
+                _domain: &str,
+            ) -> Result<(DuplexStream, RawFd), ConnectorError> {
+                let (stream, peer) = duplex(64);
+                *self.0.lock().unwrap() = Some(peer);
+                Ok((stream, -1))
+            }
+        }
+
+        let connector = MeteredConnector::new(PipeConnector::default(), "vsock");
+        let (mut stream, _) = connector.connect("pipe").await?;
+        let mut peer = connector.get_ref().0.lock().unwrap().take().expect("peer");
+        stream.write_all(b"hello").await?;
+        let mut buf = [0; 5];
+        peer.read_exact(&mut buf).await?;
+        peer.write_all(b"hi").await?;
+        stream.read_exact(&mut buf[..2]).await?;
+
+        let global = super::global();
+        assert_eq!(global.connector_bytes_written("vsock"), 5);
+        assert_eq!(global.connector_bytes_read("vsock"), 2);
+        Ok(())
+    }
+}
@@ -1,7 +1,6 @@
 use crate::net::TcpStream;
 
 pub use async_native_tls::TlsAcceptor;
-pub use async_native_tls::TlsConnector;
 pub use async_native_tls::TlsStream;
 
 // server both cliennt and server and same but use same pattern as rustls
@@ -10,12 +9,64 @@ pub type DefaultClientTlsStream = TlsStream<TcpStream>;
 
 pub use connector::*;
 pub use stream::*;
+pub use tls_connector::*;
+
+mod tls_connector {
+    use async_native_tls::Host;
+    use futures_lite::{AsyncRead, AsyncWrite};
+
+    use super::TlsStream;
+
+    /// native tls connector, tagged when built without certificate verification
+    /// so every connector made from it is refused by `AllDomainConnector` unless allowed
+    #[derive(Default)]
+    pub struct TlsConnector {
+        inner: async_native_tls::TlsConnector,
+        insecure: bool,
+    }
+
+    impl From<async_native_tls::TlsConnector> for TlsConnector {
+        fn from(inner: async_native_tls::TlsConnector) -> Self {
+            Self {
+                inner,
+                insecure: false,
+            }
+        }
+    }
+
+    impl TlsConnector {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub(crate) fn tagged(inner: async_native_tls::TlsConnector, insecure: bool) -> Self {
+            Self { inner, insecure }
+        }
+
+        /// true if connector was built without certificate or hostname verification
+        pub fn is_insecure(&self) -> bool {
+            self.insecure
+        }
+
+        pub async fn connect<S>(
+            &self,
+            host: impl Into<Host>,
+            stream: S,
+        ) -> async_native_tls::Result<TlsStream<S>>
+        where
+            S: AsyncRead + AsyncWrite + Unpin,
+        {
+            self.inner.connect(host, stream).await
+        }
+    }
+}
 
 mod connector {
 
     use std::io::Error as IoError;
     use std::io::ErrorKind;
 
+    use std::net::SocketAddr;
     use std::os::unix::io::AsRawFd;
     use std::os::unix::io::RawFd;
 
@@ -23,13 +74,28 @@ mod connector {
     use async_trait::async_trait;
     use log::debug;
 
+    use crate::instrument::handshake_done;
+    use crate::instrument::handshake_start;
+    use crate::instrument::instrument_connect;
+    use crate::instrument::record_peer;
+    use crate::net::server_name;
+    use crate::net::within_deadline;
+    use crate::net::ConnectorError;
     use crate::net::DefaultTcpDomainConnector;
+    use crate::net::EventConnector;
+    use crate::net::SharedEventListener;
+    use crate::net::TcpDomainAcceptor;
     use crate::net::TcpDomainConnector;
+    use crate::net::TransportConnector;
+    use crate::net::UnixDomainConnector;
 
     use super::AllTcpStream;
     use super::DefaultClientTlsStream;
+    use super::DefaultServerTlsStream;
     use super::TcpStream;
+    use super::TlsAcceptor;
     use super::TlsConnector;
+    use super::TlsStream;
 
     pub enum TlsError {
         Io(IoError),
@@ -48,12 +114,74 @@ mod connector {
         }
     }
 
+    /// native tls doesn't expose verify result separately, it is only part of message
+    fn handshake_error(err: NativeTlsError, peer: Option<SocketAddr>) -> ConnectorError {
+        let message = err.to_string();
+        let verify_result = if message.contains("certificate verify failed") {
+            Some(message)
+        } else {
+            None
+        };
+        let err = ConnectorError::tls_handshake(verify_result, err);
+        match peer {
+            Some(addr) => err.with_addr(addr),
+            None => err,
+        }
+    }
+
+    /// whether connector skips certificate verification, and if application allowed that
+    #[derive(Clone, Copy)]
+    struct Insecure {
+        tagged: bool,
+        allowed: bool,
+    }
+
+    impl Insecure {
+        fn of(connector: &TlsConnector) -> Self {
+            Self {
+                tagged: connector.is_insecure(),
+                allowed: false,
+            }
+        }
+
+        fn check(&self, domain: &str) -> Result<(), ConnectorError> {
+            match (self.tagged, self.allowed) {
+                (true, false) => Err(ConnectorError::InsecureNotAllowed),
+                (true, true) => {
+                    log::warn!("connecting to {} without certificate verification", domain);
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
     /// connect as anonymous client
-    pub struct TlsAnonymousConnector(TlsConnector);
+    pub struct TlsAnonymousConnector(TlsConnector, DefaultTcpDomainConnector, Insecure);
 
     impl From<TlsConnector> for TlsAnonymousConnector {
         fn from(connector: TlsConnector) -> Self {
-            Self(connector)
+            let insecure = Insecure::of(&connector);
+            Self(connector, DefaultTcpDomainConnector::new(), insecure)
+        }
+    }
+
+    impl TlsAnonymousConnector {
+        /// connector for tcp stream under tls, such as one with connect timeout
+        pub fn with_tcp(mut self, tcp: DefaultTcpDomainConnector) -> Self {
+            self.1 = tcp;
+            self
+        }
+
+        /// true if connector was built without certificate verification
+        pub fn is_insecure(&self) -> bool {
+            self.2.tagged
+        }
+
+        /// let `AllDomainConnector` use this even if it is insecure
+        pub fn allow_insecure(mut self) -> Self {
+            self.2.allowed = true;
+            self
         }
     }
 
@@ -61,27 +189,58 @@ mod connector {
     impl TcpDomainConnector for TlsAnonymousConnector {
         type WrapperStream = DefaultClientTlsStream;
 
-        async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), IoError> {
-            let tcp_stream = TcpStream::connect(domain).await?;
-            let fd = tcp_stream.as_raw_fd();
-            let connector = self.0.connect(domain, tcp_stream).await.map_err(|e| {
-                IoError::new(
-                    ErrorKind::ConnectionRefused,
-                    format!("failed to connect: {}", e),
-                )
-            })?;
-            Ok((connector, fd))
+        async fn connect(
+            &self,
+            domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            // address is host and port, only host is server name
+            let server_name = server_name(domain);
+            instrument_connect("tls_anonymous", domain, server_name, async {
+                let tcp_stream = self.1.connect_stream(domain).await?;
+                let peer = record_peer(&tcp_stream);
+                let fd = tcp_stream.as_raw_fd();
+                let start = handshake_start(server_name);
+                let result = within_deadline(self.0.connect(server_name, tcp_stream)).await?;
+                handshake_done("tls_anonymous", start, &result);
+                let connector = result.map_err(|err| handshake_error(err, peer))?;
+                Ok((connector, fd)) as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
         }
     }
 
     pub struct TlsDomainConnector {
         domain: String,
         connector: TlsConnector,
+        tcp: DefaultTcpDomainConnector,
+        insecure: Insecure,
     }
 
     impl TlsDomainConnector {
         pub fn new(connector: TlsConnector, domain: String) -> Self {
-            Self { domain, connector }
+            Self {
+                domain,
+                insecure: Insecure::of(&connector),
+                connector,
+                tcp: DefaultTcpDomainConnector::new(),
+            }
+        }
+
+        /// connector for tcp stream under tls, such as one with connect timeout
+        pub fn with_tcp(mut self, tcp: DefaultTcpDomainConnector) -> Self {
+            self.tcp = tcp;
+            self
+        }
+
+        /// true if connector was built without certificate verification
+        pub fn is_insecure(&self) -> bool {
+            self.insecure.tagged
+        }
+
+        /// let `AllDomainConnector` use this even if it is insecure
+        pub fn allow_insecure(mut self) -> Self {
+            self.insecure.allowed = true;
+            self
         }
     }
 
@@ -89,28 +248,81 @@ mod connector {
     impl TcpDomainConnector for TlsDomainConnector {
         type WrapperStream = DefaultClientTlsStream;
 
-        async fn connect(&self, addr: &str) -> Result<(Self::WrapperStream, RawFd), IoError> {
-            debug!("connect to tls addr: {}", addr);
-            let tcp_stream = TcpStream::connect(addr).await?;
-            let fd = tcp_stream.as_raw_fd();
+        async fn connect(
+            &self,
+            addr: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            instrument_connect("tls_domain", addr, &self.domain, async {
+                debug!("connect to tls addr: {}", addr);
+                let tcp_stream = self.tcp.connect_stream(addr).await?;
+                let peer = record_peer(&tcp_stream);
+                let fd = tcp_stream.as_raw_fd();
+
+                debug!("connect to tls domain: {}", self.domain);
+                let start = handshake_start(&self.domain);
+                let result =
+                    within_deadline(self.connector.connect(&self.domain, tcp_stream)).await?;
+                handshake_done("tls_domain", start, &result);
+                let connector = result.map_err(|err| handshake_error(err, peer))?;
+                Ok((connector, fd)) as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
+        }
+    }
+
+    /// tls over transport other than tcp, such as unix socket or in memory stream
+    pub struct TlsOverTransportConnector<T> {
+        transport: T,
+        connector: TlsConnector,
+        domain: Option<String>,
+    }
 
-            debug!("connect to tls domain: {}", self.domain);
-            let connector = self
-                .connector
-                .connect(&self.domain, tcp_stream)
-                .await
-                .map_err(|e| {
-                    IoError::new(
-                        ErrorKind::ConnectionRefused,
-                        format!("failed to connect: {}", e),
-                    )
-                })?;
-            Ok((connector, fd))
+    impl<T> TlsOverTransportConnector<T> {
+        /// connect tls using address passed to `connect` as domain
+        pub fn new(transport: T, connector: TlsConnector) -> Self {
+            Self {
+                transport,
+                connector,
+                domain: None,
+            }
+        }
+
+        /// domain for server name and certificate verification
+        pub fn domain(mut self, domain: impl Into<String>) -> Self {
+            self.domain = Some(domain.into());
+            self
+        }
+    }
+
+    #[async_trait]
+    impl<T: TransportConnector> TcpDomainConnector for TlsOverTransportConnector<T> {
+        type WrapperStream = TlsStream<T::WrapperStream>;
+
+        async fn connect(
+            &self,
+            addr: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            let domain = self.domain.as_deref().unwrap_or(addr);
+            instrument_connect("tls_transport", addr, domain, async {
+                let (stream, fd) = self.transport.connect(addr).await?;
+
+                debug!("connect to tls domain: {}", domain);
+                let start = handshake_start(domain);
+                let result = within_deadline(self.connector.connect(domain, stream)).await?;
+                handshake_done("tls_transport", start, &result);
+                let stream = result.map_err(|err| handshake_error(err, None))?;
+                Ok((stream, fd)) as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
         }
     }
 
+    /// connector of any transport, more may be added
+    #[non_exhaustive]
     pub enum AllDomainConnector {
         Tcp(DefaultTcpDomainConnector),
+        /// unix socket, domain is path or `@name` address of socket
+        Unix(UnixDomainConnector),
         TlsDomain(TlsDomainConnector),
         TlsAnonymous(TlsAnonymousConnector),
     }
@@ -126,6 +338,10 @@ mod connector {
             Self::Tcp(DefaultTcpDomainConnector::new())
         }
 
+        pub fn new_unix() -> Self {
+            Self::Unix(UnixDomainConnector::new())
+        }
+
         pub fn new_tls_domain(connector: TlsDomainConnector) -> Self {
             Self::TlsDomain(connector)
         }
@@ -133,30 +349,178 @@ mod connector {
         pub fn new_tls_anonymous(connector: TlsAnonymousConnector) -> Self {
             Self::TlsAnonymous(connector)
         }
+
+        /// report connection lifecycle events to listener
+        pub fn with_listener(self, listener: SharedEventListener) -> EventConnector<Self> {
+            EventConnector::new(self, listener)
+        }
+
+        /// use tls connector even if it was built without certificate verification,
+        /// otherwise connect fails with `ConnectorError::InsecureNotAllowed`
+        pub fn allow_insecure(self) -> Self {
+            match self {
+                Self::TlsDomain(connector) => Self::TlsDomain(connector.allow_insecure()),
+                Self::TlsAnonymous(connector) => Self::TlsAnonymous(connector.allow_insecure()),
+                tcp => tcp,
+            }
+        }
     }
 
     #[async_trait]
     impl TcpDomainConnector for AllDomainConnector {
         type WrapperStream = AllTcpStream;
 
-        async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), IoError> {
+        async fn connect(
+            &self,
+            domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
             match self {
                 Self::Tcp(connector) => {
                     let (stream, fd) = connector.connect(domain).await?;
                     Ok((AllTcpStream::tcp(stream), fd))
                 }
+                Self::Unix(connector) => {
+                    let (stream, fd) = connector.connect(domain).await?;
+                    Ok((AllTcpStream::unix(stream), fd))
+                }
 
                 Self::TlsDomain(connector) => {
+                    connector.insecure.check(domain)?;
                     let (stream, fd) = connector.connect(domain).await?;
                     Ok((AllTcpStream::tls(stream), fd))
                 }
                 Self::TlsAnonymous(connector) => {
+                    connector.2.check(domain)?;
                     let (stream, fd) = connector.connect(domain).await?;
                     Ok((AllTcpStream::tls(stream), fd))
                 }
             }
         }
     }
+
+    #[async_trait]
+    impl TcpDomainAcceptor for TlsAcceptor {
+        type WrapperStream = DefaultServerTlsStream;
+
+        async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+            TlsAcceptor::accept(self, stream).await.map_err(|e| {
+                IoError::new(
+                    ErrorKind::ConnectionRefused,
+                    format!("failed to accept: {}", e),
+                )
+            })
+        }
+    }
+}
+
+pub use dual_mode::*;
+
+mod dual_mode {
+
+    use std::io::Error as IoError;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use async_trait::async_trait;
+    use futures_lite::{AsyncRead, AsyncWrite};
+    use log::debug;
+
+    use crate::net::sniff_tls;
+    use crate::net::TcpDomainAcceptor;
+    use crate::net::TransportKind;
+
+    use super::DefaultServerTlsStream;
+    use super::TcpStream;
+    use super::TlsAcceptor;
+
+    /// accepted stream, tls if client started with handshake, otherwise plain tcp
+    #[allow(clippy::large_enum_variant)]
+    pub enum DualModeStream {
+        Tcp(TcpStream),
+        Tls(DefaultServerTlsStream),
+    }
+
+    impl DualModeStream {
+        pub fn transport_kind(&self) -> TransportKind {
+            match self {
+                Self::Tcp(_) => TransportKind::Tcp,
+                Self::Tls(_) => TransportKind::Tls,
+            }
+        }
+
+        pub fn is_tls(&self) -> bool {
+            matches!(self, Self::Tls(_))
+        }
+    }
+
+    impl AsyncRead for DualModeStream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize, IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+                Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+            }
+        }
+    }
+
+    impl AsyncWrite for DualModeStream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+                Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+                Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+            }
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_close(cx),
+                Self::Tls(stream) => Pin::new(stream).poll_close(cx),
+            }
+        }
+    }
+
+    /// serve tls and plaintext clients on one port, such as during migration to tls.
+    /// connection is tls if its first bytes are tls handshake, they are only peeked
+    #[derive(Clone)]
+    pub struct DualModeAcceptor {
+        tls: TlsAcceptor,
+    }
+
+    impl DualModeAcceptor {
+        pub fn new(tls: TlsAcceptor) -> Self {
+            Self { tls }
+        }
+    }
+
+    #[async_trait]
+    impl TcpDomainAcceptor for DualModeAcceptor {
+        type WrapperStream = DualModeStream;
+
+        async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+            if sniff_tls(&stream).await? {
+                debug!("client started tls handshake");
+                let stream = TcpDomainAcceptor::accept(&self.tls, stream).await?;
+                Ok(DualModeStream::Tls(stream))
+            } else {
+                debug!("plaintext client");
+                Ok(DualModeStream::Tcp(stream))
+            }
+        }
+    }
 }
 
 pub use cert::*;
@@ -271,60 +635,355 @@ mod cert {
     }
 }
 
+pub use generate::*;
+
+/// in memory certificates for tests and development, without fixture files
+mod generate {
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::net::IpAddr;
+    use std::time::SystemTime;
+    use std::time::UNIX_EPOCH;
+
+    use openssl::asn1::Asn1Time;
+    use openssl::bn::BigNum;
+    use openssl::bn::MsbOption;
+    use openssl::ec::EcGroup;
+    use openssl::ec::EcKey;
+    use openssl::error::ErrorStack;
+    use openssl::hash::MessageDigest;
+    use openssl::nid::Nid;
+    use openssl::x509::extension::BasicConstraints;
+    use openssl::x509::extension::KeyUsage;
+    use openssl::x509::extension::SubjectAlternativeName;
+    use openssl::x509::X509Builder;
+    use openssl::x509::X509Name;
+    use openssl::x509::X509NameRef;
+
+    use super::CertBuilder;
+    use super::Certificate;
+    use super::IdentityBuilder;
+    use super::PrivateKey;
+    use super::PrivateKeyBuilder;
+    use super::X509PemBuilder;
+
+    const VALIDITY_DAYS: u32 = 365;
+    /// tolerate clock skew between peers
+    const BACKDATE_SECS: u64 = 3600;
+
+    /// generated certificate and its ECDSA P-256 key
+    #[derive(Clone)]
+    pub struct CertKeyPair {
+        pub cert: Certificate,
+        pub key: PrivateKey,
+    }
+
+    impl CertKeyPair {
+        /// certificate for `ConnectorBuilder::add_root_certificate`
+        pub fn x509(&self) -> Result<X509PemBuilder, IoError> {
+            Ok(X509PemBuilder::new(
+                self.cert.to_pem().map_err(generate_error)?,
+            ))
+        }
+
+        /// identity for acceptor or client authentication
+        pub fn identity(&self) -> Result<IdentityBuilder, IoError> {
+            let key = self
+                .key
+                .private_key_to_pem_pkcs8()
+                .map_err(generate_error)?;
+            IdentityBuilder::from_x509(self.x509()?, PrivateKeyBuilder::new(key))
+        }
+    }
+
+    /// self signed certificate valid for `domains`, first domain is used as common name
+    pub fn generate_self_signed(domains: &[&str]) -> Result<CertKeyPair, IoError> {
+        let common_name = domains
+            .first()
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "no domain"))?;
+        let key = generate_key()?;
+        let name = name(common_name)?;
+        let cert = sign_cert(&key, &name, None, &name, &key, domains, false)?;
+        Ok(CertKeyPair { cert, key })
+    }
+
+    /// certificate authority issuing certificates, such as for server and client of test
+    pub fn generate_ca() -> Result<CertificateAuthority, IoError> {
+        let key = generate_key()?;
+        let name = name("fluvio test ca")?;
+        let cert = sign_cert(&key, &name, None, &name, &key, &[], true)?;
+        Ok(CertificateAuthority {
+            ca: CertKeyPair { cert, key },
+        })
+    }
+
+    pub struct CertificateAuthority {
+        ca: CertKeyPair,
+    }
+
+    impl CertificateAuthority {
+        /// certificate to trust as root
+        pub fn cert(&self) -> &Certificate {
+            &self.ca.cert
+        }
+
+        pub fn cert_key(&self) -> &CertKeyPair {
+            &self.ca
+        }
+
+        /// certificate for `domain` signed by this authority
+        pub fn issue_cert(&self, domain: &str) -> Result<CertKeyPair, IoError> {
+            let key = generate_key()?;
+            let subject = name(domain)?;
+            let cert = sign_cert(
+                &self.ca.key,
+                self.ca.cert.subject_name(),
+                Some(&self.ca.cert),
+                &subject,
+                &key,
+                &[domain],
+                false,
+            )?;
+            Ok(CertKeyPair { cert, key })
+        }
+    }
+
+    fn generate_error(err: ErrorStack) -> IoError {
+        IoError::new(
+            ErrorKind::InvalidData,
+            format!("certificate generation failed: {}", err),
+        )
+    }
+
+    fn generate_key() -> Result<PrivateKey, IoError> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).map_err(generate_error)?;
+        let key = EcKey::generate(&group).map_err(generate_error)?;
+        PrivateKey::from_ec_key(key).map_err(generate_error)
+    }
+
+    fn name(common_name: &str) -> Result<X509Name, IoError> {
+        let mut name = X509Name::builder().map_err(generate_error)?;
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name)
+            .map_err(generate_error)?;
+        Ok(name.build())
+    }
+
+    fn sign_cert(
+        issuer_key: &PrivateKey,
+        issuer: &X509NameRef,
+        issuer_cert: Option<&Certificate>,
+        subject: &X509NameRef,
+        key: &PrivateKey,
+        domains: &[&str],
+        ca: bool,
+    ) -> Result<Certificate, IoError> {
+        let build = || -> Result<Certificate, ErrorStack> {
+            let mut builder = X509Builder::new()?;
+            // v3
+            builder.set_version(2)?;
+            let mut serial = BigNum::new()?;
+            serial.rand(127, MsbOption::MAYBE_ZERO, false)?;
+            let serial = serial.to_asn1_integer()?;
+            builder.set_serial_number(&serial)?;
+            builder.set_issuer_name(issuer)?;
+            builder.set_subject_name(subject)?;
+            builder.set_pubkey(key)?;
+
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_secs())
+                .unwrap_or(0);
+            let not_before = Asn1Time::from_unix((now - BACKDATE_SECS) as _)?;
+            let not_after = Asn1Time::days_from_now(VALIDITY_DAYS)?;
+            builder.set_not_before(&not_before)?;
+            builder.set_not_after(&not_after)?;
+
+            if ca {
+                builder.append_extension(BasicConstraints::new().critical().ca().build()?)?;
+                builder.append_extension(
+                    KeyUsage::new()
+                        .critical()
+                        .key_cert_sign()
+                        .crl_sign()
+                        .build()?,
+                )?;
+            } else {
+                builder.append_extension(BasicConstraints::new().critical().build()?)?;
+                let mut names = SubjectAlternativeName::new();
+                for domain in domains {
+                    if domain.parse::<IpAddr>().is_ok() {
+                        names.ip(domain);
+                    } else {
+                        names.dns(domain);
+                    }
+                }
+                let names =
+                    names.build(&builder.x509v3_context(issuer_cert.map(|cert| &**cert), None))?;
+                builder.append_extension(names)?;
+            }
+
+            builder.sign(issuer_key, MessageDigest::sha256())?;
+            Ok(builder.build())
+        };
+        build().map_err(generate_error)
+    }
+}
+
 pub use builder::*;
 
 mod builder {
 
     use std::io::Error as IoError;
     use std::io::ErrorKind;
+    use std::time::Duration;
 
     use native_tls::Identity;
     use native_tls::TlsAcceptor as NativeTlsAcceptor;
 
+    use crate::net::CertExpiryMonitor;
+    use crate::net::TlsEnv;
+
+    use super::CertBuilder;
     use super::IdentityBuilder;
+    use super::PrivateKeyBuilder;
     use super::TlsAcceptor;
+    use super::TlsAnonymousConnector;
     use super::TlsConnector;
+    use super::TlsDomainConnector;
     use super::X509PemBuilder;
 
-    pub struct ConnectorBuilder(TlsConnector);
+    pub struct ConnectorBuilder {
+        connector: async_native_tls::TlsConnector,
+        /// der of root certificates added, kept for expiry monitoring
+        ca_certs: Vec<Vec<u8>>,
+        /// certificate or hostname verification is disabled
+        insecure: bool,
+    }
+
+    impl Default for ConnectorBuilder {
+        fn default() -> Self {
+            Self::new()
+        }
+    }
 
     impl ConnectorBuilder {
+        /// verify server with system roots, without client identity
+        pub fn new() -> Self {
+            Self::with_connector(async_native_tls::TlsConnector::new())
+        }
+
+        fn with_connector(connector: async_native_tls::TlsConnector) -> Self {
+            Self {
+                connector,
+                ca_certs: vec![],
+                insecure: false,
+            }
+        }
+
+        /// client identity from pkcs12 or x509 pem.
+        ///
+        /// identity in macOS keychain or windows certificate store can't be used,
+        /// native tls only accepts identity with exportable private key
         pub fn identity(builder: IdentityBuilder) -> Result<Self, IoError> {
             let identity = builder.build()?;
-            let connector = TlsConnector::new().identity(identity);
-            Ok(Self(connector))
+            Ok(Self::with_connector(
+                async_native_tls::TlsConnector::new().identity(identity),
+            ))
         }
 
+        /// builder from `<PREFIX>_CA_CERT`, `<PREFIX>_CLIENT_CERT`, `<PREFIX>_CLIENT_KEY`
+        /// and `<PREFIX>_INSECURE`, each certificate is either path of pem file or inline pem
+        pub fn from_env(prefix: &str) -> Result<Self, IoError> {
+            let env = TlsEnv::from_env(prefix)?;
+            let mut builder = match env.client {
+                Some((cert, key)) => Self::identity(IdentityBuilder::from_x509(
+                    X509PemBuilder::new(cert),
+                    PrivateKeyBuilder::new(key),
+                )?)?,
+                None => Self::new(),
+            };
+            if let Some(ca_cert) = env.ca_cert {
+                builder = builder.add_root_certificate(X509PemBuilder::new(ca_cert))?;
+            }
+            if env.insecure {
+                builder = builder.danger_accept_invalid_certs();
+            }
+            Ok(builder)
+        }
+
+        /// builder accepting any server certificate, see `danger_accept_invalid_certs`
         pub fn anonymous() -> Self {
-            let connector = TlsConnector::new()
+            Self::new().danger_accept_invalid_certs()
+        }
+
+        /// accept any server certificate and hostname, for development only.
+        /// built connector and every tls connector made from it are tagged insecure,
+        /// and `AllDomainConnector` refuses them unless `allow_insecure` is set
+        pub fn danger_accept_invalid_certs(mut self) -> Self {
+            log::warn!("tls certificate verification is disabled, connector is insecure");
+            self.connector = self
+                .connector
                 .danger_accept_invalid_certs(true)
                 .danger_accept_invalid_hostnames(true);
-            Self(connector)
+            self.insecure = true;
+            self
         }
 
+        #[deprecated(note = "use danger_accept_invalid_certs")]
         pub fn no_cert_verification(self) -> Self {
-            let connector = self.0.danger_accept_invalid_certs(true);
-            Self(connector)
+            self.danger_accept_invalid_certs()
         }
 
-        pub fn danger_accept_invalid_hostnames(self) -> Self {
-            let connector = self.0.danger_accept_invalid_hostnames(true);
-            Self(connector)
+        /// accept certificate issued for other hostname, chain is still verified.
+        /// connector is tagged insecure same as with `danger_accept_invalid_certs`
+        #[deprecated(note = "use danger_accept_invalid_certs to disable verification")]
+        pub fn danger_accept_invalid_hostnames(mut self) -> Self {
+            log::warn!("tls hostname verification is disabled, connector is insecure");
+            self.connector = self.connector.danger_accept_invalid_hostnames(true);
+            self.insecure = true;
+            self
         }
 
-        pub fn use_sni(self, use_sni: bool) -> Self {
-            let connector = self.0.use_sni(use_sni);
-            Self(connector)
+        /// true if certificate or hostname verification is disabled
+        pub fn is_insecure(&self) -> bool {
+            self.insecure
         }
 
-        pub fn add_root_certificate(self, builder: X509PemBuilder) -> Result<Self, IoError> {
+        pub fn use_sni(mut self, use_sni: bool) -> Self {
+            self.connector = self.connector.use_sni(use_sni);
+            self
+        }
+
+        pub fn add_root_certificate(mut self, builder: X509PemBuilder) -> Result<Self, IoError> {
             let certificate = builder.build_native()?;
-            let connector = self.0.add_root_certificate(certificate);
-            Ok(Self(connector))
+            let der = certificate.to_der().map_err(|err| {
+                IoError::new(ErrorKind::InvalidInput, format!("invalid cert: {}", err))
+            })?;
+            self.ca_certs.push(der);
+            self.connector = self.connector.add_root_certificate(certificate);
+            Ok(self)
+        }
+
+        /// monitor expiry of added root certificates, labeled `ca`.
+        /// certificate of pkcs12 identity is not monitored
+        pub fn expiry_monitor(&self, warn_before: Duration) -> Result<CertExpiryMonitor, IoError> {
+            self.ca_certs
+                .iter()
+                .try_fold(CertExpiryMonitor::new(warn_before), |monitor, der| {
+                    monitor.add_der("ca", der)
+                })
         }
 
         pub fn build(self) -> TlsConnector {
-            self.0
+            TlsConnector::tagged(self.connector, self.insecure)
+        }
+
+        pub fn build_domain(self, domain: impl Into<String>) -> TlsDomainConnector {
+            TlsDomainConnector::new(self.build(), domain.into())
+        }
+
+        pub fn build_anonymous(self) -> TlsAnonymousConnector {
+            TlsAnonymousConnector::from(self.build())
         }
     }
 
@@ -344,6 +1003,62 @@ mod builder {
     }
 }
 
+#[cfg(feature = "config")]
+mod config {
+    use std::convert::TryFrom;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use crate::net::ConnectorConfig;
+    use crate::net::TransportType;
+
+    use super::AllDomainConnector;
+    use super::CertBuilder;
+    use super::ConnectorBuilder;
+    use super::IdentityBuilder;
+    use super::PrivateKeyBuilder;
+    use super::X509PemBuilder;
+
+    impl TryFrom<ConnectorConfig> for AllDomainConnector {
+        type Error = IoError;
+
+        fn try_from(config: ConnectorConfig) -> Result<Self, Self::Error> {
+            let invalid = |message: &str| IoError::new(ErrorKind::InvalidInput, message.to_owned());
+            let tcp = config.tcp_connector();
+            match config.transport {
+                TransportType::Tcp => Ok(Self::Tcp(tcp)),
+                TransportType::Unix => Ok(Self::new_unix()),
+                TransportType::Tls => {
+                    let tls = config.tls;
+                    let mut builder = match (&tls.client_cert, &tls.client_key) {
+                        (Some(cert), Some(key)) => {
+                            ConnectorBuilder::identity(IdentityBuilder::from_x509(
+                                X509PemBuilder::new(cert.load()?),
+                                PrivateKeyBuilder::new(key.load()?),
+                            )?)?
+                        }
+                        (None, None) => ConnectorBuilder::new(),
+                        _ => {
+                            return Err(invalid("client_cert and client_key must be set together"))
+                        }
+                    };
+                    if let Some(ca_cert) = &tls.ca_cert {
+                        builder =
+                            builder.add_root_certificate(X509PemBuilder::new(ca_cert.load()?))?;
+                    }
+                    if tls.insecure {
+                        builder = builder.danger_accept_invalid_certs();
+                    }
+                    Ok(match tls.domain {
+                        Some(domain) => Self::TlsDomain(builder.build_domain(domain).with_tcp(tcp)),
+                        None => Self::TlsAnonymous(builder.build_anonymous().with_tcp(tcp)),
+                    })
+                }
+            }
+        }
+    }
+}
+
 pub use stream::AllTcpStream;
 
 mod stream {
@@ -354,12 +1069,30 @@ mod stream {
 
     use super::DefaultClientTlsStream;
     use super::TcpStream;
+    #[cfg(feature = "zero_copy")]
+    use futures_lite::AsyncWriteExt;
     use futures_lite::{AsyncRead, AsyncWrite};
     use pin_project::pin_project;
 
+    #[cfg(feature = "zero_copy")]
+    use crate::file_slice::AsyncFileSlice;
+    use crate::instrument::stream_read;
+    use crate::instrument::stream_write;
+    use crate::net::unix::UnixStream;
+    use crate::net::PollReady;
+    use crate::net::TransportKind;
+    #[cfg(feature = "zero_copy")]
+    use crate::zero_copy::{
+        copy_slice_to, copy_slice_to_with_option, SendFileError, ZeroCopyOption, ZeroCopyWrite,
+    };
+
+    /// plain tcp, unix socket or tls stream, more may be added
     #[pin_project(project = EnumProj)]
+    #[allow(clippy::large_enum_variant)]
+    #[non_exhaustive]
     pub enum AllTcpStream {
         Tcp(#[pin] TcpStream),
+        Unix(#[pin] UnixStream),
         Tls(#[pin] DefaultClientTlsStream),
     }
 
@@ -368,9 +1101,78 @@ mod stream {
             Self::Tcp(stream)
         }
 
+        pub fn unix(stream: UnixStream) -> Self {
+            Self::Unix(stream)
+        }
+
         pub fn tls(stream: DefaultClientTlsStream) -> Self {
             Self::Tls(stream)
         }
+
+        pub fn transport_kind(&self) -> TransportKind {
+            match self {
+                Self::Tcp(_) => TransportKind::Tcp,
+                Self::Unix(_) => TransportKind::Unix,
+                Self::Tls(_) => TransportKind::Tls,
+            }
+        }
+
+        pub fn is_tls(&self) -> bool {
+            matches!(self, Self::Tls(_))
+        }
+
+        /// copy next bytes into `buf` without consuming them, such as to sniff protocol.
+        /// only plain tcp can peek its socket
+        pub async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+            match self {
+                Self::Tcp(stream) => stream.peek(buf).await,
+                _ => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "peek is only supported on plain tcp",
+                )),
+            }
+        }
+
+        /// tls stream, or self back if it is plain tcp
+        #[allow(clippy::result_large_err)]
+        pub fn into_tls(self) -> Result<DefaultClientTlsStream, Self> {
+            match self {
+                Self::Tls(stream) => Ok(stream),
+                stream => Err(stream),
+            }
+        }
+
+        /// plain tcp stream, or self back if it is tls
+        #[allow(clippy::result_large_err)]
+        pub fn into_tcp(self) -> Result<TcpStream, Self> {
+            match self {
+                Self::Tcp(stream) => Ok(stream),
+                stream => Err(stream),
+            }
+        }
+    }
+
+    /// read readiness of tls is not supported, since session may hold decrypted data
+    /// which isn't visible from here while socket has none. tls writes follow socket
+    impl PollReady for AllTcpStream {
+        fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self {
+                Self::Tcp(stream) => stream.poll_read_ready(cx),
+                Self::Unix(stream) => stream.poll_read_ready(cx),
+                Self::Tls(_) => Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "read readiness of tls stream is not known, read instead",
+                ))),
+            }
+        }
+
+        fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self {
+                Self::Tcp(stream) => stream.poll_write_ready(cx),
+                Self::Unix(stream) => stream.poll_write_ready(cx),
+                Self::Tls(stream) => stream.get_ref().poll_write_ready(cx),
+            }
+        }
     }
 
     impl AsyncRead for AllTcpStream {
@@ -379,10 +1181,15 @@ mod stream {
             cx: &mut Context<'_>,
             buf: &mut [u8],
         ) -> Poll<io::Result<usize>> {
-            match self.project() {
-                EnumProj::Tcp(stream) => stream.poll_read(cx, buf),
-                EnumProj::Tls(stream) => stream.poll_read(cx, buf),
+            let (kind, result) = match self.project() {
+                EnumProj::Tcp(stream) => ("tcp", stream.poll_read(cx, buf)),
+                EnumProj::Unix(stream) => ("unix", stream.poll_read(cx, buf)),
+                EnumProj::Tls(stream) => ("tls", stream.poll_read(cx, buf)),
+            };
+            if let Poll::Ready(Ok(len)) = &result {
+                stream_read(kind, *len);
             }
+            result
         }
     }
 
@@ -392,15 +1199,21 @@ mod stream {
             cx: &mut Context,
             buf: &[u8],
         ) -> Poll<Result<usize, io::Error>> {
-            match self.project() {
-                EnumProj::Tcp(stream) => stream.poll_write(cx, buf),
-                EnumProj::Tls(stream) => stream.poll_write(cx, buf),
+            let (kind, result) = match self.project() {
+                EnumProj::Tcp(stream) => ("tcp", stream.poll_write(cx, buf)),
+                EnumProj::Unix(stream) => ("unix", stream.poll_write(cx, buf)),
+                EnumProj::Tls(stream) => ("tls", stream.poll_write(cx, buf)),
+            };
+            if let Poll::Ready(Ok(len)) = &result {
+                stream_write(kind, *len);
             }
+            result
         }
 
         fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
             match self.project() {
                 EnumProj::Tcp(stream) => stream.poll_flush(cx),
+                EnumProj::Unix(stream) => stream.poll_flush(cx),
                 EnumProj::Tls(stream) => stream.poll_flush(cx),
             }
         }
@@ -408,21 +1221,59 @@ mod stream {
         fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
             match self.project() {
                 EnumProj::Tcp(stream) => stream.poll_close(cx),
+                EnumProj::Unix(stream) => stream.poll_close(cx),
                 EnumProj::Tls(stream) => stream.poll_close(cx),
             }
         }
     }
+
+    /// tls stream can't use sendfile, so it falls back to copy thru userspace
+    #[cfg(feature = "zero_copy")]
+    #[async_trait::async_trait]
+    impl ZeroCopyWrite for AllTcpStream {
+        async fn zero_copy_write_with_option(
+            &mut self,
+            source: &AsyncFileSlice,
+            option: &ZeroCopyOption,
+        ) -> Result<usize, SendFileError> {
+            match self {
+                Self::Tcp(stream) => stream.zero_copy_write_with_option(source, option).await,
+                Self::Unix(stream) => stream.zero_copy_write_with_option(source, option).await,
+                Self::Tls(stream) => copy_slice_to_with_option(stream, source, option).await,
+            }
+        }
+
+        async fn write_framed(
+            &mut self,
+            header: &[u8],
+            slice: AsyncFileSlice,
+            trailer: &[u8],
+        ) -> Result<usize, SendFileError> {
+            match self {
+                Self::Tcp(stream) => stream.write_framed(header, slice, trailer).await,
+                Self::Unix(stream) => stream.write_framed(header, slice, trailer).await,
+                Self::Tls(stream) => {
+                    // tls record boundaries are up to tls, so just write in order
+                    stream.write_all(header).await?;
+                    let len = copy_slice_to(stream, &slice).await?;
+                    stream.write_all(trailer).await?;
+                    stream.flush().await?;
+                    Ok(header.len() + len + trailer.len())
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
 
     use std::io::Error as IoError;
+    use std::io::ErrorKind;
     use std::net::SocketAddr;
     use std::time;
 
     use async_native_tls::TlsAcceptor;
-    use async_native_tls::TlsConnector;
     use bytes::buf::ext::BufExt;
     use bytes::BufMut;
     use bytes::Bytes;
@@ -441,8 +1292,8 @@ mod test {
     use fluvio_future::timer::sleep;
 
     use super::{
-        AcceptorBuilder, AllTcpStream, CertBuilder, ConnectorBuilder, IdentityBuilder,
-        PrivateKeyBuilder, X509PemBuilder,
+        generate_ca, generate_self_signed, AcceptorBuilder, AllTcpStream, CertBuilder,
+        ConnectorBuilder, IdentityBuilder, PrivateKeyBuilder, TlsConnector, X509PemBuilder,
     };
 
     const CA_PATH: &str = "certs/certs/ca.crt";
@@ -472,8 +1323,7 @@ mod test {
 
         let connector = ConnectorBuilder::identity(IdentityBuilder::from_path(CLIENT_IDENTITY)?)
             .expect("connector")
-            .danger_accept_invalid_hostnames()
-            .no_cert_verification()
+            .danger_accept_invalid_certs()
             .build();
 
         test_tls(PK12_PORT, acceptor, connector)
@@ -489,7 +1339,7 @@ mod test {
 
         let connector = ConnectorBuilder::identity(IdentityBuilder::from_path(CLIENT_IDENTITY)?)
             .expect("connector")
-            .no_cert_verification()
+            .danger_accept_invalid_certs()
             .build();
 
         test_tls(PK12_PORT, acceptor, connector)
@@ -522,8 +1372,7 @@ mod test {
             .expect("509"),
         )
         .expect("connector")
-        .danger_accept_invalid_hostnames()
-        .no_cert_verification()
+        .danger_accept_invalid_certs()
         .build();
 
         test_tls(X500_PORT, acceptor, connector)
@@ -551,7 +1400,7 @@ mod test {
         .expect("connector")
         .add_root_certificate(X509PemBuilder::from_path(CA_PATH).expect("cert"))
         .expect("root")
-        .no_cert_verification() // for mac
+        .danger_accept_invalid_certs() // for mac
         .build();
 
         test_tls(X500_PORT, acceptor, connector)
@@ -561,6 +1410,68 @@ mod test {
         Ok(())
     }
 
+    async fn handshake(acceptor: TlsAcceptor, connector: TlsConnector) -> Result<(), IoError> {
+        let (client_stream, server_stream) = crate::net::duplex(16 * 1024);
+        let (server_result, client_result) = zip(acceptor.accept(server_stream), async {
+            connector.connect("localhost", client_stream).await
+        })
+        .await;
+        client_result.map_err(|err| IoError::new(ErrorKind::InvalidData, err.to_string()))?;
+        server_result.map_err(|err| IoError::new(ErrorKind::InvalidData, err.to_string()))?;
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_accept_invalid_hostnames() -> Result<(), IoError> {
+        let ca = generate_ca()?;
+        // certificate for other name is accepted
+        let server = ca.issue_cert("other.local")?;
+        let acceptor = AcceptorBuilder::identity(server.identity()?)?.build()?;
+        #[allow(deprecated)]
+        let builder = ConnectorBuilder::new()
+            .add_root_certificate(ca.cert_key().x509()?)?
+            .danger_accept_invalid_hostnames();
+        assert!(builder.is_insecure());
+        handshake(acceptor, builder.build()).await?;
+
+        // but chain is still verified
+        let other = generate_ca()?.issue_cert("other.local")?;
+        let acceptor = AcceptorBuilder::identity(other.identity()?)?.build()?;
+        #[allow(deprecated)]
+        let connector = ConnectorBuilder::new()
+            .add_root_certificate(ca.cert_key().x509()?)?
+            .danger_accept_invalid_hostnames()
+            .build();
+        assert!(handshake(acceptor, connector).await.is_err());
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_generated_certs() -> Result<(), IoError> {
+        let ca = generate_ca()?;
+        let server = ca.issue_cert("localhost")?;
+        let acceptor = AcceptorBuilder::identity(server.identity()?)?.build()?;
+        let connector = ConnectorBuilder::new()
+            .add_root_certificate(ca.cert_key().x509()?)?
+            .build();
+        handshake(acceptor, connector).await?;
+
+        // certificate of other authority is rejected
+        let other = generate_ca()?.issue_cert("localhost")?;
+        let acceptor = AcceptorBuilder::identity(other.identity()?)?.build()?;
+        let connector = ConnectorBuilder::new()
+            .add_root_certificate(ca.cert_key().x509()?)?
+            .build();
+        assert!(handshake(acceptor, connector).await.is_err());
+
+        let server = generate_self_signed(&["localhost"])?;
+        let acceptor = AcceptorBuilder::identity(server.identity()?)?.build()?;
+        let connector = ConnectorBuilder::new()
+            .add_root_certificate(server.x509()?)?
+            .build();
+        handshake(acceptor, connector).await
+    }
+
     async fn test_tls(
         port: u16,
         acceptor: TlsAcceptor,
@@ -639,4 +1550,54 @@ mod test {
 
         Ok(())
     }
+
+    #[test_async]
+    async fn test_insecure_refused() -> Result<(), IoError> {
+        use crate::net::ConnectorError;
+        use crate::net::TcpDomainConnector;
+
+        use super::AllDomainConnector;
+        use super::TlsAnonymousConnector;
+        use super::TlsDomainConnector;
+
+        let builder = ConnectorBuilder::anonymous();
+        assert!(builder.is_insecure());
+        let connector = AllDomainConnector::new_tls_domain(builder.build_domain("localhost"));
+        let err = connector
+            .connect("127.0.0.1:1")
+            .await
+            .err()
+            .expect("insecure");
+        assert!(matches!(err.inner(), ConnectorError::InsecureNotAllowed));
+        // allowed connector gets to connect, which fails since nothing listens
+        let err = connector
+            .allow_insecure()
+            .connect("127.0.0.1:1")
+            .await
+            .err()
+            .expect("connect");
+        assert!(!matches!(err.inner(), ConnectorError::InsecureNotAllowed));
+
+        // tag is carried by connector, so connectors made from it directly are refused too
+        let connector = ConnectorBuilder::anonymous().build();
+        assert!(connector.is_insecure());
+        let connectors = [
+            AllDomainConnector::new_tls_domain(TlsDomainConnector::new(
+                connector,
+                "localhost".to_owned(),
+            )),
+            AllDomainConnector::new_tls_anonymous(TlsAnonymousConnector::from(
+                ConnectorBuilder::anonymous().build(),
+            )),
+        ];
+        for connector in connectors.iter() {
+            let err = connector
+                .connect("127.0.0.1:1")
+                .await
+                .err()
+                .expect("insecure");
+            assert!(matches!(err.inner(), ConnectorError::InsecureNotAllowed));
+        }
+        Ok(())
+    }
 }
@@ -0,0 +1,164 @@
+//! systemd socket activation, see `sd_listen_fds(3)`
+use std::convert::TryFrom;
+use std::env;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::RawFd;
+
+use log::debug;
+use nix::fcntl::fcntl;
+use nix::fcntl::FcntlArg;
+use nix::fcntl::FdFlag;
+use nix::sys::socket::getsockname;
+use nix::sys::socket::getsockopt;
+use nix::sys::socket::sockopt;
+use nix::sys::socket::SockAddr;
+use nix::Error as NixError;
+
+use super::TcpListener;
+
+/// first fd passed by systemd
+const LISTEN_FDS_START: RawFd = 3;
+
+pub(crate) fn nix_error(err: NixError) -> IoError {
+    match err.as_errno() {
+        Some(errno) => IoError::from_raw_os_error(errno as i32),
+        None => IoError::new(ErrorKind::InvalidInput, err.to_string()),
+    }
+}
+
+/// tcp listener inherited thru socket activation
+#[derive(Debug)]
+pub struct Listener {
+    name: Option<String>,
+    listener: TcpListener,
+}
+
+impl Listener {
+    /// listeners passed by systemd, empty if process was not socket activated.
+    /// activation variables are removed so child processes don't inherit them
+    pub fn from_env() -> Result<Vec<Self>, IoError> {
+        let fds = listen_fds(
+            env::var("LISTEN_PID").ok().as_deref(),
+            env::var("LISTEN_FDS").ok().as_deref(),
+            env::var("LISTEN_FDNAMES").ok().as_deref(),
+        );
+        env::remove_var("LISTEN_PID");
+        env::remove_var("LISTEN_FDS");
+        env::remove_var("LISTEN_FDNAMES");
+
+        fds?.into_iter()
+            .map(|(fd, name)| Self::from_fd(fd, name))
+            .collect()
+    }
+
+    /// validate that fd is listening tcp socket
+    fn from_fd(fd: RawFd, name: Option<String>) -> Result<Self, IoError> {
+        let not_tcp_listener = || {
+            IoError::new(
+                ErrorKind::InvalidInput,
+                format!("fd: {} is not listening tcp socket", fd),
+            )
+        };
+        // listening inet socket is always stream socket
+        if !getsockopt(fd, sockopt::AcceptConn).map_err(nix_error)?
+            || !matches!(getsockname(fd).map_err(nix_error)?, SockAddr::Inet(_))
+        {
+            return Err(not_tcp_listener());
+        }
+        fcntl(fd, FcntlArg::F_SETFD(FdFlag::FD_CLOEXEC)).map_err(nix_error)?;
+
+        debug!("inherited listener fd: {}, name: {:?}", fd, name);
+        let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+        Ok(Self {
+            name,
+            listener: TcpListener::try_from(listener)?,
+        })
+    }
+
+    /// name from `FileDescriptorName=` of socket unit
+    pub fn name(&self) -> Option<&str> {
+        self.name.as_deref()
+    }
+
+    pub fn listener(&self) -> &TcpListener {
+        &self.listener
+    }
+
+    pub fn into_listener(self) -> TcpListener {
+        self.listener
+    }
+}
+
+/// fds and their names from activation variables
+fn listen_fds(
+    pid: Option<&str>,
+    fds: Option<&str>,
+    names: Option<&str>,
+) -> Result<Vec<(RawFd, Option<String>)>, IoError> {
+    let invalid = |message: &str| IoError::new(ErrorKind::InvalidData, message.to_owned());
+
+    let (pid, fds) = match (pid, fds) {
+        (Some(pid), Some(fds)) => (pid, fds),
+        _ => return Ok(vec![]),
+    };
+    let pid: u32 = pid.parse().map_err(|_| invalid("invalid LISTEN_PID"))?;
+    if pid != std::process::id() {
+        debug!("sockets are passed to pid: {}, ignoring", pid);
+        return Ok(vec![]);
+    }
+    let count: usize = fds.parse().map_err(|_| invalid("invalid LISTEN_FDS"))?;
+    let names: Vec<Option<String>> = match names {
+        Some(names) => names.split(':').map(|name| Some(name.to_owned())).collect(),
+        None => vec![None; count],
+    };
+    if names.len() != count {
+        return Err(invalid("LISTEN_FDNAMES doesn't match LISTEN_FDS"));
+    }
+    Ok((LISTEN_FDS_START..).zip(names).collect())
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::io::IntoRawFd;
+
+    use super::listen_fds;
+    use super::Listener;
+
+    #[test]
+    fn test_listen_fds() {
+        let pid = std::process::id().to_string();
+        assert!(listen_fds(None, None, None).unwrap().is_empty());
+        assert!(listen_fds(Some("1"), Some("2"), None).unwrap().is_empty());
+        assert_eq!(
+            listen_fds(Some(&pid), Some("2"), Some("http:admin")).unwrap(),
+            vec![(3, Some("http".to_owned())), (4, Some("admin".to_owned()))]
+        );
+        assert_eq!(
+            listen_fds(Some(&pid), Some("1"), None).unwrap(),
+            vec![(3, None)]
+        );
+        let err = listen_fds(Some(&pid), Some("2"), Some("http")).expect_err("mismatch");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(listen_fds(Some(&pid), Some("-1"), None).is_err());
+    }
+
+    #[test]
+    fn test_validate_fd() -> Result<(), IoError> {
+        let tcp = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let addr = tcp.local_addr()?;
+        let listener = Listener::from_fd(tcp.into_raw_fd(), Some("http".to_owned()))?;
+        assert_eq!(listener.name(), Some("http"));
+        assert_eq!(listener.listener().local_addr()?, addr);
+
+        let udp = std::net::UdpSocket::bind("127.0.0.1:0")?;
+        let err = Listener::from_fd(udp.as_raw_fd(), None).expect_err("not tcp");
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        Ok(())
+    }
+}
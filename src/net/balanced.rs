@@ -0,0 +1,541 @@
+//! client side load balancing over multiple endpoints
+use std::future::Future;
+use std::io::Error as IoError;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures_lite::future::poll_fn;
+use futures_lite::{AsyncRead, AsyncWrite, Stream, StreamExt};
+use log::debug;
+
+use super::ConnectorError;
+use super::Deadline;
+use super::TcpDomainConnector;
+use crate::timer::sleep;
+
+const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(5);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BalanceStrategy {
+    #[default]
+    RoundRobin,
+    /// endpoint with fewest open connections and connects in progress
+    LeastOutstanding,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointStatus {
+    pub addr: String,
+    pub healthy: bool,
+    /// open connections and connects in progress
+    pub outstanding: usize,
+}
+
+struct Endpoint {
+    addr: String,
+    outstanding: Arc<AtomicUsize>,
+    unhealthy_until: Option<Instant>,
+}
+
+impl Endpoint {
+    fn new(addr: String) -> Self {
+        Self {
+            addr,
+            outstanding: Arc::new(AtomicUsize::new(0)),
+            unhealthy_until: None,
+        }
+    }
+
+    fn is_healthy(&self, now: Instant) -> bool {
+        self.unhealthy_until.is_none_or(|until| until <= now)
+    }
+}
+
+#[derive(Default)]
+struct Endpoints {
+    list: Vec<Endpoint>,
+    next: usize,
+}
+
+impl Endpoints {
+    /// endpoints to try in order, healthy ones unless none is healthy.
+    /// round robin moves on right away, so concurrent connects start at different endpoints
+    fn candidates(&mut self, strategy: BalanceStrategy) -> Vec<(String, Arc<AtomicUsize>)> {
+        let now = Instant::now();
+        let len = self.list.len();
+        let start = self.next % len.max(1);
+        self.next = start + 1;
+        let mut order: Vec<usize> = (0..len).map(|i| (start + i) % len).collect();
+        if order.iter().any(|i| self.list[*i].is_healthy(now)) {
+            let list = &self.list;
+            order.retain(|i| list[*i].is_healthy(now));
+        }
+        if strategy == BalanceStrategy::LeastOutstanding {
+            // stable sort keeps round robin order among ties
+            let list = &self.list;
+            order.sort_by_key(|i| list[*i].outstanding.load(Ordering::Relaxed));
+        }
+        order
+            .into_iter()
+            .map(|i| (self.list[i].addr.clone(), self.list[i].outstanding.clone()))
+            .collect()
+    }
+
+    /// `failover` if other endpoints were tried first, round robin continues after this one
+    fn succeeded(&mut self, addr: &str, failover: bool) {
+        if let Some(index) = self.list.iter().position(|e| e.addr == addr) {
+            self.list[index].unhealthy_until = None;
+            if failover {
+                self.next = index + 1;
+            }
+        }
+    }
+
+    fn failed(&mut self, addr: &str, unhealthy_until: Instant) {
+        if let Some(endpoint) = self.list.iter_mut().find(|e| e.addr == addr) {
+            endpoint.unhealthy_until = Some(unhealthy_until);
+        }
+    }
+}
+
+/// spread connections over endpoints, failing endpoint is skipped for `retry_after`.
+/// connect tries other endpoints before giving up or current deadline passes, domain passed
+/// to connect is only used when there is no endpoint
+pub struct BalancedConnector<C> {
+    inner: C,
+    strategy: BalanceStrategy,
+    retry_after: Duration,
+    hedge_after: Option<Duration>,
+    endpoints: Arc<Mutex<Endpoints>>,
+}
+
+impl<C> BalancedConnector<C> {
+    pub fn new(inner: C, endpoints: Vec<String>) -> Self {
+        let connector = Self {
+            inner,
+            strategy: BalanceStrategy::default(),
+            retry_after: DEFAULT_RETRY_AFTER,
+            hedge_after: None,
+            endpoints: Arc::new(Mutex::new(Endpoints::default())),
+        };
+        connector.set_endpoints(endpoints);
+        connector
+    }
+
+    pub fn strategy(mut self, strategy: BalanceStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// how long failed endpoint is skipped
+    pub fn retry_after(mut self, retry_after: Duration) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    /// start second connect to next endpoint if first hasn't finished within `hedge_after`,
+    /// connect which loses is cancelled
+    pub fn hedge_after(mut self, hedge_after: Duration) -> Self {
+        self.hedge_after = Some(hedge_after);
+        self
+    }
+
+    /// replace endpoints, state of endpoints which are kept is preserved
+    pub fn set_endpoints(&self, endpoints: Vec<String>) {
+        let mut current = self.endpoints.lock().unwrap();
+        let mut previous = std::mem::take(&mut current.list);
+        current.list = endpoints
+            .into_iter()
+            .map(|addr| match previous.iter().position(|e| e.addr == addr) {
+                Some(index) => previous.swap_remove(index),
+                None => Endpoint::new(addr),
+            })
+            .collect();
+        debug!("balanced endpoints: {}", current.list.len());
+    }
+
+    /// apply endpoint updates until stream ends, such as from watch of service registry
+    pub async fn follow<S>(&self, mut updates: S)
+    where
+        S: Stream<Item = Vec<String>> + Unpin,
+    {
+        while let Some(endpoints) = updates.next().await {
+            self.set_endpoints(endpoints);
+        }
+    }
+
+    pub fn status(&self) -> Vec<EndpointStatus> {
+        let now = Instant::now();
+        self.endpoints
+            .lock()
+            .unwrap()
+            .list
+            .iter()
+            .map(|endpoint| EndpointStatus {
+                addr: endpoint.addr.clone(),
+                healthy: endpoint.is_healthy(now),
+                outstanding: endpoint.outstanding.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for BalancedConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = BalancedStream<C::WrapperStream>;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        let mut candidates = self.endpoints.lock().unwrap().candidates(self.strategy);
+        if candidates.is_empty() {
+            candidates.push((domain.to_owned(), Arc::new(AtomicUsize::new(0))));
+        }
+        let mut candidates = candidates.into_iter();
+
+        let mut last_error = None;
+        if let Some(hedge_after) = self.hedge_after {
+            if candidates.len() > 1 {
+                let (addr, outstanding) = candidates.next().expect("candidate");
+                let (hedge_addr, hedge_outstanding) = candidates.next().expect("candidate");
+                match hedged(
+                    self.attempt(addr, outstanding, false),
+                    self.attempt(hedge_addr, hedge_outstanding, true),
+                    hedge_after,
+                )
+                .await
+                {
+                    Ok(connected) => return Ok(connected),
+                    Err(err) => last_error = Some(err),
+                }
+            }
+        }
+
+        for (addr, outstanding) in candidates {
+            let failover = last_error.is_some();
+            if Deadline::current().is_some_and(|deadline| deadline.is_expired()) {
+                debug!("deadline passed, not trying endpoint: {}", addr);
+                return Err(last_error.unwrap_or(ConnectorError::Timeout));
+            }
+            match self.attempt(addr, outstanding, failover).await {
+                Ok(connected) => return Ok(connected),
+                Err(err) => last_error = Some(err),
+            }
+        }
+        Err(last_error.expect("at least one candidate"))
+    }
+}
+
+impl<C> BalancedConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    /// connect to one endpoint, updating its health.
+    /// attempt is outstanding while in progress, then as long as its stream is open
+    async fn attempt(
+        &self,
+        addr: String,
+        outstanding: Arc<AtomicUsize>,
+        failover: bool,
+    ) -> Result<(BalancedStream<C::WrapperStream>, RawFd), ConnectorError> {
+        let outstanding = Outstanding::new(outstanding);
+        match self.inner.connect(&addr).await {
+            Ok((stream, fd)) => {
+                self.endpoints.lock().unwrap().succeeded(&addr, failover);
+                Ok((
+                    BalancedStream {
+                        inner: stream,
+                        _outstanding: outstanding,
+                    },
+                    fd,
+                ))
+            }
+            // endpoint isn't at fault if overall deadline ran out
+            Err(err) if Deadline::current().is_some_and(|deadline| deadline.is_expired()) => {
+                debug!("endpoint: {} didn't connect before deadline", addr);
+                Err(err)
+            }
+            Err(err) => {
+                debug!("endpoint: {} failed: {}, marking unhealthy", addr, err);
+                self.endpoints
+                    .lock()
+                    .unwrap()
+                    .failed(&addr, Instant::now() + self.retry_after);
+                Err(err)
+            }
+        }
+    }
+}
+
+/// start hedge if primary hasn't finished in time, first success wins and other is dropped.
+/// hedge is started right away if primary fails early
+async fn hedged<P, H, T>(primary: P, hedge: H, hedge_after: Duration) -> Result<T, ConnectorError>
+where
+    P: Future<Output = Result<T, ConnectorError>>,
+    H: Future<Output = Result<T, ConnectorError>>,
+{
+    futures_lite::pin!(primary);
+    futures_lite::pin!(hedge);
+    let mut primary_error = None;
+    let mut hedge_error = None;
+    let mut delay = Some(sleep(hedge_after));
+    poll_fn(|cx| {
+        if primary_error.is_none() {
+            if let Poll::Ready(result) = primary.as_mut().poll(cx) {
+                match result {
+                    Ok(connected) => return Poll::Ready(Ok(connected)),
+                    Err(err) => primary_error = Some(err),
+                }
+            }
+        }
+        if let Some(timer) = delay.as_mut() {
+            if primary_error.is_none() && Pin::new(timer).poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            debug!("starting hedged connect");
+            delay = None;
+        }
+        if hedge_error.is_none() {
+            if let Poll::Ready(result) = hedge.as_mut().poll(cx) {
+                match result {
+                    Ok(connected) => return Poll::Ready(Ok(connected)),
+                    Err(err) => hedge_error = Some(err),
+                }
+            }
+        }
+        match (primary_error.take(), hedge_error.take()) {
+            (Some(_), Some(err)) => Poll::Ready(Err(err)),
+            (primary, hedge) => {
+                primary_error = primary;
+                hedge_error = hedge;
+                Poll::Pending
+            }
+        }
+    })
+    .await
+}
+
+/// count on endpoint, taken back when dropped such as when attempt is cancelled
+struct Outstanding(Arc<AtomicUsize>);
+
+impl Outstanding {
+    fn new(count: Arc<AtomicUsize>) -> Self {
+        count.fetch_add(1, Ordering::Relaxed);
+        Self(count)
+    }
+}
+
+impl Drop for Outstanding {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// stream counted as outstanding on its endpoint until dropped
+pub struct BalancedStream<S> {
+    inner: S,
+    _outstanding: Outstanding,
+}
+
+impl<S> BalancedStream<S> {
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for BalancedStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for BalancedStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::os::unix::io::RawFd;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use async_trait::async_trait;
+    use futures_lite::future::zip;
+    use futures_util::future::join_all;
+
+    use crate::net::duplex;
+    use crate::net::ConnectorError;
+    use crate::net::DuplexStream;
+    use crate::net::TcpDomainConnector;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::BalanceStrategy;
+    use super::BalancedConnector;
+
+    /// connect to in memory stream, endpoints starting with `down` fail and `slow` take 500ms
+    #[derive(Default)]
+    struct MemoryConnector(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl TcpDomainConnector for MemoryConnector {
+        type WrapperStream = DuplexStream;
+
+        async fn connect(
+            &self,
+            domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            self.0.lock().unwrap().push(domain.to_owned());
+            if domain.starts_with("down") {
+                return Err(IoError::from(ErrorKind::ConnectionRefused).into());
+            }
+            if domain.starts_with("slow") {
+                sleep(Duration::from_millis(500)).await;
+            }
+            Ok((duplex(64).0, 0))
+        }
+    }
+
+    fn endpoints(addrs: &[&str]) -> Vec<String> {
+        addrs.iter().map(|addr| addr.to_string()).collect()
+    }
+
+    #[test_async]
+    async fn test_round_robin() -> Result<(), IoError> {
+        let connector = BalancedConnector::new(
+            MemoryConnector::default(),
+            endpoints(&["a:9092", "down:9092", "b:9092"]),
+        );
+        for _ in 0..4u16 {
+            connector.connect("unused").await?;
+        }
+        // failed endpoint is tried once then skipped
+        assert_eq!(
+            *connector.inner.0.lock().unwrap(),
+            endpoints(&["a:9092", "down:9092", "b:9092", "a:9092", "b:9092"])
+        );
+        let status = connector.status();
+        assert!(status[0].healthy);
+        assert!(!status[1].healthy);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_least_outstanding() -> Result<(), IoError> {
+        let connector =
+            BalancedConnector::new(MemoryConnector::default(), endpoints(&["a:9092", "b:9092"]))
+                .strategy(BalanceStrategy::LeastOutstanding);
+        let (_first, _fd) = connector.connect("unused").await?;
+        let (second, _fd) = connector.connect("unused").await?;
+        drop(second);
+        let (_third, _fd) = connector.connect("unused").await?;
+        assert_eq!(
+            *connector.inner.0.lock().unwrap(),
+            endpoints(&["a:9092", "b:9092", "b:9092"])
+        );
+        assert_eq!(connector.status()[0].outstanding, 1);
+
+        connector.set_endpoints(endpoints(&["a:9092", "c:9092"]));
+        assert_eq!(connector.status()[0].outstanding, 1);
+        assert_eq!(connector.status()[1].outstanding, 0);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_hedged_connect() -> Result<(), IoError> {
+        let connector = BalancedConnector::new(
+            MemoryConnector::default(),
+            endpoints(&["slow:9092", "a:9092"]),
+        )
+        .hedge_after(Duration::from_millis(20));
+        let start = Instant::now();
+        let (_stream, _fd) = connector.connect("unused").await?;
+        assert!(start.elapsed() < Duration::from_millis(400));
+        assert_eq!(
+            *connector.inner.0.lock().unwrap(),
+            endpoints(&["slow:9092", "a:9092"])
+        );
+        // slow connect was cancelled
+        assert_eq!(connector.status()[0].outstanding, 0);
+        assert_eq!(connector.status()[1].outstanding, 1);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_concurrent_connects() -> Result<(), IoError> {
+        for strategy in [
+            BalanceStrategy::RoundRobin,
+            BalanceStrategy::LeastOutstanding,
+        ] {
+            let connector = BalancedConnector::new(
+                MemoryConnector::default(),
+                endpoints(&["slow-a:9092", "slow-b:9092", "slow-c:9092"]),
+            )
+            .strategy(strategy);
+            let (streams, in_progress) = zip(
+                join_all((0..6).map(|_| connector.connect("unused"))),
+                async {
+                    sleep(Duration::from_millis(100)).await;
+                    connector.status()
+                },
+            )
+            .await;
+            // connects in progress are counted, and spread evenly
+            for status in in_progress {
+                assert_eq!(status.outstanding, 2, "{:?}", strategy);
+            }
+            let mut connected = connector.inner.0.lock().unwrap().clone();
+            connected.sort();
+            assert_eq!(
+                connected,
+                endpoints(&[
+                    "slow-a:9092",
+                    "slow-a:9092",
+                    "slow-b:9092",
+                    "slow-b:9092",
+                    "slow-c:9092",
+                    "slow-c:9092"
+                ])
+            );
+
+            drop(streams);
+            assert!(connector.status().iter().all(|e| e.outstanding == 0));
+        }
+        Ok(())
+    }
+}
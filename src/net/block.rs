@@ -0,0 +1,196 @@
+//! framing of stream into blocks, shared by checksum and compressed streams.
+//!
+//! writes are buffered until block is full or stream is flushed, then block is encoded
+//! into one frame. frames read are decoded back into blocks by codec of stream
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+
+const READ_CHUNK: usize = 8 * 1024;
+
+/// per block step of framing
+pub(crate) trait BlockCodec {
+    /// append frame of `block` to empty `frame`
+    fn encode(&mut self, block: &[u8], frame: &mut Vec<u8>);
+
+    /// length of frame at start of `incoming` once its header is received,
+    /// fails if header is invalid
+    fn frame_len(&self, incoming: &[u8]) -> Result<Option<usize>, IoError>;
+
+    /// decode complete frame into its block
+    fn decode(&mut self, frame: &[u8]) -> Result<Vec<u8>, IoError>;
+}
+
+/// buffers of block framed stream, inner stream is passed to each poll
+pub(crate) struct BlockFraming<C> {
+    codec: C,
+    block_size: usize,
+    /// written bytes not yet encoded
+    pending: Vec<u8>,
+    /// encoded frame and how much of it is written
+    frame: Vec<u8>,
+    frame_written: usize,
+    /// received bytes not yet decoded
+    incoming: Vec<u8>,
+    decoded: Vec<u8>,
+    decoded_read: usize,
+}
+
+impl<C: BlockCodec> BlockFraming<C> {
+    pub(crate) fn new(codec: C, block_size: usize) -> Self {
+        Self {
+            codec,
+            block_size,
+            pending: vec![],
+            frame: vec![],
+            frame_written: 0,
+            incoming: vec![],
+            decoded: vec![],
+            decoded_read: 0,
+        }
+    }
+
+    pub(crate) fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    pub(crate) fn set_block_size(&mut self, size: usize) {
+        self.block_size = size;
+    }
+
+    /// true if read can complete without reading inner stream
+    pub(crate) fn read_buffered(&self) -> bool {
+        if self.decoded_read < self.decoded.len() {
+            return true;
+        }
+        match self.codec.frame_len(&self.incoming) {
+            Ok(Some(len)) => self.incoming.len() >= len,
+            Ok(None) => false,
+            // error is returned by read at once
+            Err(_) => true,
+        }
+    }
+
+    fn encode_pending(&mut self) {
+        self.frame.clear();
+        self.codec.encode(&self.pending, &mut self.frame);
+        self.pending.clear();
+        self.frame_written = 0;
+    }
+
+    /// decode frame at start of incoming bytes, false if it isn't complete yet
+    fn decode_frame(&mut self) -> Result<bool, IoError> {
+        let frame_len = match self.codec.frame_len(&self.incoming)? {
+            Some(len) if self.incoming.len() >= len => len,
+            _ => return Ok(false),
+        };
+        self.decoded = self.codec.decode(&self.incoming[..frame_len])?;
+        self.decoded_read = 0;
+        self.incoming.drain(..frame_len);
+        Ok(true)
+    }
+
+    /// write out encoded frame
+    fn poll_frame<S: AsyncWrite + Unpin>(
+        &mut self,
+        inner: &mut S,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), IoError>> {
+        while self.frame_written < self.frame.len() {
+            let n = match Pin::new(&mut *inner).poll_write(cx, &self.frame[self.frame_written..]) {
+                Poll::Ready(Ok(n)) => n,
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            };
+            if n == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            self.frame_written += n;
+        }
+        self.frame.clear();
+        self.frame_written = 0;
+        Poll::Ready(Ok(()))
+    }
+
+    /// encode and write out pending bytes
+    pub(crate) fn poll_flush_pending<S: AsyncWrite + Unpin>(
+        &mut self,
+        inner: &mut S,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<(), IoError>> {
+        if let Poll::Ready(Err(err)) = self.poll_frame(inner, cx) {
+            return Poll::Ready(Err(err));
+        }
+        if !self.frame.is_empty() {
+            return Poll::Pending;
+        }
+        if !self.pending.is_empty() {
+            self.encode_pending();
+            return self.poll_frame(inner, cx);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    pub(crate) fn poll_read<S: AsyncRead + Unpin>(
+        &mut self,
+        inner: &mut S,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        loop {
+            if self.decoded_read < self.decoded.len() {
+                let n = buf.len().min(self.decoded.len() - self.decoded_read);
+                buf[..n].copy_from_slice(&self.decoded[self.decoded_read..self.decoded_read + n]);
+                self.decoded_read += n;
+                return Poll::Ready(Ok(n));
+            }
+            if self.decode_frame()? {
+                continue;
+            }
+            let len = self.incoming.len();
+            self.incoming.resize(len + READ_CHUNK, 0);
+            let result = Pin::new(&mut *inner).poll_read(cx, &mut self.incoming[len..]);
+            let n = match result {
+                Poll::Ready(Ok(n)) => n,
+                other => {
+                    self.incoming.truncate(len);
+                    return other;
+                }
+            };
+            self.incoming.truncate(len + n);
+            if n == 0 {
+                return if len == 0 {
+                    Poll::Ready(Ok(0))
+                } else {
+                    Poll::Ready(Err(ErrorKind::UnexpectedEof.into()))
+                };
+            }
+        }
+    }
+
+    pub(crate) fn poll_write<S: AsyncWrite + Unpin>(
+        &mut self,
+        inner: &mut S,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        if self.pending.len() >= self.block_size {
+            // only one frame is outstanding, so block waits until previous is written
+            match self.poll_frame(inner, cx) {
+                Poll::Ready(Ok(())) => {}
+                Poll::Ready(Err(err)) => return Poll::Ready(Err(err)),
+                Poll::Pending => return Poll::Pending,
+            }
+            self.encode_pending();
+            if let Poll::Ready(Err(err)) = self.poll_frame(inner, cx) {
+                return Poll::Ready(Err(err));
+            }
+        }
+        let n = buf.len().min(self.block_size - self.pending.len());
+        self.pending.extend_from_slice(&buf[..n]);
+        Poll::Ready(Ok(n))
+    }
+}
@@ -0,0 +1,230 @@
+//! circuit breaker for connectors, rejects connects fast while endpoint keeps failing
+use std::collections::VecDeque;
+use std::os::unix::io::RawFd;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use log::debug;
+
+use super::ConnectorError;
+use super::TcpDomainConnector;
+
+#[derive(Debug, Clone)]
+pub struct BreakerConfig {
+    /// ratio of failures in window which opens circuit
+    pub failure_rate: f64,
+    /// number of recent connects failure rate is computed over
+    pub window: usize,
+    /// connects needed in window before circuit can open
+    pub min_requests: usize,
+    /// how long circuit stays open before probe is allowed
+    pub open_duration: Duration,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            failure_rate: 0.5,
+            window: 20,
+            min_requests: 5,
+            open_duration: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BreakerState {
+    Closed,
+    Open,
+    /// probe connect is in progress
+    HalfOpen,
+}
+
+enum State {
+    Closed,
+    Open { until: Instant },
+    HalfOpen { since: Instant },
+}
+
+struct Breaker {
+    state: State,
+    outcomes: VecDeque<bool>,
+}
+
+/// connector which opens after failure rate is reached, rejecting connects with
+/// `ConnectorError::CircuitOpen`. once open duration passes, one probe connect is let thru,
+/// its result closes or reopens circuit
+pub struct CircuitBreakerConnector<C> {
+    inner: C,
+    config: BreakerConfig,
+    breaker: Mutex<Breaker>,
+}
+
+impl<C> CircuitBreakerConnector<C> {
+    pub fn new(inner: C, config: BreakerConfig) -> Self {
+        Self {
+            inner,
+            config,
+            breaker: Mutex::new(Breaker {
+                state: State::Closed,
+                outcomes: VecDeque::new(),
+            }),
+        }
+    }
+
+    pub fn state(&self) -> BreakerState {
+        match self.breaker.lock().unwrap().state {
+            State::Closed => BreakerState::Closed,
+            State::Open { .. } => BreakerState::Open,
+            State::HalfOpen { .. } => BreakerState::HalfOpen,
+        }
+    }
+
+    /// check if connect is allowed, returns true if it is probe
+    fn acquire(&self) -> Result<bool, ConnectorError> {
+        let now = Instant::now();
+        let mut breaker = self.breaker.lock().unwrap();
+        match breaker.state {
+            State::Closed => Ok(false),
+            State::Open { until } if now < until => Err(ConnectorError::CircuitOpen),
+            // probe which was cancelled never reports, so allow new one after open duration
+            State::HalfOpen { since } if now < since + self.config.open_duration => {
+                Err(ConnectorError::CircuitOpen)
+            }
+            _ => {
+                debug!("circuit half open, probing");
+                breaker.state = State::HalfOpen { since: now };
+                Ok(true)
+            }
+        }
+    }
+
+    fn release(&self, probe: bool, success: bool) {
+        let mut breaker = self.breaker.lock().unwrap();
+        if probe {
+            if success {
+                debug!("probe succeeded, closing circuit");
+                breaker.state = State::Closed;
+            } else {
+                debug!("probe failed, reopening circuit");
+                breaker.state = State::Open {
+                    until: Instant::now() + self.config.open_duration,
+                };
+            }
+            breaker.outcomes.clear();
+            return;
+        }
+
+        breaker.outcomes.push_back(success);
+        while breaker.outcomes.len() > self.config.window {
+            breaker.outcomes.pop_front();
+        }
+        let total = breaker.outcomes.len();
+        let failures = breaker.outcomes.iter().filter(|success| !**success).count();
+        if total >= self.config.min_requests
+            && failures as f64 >= self.config.failure_rate * total as f64
+        {
+            debug!("{} of {} connects failed, opening circuit", failures, total);
+            breaker.state = State::Open {
+                until: Instant::now() + self.config.open_duration,
+            };
+            breaker.outcomes.clear();
+        }
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for CircuitBreakerConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = C::WrapperStream;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        let probe = self.acquire()?;
+        let result = self.inner.connect(domain).await;
+        self.release(probe, result.is_ok());
+        result
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::os::unix::io::RawFd;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::net::duplex;
+    use crate::net::ConnectorError;
+    use crate::net::DuplexStream;
+    use crate::net::TcpDomainConnector;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::BreakerConfig;
+    use super::BreakerState;
+    use super::CircuitBreakerConnector;
+
+    #[derive(Default)]
+    struct FlakyConnector {
+        down: AtomicBool,
+        attempts: AtomicUsize,
+    }
+
+    #[async_trait]
+    impl TcpDomainConnector for FlakyConnector {
+        type WrapperStream = DuplexStream;
+
+        async fn connect(
+            &self,
+            _domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            self.attempts.fetch_add(1, Ordering::Relaxed);
+            if self.down.load(Ordering::Relaxed) {
+                return Err(IoError::from(ErrorKind::ConnectionRefused).into());
+            }
+            Ok((duplex(64).0, 0))
+        }
+    }
+
+    #[test_async]
+    async fn test_circuit_breaker() -> Result<(), IoError> {
+        let connector = CircuitBreakerConnector::new(
+            FlakyConnector::default(),
+            BreakerConfig {
+                min_requests: 2,
+                open_duration: Duration::from_millis(50),
+                ..Default::default()
+            },
+        );
+        connector.connect("endpoint").await?;
+        connector.inner.down.store(true, Ordering::Relaxed);
+        assert!(connector.connect("endpoint").await.is_err());
+        assert_eq!(connector.state(), BreakerState::Open);
+
+        let err = connector.connect("endpoint").await.err().expect("open");
+        assert!(matches!(err, ConnectorError::CircuitOpen));
+        assert_eq!(connector.inner.attempts.load(Ordering::Relaxed), 2);
+
+        // failed probe reopens
+        sleep(Duration::from_millis(60)).await;
+        assert!(connector.connect("endpoint").await.is_err());
+        assert_eq!(connector.state(), BreakerState::Open);
+
+        sleep(Duration::from_millis(60)).await;
+        connector.inner.down.store(false, Ordering::Relaxed);
+        connector.connect("endpoint").await?;
+        assert_eq!(connector.state(), BreakerState::Closed);
+        assert_eq!(connector.inner.attempts.load(Ordering::Relaxed), 4);
+        Ok(())
+    }
+}
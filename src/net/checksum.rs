@@ -0,0 +1,298 @@
+//! crc32c integrity of plain tcp streams, for deployments skipping tls inside trusted network.
+//!
+//! writes are buffered into frames of payload length, payload and crc32c of payload.
+//! reader fails with `InvalidData` when checksum doesn't match, so corruption by
+//! middlebox is detected instead of being passed on. both ends must use it
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures_lite::{AsyncRead, AsyncWrite};
+
+use super::block::{BlockCodec, BlockFraming};
+use super::ConnectorError;
+use super::PollReady;
+use super::TcpDomainConnector;
+
+const HEADER_LEN: usize = 4;
+const TRAILER_LEN: usize = 4;
+const DEFAULT_FRAME_SIZE: usize = 16 * 1024;
+/// largest frame peer is allowed to send, protects reader from huge allocations
+const MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+/// reflected castagnoli polynomial
+const POLYNOMIAL: u32 = 0x82F6_3B78;
+
+const TABLE: [u32; 256] = crc_table();
+
+const fn crc_table() -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut index = 0;
+    while index < 256 {
+        let mut crc = index as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[index] = crc;
+        index += 1;
+    }
+    table
+}
+
+/// crc32c (castagnoli) of `data`
+pub fn crc32c(data: &[u8]) -> u32 {
+    !data.iter().fold(!0, |crc, byte| {
+        TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    })
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+/// frame of payload length, payload and crc32c of payload
+struct Crc32cCodec;
+
+impl BlockCodec for Crc32cCodec {
+    fn encode(&mut self, block: &[u8], frame: &mut Vec<u8>) {
+        frame.reserve(HEADER_LEN + block.len() + TRAILER_LEN);
+        frame.extend_from_slice(&(block.len() as u32).to_be_bytes());
+        frame.extend_from_slice(block);
+        frame.extend_from_slice(&crc32c(block).to_be_bytes());
+    }
+
+    fn frame_len(&self, incoming: &[u8]) -> Result<Option<usize>, IoError> {
+        match incoming.get(..HEADER_LEN).map(read_u32) {
+            Some(len) if len as usize > MAX_FRAME_SIZE => {
+                Err(IoError::new(ErrorKind::InvalidData, "frame too large"))
+            }
+            Some(len) => Ok(Some(HEADER_LEN + len as usize + TRAILER_LEN)),
+            None => Ok(None),
+        }
+    }
+
+    fn decode(&mut self, frame: &[u8]) -> Result<Vec<u8>, IoError> {
+        let (payload, trailer) =
+            frame[HEADER_LEN..].split_at(frame.len() - HEADER_LEN - TRAILER_LEN);
+        if crc32c(payload) != read_u32(trailer) {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                "frame checksum mismatch",
+            ));
+        }
+        Ok(payload.to_vec())
+    }
+}
+
+/// stream appending crc32c to every frame written and verifying frames read
+pub struct ChecksumStream<S> {
+    inner: S,
+    framing: BlockFraming<Crc32cCodec>,
+}
+
+impl<S> ChecksumStream<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            framing: BlockFraming::new(Crc32cCodec, DEFAULT_FRAME_SIZE),
+        }
+    }
+
+    /// bytes buffered before frame is sent, default is 16k
+    pub fn frame_size(mut self, size: usize) -> Self {
+        self.framing.set_block_size(size.clamp(1, MAX_FRAME_SIZE));
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ChecksumStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        this.framing.poll_read(&mut this.inner, cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ChecksumStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        this.framing.poll_write(&mut this.inner, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        match this.framing.poll_flush_pending(&mut this.inner, cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        match this.framing.poll_flush_pending(&mut this.inner, cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_close(cx),
+            other => other,
+        }
+    }
+}
+
+impl<S: PollReady> PollReady for ChecksumStream<S> {
+    /// ready without touching socket if buffered bytes can be read
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        if self.framing.read_buffered() {
+            Poll::Ready(Ok(()))
+        } else {
+            self.inner.poll_read_ready(cx)
+        }
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.inner.poll_write_ready(cx)
+    }
+}
+
+/// checksum frames on every stream of inner connector
+pub struct ChecksumConnector<C> {
+    inner: C,
+    frame_size: usize,
+}
+
+impl<C> ChecksumConnector<C> {
+    pub fn new(inner: C) -> Self {
+        Self {
+            inner,
+            frame_size: DEFAULT_FRAME_SIZE,
+        }
+    }
+
+    pub fn frame_size(mut self, size: usize) -> Self {
+        self.frame_size = size;
+        self
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for ChecksumConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = ChecksumStream<C::WrapperStream>;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        let (stream, fd) = self.inner.connect(domain).await?;
+        Ok((ChecksumStream::new(stream).frame_size(self.frame_size), fd))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+    use futures_lite::StreamExt;
+
+    use crate::net::duplex;
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::TcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::test_async;
+
+    use super::crc32c;
+    use super::ChecksumConnector;
+    use super::ChecksumStream;
+
+    #[test_async]
+    async fn test_checksum_stream() -> Result<(), IoError> {
+        // check value of crc32c
+        assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+        assert_eq!(crc32c(b""), 0);
+
+        let (local, peer) = duplex(4096);
+        let mut local = ChecksumStream::new(local).frame_size(1000);
+        let mut peer = ChecksumStream::new(peer);
+        let message = b"record batch ".repeat(500);
+        let write_ft = async {
+            local.write_all(&message).await?;
+            local.close().await
+        };
+        let read_ft = async {
+            let mut received = vec![];
+            peer.read_to_end(&mut received).await?;
+            Ok(received) as Result<Vec<u8>, IoError>
+        };
+        let (write_result, read_result) = zip(write_ft, read_ft).await;
+        write_result?;
+        assert_eq!(read_result?, message);
+
+        // flip bit in payload of frame written by plain stream
+        let (mut local, peer) = duplex(4096);
+        let mut peer = ChecksumStream::new(peer);
+        let mut frame = vec![0, 0, 0, 5];
+        frame.extend_from_slice(b"hello");
+        frame.extend_from_slice(&crc32c(b"hello").to_be_bytes());
+        frame[6] ^= 0x10;
+        local.write_all(&frame).await?;
+        let mut buf = [0; 5];
+        let err = peer.read(&mut buf).await.expect_err("corrupt");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_checksum_connector() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8902";
+        let listener = TcpListener::bind(addr).await?;
+        let connector = ChecksumConnector::new(DefaultTcpDomainConnector::new());
+        let server_ft = async {
+            let stream = listener.incoming().next().await.expect("stream")?;
+            let mut stream = ChecksumStream::new(stream);
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            stream.write_all(b"pong").await?;
+            stream.flush().await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client_ft = async {
+            let (mut stream, _) = connector.connect(addr).await?;
+            stream.write_all(b"ping").await?;
+            stream.flush().await?;
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"pong");
+            Ok(()) as Result<(), IoError>
+        };
+        let (server_result, client_result) = zip(server_ft, client_ft).await;
+        client_result?;
+        server_result?;
+        Ok(())
+    }
+}
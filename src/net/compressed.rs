@@ -0,0 +1,329 @@
+//! compression of any stream, such as tls stream for replication over wan.
+//!
+//! writes are buffered into blocks, each sent as frame of algorithm id, decoded length,
+//! payload length and payload. block which doesn't shrink is sent uncompressed.
+//! only lz4 block format is built in, there is no zstd codec
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use log::debug;
+
+use super::block::{BlockCodec, BlockFraming};
+use super::lz4;
+use super::ConnectorError;
+use super::TcpDomainConnector;
+
+const HEADER_LEN: usize = 9;
+const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+/// largest block peer is allowed to send, protects reader from huge allocations
+const MAX_BLOCK_SIZE: usize = 4 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Lz4,
+}
+
+impl Compression {
+    fn id(&self) -> u8 {
+        match self {
+            Self::None => 0,
+            Self::Lz4 => 1,
+        }
+    }
+
+    fn from_id(id: u8) -> Option<Self> {
+        match id {
+            0 => Some(Self::None),
+            1 => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CompressOptions {
+    algorithms: Vec<Compression>,
+    level: u32,
+    block_size: usize,
+}
+
+impl Default for CompressOptions {
+    fn default() -> Self {
+        Self {
+            algorithms: vec![Compression::Lz4],
+            level: 3,
+            block_size: DEFAULT_BLOCK_SIZE,
+        }
+    }
+}
+
+impl CompressOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// algorithms in order of preference, uncompressed is always accepted
+    pub fn algorithms(mut self, algorithms: Vec<Compression>) -> Self {
+        self.algorithms = algorithms;
+        self
+    }
+
+    /// 1 is fastest, 6 compresses best. higher level uses larger match table
+    pub fn level(mut self, level: u32) -> Self {
+        self.level = level.clamp(1, 6);
+        self
+    }
+
+    /// bytes buffered before block is compressed and sent
+    pub fn block_size(mut self, size: usize) -> Self {
+        self.block_size = size.clamp(1, MAX_BLOCK_SIZE);
+        self
+    }
+}
+
+/// frame of algorithm id, decoded length, payload length and payload
+struct CompressCodec {
+    algorithm: Compression,
+    /// only allocated when writes are compressed
+    compressor: Option<lz4::Compressor>,
+}
+
+fn read_len(bytes: &[u8]) -> usize {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as usize
+}
+
+impl BlockCodec for CompressCodec {
+    fn encode(&mut self, block: &[u8], frame: &mut Vec<u8>) {
+        frame.reserve(HEADER_LEN + lz4::compress_bound(block.len()));
+        frame.extend_from_slice(&[0; HEADER_LEN]);
+        let mut algorithm = self.algorithm;
+        if let Some(compressor) = self.compressor.as_mut() {
+            compressor.compress(block, frame);
+            if frame.len() - HEADER_LEN >= block.len() {
+                frame.truncate(HEADER_LEN);
+                algorithm = Compression::None;
+            }
+        }
+        if algorithm == Compression::None {
+            frame.extend_from_slice(block);
+        }
+        let payload_len = (frame.len() - HEADER_LEN) as u32;
+        frame[0] = algorithm.id();
+        frame[1..5].copy_from_slice(&(block.len() as u32).to_be_bytes());
+        frame[5..9].copy_from_slice(&payload_len.to_be_bytes());
+    }
+
+    fn frame_len(&self, incoming: &[u8]) -> Result<Option<usize>, IoError> {
+        if incoming.len() < HEADER_LEN {
+            return Ok(None);
+        }
+        let len = read_len(&incoming[1..5]);
+        let payload_len = read_len(&incoming[5..9]);
+        if len > MAX_BLOCK_SIZE || payload_len > lz4::compress_bound(MAX_BLOCK_SIZE) {
+            return Err(IoError::new(
+                ErrorKind::InvalidData,
+                "compressed block too large",
+            ));
+        }
+        Ok(Some(HEADER_LEN + payload_len))
+    }
+
+    fn decode(&mut self, frame: &[u8]) -> Result<Vec<u8>, IoError> {
+        let len = read_len(&frame[1..5]);
+        let payload = &frame[HEADER_LEN..];
+        match Compression::from_id(frame[0]) {
+            Some(Compression::None) if payload.len() == len => Ok(payload.to_vec()),
+            Some(Compression::Lz4) => lz4::decompress(payload, len),
+            _ => Err(IoError::new(
+                ErrorKind::InvalidData,
+                "invalid compressed frame",
+            )),
+        }
+    }
+}
+
+/// stream compressing writes and decompressing reads, both ends must use it
+pub struct CompressedStream<S> {
+    inner: S,
+    framing: BlockFraming<CompressCodec>,
+}
+
+impl<S> CompressedStream<S> {
+    /// compress writes with `algorithm`, without negotiating with peer
+    pub fn new(inner: S, algorithm: Compression, options: &CompressOptions) -> Self {
+        let codec = CompressCodec {
+            algorithm,
+            compressor: match algorithm {
+                Compression::Lz4 => Some(lz4::Compressor::new(10 + options.level)),
+                Compression::None => None,
+            },
+        };
+        Self {
+            inner,
+            framing: BlockFraming::new(codec, options.block_size),
+        }
+    }
+
+    /// algorithm used for writes
+    pub fn algorithm(&self) -> Compression {
+        self.framing.codec().algorithm
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> CompressedStream<S> {
+    /// exchange supported algorithms with peer, which must also negotiate.
+    /// writes use first algorithm of `options` peer supports, or no compression
+    pub async fn negotiate(mut inner: S, options: CompressOptions) -> Result<Self, IoError> {
+        let mut hello = vec![options.algorithms.len() as u8];
+        hello.extend(options.algorithms.iter().map(|algorithm| algorithm.id()));
+        inner.write_all(&hello).await?;
+        inner.flush().await?;
+
+        let mut count = [0; 1];
+        inner.read_exact(&mut count).await?;
+        let mut peer = vec![0; count[0] as usize];
+        inner.read_exact(&mut peer).await?;
+        let algorithm = options
+            .algorithms
+            .iter()
+            .copied()
+            .find(|algorithm| peer.contains(&algorithm.id()))
+            .unwrap_or(Compression::None);
+        debug!("negotiated compression: {:?}", algorithm);
+        Ok(Self::new(inner, algorithm, &options))
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for CompressedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        this.framing.poll_read(&mut this.inner, cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for CompressedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        this.framing.poll_write(&mut this.inner, cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        match this.framing.poll_flush_pending(&mut this.inner, cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_flush(cx),
+            other => other,
+        }
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        match this.framing.poll_flush_pending(&mut this.inner, cx) {
+            Poll::Ready(Ok(())) => Pin::new(&mut this.inner).poll_close(cx),
+            other => other,
+        }
+    }
+}
+
+/// negotiate compression on every stream of inner connector
+pub struct CompressedConnector<C> {
+    inner: C,
+    options: CompressOptions,
+}
+
+impl<C> CompressedConnector<C> {
+    pub fn new(inner: C, options: CompressOptions) -> Self {
+        Self { inner, options }
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for CompressedConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = CompressedStream<C::WrapperStream>;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        let (stream, fd) = self.inner.connect(domain).await?;
+        let stream = CompressedStream::negotiate(stream, self.options.clone()).await?;
+        Ok((stream, fd))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::duplex;
+    use crate::test_async;
+
+    use super::CompressOptions;
+    use super::CompressedStream;
+    use super::Compression;
+
+    #[test_async]
+    async fn test_compressed_stream() -> Result<(), IoError> {
+        let options = CompressOptions::new().block_size(16 * 1024);
+
+        // each side compresses only with algorithm other side accepts
+        let (local, peer) = duplex(4096);
+        let (local, peer) = zip(
+            CompressedStream::negotiate(local, options.clone()),
+            CompressedStream::negotiate(
+                peer,
+                CompressOptions::new().algorithms(vec![Compression::None]),
+            ),
+        )
+        .await;
+        assert_eq!(local?.algorithm(), Compression::None);
+        assert_eq!(peer?.algorithm(), Compression::None);
+
+        let (local, peer) = duplex(4096);
+        let (local, peer) = zip(
+            CompressedStream::negotiate(local, options.clone()),
+            CompressedStream::negotiate(peer, options),
+        )
+        .await;
+        let (mut local, mut peer) = (local?, peer?);
+        assert_eq!(local.algorithm(), Compression::Lz4);
+
+        let message = b"replicated record batch ".repeat(4000);
+        let write_ft = async {
+            local.write_all(&message).await?;
+            local.write_all(b"end").await?;
+            local.close().await
+        };
+        let read_ft = async {
+            let mut received = vec![];
+            peer.read_to_end(&mut received).await?;
+            Ok(received) as Result<Vec<u8>, IoError>
+        };
+        let (write_result, read_result) = zip(write_ft, read_ft).await;
+        write_result?;
+        assert_eq!(read_result?, [&message[..], b"end"].concat());
+        Ok(())
+    }
+}
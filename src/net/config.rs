@@ -0,0 +1,126 @@
+//! connector settings deserialized from yaml, toml or other serde format,
+//! converted to connector with `AllDomainConnector::try_from`
+use std::fs;
+use std::io::Error as IoError;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use serde::Deserialize;
+
+use super::DefaultTcpDomainConnector;
+use super::ProxyVersion;
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectorConfig {
+    pub transport: TransportType,
+    pub tls: TlsConfig,
+    /// timeout of dns resolution and tcp connect, in milliseconds
+    pub connect_timeout_ms: Option<u64>,
+    /// send PROXY protocol header with this version after connecting
+    pub proxy_protocol: Option<ProxyVersion>,
+}
+
+impl ConnectorConfig {
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout_ms.map(Duration::from_millis)
+    }
+
+    /// tcp connector with timeout and proxy settings, tls is layered over it
+    pub fn tcp_connector(&self) -> DefaultTcpDomainConnector {
+        let mut connector = DefaultTcpDomainConnector::new();
+        if let Some(timeout) = self.connect_timeout() {
+            connector = connector.connect_timeout(timeout);
+        }
+        if let Some(version) = self.proxy_protocol {
+            connector = connector.proxy_protocol(version);
+        }
+        connector
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportType {
+    #[default]
+    Tcp,
+    Tls,
+    /// unix socket, tcp settings such as connect timeout don't apply
+    Unix,
+}
+
+/// used only when transport is tls
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsConfig {
+    /// domain to verify, otherwise connect is anonymous with host of address as server name
+    pub domain: Option<String>,
+    pub ca_cert: Option<PemSource>,
+    /// client certificate, must be set together with `client_key`
+    pub client_cert: Option<PemSource>,
+    pub client_key: Option<PemSource>,
+    /// skip verification of server certificate, connector then needs `allow_insecure`
+    pub insecure: bool,
+}
+
+/// pem file, either as `path: <file>` or inline as `pem: <contents>`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PemSource {
+    Path(PathBuf),
+    Pem(String),
+}
+
+impl PemSource {
+    pub fn load(&self) -> Result<Vec<u8>, IoError> {
+        match self {
+            Self::Path(path) => fs::read(path),
+            Self::Pem(pem) => Ok(pem.as_bytes().to_vec()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use super::ConnectorConfig;
+    use super::PemSource;
+    use super::TransportType;
+    use crate::net::ProxyVersion;
+
+    #[test]
+    fn test_connector_config() {
+        let config: ConnectorConfig = serde_json::from_str(
+            r#"{
+                "transport": "tls",
+                "tls": {
+                    "domain": "localhost",
+                    "ca_cert": { "path": "certs/certs/ca.crt" },
+                    "client_key": { "pem": "key" },
+                    "insecure": true
+                },
+                "connect_timeout_ms": 500,
+                "proxy_protocol": "v2"
+            }"#,
+        )
+        .expect("parse");
+        assert_eq!(config.transport, TransportType::Tls);
+        assert_eq!(config.tls.domain.as_deref(), Some("localhost"));
+        assert!(config.tls.ca_cert.as_ref().unwrap().load().is_ok());
+        assert_eq!(
+            config.tls.client_key,
+            Some(PemSource::Pem("key".to_owned()))
+        );
+        assert!(config.tls.insecure);
+        assert_eq!(config.connect_timeout(), Some(Duration::from_millis(500)));
+        assert_eq!(config.proxy_protocol, Some(ProxyVersion::V2));
+
+        let config: ConnectorConfig = serde_json::from_str("{}").expect("parse");
+        assert_eq!(config, ConnectorConfig::default());
+        assert_eq!(config.transport, TransportType::Tcp);
+
+        assert!(serde_json::from_str::<ConnectorConfig>(r#"{"timeout": 1}"#).is_err());
+    }
+}
@@ -0,0 +1,449 @@
+//! overall deadline of connect, shared by resolution, tcp connect, tls handshake and retries.
+//!
+//! connectors bound their stages by deadline of enclosing `Deadline::scope`, so budget set once
+//! at top is enforced by every layer below it. scope is per task, spawned futures don't inherit it.
+//!
+//! `DeadlineAcceptor` bounds accepted connections instead, against slow loris clients
+use std::cell::Cell;
+use std::future::Future;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures_lite::FutureExt;
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use super::ConnectorError;
+use super::SharedEventListener;
+use super::TcpDomainAcceptor;
+use super::TcpDomainConnector;
+use super::TcpStream;
+use super::Violation;
+use crate::timer::{now, sleep, sleep_until, Sleeper};
+
+thread_local! {
+    static CURRENT: Cell<Option<Instant>> = const { Cell::new(None) };
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Deadline(Instant);
+
+impl Deadline {
+    pub fn at(instant: Instant) -> Self {
+        Self(instant)
+    }
+
+    pub fn after(budget: Duration) -> Self {
+        Self(now() + budget)
+    }
+
+    pub fn instant(&self) -> Instant {
+        self.0
+    }
+
+    pub fn remaining(&self) -> Duration {
+        self.0.saturating_duration_since(now())
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.remaining() == Duration::ZERO
+    }
+
+    /// deadline of enclosing scope, if any
+    pub fn current() -> Option<Self> {
+        CURRENT.with(|current| current.get()).map(Self)
+    }
+
+    /// run `future` with this deadline, earlier one of enclosing scope still applies
+    pub fn scope<F: Future>(self, future: F) -> Scoped<F> {
+        Scoped {
+            deadline: self,
+            inner: Box::pin(future),
+        }
+    }
+}
+
+/// future running with deadline set, see `Deadline::scope`
+pub struct Scoped<F> {
+    deadline: Deadline,
+    inner: Pin<Box<F>>,
+}
+
+/// restores deadline of enclosing scope, even if polled future panics
+struct Restore(Option<Instant>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        CURRENT.with(|current| current.set(self.0));
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let deadline = match Deadline::current() {
+            Some(outer) => outer.min(this.deadline),
+            None => this.deadline,
+        };
+        let _restore = Restore(CURRENT.with(|current| current.replace(Some(deadline.0))));
+        this.inner.as_mut().poll(cx)
+    }
+}
+
+/// fail with `ConnectorError::Timeout` if `future` doesn't finish by current deadline
+pub async fn within_deadline<F: Future>(future: F) -> Result<F::Output, ConnectorError> {
+    match Deadline::current() {
+        Some(deadline) if deadline.is_expired() => Err(ConnectorError::Timeout),
+        Some(deadline) => {
+            let timed_out = async {
+                sleep_until(deadline.0).await;
+                Err(ConnectorError::Timeout)
+            };
+            async { Ok(future.await) }.or(timed_out).await
+        }
+        None => Ok(future.await),
+    }
+}
+
+/// give every connect of inner connector `budget`, covering all its stages and retries
+pub struct DeadlineConnector<C> {
+    inner: C,
+    budget: Duration,
+}
+
+impl<C> DeadlineConnector<C> {
+    pub fn new(inner: C, budget: Duration) -> Self {
+        Self { inner, budget }
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for DeadlineConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = C::WrapperStream;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        Deadline::after(self.budget)
+            .scope(self.inner.connect(domain))
+            .await
+    }
+}
+
+/// drop accepted connections whose handshake or first bytes are too slow,
+/// violations are reported to listener
+#[derive(Clone)]
+pub struct DeadlineAcceptor<A> {
+    inner: A,
+    handshake_timeout: Option<Duration>,
+    first_byte_timeout: Option<Duration>,
+    listener: Option<SharedEventListener>,
+}
+
+impl<A> DeadlineAcceptor<A> {
+    pub fn new(inner: A) -> Self {
+        Self {
+            inner,
+            handshake_timeout: None,
+            first_byte_timeout: None,
+            listener: None,
+        }
+    }
+
+    /// accept of inner acceptor, such as tls handshake, must finish within `timeout`
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = Some(timeout);
+        self
+    }
+
+    /// first bytes must be received within `timeout` after accept
+    pub fn first_byte_timeout(mut self, timeout: Duration) -> Self {
+        self.first_byte_timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_listener(mut self, listener: SharedEventListener) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+}
+
+fn violated(listener: &Option<SharedEventListener>, target: &str, violation: Violation) -> IoError {
+    debug!("peer: {} {}", target, violation);
+    if let Some(listener) = listener {
+        listener.on_violation(target, &violation);
+    }
+    IoError::new(ErrorKind::TimedOut, violation.to_string())
+}
+
+#[async_trait]
+impl<A> TcpDomainAcceptor for DeadlineAcceptor<A>
+where
+    A: TcpDomainAcceptor + Send + Sync,
+{
+    type WrapperStream = FirstByteStream<A::WrapperStream>;
+
+    async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+        let target: Arc<str> = match stream.peer_addr() {
+            Ok(addr) => Arc::from(addr.to_string()),
+            Err(_) => Arc::from("unknown"),
+        };
+        let accepted = match self.handshake_timeout {
+            Some(timeout) => {
+                let timed_out = async {
+                    sleep(timeout).await;
+                    None
+                };
+                match async { Some(self.inner.accept(stream).await) }
+                    .or(timed_out)
+                    .await
+                {
+                    Some(result) => result?,
+                    None => {
+                        let violation = Violation::HandshakeTimeout(timeout);
+                        return Err(violated(&self.listener, &target, violation));
+                    }
+                }
+            }
+            None => self.inner.accept(stream).await?,
+        };
+        Ok(FirstByteStream {
+            inner: accepted,
+            timer: self
+                .first_byte_timeout
+                .map(|timeout| (sleep(timeout), timeout)),
+            expired: None,
+            listener: self.listener.clone(),
+            target,
+        })
+    }
+}
+
+/// accepted stream failing reads with `ErrorKind::TimedOut` if nothing is received in time.
+/// deadline is checked while stream is read
+pub struct FirstByteStream<S> {
+    inner: S,
+    timer: Option<(Sleeper, Duration)>,
+    expired: Option<Violation>,
+    listener: Option<SharedEventListener>,
+    target: Arc<str>,
+}
+
+impl<S> FirstByteStream<S> {
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for FirstByteStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        if let Some(violation) = this.expired {
+            return Poll::Ready(Err(IoError::new(
+                ErrorKind::TimedOut,
+                violation.to_string(),
+            )));
+        }
+        let result = Pin::new(&mut this.inner).poll_read(cx, buf);
+        match &result {
+            Poll::Ready(Ok(len)) if *len > 0 => this.timer = None,
+            Poll::Pending => {
+                if let Some((timer, timeout)) = &mut this.timer {
+                    if Pin::new(timer).poll(cx).is_ready() {
+                        let violation = Violation::FirstByteTimeout(*timeout);
+                        this.timer = None;
+                        this.expired = Some(violation);
+                        return Poll::Ready(Err(violated(&this.listener, &this.target, violation)));
+                    }
+                }
+            }
+            Poll::Ready(_) => {}
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for FirstByteStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::net::SocketAddr;
+    use std::os::unix::io::RawFd;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use async_trait::async_trait;
+    use futures_lite::future::pending;
+    use futures_lite::stream::StreamExt;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::duplex;
+    use crate::net::ConnectorError;
+    use crate::net::DefaultTcpDomainAcceptor;
+    use crate::net::DuplexStream;
+    use crate::net::EventListener;
+    use crate::net::TcpDomainAcceptor;
+    use crate::net::TcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::net::TcpStream;
+    use crate::net::Violation;
+    use crate::test_async;
+
+    use super::within_deadline;
+    use super::Deadline;
+    use super::DeadlineAcceptor;
+    use super::DeadlineConnector;
+
+    /// connector whose stages never finish
+    struct StuckConnector;
+
+    #[async_trait]
+    impl TcpDomainConnector for StuckConnector {
+        type WrapperStream = DuplexStream;
+
+        async fn connect(
+            &self,
+            _domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            within_deadline(pending::<()>()).await?;
+            Ok((duplex(16).0, 0))
+        }
+    }
+
+    /// acceptor whose handshake never finishes
+    struct StuckAcceptor;
+
+    #[async_trait]
+    impl TcpDomainAcceptor for StuckAcceptor {
+        type WrapperStream = TcpStream;
+
+        async fn accept(&self, _stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+            pending().await
+        }
+    }
+
+    #[derive(Default)]
+    struct Violations(Mutex<Vec<Violation>>);
+
+    impl EventListener for Violations {
+        fn on_violation(&self, _target: &str, violation: &Violation) {
+            self.0.lock().unwrap().push(*violation);
+        }
+    }
+
+    #[test_async]
+    async fn test_deadline() -> Result<(), IoError> {
+        assert!(Deadline::current().is_none());
+        assert_eq!(within_deadline(async { 1 }).await.ok(), Some(1));
+
+        let outer = Deadline::after(Duration::from_millis(100));
+        let inner = Deadline::after(Duration::from_secs(10));
+        let current = outer
+            .scope(inner.scope(async { Deadline::current() }))
+            .await;
+        assert_eq!(current, Some(outer));
+        assert!(Deadline::current().is_none());
+
+        let start = Instant::now();
+        let result = DeadlineConnector::new(StuckConnector, Duration::from_millis(50))
+            .connect("stuck")
+            .await;
+        assert!(matches!(result, Err(ConnectorError::Timeout)));
+        assert!(start.elapsed() < Duration::from_secs(5));
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_deadline_acceptor() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8907".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let violations = Arc::new(Violations::default());
+        let timeout = Duration::from_millis(50);
+
+        let _client = TcpStream::connect(&addr).await?;
+        let stream = listener.incoming().next().await.expect("stream")?;
+        let err = DeadlineAcceptor::new(StuckAcceptor)
+            .handshake_timeout(timeout)
+            .with_listener(violations.clone())
+            .accept(stream)
+            .await
+            .err()
+            .expect("stuck");
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        let acceptor = DeadlineAcceptor::new(DefaultTcpDomainAcceptor::new())
+            .first_byte_timeout(timeout)
+            .with_listener(violations.clone());
+        let _silent = TcpStream::connect(&addr).await?;
+        let stream = listener.incoming().next().await.expect("stream")?;
+        let mut stream = acceptor.accept(stream).await?;
+        let mut buf = [0; 4];
+        let err = stream.read(&mut buf).await.expect_err("silent");
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+
+        // once data arrived, idle peer is not dropped
+        let mut client = TcpStream::connect(&addr).await?;
+        client.write_all(b"ping").await?;
+        let stream = listener.incoming().next().await.expect("stream")?;
+        let mut stream = acceptor.accept(stream).await?;
+        stream.read_exact(&mut buf).await?;
+        let idle = async {
+            stream.read(&mut buf).await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let waited = async {
+            crate::timer::sleep(timeout * 3).await;
+            Ok(())
+        };
+        futures_lite::future::or(idle, waited).await?;
+
+        assert_eq!(
+            *violations.0.lock().unwrap(),
+            vec![
+                Violation::HandshakeTimeout(timeout),
+                Violation::FirstByteTimeout(timeout)
+            ]
+        );
+        Ok(())
+    }
+}
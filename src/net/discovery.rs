@@ -0,0 +1,274 @@
+//! peer discovery over udp multicast, for bootstrapping local cluster.
+//!
+//! peers periodically announce themselves to multicast group with `multicast_announce`,
+//! others collect announcements with `multicast_listen`. payload of announcement is up to caller
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::mem;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use futures_lite::future::FutureExt;
+use futures_lite::stream;
+use futures_lite::Stream;
+use futures_lite::StreamExt;
+use log::debug;
+use nix::sys::socket::bind;
+use nix::sys::socket::setsockopt;
+use nix::sys::socket::socket;
+use nix::sys::socket::sockopt;
+use nix::sys::socket::AddressFamily;
+use nix::sys::socket::InetAddr;
+use nix::sys::socket::SockAddr;
+use nix::sys::socket::SockFlag;
+use nix::sys::socket::SockType;
+
+use super::nix_error;
+use super::UdpSocket;
+use crate::timer::sleep;
+
+/// announcements are expected to fit in one datagram without fragmentation
+const MAX_ANNOUNCEMENT: usize = 1472;
+
+/// multicast group and how to reach it
+#[derive(Debug, Clone)]
+pub struct MulticastConfig {
+    group: SocketAddr,
+    ttl: u32,
+    loopback: bool,
+    interface_v4: Option<Ipv4Addr>,
+    interface_v6: u32,
+}
+
+impl MulticastConfig {
+    /// group such as `239.255.0.1:7000`, announcements stay in local network by default
+    pub fn new(group: SocketAddr) -> Self {
+        Self {
+            group,
+            ttl: 1,
+            loopback: true,
+            interface_v4: None,
+            interface_v6: 0,
+        }
+    }
+
+    pub fn group(&self) -> SocketAddr {
+        self.group
+    }
+
+    /// hops announcement may travel, 1 keeps it in local network
+    pub fn ttl(mut self, ttl: u32) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// whether announcements are delivered to listeners on same host
+    pub fn loopback(mut self, loopback: bool) -> Self {
+        self.loopback = loopback;
+        self
+    }
+
+    /// address of interface for ipv4 group, default is picked by routing table
+    pub fn interface_v4(mut self, interface: Ipv4Addr) -> Self {
+        self.interface_v4 = Some(interface);
+        self
+    }
+
+    /// index of interface for ipv6 group, 0 is default interface
+    pub fn interface_v6(mut self, index: u32) -> Self {
+        self.interface_v6 = index;
+        self
+    }
+}
+
+/// announcement received from peer
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Announcement {
+    pub from: SocketAddr,
+    pub payload: Vec<u8>,
+}
+
+fn set_option<T>(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: &T,
+) -> Result<(), IoError> {
+    let result = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            value as *const T as *const libc::c_void,
+            mem::size_of::<T>() as libc::socklen_t,
+        )
+    };
+    if result < 0 {
+        Err(IoError::last_os_error())
+    } else {
+        Ok(())
+    }
+}
+
+/// socket for sending to group, with ttl, loopback and interface of config
+fn announce_socket(config: &MulticastConfig) -> Result<std::net::UdpSocket, IoError> {
+    match config.group.ip() {
+        IpAddr::V4(_) => {
+            let socket = std::net::UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+            socket.set_multicast_ttl_v4(config.ttl)?;
+            socket.set_multicast_loop_v4(config.loopback)?;
+            if let Some(interface) = config.interface_v4 {
+                let addr = libc::in_addr {
+                    s_addr: u32::from(interface).to_be(),
+                };
+                set_option(
+                    socket.as_raw_fd(),
+                    libc::IPPROTO_IP,
+                    libc::IP_MULTICAST_IF,
+                    &addr,
+                )?;
+            }
+            Ok(socket)
+        }
+        IpAddr::V6(_) => {
+            let socket = std::net::UdpSocket::bind((Ipv6Addr::UNSPECIFIED, 0))?;
+            socket.set_multicast_loop_v6(config.loopback)?;
+            let fd = socket.as_raw_fd();
+            let hops = config.ttl as libc::c_int;
+            set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_HOPS, &hops)?;
+            if config.interface_v6 != 0 {
+                let index = config.interface_v6 as libc::c_int;
+                set_option(fd, libc::IPPROTO_IPV6, libc::IPV6_MULTICAST_IF, &index)?;
+            }
+            Ok(socket)
+        }
+    }
+}
+
+/// send one announcement to group, caller repeats it at interval so late listeners see peer
+pub async fn multicast_announce(config: &MulticastConfig, payload: &[u8]) -> Result<(), IoError> {
+    if payload.len() > MAX_ANNOUNCEMENT {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!("announcement of {} bytes is too large", payload.len()),
+        ));
+    }
+    let socket = UdpSocket::try_from(announce_socket(config)?)?;
+    socket.send_to(payload, config.group).await?;
+    debug!("announced {} bytes to: {}", payload.len(), config.group);
+    Ok(())
+}
+
+/// socket bound to port of group with `SO_REUSEADDR`, so multiple listeners can share host
+fn listen_socket(config: &MulticastConfig) -> Result<std::net::UdpSocket, IoError> {
+    let (family, unspecified) = match config.group {
+        SocketAddr::V4(_) => (AddressFamily::Inet, IpAddr::V4(Ipv4Addr::UNSPECIFIED)),
+        SocketAddr::V6(_) => (AddressFamily::Inet6, IpAddr::V6(Ipv6Addr::UNSPECIFIED)),
+    };
+    let fd = socket(family, SockType::Datagram, SockFlag::SOCK_CLOEXEC, None).map_err(nix_error)?;
+    // owned right away so fd is closed on error
+    let socket = unsafe { std::net::UdpSocket::from_raw_fd(fd) };
+    setsockopt(fd, sockopt::ReuseAddr, &true).map_err(nix_error)?;
+    setsockopt(fd, sockopt::ReusePort, &true).map_err(nix_error)?;
+    let addr = SocketAddr::new(unspecified, config.group.port());
+    bind(fd, &SockAddr::new_inet(InetAddr::from_std(&addr))).map_err(nix_error)?;
+    match config.group.ip() {
+        IpAddr::V4(group) => socket.join_multicast_v4(
+            &group,
+            &config.interface_v4.unwrap_or(Ipv4Addr::UNSPECIFIED),
+        )?,
+        IpAddr::V6(group) => socket.join_multicast_v6(&group, config.interface_v6)?,
+    }
+    Ok(socket)
+}
+
+/// join group and receive announcements of peers, until stream is dropped
+pub fn multicast_listen(
+    config: &MulticastConfig,
+) -> Result<impl Stream<Item = Result<Announcement, IoError>> + Send + 'static, IoError> {
+    let socket = UdpSocket::try_from(listen_socket(config)?)?;
+    debug!("listening for announcements on: {}", config.group);
+    Ok(stream::unfold(socket, |socket| async move {
+        let mut buf = vec![0; MAX_ANNOUNCEMENT];
+        let announcement = socket.recv_from(&mut buf).await.map(|(n, from)| {
+            buf.truncate(n);
+            Announcement { from, payload: buf }
+        });
+        Some((announcement, socket))
+    }))
+}
+
+/// latest announcement of each peer heard within `window`
+pub async fn collect_announcements(
+    config: &MulticastConfig,
+    window: Duration,
+) -> Result<BTreeMap<SocketAddr, Vec<u8>>, IoError> {
+    let announcements = multicast_listen(config)?;
+    futures_lite::pin!(announcements);
+    let mut peers = BTreeMap::new();
+    let collect = async {
+        while let Some(announcement) = announcements.next().await {
+            let announcement = announcement?;
+            peers.insert(announcement.from, announcement.payload);
+        }
+        Ok(()) as Result<(), IoError>
+    };
+    let window_end = async {
+        sleep(window).await;
+        Ok(())
+    };
+    collect.or(window_end).await?;
+    Ok(peers)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::StreamExt;
+    use log::debug;
+
+    use crate::test_async;
+
+    use super::collect_announcements;
+    use super::multicast_announce;
+    use super::multicast_listen;
+    use super::MulticastConfig;
+
+    #[test_async]
+    async fn test_multicast_discovery() -> Result<(), IoError> {
+        let config = MulticastConfig::new("239.255.70.70:8912".parse().expect("group"));
+        let first = multicast_listen(&config)?;
+        // port is shared by listeners on same host
+        let second = multicast_listen(&config)?;
+        futures_lite::pin!(first);
+        futures_lite::pin!(second);
+
+        // multicast needs route, missing in some sandboxes
+        if let Err(err) = multicast_announce(&config, b"node-1").await {
+            debug!("multicast not routable: {}", err);
+            return Ok(());
+        }
+        let announcement = first.next().await.expect("announcement")?;
+        assert_eq!(announcement.payload, b"node-1");
+        let announcement = second.next().await.expect("announcement")?;
+        assert_eq!(announcement.payload, b"node-1");
+
+        let too_large = vec![0; 2000];
+        assert!(multicast_announce(&config, &too_large).await.is_err());
+
+        let peers = collect_announcements(&config, Duration::from_millis(100)).await?;
+        assert!(peers.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,181 @@
+//! in memory stream pair, for testing protocol and tls logic without sockets
+use std::collections::VecDeque;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+
+/// create two connected streams, what is written to one can be read from other.
+/// writes wait once `buffer_size` bytes are waiting to be read
+pub fn duplex(buffer_size: usize) -> (DuplexStream, DuplexStream) {
+    assert!(buffer_size > 0, "buffer size must be positive");
+    let one = Arc::new(Mutex::new(Pipe::new(buffer_size)));
+    let two = Arc::new(Mutex::new(Pipe::new(buffer_size)));
+    (
+        DuplexStream {
+            read: one.clone(),
+            write: two.clone(),
+        },
+        DuplexStream {
+            read: two,
+            write: one,
+        },
+    )
+}
+
+/// one direction of duplex
+struct Pipe {
+    buffer: VecDeque<u8>,
+    max_size: usize,
+    closed: bool,
+    read_waker: Option<Waker>,
+    write_waker: Option<Waker>,
+}
+
+impl Pipe {
+    fn new(max_size: usize) -> Self {
+        Self {
+            buffer: VecDeque::with_capacity(max_size),
+            max_size,
+            closed: false,
+            read_waker: None,
+            write_waker: None,
+        }
+    }
+
+    fn close(&mut self) {
+        self.closed = true;
+        if let Some(waker) = self.read_waker.take() {
+            waker.wake();
+        }
+        if let Some(waker) = self.write_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// one end of `duplex`, closing or dropping it makes peer read eof
+pub struct DuplexStream {
+    read: Arc<Mutex<Pipe>>,
+    write: Arc<Mutex<Pipe>>,
+}
+
+impl Drop for DuplexStream {
+    fn drop(&mut self) {
+        self.read.lock().unwrap().close();
+        self.write.lock().unwrap().close();
+    }
+}
+
+impl AsyncRead for DuplexStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let mut pipe = self.read.lock().unwrap();
+        if pipe.buffer.is_empty() {
+            if pipe.closed || buf.is_empty() {
+                return Poll::Ready(Ok(0));
+            }
+            pipe.read_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let len = buf.len().min(pipe.buffer.len());
+        for (dest, src) in buf.iter_mut().zip(pipe.buffer.drain(..len)) {
+            *dest = src;
+        }
+        if let Some(waker) = pipe.write_waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for DuplexStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let mut pipe = self.write.lock().unwrap();
+        if pipe.closed {
+            return Poll::Ready(Err(IoError::new(
+                ErrorKind::BrokenPipe,
+                "duplex stream closed",
+            )));
+        }
+        let available = pipe.max_size - pipe.buffer.len();
+        if available == 0 {
+            pipe.write_waker = Some(cx.waker().clone());
+            return Poll::Pending;
+        }
+
+        let len = buf.len().min(available);
+        pipe.buffer.extend(&buf[..len]);
+        if let Some(waker) = pipe.read_waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.write.lock().unwrap().close();
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::test_async;
+
+    use super::duplex;
+
+    #[test_async]
+    async fn test_duplex() -> Result<(), IoError> {
+        let (mut client, mut server) = duplex(4);
+
+        // writer must wait for reader since message is larger than buffer
+        let message = b"hello world";
+        let write = async {
+            client.write_all(message).await?;
+            client.close().await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let read = async {
+            let mut received = vec![];
+            server.read_to_end(&mut received).await?;
+            Ok(received) as Result<Vec<u8>, IoError>
+        };
+        let (write_result, read_result) = zip(write, read).await;
+        write_result?;
+        assert_eq!(read_result?, message);
+
+        server.write_all(b"ok").await?;
+        let mut buf = [0; 2];
+        client.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ok");
+
+        drop(server);
+        let err = client.write_all(b"a").await.expect_err("peer dropped");
+        assert_eq!(err.kind(), ErrorKind::BrokenPipe);
+        Ok(())
+    }
+}
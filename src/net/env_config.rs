@@ -0,0 +1,112 @@
+//! tls settings from environment variables, shared by tls connector builders
+use std::env;
+use std::fs;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+
+/// tls settings read from `<PREFIX>_CA_CERT`, `<PREFIX>_CLIENT_CERT`, `<PREFIX>_CLIENT_KEY`
+/// and `<PREFIX>_INSECURE`. certificates and key are either path of pem file or inline pem
+#[derive(Debug, Default, PartialEq)]
+pub(crate) struct TlsEnv {
+    pub ca_cert: Option<Vec<u8>>,
+    /// pem of client certificate and key
+    pub client: Option<(Vec<u8>, Vec<u8>)>,
+    pub insecure: bool,
+}
+
+impl TlsEnv {
+    pub fn from_env(prefix: &str) -> Result<Self, IoError> {
+        Self::from_vars(prefix, |name| env::var(name).ok())
+    }
+
+    fn from_vars<F>(prefix: &str, var: F) -> Result<Self, IoError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let var = |suffix: &str| {
+            let name = format!("{}_{}", prefix, suffix);
+            var(&name)
+                .filter(|value| !value.is_empty())
+                .map(|value| (name, value))
+        };
+        let invalid = |message: String| IoError::new(ErrorKind::InvalidInput, message);
+
+        let ca_cert = var("CA_CERT")
+            .map(|(_, value)| load_pem(&value))
+            .transpose()?;
+        let client = match (var("CLIENT_CERT"), var("CLIENT_KEY")) {
+            (Some((_, cert)), Some((_, key))) => Some((load_pem(&cert)?, load_pem(&key)?)),
+            (None, None) => None,
+            (Some((name, _)), None) | (None, Some((name, _))) => {
+                return Err(invalid(format!(
+                    "{} is set without other half of client certificate and key",
+                    name
+                )))
+            }
+        };
+        let insecure = match var("INSECURE") {
+            Some((name, value)) => match value.to_lowercase().as_str() {
+                "1" | "true" | "yes" => true,
+                "0" | "false" | "no" => false,
+                _ => return Err(invalid(format!("invalid {}: {}", name, value))),
+            },
+            None => false,
+        };
+
+        Ok(Self {
+            ca_cert,
+            client,
+            insecure,
+        })
+    }
+}
+
+/// inline pem is used as is, otherwise value is path of pem file
+fn load_pem(value: &str) -> Result<Vec<u8>, IoError> {
+    if value.trim_start().starts_with("-----BEGIN") {
+        Ok(value.as_bytes().to_vec())
+    } else {
+        fs::read(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::collections::HashMap;
+    use std::fs;
+    use std::io::ErrorKind;
+
+    use super::TlsEnv;
+
+    #[test]
+    fn test_tls_env() {
+        let from = |vars: &[(&str, &str)]| {
+            let vars: HashMap<String, String> = vars
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect();
+            TlsEnv::from_vars("APP", |name| vars.get(name).cloned())
+        };
+
+        assert_eq!(from(&[]).unwrap(), TlsEnv::default());
+
+        let ca = fs::read("certs/certs/ca.crt").unwrap();
+        let env = from(&[
+            ("APP_CA_CERT", "certs/certs/ca.crt"),
+            ("APP_CLIENT_CERT", "-----BEGIN CERTIFICATE-----"),
+            ("APP_CLIENT_KEY", "certs/certs/client.key"),
+            ("APP_INSECURE", "True"),
+        ])
+        .unwrap();
+        assert_eq!(env.ca_cert, Some(ca));
+        let (cert, _) = env.client.expect("client");
+        assert_eq!(cert, b"-----BEGIN CERTIFICATE-----");
+        assert!(env.insecure);
+
+        let err = from(&[("APP_CLIENT_CERT", "client.crt")]).expect_err("no key");
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert!(from(&[("APP_INSECURE", "maybe")]).is_err());
+        assert!(from(&[("APP_CA_CERT", "missing.crt")]).is_err());
+    }
+}
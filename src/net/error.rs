@@ -0,0 +1,254 @@
+use std::error::Error as StdError;
+use std::fmt;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use thiserror::Error;
+
+pub type BoxError = Box<dyn StdError + Send + Sync>;
+
+/// failure of connector, keeps which phase of connect failed
+#[derive(Error, Debug)]
+pub enum ConnectorError {
+    #[error("dns resolution of {target} failed: {source}")]
+    Dns {
+        target: String,
+        #[source]
+        source: IoError,
+    },
+    #[error("tcp connect failed: {0}")]
+    Tcp(#[from] IoError),
+    #[error("tls handshake failed: {source}")]
+    TlsHandshake {
+        /// reason certificate was rejected, if failure was from verification
+        verify_result: Option<String>,
+        #[source]
+        source: BoxError,
+    },
+    #[error("connect timed out")]
+    Timeout,
+    /// rejected without connecting since circuit breaker is open
+    #[error("circuit breaker open")]
+    CircuitOpen,
+    /// tls connector doesn't verify certificates and application didn't allow that
+    #[error("insecure tls connector is not allowed")]
+    InsecureNotAllowed,
+    /// target isn't `scheme://address` uri
+    #[error("invalid connect target: {0}")]
+    InvalidTarget(String),
+    #[error("no connector for scheme: {0}")]
+    UnknownScheme(String),
+    /// address is outside allow list or in deny list of `IpFilterConnector`
+    #[error("address {0} is not allowed")]
+    AddressNotAllowed(SocketAddr),
+    #[error("{context}, {source}")]
+    Context {
+        context: ErrorContext,
+        #[source]
+        source: Box<ConnectorError>,
+    },
+}
+
+/// where connect was going when it failed
+#[derive(Debug, Clone, Default)]
+pub struct ErrorContext {
+    domain: Option<String>,
+    addr: Option<SocketAddr>,
+    elapsed: Option<Duration>,
+}
+
+impl ErrorContext {
+    /// logical domain, for tls this is domain used for verification
+    pub fn domain(&self) -> Option<&str> {
+        self.domain.as_deref()
+    }
+
+    /// resolved socket address, if connect got that far
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.addr
+    }
+
+    /// time spent in connect until failure
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.elapsed
+    }
+}
+
+impl fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "domain: {}", self.domain().unwrap_or("unknown"))?;
+        if let Some(addr) = self.addr {
+            write!(f, ", addr: {}", addr)?;
+        }
+        if let Some(elapsed) = self.elapsed {
+            write!(f, ", elapsed: {}ms", elapsed.as_millis())?;
+        }
+        Ok(())
+    }
+}
+
+impl ConnectorError {
+    pub fn tls_handshake<E>(verify_result: Option<String>, source: E) -> Self
+    where
+        E: Into<BoxError>,
+    {
+        Self::TlsHandshake {
+            verify_result,
+            source: source.into(),
+        }
+    }
+
+    /// true if peer certificate was rejected
+    pub fn is_certificate_error(&self) -> bool {
+        matches!(
+            self.inner(),
+            Self::TlsHandshake {
+                verify_result: Some(_),
+                ..
+            }
+        )
+    }
+
+    pub fn kind(&self) -> ErrorKind {
+        match self.inner() {
+            Self::Dns { .. } => ErrorKind::NotFound,
+            Self::Tcp(err) => err.kind(),
+            Self::TlsHandshake { .. } => ErrorKind::ConnectionRefused,
+            Self::Timeout => ErrorKind::TimedOut,
+            Self::CircuitOpen => ErrorKind::ConnectionRefused,
+            Self::InsecureNotAllowed | Self::AddressNotAllowed(_) => ErrorKind::PermissionDenied,
+            Self::InvalidTarget(_) | Self::UnknownScheme(_) => ErrorKind::InvalidInput,
+            Self::Context { .. } => ErrorKind::Other,
+        }
+    }
+
+    /// error without context
+    pub fn inner(&self) -> &ConnectorError {
+        match self {
+            Self::Context { source, .. } => source.inner(),
+            other => other,
+        }
+    }
+
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::Context { context, .. } => Some(context),
+            _ => None,
+        }
+    }
+
+    pub fn domain(&self) -> Option<&str> {
+        self.context().and_then(|context| context.domain())
+    }
+
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.context().and_then(|context| context.addr())
+    }
+
+    pub fn elapsed(&self) -> Option<Duration> {
+        self.context().and_then(|context| context.elapsed())
+    }
+
+    fn update_context<F>(self, update: F) -> Self
+    where
+        F: FnOnce(&mut ErrorContext),
+    {
+        match self {
+            Self::Context {
+                mut context,
+                source,
+            } => {
+                update(&mut context);
+                Self::Context { context, source }
+            }
+            other => {
+                let mut context = ErrorContext::default();
+                update(&mut context);
+                Self::Context {
+                    context,
+                    source: Box::new(other),
+                }
+            }
+        }
+    }
+
+    /// set domain, unless more specific domain is already set
+    pub fn with_domain(self, domain: &str) -> Self {
+        self.update_context(|context| {
+            if context.domain.is_none() {
+                context.domain = Some(domain.to_owned());
+            }
+        })
+    }
+
+    pub fn with_addr(self, addr: SocketAddr) -> Self {
+        self.update_context(|context| context.addr = Some(addr))
+    }
+
+    pub fn with_elapsed(self, elapsed: Duration) -> Self {
+        self.update_context(|context| context.elapsed = Some(elapsed))
+    }
+
+    /// shim for callers expecting io error, original error is kept as inner error
+    pub fn into_io_error(self) -> IoError {
+        match self {
+            Self::Tcp(err) => err,
+            other => IoError::new(other.kind(), other),
+        }
+    }
+}
+
+impl From<ConnectorError> for IoError {
+    fn from(err: ConnectorError) -> Self {
+        err.into_io_error()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::TcpDomainConnector;
+    use crate::test_async;
+
+    use super::ConnectorError;
+
+    #[test]
+    fn test_into_io_error() {
+        let tcp: ConnectorError = IoError::from(ErrorKind::ConnectionReset).into();
+        assert_eq!(tcp.into_io_error().kind(), ErrorKind::ConnectionReset);
+
+        let tls = ConnectorError::tls_handshake(Some("expired".to_owned()), "bad certificate");
+        assert!(tls.is_certificate_error());
+        let io_error = tls.into_io_error();
+        assert_eq!(io_error.kind(), ErrorKind::ConnectionRefused);
+        let inner = io_error
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<ConnectorError>())
+            .expect("connector error");
+        assert!(inner.is_certificate_error());
+    }
+
+    #[test_async]
+    async fn test_error_context() -> Result<(), ()> {
+        // nothing is listening on port 1
+        let err = DefaultTcpDomainConnector::new()
+            .connect("127.0.0.1:1")
+            .await
+            .expect_err("connect should fail");
+        assert_eq!(err.domain(), Some("127.0.0.1:1"));
+        assert_eq!(err.addr(), "127.0.0.1:1".parse().ok());
+        assert!(err.elapsed().is_some());
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        assert!(matches!(err.inner(), ConnectorError::Tcp(_)));
+        assert!(err
+            .to_string()
+            .starts_with("domain: 127.0.0.1:1, addr: 127.0.0.1:1"));
+        Ok(())
+    }
+}
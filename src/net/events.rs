@@ -0,0 +1,378 @@
+//! connection lifecycle events.
+//!
+//! `EventConnector` and `EventAcceptor` wrap any connector or acceptor and report to `EventListener`,
+//! so audit logging and connection accounting don't need to wrap every stream
+use std::cell::RefCell;
+use std::error::Error as StdError;
+use std::future::Future;
+use std::io::Error as IoError;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures_lite::{AsyncRead, AsyncWrite};
+
+use super::ConnectorError;
+use super::TcpDomainAcceptor;
+use super::TcpDomainConnector;
+use super::TcpStream;
+
+/// callbacks for connection lifecycle, all are optional.
+/// target is address passed to connect, or peer address for accepted connections
+pub trait EventListener: Send + Sync {
+    fn on_connect_start(&self, _target: &str) {}
+
+    fn on_connected(&self, _target: &str, _fd: RawFd) {}
+
+    fn on_handshake_done(&self, _target: &str, _elapsed: Duration) {}
+
+    fn on_close(&self, _target: &str) {}
+
+    fn on_error(&self, _target: &str, _error: &dyn StdError) {}
+
+    /// accepted peer broke limit of `DeadlineAcceptor`, connection is dropped
+    fn on_violation(&self, _target: &str, _violation: &Violation) {}
+}
+
+/// limit broken by accepted peer, such as slow loris client
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Violation {
+    /// handshake of acceptor didn't finish within timeout
+    HandshakeTimeout(Duration),
+    /// nothing was received within timeout after handshake
+    FirstByteTimeout(Duration),
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::HandshakeTimeout(timeout) => {
+                write!(f, "handshake not finished within {:?}", timeout)
+            }
+            Self::FirstByteTimeout(timeout) => write!(f, "no data received within {:?}", timeout),
+        }
+    }
+}
+
+pub type SharedEventListener = Arc<dyn EventListener>;
+
+thread_local! {
+    /// listener of connect being polled on this thread, so tls connectors can report handshake
+    static CURRENT: RefCell<Option<(SharedEventListener, Arc<str>)>> = RefCell::new(None);
+}
+
+/// called by tls connectors once handshake is finished
+#[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+pub(crate) fn handshake_done(elapsed: Duration) {
+    CURRENT.with(|current| {
+        if let Some((listener, target)) = current.borrow().as_ref() {
+            listener.on_handshake_done(target, elapsed);
+        }
+    });
+}
+
+/// make listener current while inner future is polled
+struct Scoped<F> {
+    inner: F,
+    listener: SharedEventListener,
+    target: Arc<str>,
+}
+
+impl<F: Future + Unpin> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let scope = Some((self.listener.clone(), self.target.clone()));
+        let previous = CURRENT.with(|current| current.replace(scope));
+        let result = Pin::new(&mut self.inner).poll(cx);
+        CURRENT.with(|current| current.replace(previous));
+        result
+    }
+}
+
+/// connector reporting lifecycle events of inner connector
+#[derive(Clone)]
+pub struct EventConnector<C> {
+    inner: C,
+    listener: SharedEventListener,
+}
+
+impl<C> EventConnector<C> {
+    pub fn new(inner: C, listener: SharedEventListener) -> Self {
+        Self { inner, listener }
+    }
+
+    pub fn inner(&self) -> &C {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for EventConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = EventStream<C::WrapperStream>;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        self.listener.on_connect_start(domain);
+        let target: Arc<str> = Arc::from(domain);
+        let result = Scoped {
+            inner: self.inner.connect(domain),
+            listener: self.listener.clone(),
+            target: target.clone(),
+        }
+        .await;
+        match result {
+            Ok((stream, fd)) => {
+                self.listener.on_connected(domain, fd);
+                Ok((EventStream::new(stream, self.listener.clone(), target), fd))
+            }
+            Err(err) => {
+                self.listener.on_error(domain, &err);
+                Err(err)
+            }
+        }
+    }
+}
+
+/// acceptor reporting lifecycle events of inner acceptor
+#[derive(Clone)]
+pub struct EventAcceptor<A> {
+    inner: A,
+    listener: SharedEventListener,
+}
+
+impl<A> EventAcceptor<A> {
+    pub fn new(inner: A, listener: SharedEventListener) -> Self {
+        Self { inner, listener }
+    }
+
+    pub fn inner(&self) -> &A {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<A> TcpDomainAcceptor for EventAcceptor<A>
+where
+    A: TcpDomainAcceptor + Send + Sync,
+{
+    type WrapperStream = EventStream<A::WrapperStream>;
+
+    async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+        use std::os::unix::io::AsRawFd;
+
+        let target: Arc<str> = match stream.peer_addr() {
+            Ok(addr) => Arc::from(addr.to_string()),
+            Err(_) => Arc::from("unknown"),
+        };
+        let fd = stream.as_raw_fd();
+        self.listener.on_connect_start(&target);
+        let start = Instant::now();
+        match self.inner.accept(stream).await {
+            Ok(stream) => {
+                self.listener.on_connected(&target, fd);
+                self.listener.on_handshake_done(&target, start.elapsed());
+                Ok(EventStream::new(stream, self.listener.clone(), target))
+            }
+            Err(err) => {
+                self.listener.on_error(&target, &err);
+                Err(err)
+            }
+        }
+    }
+}
+
+/// stream reporting errors and close, close is reported once on close or drop
+pub struct EventStream<S> {
+    inner: S,
+    listener: SharedEventListener,
+    target: Arc<str>,
+    closed: bool,
+}
+
+impl<S> EventStream<S> {
+    fn new(inner: S, listener: SharedEventListener, target: Arc<str>) -> Self {
+        Self {
+            inner,
+            listener,
+            target,
+            closed: false,
+        }
+    }
+
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    fn report_close(&mut self) {
+        if !self.closed {
+            self.closed = true;
+            self.listener.on_close(&self.target);
+        }
+    }
+
+    fn report<T>(&self, result: Poll<Result<T, IoError>>) -> Poll<Result<T, IoError>> {
+        if let Poll::Ready(Err(err)) = &result {
+            self.listener.on_error(&self.target, err);
+        }
+        result
+    }
+}
+
+impl<S> Drop for EventStream<S> {
+    fn drop(&mut self) {
+        self.report_close();
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for EventStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        self.report(result)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for EventStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+        self.report(result)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let result = Pin::new(&mut self.inner).poll_flush(cx);
+        self.report(result)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let result = Pin::new(&mut self.inner).poll_close(cx);
+        if result.is_ready() {
+            self.report_close();
+        }
+        self.report(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::error::Error as StdError;
+    use std::io::Error as IoError;
+    use std::os::unix::io::RawFd;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncWriteExt;
+    use futures_lite::StreamExt;
+
+    use crate::net::DefaultTcpDomainAcceptor;
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::TcpDomainAcceptor;
+    use crate::net::TcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::EventAcceptor;
+    use super::EventConnector;
+    use super::EventListener;
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl Recorder {
+        fn events(&self) -> Vec<String> {
+            self.0.lock().unwrap().clone()
+        }
+    }
+
+    impl EventListener for Recorder {
+        fn on_connect_start(&self, _target: &str) {
+            self.0.lock().unwrap().push("start".to_owned());
+        }
+
+        fn on_connected(&self, _target: &str, _fd: RawFd) {
+            self.0.lock().unwrap().push("connected".to_owned());
+        }
+
+        fn on_handshake_done(&self, _target: &str, _elapsed: Duration) {
+            self.0.lock().unwrap().push("handshake".to_owned());
+        }
+
+        fn on_close(&self, _target: &str) {
+            self.0.lock().unwrap().push("close".to_owned());
+        }
+
+        fn on_error(&self, _target: &str, _error: &dyn StdError) {
+            self.0.lock().unwrap().push("error".to_owned());
+        }
+    }
+
+    const EVENT_ADDR: &str = "127.0.0.1:8893";
+
+    #[test_async]
+    async fn test_connection_events() -> Result<(), IoError> {
+        let client_events = Arc::new(Recorder::default());
+        let server_events = Arc::new(Recorder::default());
+
+        let server = async {
+            let listener = TcpListener::bind(EVENT_ADDR).await?;
+            let acceptor =
+                EventAcceptor::new(DefaultTcpDomainAcceptor::new(), server_events.clone());
+            let mut incoming = listener.incoming();
+            let stream = incoming.next().await.expect("client should connect")?;
+            let mut stream = acceptor.accept(stream).await?;
+            stream.close().await?;
+            Ok(()) as Result<(), IoError>
+        };
+
+        let client = async {
+            sleep(Duration::from_millis(100)).await;
+            let connector =
+                EventConnector::new(DefaultTcpDomainConnector::new(), client_events.clone());
+            let (stream, _fd) = connector.connect(EVENT_ADDR).await?;
+            drop(stream);
+
+            // nothing is listening on port 1
+            assert!(connector.connect("127.0.0.1:1").await.is_err());
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (client_result, server_result) = zip(client, server).await;
+        client_result?;
+        server_result?;
+
+        assert_eq!(
+            client_events.events(),
+            vec!["start", "connected", "close", "start", "error"]
+        );
+        assert_eq!(
+            server_events.events(),
+            vec!["start", "connected", "handshake", "close"]
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,366 @@
+//! certificate expiry monitoring.
+//!
+//! checks `notAfter` of certificates, such as client cert and trust anchors of tls connector,
+//! and reports ones which are about to expire so failed rotation is caught before outage
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::sync::Arc;
+use std::time::Duration;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use log::debug;
+use log::warn;
+
+use crate::timer::sleep;
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(3600);
+const DAY: Duration = Duration::from_secs(24 * 3600);
+
+/// callbacks for certificates about to expire, called on every check
+pub trait ExpiryListener: Send + Sync {
+    fn on_expiring(&self, _label: &str, _remaining: Duration) {}
+
+    fn on_expired(&self, _label: &str) {}
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpiryStatus {
+    pub label: String,
+    pub not_after: SystemTime,
+    /// time left until expiry, none if already expired
+    pub remaining: Option<Duration>,
+}
+
+impl ExpiryStatus {
+    pub fn is_expired(&self) -> bool {
+        self.remaining.is_none()
+    }
+}
+
+/// periodically check certificates and report those expiring within `warn_before`
+pub struct CertExpiryMonitor {
+    certs: Vec<(String, SystemTime)>,
+    warn_before: Duration,
+    interval: Duration,
+    listener: Option<Arc<dyn ExpiryListener>>,
+}
+
+impl CertExpiryMonitor {
+    pub fn new(warn_before: Duration) -> Self {
+        Self {
+            certs: vec![],
+            warn_before,
+            interval: DEFAULT_INTERVAL,
+            listener: None,
+        }
+    }
+
+    /// warn when certificate expires within `days`
+    pub fn warn_days(days: u64) -> Self {
+        Self::new(DAY * days as u32)
+    }
+
+    /// monitor der encoded certificate
+    pub fn add_der(mut self, label: impl Into<String>, der: &[u8]) -> Result<Self, IoError> {
+        let not_after = cert_not_after(der)?;
+        self.certs.push((label.into(), not_after));
+        Ok(self)
+    }
+
+    /// how often `run` checks
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    pub fn listener(mut self, listener: Arc<dyn ExpiryListener>) -> Self {
+        self.listener = Some(listener);
+        self
+    }
+
+    /// status of all certificates, listener is notified of ones expiring
+    pub fn check(&self) -> Vec<ExpiryStatus> {
+        let now = SystemTime::now();
+        let statuses: Vec<ExpiryStatus> = self
+            .certs
+            .iter()
+            .map(|(label, not_after)| ExpiryStatus {
+                label: label.clone(),
+                not_after: *not_after,
+                remaining: not_after.duration_since(now).ok(),
+            })
+            .collect();
+
+        let mut expiring: u64 = 0;
+        for status in &statuses {
+            match status.remaining {
+                None => {
+                    warn!("certificate: {} has expired", status.label);
+                    expiring += 1;
+                    if let Some(listener) = &self.listener {
+                        listener.on_expired(&status.label);
+                    }
+                }
+                Some(remaining) if remaining <= self.warn_before => {
+                    warn!(
+                        "certificate: {} expires in {} days",
+                        status.label,
+                        remaining.as_secs() / DAY.as_secs()
+                    );
+                    expiring += 1;
+                    if let Some(listener) = &self.listener {
+                        listener.on_expiring(&status.label, remaining);
+                    }
+                }
+                Some(_) => debug!("certificate: {} is valid", status.label),
+            }
+        }
+        #[cfg(feature = "metrics")]
+        crate::metrics::certs_expiring(expiring);
+        #[cfg(not(feature = "metrics"))]
+        let _ = expiring;
+        statuses
+    }
+
+    /// check every interval, forever
+    pub async fn run(self) {
+        loop {
+            self.check();
+            sleep(self.interval).await;
+        }
+    }
+
+    /// run checks in background task
+    #[cfg(feature = "task")]
+    pub fn spawn(self) -> crate::task::Task<()> {
+        crate::task::spawn_task(self.run())
+    }
+}
+
+fn invalid(message: &str) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        format!("invalid certificate: {}", message),
+    )
+}
+
+/// split der element into tag, content and rest
+fn read_element(data: &[u8]) -> Result<(u8, &[u8], &[u8]), IoError> {
+    let tag = *data.first().ok_or_else(|| invalid("truncated"))?;
+    let first = *data.get(1).ok_or_else(|| invalid("truncated"))? as usize;
+    let (len, header) = if first < 0x80 {
+        (first, 2)
+    } else {
+        let count = first & 0x7F;
+        if count == 0 || count > 4 {
+            return Err(invalid("unsupported length"));
+        }
+        let bytes = data.get(2..2 + count).ok_or_else(|| invalid("truncated"))?;
+        let len = bytes.iter().fold(0, |len, b| len << 8 | *b as usize);
+        (len, 2 + count)
+    };
+    let content = data
+        .get(header..header + len)
+        .ok_or_else(|| invalid("truncated"))?;
+    Ok((tag, content, &data[header + len..]))
+}
+
+/// expiry time of der encoded x509 certificate
+pub fn cert_not_after(der: &[u8]) -> Result<SystemTime, IoError> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xA0;
+
+    let (tag, certificate, _) = read_element(der)?;
+    if tag != SEQUENCE {
+        return Err(invalid("not a sequence"));
+    }
+    let (_, tbs, _) = read_element(certificate)?;
+    let (tag, _, mut rest) = read_element(tbs)?;
+    if tag == VERSION {
+        // skip serial number
+        rest = read_element(rest)?.2;
+    }
+    // skip signature algorithm and issuer
+    rest = read_element(rest)?.2;
+    rest = read_element(rest)?.2;
+    let (tag, validity, _) = read_element(rest)?;
+    if tag != SEQUENCE {
+        return Err(invalid("validity not found"));
+    }
+    let not_before = read_element(validity)?.2;
+    let (tag, not_after, _) = read_element(not_before)?;
+    parse_time(tag, not_after)
+}
+
+/// der encoded subject public key info of x509 certificate, including its header
+pub fn cert_spki(der: &[u8]) -> Result<&[u8], IoError> {
+    const SEQUENCE: u8 = 0x30;
+
+    let mut rest = tbs_after_serial(der)?;
+    // skip signature algorithm, issuer, validity and subject
+    for _ in 0..4 {
+        rest = read_element(rest)?.2;
+    }
+    let (tag, _, after) = read_element(rest)?;
+    if tag != SEQUENCE {
+        return Err(invalid("public key not found"));
+    }
+    Ok(&rest[..rest.len() - after.len()])
+}
+
+/// der encoded issuer name of x509 certificate, including its header.
+/// same encoding as ca names server sends in certificate request
+pub fn cert_issuer(der: &[u8]) -> Result<&[u8], IoError> {
+    const SEQUENCE: u8 = 0x30;
+
+    // skip signature algorithm
+    let rest = read_element(tbs_after_serial(der)?)?.2;
+    let (tag, _, after) = read_element(rest)?;
+    if tag != SEQUENCE {
+        return Err(invalid("issuer not found"));
+    }
+    Ok(&rest[..rest.len() - after.len()])
+}
+
+/// fields of tbs certificate following serial number
+fn tbs_after_serial(der: &[u8]) -> Result<&[u8], IoError> {
+    const SEQUENCE: u8 = 0x30;
+    const VERSION: u8 = 0xA0;
+
+    let (tag, certificate, _) = read_element(der)?;
+    if tag != SEQUENCE {
+        return Err(invalid("not a sequence"));
+    }
+    let (_, tbs, _) = read_element(certificate)?;
+    let (tag, _, mut rest) = read_element(tbs)?;
+    if tag == VERSION {
+        rest = read_element(rest)?.2;
+    }
+    Ok(rest)
+}
+
+/// parse utc or generalized time, only utc `Z` form is supported
+fn parse_time(tag: u8, time: &[u8]) -> Result<SystemTime, IoError> {
+    const UTC_TIME: u8 = 0x17;
+    const GENERALIZED_TIME: u8 = 0x18;
+
+    let time = std::str::from_utf8(time).map_err(|_| invalid("time is not ascii"))?;
+    let digits = time
+        .strip_suffix('Z')
+        .filter(|digits| digits.bytes().all(|b| b.is_ascii_digit()))
+        .ok_or_else(|| invalid("unsupported time format"))?;
+    let number = |range: std::ops::Range<usize>| -> Result<i64, IoError> {
+        digits
+            .get(range)
+            .and_then(|part| part.parse().ok())
+            .ok_or_else(|| invalid("unsupported time format"))
+    };
+    let (year, rest) = match (tag, digits.len()) {
+        (UTC_TIME, 12) => {
+            let year = number(0..2)?;
+            (if year >= 50 { 1900 + year } else { 2000 + year }, 2)
+        }
+        (GENERALIZED_TIME, 14) => (number(0..4)?, 4),
+        _ => return Err(invalid("unsupported time format")),
+    };
+    let month = number(rest..rest + 2)?;
+    let day = number(rest + 2..rest + 4)?;
+    let hour = number(rest + 4..rest + 6)?;
+    let minute = number(rest + 6..rest + 8)?;
+    let second = number(rest + 8..rest + 10)?;
+
+    let seconds = days_from_civil(year, month, day) * 86400 + hour * 3600 + minute * 60 + second;
+    if seconds >= 0 {
+        Ok(UNIX_EPOCH + Duration::from_secs(seconds as u64))
+    } else {
+        Ok(UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs()))
+    }
+}
+
+/// days since unix epoch of date in proleptic gregorian calendar
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = if year >= 0 { year } else { year - 399 } / 400;
+    let year_of_era = year - era * 400;
+    let day_of_year = (153 * ((month + 9) % 12) + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use std::time::UNIX_EPOCH;
+
+    use super::cert_not_after;
+    use super::CertExpiryMonitor;
+    use super::ExpiryListener;
+
+    /// der element with short length
+    fn element(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag, content.len() as u8];
+        out.extend_from_slice(content);
+        out
+    }
+
+    /// skeleton certificate, only fields needed to reach validity
+    fn certificate(not_after: (u8, &[u8])) -> Vec<u8> {
+        let mut validity = element(0x17, b"200101000000Z");
+        validity.extend(element(not_after.0, not_after.1));
+
+        let mut tbs = element(0xA0, &element(0x02, &[2]));
+        tbs.extend(element(0x02, &[1]));
+        tbs.extend(element(0x30, &[]));
+        tbs.extend(element(0x30, &[]));
+        tbs.extend(element(0x30, &validity));
+        element(0x30, &element(0x30, &tbs))
+    }
+
+    #[test]
+    fn test_cert_not_after() {
+        let der = certificate((0x17, b"210203040506Z"));
+        assert_eq!(
+            cert_not_after(&der).expect("parse"),
+            UNIX_EPOCH + Duration::from_secs(1_612_325_106)
+        );
+        let der = certificate((0x18, b"20500101000000Z"));
+        assert_eq!(
+            cert_not_after(&der).expect("parse"),
+            UNIX_EPOCH + Duration::from_secs(2_524_608_000)
+        );
+        assert!(cert_not_after(&certificate((0x17, b"2102030405Z"))).is_err());
+        assert!(cert_not_after(&[0x30, 0x05, 0x30]).is_err());
+    }
+
+    #[derive(Default)]
+    struct Recorder(Mutex<Vec<String>>);
+
+    impl ExpiryListener for Recorder {
+        fn on_expiring(&self, label: &str, _remaining: Duration) {
+            self.0.lock().unwrap().push(format!("expiring {}", label));
+        }
+
+        fn on_expired(&self, label: &str) {
+            self.0.lock().unwrap().push(format!("expired {}", label));
+        }
+    }
+
+    #[test]
+    fn test_expiry_monitor() {
+        let recorder = Arc::new(Recorder::default());
+        let monitor = CertExpiryMonitor::warn_days(30)
+            .listener(recorder.clone())
+            .add_der("old", &certificate((0x17, b"210203040506Z")))
+            .expect("add")
+            .add_der("new", &certificate((0x18, b"99991231235959Z")))
+            .expect("add");
+        let statuses = monitor.check();
+        assert!(statuses[0].is_expired());
+        assert!(!statuses[1].is_expired());
+        assert_eq!(*recorder.0.lock().unwrap(), vec!["expired old"]);
+    }
+}
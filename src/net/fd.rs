@@ -0,0 +1,92 @@
+//! conversion between crate tcp stream and raw fd or std stream, for sockets
+//! accepted elsewhere such as by systemd socket activation
+use std::convert::TryFrom;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+
+use async_io::Async;
+
+use super::TcpStream;
+
+/// wrap std stream, switching it to non blocking mode
+pub fn tcp_stream_from_std(stream: std::net::TcpStream) -> Result<TcpStream, IoError> {
+    TcpStream::try_from(stream)
+}
+
+/// wrap connected tcp socket
+///
+/// # Safety
+/// `fd` must be open tcp socket which is not owned by anything else
+pub unsafe fn tcp_stream_from_raw_fd(fd: RawFd) -> Result<TcpStream, IoError> {
+    tcp_stream_from_std(std::net::TcpStream::from_raw_fd(fd))
+}
+
+/// unwrap to std stream, which is left in non blocking mode.
+/// fails if stream has been cloned
+pub fn tcp_stream_into_std(stream: TcpStream) -> Result<std::net::TcpStream, IoError> {
+    let inner: Arc<Async<std::net::TcpStream>> = stream.into();
+    Arc::try_unwrap(inner)
+        .map_err(|_| {
+            IoError::new(
+                ErrorKind::InvalidInput,
+                "tcp stream is shared by its clones",
+            )
+        })?
+        .into_inner()
+}
+
+/// release ownership of socket, caller must close it
+pub fn tcp_stream_into_raw_fd(stream: TcpStream) -> Result<RawFd, IoError> {
+    Ok(tcp_stream_into_std(stream)?.into_raw_fd())
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::os::unix::io::AsFd;
+    use std::os::unix::io::AsRawFd;
+    use std::os::unix::io::IntoRawFd;
+
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::test_async;
+
+    use super::tcp_stream_from_raw_fd;
+    use super::tcp_stream_from_std;
+    use super::tcp_stream_into_raw_fd;
+
+    #[test_async]
+    async fn test_raw_fd_conversion() -> Result<(), IoError> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let client = std::net::TcpStream::connect(listener.local_addr()?)?;
+        let (server, _) = listener.accept()?;
+
+        let mut client = tcp_stream_from_std(client)?;
+        let server_fd = server.into_raw_fd();
+        let mut server = unsafe { tcp_stream_from_raw_fd(server_fd)? };
+        assert_eq!(server.as_fd().as_raw_fd(), server_fd);
+
+        client.write_all(b"ping").await?;
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+
+        // shared stream can't be released
+        let clone = client.clone();
+        assert!(tcp_stream_into_raw_fd(clone).is_err());
+
+        // round trip thru raw fd keeps connection
+        let fd = tcp_stream_into_raw_fd(server)?;
+        let mut server = unsafe { tcp_stream_from_raw_fd(fd)? };
+        server.write_all(b"pong").await?;
+        client.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"pong");
+        Ok(())
+    }
+}
@@ -0,0 +1,171 @@
+//! passing file descriptors over unix stream with `SCM_RIGHTS`, so supervisor process can
+//! hand live connections to worker processes, such as during upgrade without downtime.
+//!
+//! each fd is sent along with one marker byte, so fd messages must not be interleaved
+//! with other data written to same stream
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+use std::ptr;
+use std::sync::Arc;
+
+use async_io::Async;
+use async_trait::async_trait;
+use log::debug;
+
+use super::unix::UnixStream;
+
+/// byte carrying control message, stream sockets don't send control message alone
+const FD_MARKER: u8 = 1;
+
+/// space for control message with one fd, as u64 so it is aligned for cmsghdr
+type ControlBuffer = [u64; 8];
+
+fn control_space() -> usize {
+    unsafe { libc::CMSG_SPACE(mem::size_of::<RawFd>() as u32) as usize }
+}
+
+fn send_raw(socket: RawFd, fd: RawFd) -> Result<(), IoError> {
+    let mut data = [FD_MARKER];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let mut control: ControlBuffer = [0; 8];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control_space() as _;
+    unsafe {
+        let cmsg = libc::CMSG_FIRSTHDR(&msg);
+        (*cmsg).cmsg_level = libc::SOL_SOCKET;
+        (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+        (*cmsg).cmsg_len = libc::CMSG_LEN(mem::size_of::<RawFd>() as u32) as _;
+        ptr::write_unaligned(libc::CMSG_DATA(cmsg) as *mut RawFd, fd);
+        if libc::sendmsg(socket, &msg, libc::MSG_NOSIGNAL) < 0 {
+            return Err(IoError::last_os_error());
+        }
+    }
+    Ok(())
+}
+
+fn recv_raw(socket: RawFd) -> Result<RawFd, IoError> {
+    let mut data = [0; 1];
+    let mut iov = libc::iovec {
+        iov_base: data.as_mut_ptr() as *mut libc::c_void,
+        iov_len: data.len(),
+    };
+    let mut control: ControlBuffer = [0; 8];
+    let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+    msg.msg_iov = &mut iov;
+    msg.msg_iovlen = 1;
+    msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+    msg.msg_controllen = control_space() as _;
+
+    let received = unsafe { libc::recvmsg(socket, &mut msg, libc::MSG_CMSG_CLOEXEC) };
+    if received < 0 {
+        return Err(IoError::last_os_error());
+    }
+    if received == 0 {
+        return Err(IoError::new(
+            ErrorKind::UnexpectedEof,
+            "stream closed before fd was received",
+        ));
+    }
+
+    let mut fd = None;
+    unsafe {
+        let mut cmsg = libc::CMSG_FIRSTHDR(&msg);
+        while !cmsg.is_null() {
+            if (*cmsg).cmsg_level == libc::SOL_SOCKET && (*cmsg).cmsg_type == libc::SCM_RIGHTS {
+                let len = (*cmsg).cmsg_len as usize - libc::CMSG_LEN(0) as usize;
+                let fds = libc::CMSG_DATA(cmsg) as *const RawFd;
+                for index in 0..len / mem::size_of::<RawFd>() {
+                    let received_fd = ptr::read_unaligned(fds.add(index));
+                    // only one fd is expected, rest would leak
+                    if fd.is_none() {
+                        fd = Some(received_fd);
+                    } else {
+                        libc::close(received_fd);
+                    }
+                }
+            }
+            cmsg = libc::CMSG_NXTHDR(&msg, cmsg);
+        }
+    }
+    fd.ok_or_else(|| IoError::new(ErrorKind::InvalidData, "message without fd"))
+}
+
+/// send and receive file descriptors over unix stream
+#[async_trait]
+pub trait FdPassing {
+    /// send duplicate of `fd` to peer, caller still owns `fd` and should close it
+    async fn send_fd(&self, fd: RawFd) -> Result<(), IoError>;
+
+    /// receive fd sent by peer, caller becomes owner of it.
+    /// fd is close on exec, it can be wrapped such as with `tcp_stream_from_raw_fd`
+    async fn recv_fd(&self) -> Result<RawFd, IoError>;
+}
+
+#[async_trait]
+impl FdPassing for UnixStream {
+    async fn send_fd(&self, fd: RawFd) -> Result<(), IoError> {
+        debug!("sending fd: {}", fd);
+        let socket: Arc<Async<std::os::unix::net::UnixStream>> = self.clone().into();
+        socket
+            .write_with(|stream| send_raw(stream.as_raw_fd(), fd))
+            .await
+    }
+
+    async fn recv_fd(&self) -> Result<RawFd, IoError> {
+        let socket: Arc<Async<std::os::unix::net::UnixStream>> = self.clone().into();
+        let fd = socket
+            .read_with(|stream| recv_raw(stream.as_raw_fd()))
+            .await?;
+        debug!("received fd: {}", fd);
+        Ok(fd)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::io::Write;
+    use std::os::unix::io::AsRawFd;
+
+    use futures_lite::AsyncReadExt;
+
+    use crate::net::tcp_stream_from_raw_fd;
+    use crate::net::unix::UnixStream;
+    use crate::test_async;
+
+    use super::FdPassing;
+
+    #[test_async]
+    async fn test_fd_passing() -> Result<(), IoError> {
+        let (supervisor, worker) = UnixStream::pair()?;
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        let mut client = std::net::TcpStream::connect(listener.local_addr()?)?;
+        let (accepted, _) = listener.accept()?;
+
+        supervisor.send_fd(accepted.as_raw_fd()).await?;
+        // connection stays open thru fd held by worker
+        drop(accepted);
+        let fd = worker.recv_fd().await?;
+        let mut stream = unsafe { tcp_stream_from_raw_fd(fd)? };
+        client.write_all(b"ping")?;
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+
+        drop(supervisor);
+        let err = worker.recv_fd().await.expect_err("closed");
+        assert_eq!(err.kind(), ErrorKind::UnexpectedEof);
+        Ok(())
+    }
+}
@@ -0,0 +1,362 @@
+//! length delimited frames over connected stream, such as tls stream.
+//!
+//! each frame is prefixed by its length as big endian u32. length is checked against
+//! max frame size as soon as header arrives, so oversized frame is rejected before
+//! any of it is buffered. payload buffer only grows as its bytes arrive, so header alone
+//! doesn't reserve memory for whole frame. `FramedTls` combines connector or acceptor with framing
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures_lite::future::poll_fn;
+use futures_lite::{AsyncRead, AsyncWrite, Stream};
+use log::debug;
+
+use crate::buf::BufPool;
+use crate::buf::PooledBuf;
+
+use super::ConnectorError;
+use super::TcpDomainAcceptor;
+use super::TcpDomainConnector;
+use super::TcpStream;
+
+/// default limit of frame payload
+pub const DEFAULT_MAX_FRAME_SIZE: usize = 1024 * 1024;
+
+const HEADER_LEN: usize = 4;
+
+fn too_large(len: usize, max: usize) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        format!("frame of {} bytes exceeds max frame size {}", len, max),
+    )
+}
+
+/// stream of frames read, and frames written with `send` or as `Sink` with `sink` feature.
+/// once read fails, such as for oversized frame, stream ends since framing is lost
+pub struct Framed<S> {
+    inner: S,
+    max_frame_size: usize,
+    /// payload is read in chunks of at most this
+    read_chunk_size: usize,
+    header: [u8; HEADER_LEN],
+    header_read: usize,
+    /// length and payload read so far of frame being read, once its header is read
+    frame: Option<(usize, PooledBuf)>,
+    failed: bool,
+    /// encoded frames not yet written, returned to pool once written
+    write_buf: Option<PooledBuf>,
+    written: usize,
+}
+
+impl<S> Framed<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+            read_chunk_size: crate::Runtime::current().read_buffer_size(),
+            header: [0; HEADER_LEN],
+            header_read: 0,
+            frame: None,
+            failed: false,
+            write_buf: None,
+            written: 0,
+        }
+    }
+
+    /// largest payload read or written, up to `u32::MAX`
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size.min(u32::MAX as usize);
+        self
+    }
+
+    /// buffer of frame grows by at most this, default is `Runtime::read_buffer_size`
+    pub fn read_chunk_size(mut self, size: usize) -> Self {
+        self.read_chunk_size = size.max(1);
+        self
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// return inner stream, partially read frame and unwritten frames are dropped
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    /// queue frame, it is written once stream is flushed
+    fn encode(&mut self, frame: &[u8]) -> Result<(), IoError> {
+        if frame.len() > self.max_frame_size {
+            return Err(too_large(frame.len(), self.max_frame_size));
+        }
+        let buf = self
+            .write_buf
+            .get_or_insert_with(|| BufPool::global().get(HEADER_LEN + frame.len()));
+        buf.extend_from_slice(&(frame.len() as u32).to_be_bytes());
+        buf.extend_from_slice(frame);
+        Ok(())
+    }
+}
+
+impl<S: AsyncWrite + Unpin> Framed<S> {
+    /// write queued frames and flush stream
+    fn poll_flush_frames(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        if let Some(buf) = &self.write_buf {
+            while self.written < buf.len() {
+                let n = futures_lite::ready!(
+                    Pin::new(&mut self.inner).poll_write(cx, &buf[self.written..])
+                )?;
+                if n == 0 {
+                    return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+                }
+                self.written += n;
+            }
+        }
+        self.write_buf = None;
+        self.written = 0;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    /// write one frame and flush it
+    pub async fn send(&mut self, frame: &[u8]) -> Result<(), IoError> {
+        self.encode(frame)?;
+        poll_fn(|cx| self.poll_flush_frames(cx)).await
+    }
+
+    pub async fn close(&mut self) -> Result<(), IoError> {
+        poll_fn(|cx| self.poll_flush_frames(cx)).await?;
+        poll_fn(|cx| Pin::new(&mut self.inner).poll_close(cx)).await
+    }
+}
+
+impl<S: AsyncRead + Unpin> Framed<S> {
+    fn poll_frame(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Vec<u8>, IoError>>> {
+        loop {
+            match &mut self.frame {
+                None => {
+                    let n = futures_lite::ready!(Pin::new(&mut self.inner)
+                        .poll_read(cx, &mut self.header[self.header_read..]))?;
+                    if n == 0 {
+                        if self.header_read == 0 {
+                            return Poll::Ready(None);
+                        }
+                        return Poll::Ready(Some(Err(ErrorKind::UnexpectedEof.into())));
+                    }
+                    self.header_read += n;
+                    if self.header_read == HEADER_LEN {
+                        let len = u32::from_be_bytes(self.header) as usize;
+                        if len > self.max_frame_size {
+                            debug!("rejected frame of {} bytes", len);
+                            return Poll::Ready(Some(Err(too_large(len, self.max_frame_size))));
+                        }
+                        self.header_read = 0;
+                        self.frame =
+                            Some((len, BufPool::global().get(len.min(self.read_chunk_size))));
+                    }
+                }
+                Some((len, frame)) if frame.len() == *len => {
+                    let frame = self.frame.take().map(|(_, frame)| frame.to_vec());
+                    return Poll::Ready(frame.map(Ok));
+                }
+                Some((len, frame)) => {
+                    let filled = frame.len();
+                    frame.resize(filled + (*len - filled).min(self.read_chunk_size), 0);
+                    let result = Pin::new(&mut self.inner).poll_read(cx, &mut frame[filled..]);
+                    let n = match result {
+                        Poll::Ready(Ok(n)) => n,
+                        Poll::Ready(Err(err)) => return Poll::Ready(Some(Err(err))),
+                        Poll::Pending => {
+                            frame.truncate(filled);
+                            return Poll::Pending;
+                        }
+                    };
+                    frame.truncate(filled + n);
+                    if n == 0 {
+                        return Poll::Ready(Some(Err(ErrorKind::UnexpectedEof.into())));
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> Stream for Framed<S> {
+    type Item = Result<Vec<u8>, IoError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.failed {
+            return Poll::Ready(None);
+        }
+        let result = this.poll_frame(cx);
+        if let Poll::Ready(Some(Err(_))) = &result {
+            this.failed = true;
+        }
+        result
+    }
+}
+
+#[cfg(feature = "sink")]
+impl<S: AsyncWrite + Unpin, T: AsRef<[u8]>> futures_sink::Sink<T> for Framed<S> {
+    type Error = IoError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        // one frame is buffered at most
+        if this.write_buf.is_none() {
+            Poll::Ready(Ok(()))
+        } else {
+            this.poll_flush_frames(cx)
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: T) -> Result<(), IoError> {
+        self.get_mut().encode(item.as_ref())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.get_mut().poll_flush_frames(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let this = self.get_mut();
+        futures_lite::ready!(this.poll_flush_frames(cx))?;
+        Pin::new(&mut this.inner).poll_close(cx)
+    }
+}
+
+/// framed connections thru connector or acceptor, such as tls, with max frame size enforced
+#[derive(Clone)]
+pub struct FramedTls<T> {
+    inner: T,
+    max_frame_size: usize,
+}
+
+impl<T> FramedTls<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            max_frame_size: DEFAULT_MAX_FRAME_SIZE,
+        }
+    }
+
+    pub fn max_frame_size(mut self, max_frame_size: usize) -> Self {
+        self.max_frame_size = max_frame_size;
+        self
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.inner
+    }
+}
+
+impl<C: TcpDomainConnector> FramedTls<C> {
+    pub async fn connect(&self, domain: &str) -> Result<Framed<C::WrapperStream>, ConnectorError> {
+        let (stream, _) = self.inner.connect(domain).await?;
+        Ok(Framed::new(stream).max_frame_size(self.max_frame_size))
+    }
+}
+
+impl<A: TcpDomainAcceptor> FramedTls<A> {
+    pub async fn accept(&self, stream: TcpStream) -> Result<Framed<A::WrapperStream>, IoError> {
+        let stream = self.inner.accept(stream).await?;
+        Ok(Framed::new(stream).max_frame_size(self.max_frame_size))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use std::time::Duration;
+
+    use futures_lite::future::{or, zip};
+    use futures_lite::AsyncWriteExt;
+    use futures_lite::StreamExt;
+
+    use crate::net::duplex;
+    use crate::net::DefaultTcpDomainAcceptor;
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::Framed;
+    use super::FramedTls;
+
+    #[test_async]
+    async fn test_framed() -> Result<(), IoError> {
+        let (client, server) = duplex(16);
+        let mut client = Framed::new(client).max_frame_size(64);
+        let mut server = Framed::new(server).max_frame_size(64);
+        // frames larger than buffer of duplex
+        let frame = vec![7; 40];
+        let (sent, received) = zip(
+            async {
+                client.send(b"hello").await?;
+                client.send(&frame).await?;
+                client.send(b"").await
+            },
+            async {
+                let hello = server.next().await.expect("frame")?;
+                let large = server.next().await.expect("frame")?;
+                let empty = server.next().await.expect("frame")?;
+                Ok((hello, large, empty)) as Result<(Vec<u8>, Vec<u8>, Vec<u8>), IoError>
+            },
+        )
+        .await;
+        sent?;
+        let (hello, large, empty) = received?;
+        assert_eq!(hello, b"hello");
+        assert_eq!(large, frame);
+        assert!(empty.is_empty());
+
+        let err = client.send(&[0; 65]).await.expect_err("too large");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+
+        // header announcing 4gb is rejected without waiting for payload
+        let (mut raw, framed) = duplex(64);
+        let mut framed = Framed::new(framed);
+        raw.write_all(&u32::MAX.to_be_bytes()).await?;
+        let err = framed.next().await.expect("error").expect_err("rejected");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(framed.next().await.is_none());
+
+        // buffer of large frame grows with payload, not with announced length
+        let (mut raw, framed) = duplex(64);
+        let mut framed = Framed::new(framed).read_chunk_size(4 * 1024);
+        raw.write_all(&(1024 * 1024u32).to_be_bytes()).await?;
+        raw.write_all(&[1; 10]).await?;
+        let pending = or(async { Some(framed.next().await) }, async {
+            sleep(Duration::from_millis(20)).await;
+            None
+        })
+        .await;
+        assert!(pending.is_none());
+        let (len, payload) = framed.frame.as_ref().expect("frame in progress");
+        assert_eq!((*len, payload.len()), (1024 * 1024, 10));
+        assert!(payload.capacity() < 64 * 1024);
+
+        // thru connector and acceptor
+        let addr = "127.0.0.1:8913";
+        let listener = TcpListener::bind(addr).await?;
+        let connector = FramedTls::new(DefaultTcpDomainConnector::new()).max_frame_size(8);
+        let acceptor = FramedTls::new(DefaultTcpDomainAcceptor::new()).max_frame_size(8);
+        let mut client = connector.connect(addr).await.map_err(IoError::from)?;
+        let (stream, _) = listener.accept().await?;
+        let mut server = acceptor.accept(stream).await?;
+        client.send(b"ping").await?;
+        assert_eq!(server.next().await.expect("frame")?, b"ping");
+        client.close().await?;
+        assert!(server.next().await.is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,256 @@
+//! application level heartbeat, detects dead peer faster than tcp keepalive
+use std::future::Future;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use std::time::Instant;
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use crate::timer::{sleep_until, Sleeper};
+
+#[derive(Debug, Clone)]
+pub struct HeartbeatConfig {
+    /// frame written after writes have been idle for `interval`, empty to not send pings
+    pub ping: Vec<u8>,
+    pub interval: Duration,
+    /// peer is considered dead if nothing is read within timeout, including pongs
+    pub timeout: Duration,
+}
+
+impl Default for HeartbeatConfig {
+    fn default() -> Self {
+        Self {
+            ping: vec![],
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(3),
+        }
+    }
+}
+
+/// stream writing ping frame while idle. once nothing is read within timeout,
+/// reads and writes fail with `ErrorKind::TimedOut`.
+///
+/// heartbeat is driven by reads, so stream must be read continuously.
+/// ping is only written between writes, so frame written in multiple writes may be split by it
+pub struct Heartbeat<S> {
+    inner: S,
+    config: HeartbeatConfig,
+    last_read: Instant,
+    last_write: Instant,
+    /// bytes of ping frame written, if ping is in progress
+    ping: Option<usize>,
+    flushing: bool,
+    timer: Sleeper,
+    alive: bool,
+}
+
+impl<S> Heartbeat<S> {
+    pub fn new(inner: S, config: HeartbeatConfig) -> Self {
+        let now = crate::timer::now();
+        Self {
+            inner,
+            config,
+            last_read: now,
+            last_write: now,
+            ping: None,
+            flushing: false,
+            timer: sleep_until(now),
+            alive: true,
+        }
+    }
+
+    /// false once liveness is lost
+    pub fn is_alive(&self) -> bool {
+        self.alive
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+
+    fn check_alive(&self) -> Result<(), IoError> {
+        if self.alive {
+            Ok(())
+        } else {
+            Err(IoError::new(ErrorKind::TimedOut, "heartbeat timed out"))
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> Heartbeat<S> {
+    /// write in progress ping, ready once it is written and flushed
+    fn poll_ping(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        while let Some(written) = self.ping {
+            let n = futures_lite::ready!(
+                Pin::new(&mut self.inner).poll_write(cx, &self.config.ping[written..])
+            )?;
+            if n == 0 {
+                return Poll::Ready(Err(ErrorKind::WriteZero.into()));
+            }
+            if written + n < self.config.ping.len() {
+                self.ping = Some(written + n);
+            } else {
+                self.ping = None;
+                self.flushing = true;
+                self.last_write = crate::timer::now();
+            }
+        }
+        if self.flushing {
+            futures_lite::ready!(Pin::new(&mut self.inner).poll_flush(cx))?;
+            self.flushing = false;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// check liveness and send pings when due, registering timer for next deadline
+    fn poll_heartbeat(&mut self, cx: &mut Context<'_>) -> Result<(), IoError> {
+        loop {
+            self.check_alive()?;
+            let now = crate::timer::now();
+            if now >= self.last_read + self.config.timeout {
+                debug!(
+                    "nothing read within {:?}, peer is dead",
+                    self.config.timeout
+                );
+                self.alive = false;
+                continue;
+            }
+            if self.ping.is_none()
+                && !self.flushing
+                && !self.config.ping.is_empty()
+                && now >= self.last_write + self.config.interval
+            {
+                debug!("writes idle, sending ping");
+                self.ping = Some(0);
+            }
+            let mut deadline = self.last_read + self.config.timeout;
+            // while ping is blocked, only timeout is waited for
+            if self.poll_ping(cx)?.is_ready() && !self.config.ping.is_empty() {
+                deadline = deadline.min(self.last_write + self.config.interval);
+            }
+            self.timer = sleep_until(deadline);
+            if Pin::new(&mut self.timer).poll(cx).is_pending() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> AsyncRead for Heartbeat<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        self.poll_heartbeat(cx)?;
+        let read = futures_lite::ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        if read > 0 {
+            self.last_read = crate::timer::now();
+        }
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for Heartbeat<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        self.check_alive()?;
+        // don't interleave with ping
+        futures_lite::ready!(self.poll_ping(cx))?;
+        let written = futures_lite::ready!(Pin::new(&mut self.inner).poll_write(cx, buf))?;
+        if written > 0 {
+            self.last_write = crate::timer::now();
+        }
+        Poll::Ready(Ok(written))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        self.check_alive()?;
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::duplex;
+    use crate::test_async;
+
+    use super::Heartbeat;
+    use super::HeartbeatConfig;
+
+    #[test_async]
+    async fn test_heartbeat() -> Result<(), IoError> {
+        let (local, mut peer) = duplex(64);
+        let mut heartbeat = Heartbeat::new(
+            local,
+            HeartbeatConfig {
+                ping: b"ping".to_vec(),
+                interval: Duration::from_millis(20),
+                timeout: Duration::from_millis(100),
+            },
+        );
+
+        let peer_ft = async {
+            // answer two pings, then go silent
+            let mut buf = [0; 4];
+            for _ in 0..2u16 {
+                peer.read_exact(&mut buf).await?;
+                assert_eq!(&buf, b"ping");
+                peer.write_all(b"pong").await?;
+            }
+            Ok::<_, IoError>(peer)
+        };
+
+        let local_ft = async {
+            let mut buf = [0; 4];
+            heartbeat.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"pong");
+            heartbeat.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"pong");
+
+            let start = Instant::now();
+            let err = heartbeat.read(&mut buf).await.expect_err("timed out");
+            assert_eq!(err.kind(), ErrorKind::TimedOut);
+            assert!(start.elapsed() >= Duration::from_millis(90));
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (peer_result, local_result) = zip(peer_ft, local_ft).await;
+        // keep peer open so only heartbeat can fail the read
+        let _peer = peer_result?;
+        local_result?;
+        assert!(!heartbeat.is_alive());
+        let err = heartbeat.write_all(b"data").await.expect_err("dead");
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        Ok(())
+    }
+}
@@ -0,0 +1,358 @@
+//! cidr allow and deny lists for accepted peers and outbound targets, so embedded
+//! servers get basic network acl without external firewall.
+//!
+//! rules are shared by `SharedIpFilter`, replacing them applies to next accept or connect
+use std::fmt;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::os::unix::io::RawFd;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use futures_lite::{Stream, StreamExt};
+use log::debug;
+
+use super::resolver;
+use super::ConnectorError;
+use super::TcpDomainAcceptor;
+use super::TcpDomainConnector;
+use super::TcpStream;
+
+/// network in `10.0.0.0/8` form, address without prefix is single host
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+/// ipv4 mapped ipv6 address as ipv4, so one rule covers both forms
+fn canonical(addr: IpAddr) -> IpAddr {
+    match addr {
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => IpAddr::V4(v4),
+            None => IpAddr::V6(v6),
+        },
+        v4 => v4,
+    }
+}
+
+impl Cidr {
+    pub fn new(addr: IpAddr, prefix: u8) -> Result<Self, IoError> {
+        let addr = canonical(addr);
+        let max = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("prefix {} is longer than address", prefix),
+            ));
+        }
+        Ok(Self { addr, prefix })
+    }
+
+    pub fn contains(&self, addr: IpAddr) -> bool {
+        match (self.addr, canonical(addr)) {
+            (IpAddr::V4(net), IpAddr::V4(addr)) => {
+                let mask = u32::MAX.checked_shl(32 - self.prefix as u32).unwrap_or(0);
+                u32::from(net) & mask == u32::from(addr) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(addr)) => {
+                let mask = u128::MAX.checked_shl(128 - self.prefix as u32).unwrap_or(0);
+                u128::from(net) & mask == u128::from(addr) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = IoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || IoError::new(ErrorKind::InvalidInput, format!("invalid cidr: {}", s));
+        let (addr, prefix) = match s.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix)),
+            None => (s, None),
+        };
+        let addr = canonical(addr.parse::<IpAddr>().map_err(|_| invalid())?);
+        let prefix = match prefix {
+            Some(prefix) => prefix.parse::<u8>().map_err(|_| invalid())?,
+            None if addr.is_ipv4() => 32,
+            None => 128,
+        };
+        Self::new(addr, prefix)
+    }
+}
+
+impl fmt::Display for Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+/// deny rules win over allow rules. without allow rules, every address not denied is allowed
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IpFilter {
+    allow: Vec<Cidr>,
+    deny: Vec<Cidr>,
+}
+
+impl IpFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn allow(mut self, cidr: Cidr) -> Self {
+        self.allow.push(cidr);
+        self
+    }
+
+    pub fn deny(mut self, cidr: Cidr) -> Self {
+        self.deny.push(cidr);
+        self
+    }
+
+    pub fn is_allowed(&self, addr: IpAddr) -> bool {
+        !self.deny.iter().any(|cidr| cidr.contains(addr))
+            && (self.allow.is_empty() || self.allow.iter().any(|cidr| cidr.contains(addr)))
+    }
+}
+
+/// filter shared by acceptors and connectors, rules can be replaced at runtime
+#[derive(Debug, Clone, Default)]
+pub struct SharedIpFilter(Arc<RwLock<IpFilter>>);
+
+impl SharedIpFilter {
+    pub fn new(filter: IpFilter) -> Self {
+        Self(Arc::new(RwLock::new(filter)))
+    }
+
+    pub fn set(&self, filter: IpFilter) {
+        *self.0.write().unwrap() = filter;
+    }
+
+    pub fn get(&self) -> IpFilter {
+        self.0.read().unwrap().clone()
+    }
+
+    pub fn is_allowed(&self, addr: IpAddr) -> bool {
+        self.0.read().unwrap().is_allowed(addr)
+    }
+
+    /// apply filter updates until stream ends, such as from watch of config
+    pub async fn follow<S>(&self, mut updates: S)
+    where
+        S: Stream<Item = IpFilter> + Unpin,
+    {
+        while let Some(filter) = updates.next().await {
+            self.set(filter);
+        }
+    }
+}
+
+/// refuse accepted peers not allowed by filter, before inner acceptor runs
+#[derive(Clone)]
+pub struct IpFilterAcceptor<A> {
+    inner: A,
+    filter: SharedIpFilter,
+}
+
+impl<A> IpFilterAcceptor<A> {
+    pub fn new(inner: A, filter: SharedIpFilter) -> Self {
+        Self { inner, filter }
+    }
+}
+
+#[async_trait]
+impl<A> TcpDomainAcceptor for IpFilterAcceptor<A>
+where
+    A: TcpDomainAcceptor + Send + Sync,
+{
+    type WrapperStream = A::WrapperStream;
+
+    async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+        let peer = stream.peer_addr()?;
+        if !self.filter.is_allowed(peer.ip()) {
+            debug!("refused peer: {}", peer);
+            return Err(IoError::new(
+                ErrorKind::PermissionDenied,
+                format!("peer {} is not allowed", peer),
+            ));
+        }
+        self.inner.accept(stream).await
+    }
+}
+
+/// refuse targets not allowed by filter. host names are resolved with `resolver` first
+/// and inner connector is only asked to connect to allowed addresses, in resolved order.
+/// since inner connector gets address instead of host name, it should be plain tcp
+/// connector or transport, not one which needs host name such as for tls
+pub struct IpFilterConnector<C> {
+    inner: C,
+    filter: SharedIpFilter,
+}
+
+impl<C> IpFilterConnector<C> {
+    pub fn new(inner: C, filter: SharedIpFilter) -> Self {
+        Self { inner, filter }
+    }
+
+    fn check(&self, addr: SocketAddr) -> Result<(), ConnectorError> {
+        if self.filter.is_allowed(addr.ip()) {
+            Ok(())
+        } else {
+            debug!("refused target: {}", addr);
+            Err(ConnectorError::AddressNotAllowed(addr))
+        }
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for IpFilterConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = C::WrapperStream;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        if let Ok(addr) = domain.parse::<SocketAddr>() {
+            self.check(addr)?;
+            return self.inner.connect(domain).await;
+        }
+        let addrs = resolver()
+            .resolve(domain)
+            .await
+            .map_err(|source| ConnectorError::Dns {
+                target: domain.to_owned(),
+                source,
+            })?
+            .addrs;
+        let mut last_error = None;
+        for addr in addrs {
+            if let Err(err) = self.check(addr) {
+                last_error.get_or_insert(err);
+                continue;
+            }
+            match self.inner.connect(&addr.to_string()).await {
+                Ok(connected) => return Ok(connected),
+                Err(err) => {
+                    debug!("connect to: {} failed: {}", addr, err);
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| ConnectorError::Dns {
+            target: domain.to_owned(),
+            source: IoError::new(ErrorKind::NotFound, "no address found"),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::net::IpAddr;
+    use std::os::unix::io::RawFd;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use futures_lite::stream::StreamExt;
+
+    use crate::net::duplex;
+    use crate::net::ConnectorError;
+    use crate::net::DefaultTcpDomainAcceptor;
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::DuplexStream;
+    use crate::net::TcpDomainAcceptor;
+    use crate::net::TcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::test_async;
+
+    use super::Cidr;
+    use super::IpFilter;
+    use super::IpFilterAcceptor;
+    use super::IpFilterConnector;
+    use super::SharedIpFilter;
+
+    fn ip(addr: &str) -> IpAddr {
+        addr.parse().expect("ip")
+    }
+
+    /// connector without socket, which records targets it was asked to connect to
+    #[derive(Default)]
+    struct MemoryConnector(Mutex<Vec<String>>);
+
+    #[async_trait]
+    impl TcpDomainConnector for MemoryConnector {
+        type WrapperStream = DuplexStream;
+
+        async fn connect(&self, domain: &str) -> Result<(DuplexStream, RawFd), ConnectorError> {
+            self.0.lock().unwrap().push(domain.to_owned());
+            Ok((duplex(64).0, -1))
+        }
+    }
+
+    #[test_async]
+    async fn test_ip_filter() -> Result<(), IoError> {
+        let private: Cidr = "10.0.0.0/8".parse()?;
+        assert!(private.contains(ip("10.1.2.3")));
+        assert!(!private.contains(ip("11.0.0.1")));
+        assert!(private.contains(ip("::ffff:10.0.0.1")));
+        assert!("fd00::/8".parse::<Cidr>()?.contains(ip("fd12::1")));
+        assert!("10.0.0.0/33".parse::<Cidr>().is_err());
+
+        let filter = IpFilter::new().allow(private).deny("10.0.0.0/24".parse()?);
+        assert!(filter.is_allowed(ip("10.2.0.1")));
+        assert!(!filter.is_allowed(ip("10.0.0.7")));
+        assert!(!filter.is_allowed(ip("192.168.0.1")));
+        assert!(IpFilter::new().is_allowed(ip("192.168.0.1")));
+
+        let filter = SharedIpFilter::new(IpFilter::new().allow(private));
+        let addr = "127.0.0.1:8908";
+        let listener = TcpListener::bind(addr).await?;
+        let connector = IpFilterConnector::new(DefaultTcpDomainConnector::new(), filter.clone());
+        let err = connector.connect(addr).await.expect_err("denied");
+        assert!(matches!(err, ConnectorError::AddressNotAllowed(_)));
+        // host name is checked against address it resolved to
+        let err = connector
+            .connect("localhost:8908")
+            .await
+            .expect_err("denied");
+        assert!(matches!(err, ConnectorError::AddressNotAllowed(_)));
+
+        // loopback is allowed for connector, denied for acceptor after update
+        filter.set(IpFilter::new().allow("127.0.0.0/8".parse()?));
+        let (_client, _) = connector.connect(addr).await?;
+        filter
+            .follow(futures_lite::stream::once(
+                IpFilter::new().deny("127.0.0.1".parse()?),
+            ))
+            .await;
+        let acceptor = IpFilterAcceptor::new(DefaultTcpDomainAcceptor::new(), filter);
+        let stream = listener.incoming().next().await.expect("stream")?;
+        let err = acceptor.accept(stream).await.expect_err("denied");
+        assert_eq!(err.kind(), ErrorKind::PermissionDenied);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_ip_filter_resolved() -> Result<(), IoError> {
+        let filter = SharedIpFilter::new(IpFilter::new().allow("127.0.0.0/8".parse()?));
+        let connector = IpFilterConnector::new(MemoryConnector::default(), filter.clone());
+        // only allowed address of host name is passed on, such as not ipv6 loopback
+        let (_stream, fd) = connector.connect("localhost:8915").await?;
+        assert_eq!(fd, -1);
+        assert_eq!(*connector.inner.0.lock().unwrap(), vec!["127.0.0.1:8915"]);
+
+        filter.set(IpFilter::new().allow("10.0.0.0/8".parse()?));
+        let result = connector.connect("localhost:8915").await;
+        assert!(matches!(result, Err(ConnectorError::AddressNotAllowed(_))));
+        assert_eq!(connector.inner.0.lock().unwrap().len(), 1);
+        Ok(())
+    }
+}
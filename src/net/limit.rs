@@ -0,0 +1,235 @@
+//! listener with limit on open connections, so accept storm can't exhaust file descriptors.
+//!
+//! once limit is reached, listener stops accepting until connection is closed, leaving new
+//! connections in kernel backlog. accept failing for lack of descriptors is retried after backoff
+use std::io::Error as IoError;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+use std::time::Duration;
+
+use futures_lite::future::poll_fn;
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::warn;
+
+use super::TcpListener;
+use super::TcpStream;
+use crate::timer::sleep;
+
+const DEFAULT_FD_BACKOFF: Duration = Duration::from_millis(100);
+/// errors of accept when process or system is out of file descriptors
+const ENFILE: i32 = 23;
+const EMFILE: i32 = 24;
+
+#[derive(Default)]
+struct GateState {
+    active: usize,
+    peak: usize,
+    waiters: Vec<Waker>,
+}
+
+struct Gate {
+    max: usize,
+    state: Mutex<GateState>,
+}
+
+impl Gate {
+    fn poll_acquire(self: &Arc<Self>, cx: &mut Context<'_>) -> Poll<ConnectionPermit> {
+        let mut state = self.state.lock().unwrap();
+        if state.active >= self.max {
+            state.waiters.push(cx.waker().clone());
+            return Poll::Pending;
+        }
+        state.active += 1;
+        state.peak = state.peak.max(state.active);
+        #[cfg(feature = "metrics")]
+        let metered = crate::metrics::connection_opened();
+        #[cfg(not(feature = "metrics"))]
+        let metered = false;
+        Poll::Ready(ConnectionPermit {
+            gate: self.clone(),
+            metered,
+        })
+    }
+}
+
+/// slot of open connection, released when dropped
+pub struct ConnectionPermit {
+    gate: Arc<Gate>,
+    /// counted as active connection, so its close is counted too
+    metered: bool,
+}
+
+impl Drop for ConnectionPermit {
+    fn drop(&mut self) {
+        let waiters = {
+            let mut state = self.gate.state.lock().unwrap();
+            state.active -= 1;
+            std::mem::take(&mut state.waiters)
+        };
+        if self.metered {
+            #[cfg(feature = "metrics")]
+            crate::metrics::connection_closed();
+        }
+        waiters.into_iter().for_each(Waker::wake);
+    }
+}
+
+pub struct LimitedListener {
+    listener: TcpListener,
+    gate: Arc<Gate>,
+    fd_backoff: Duration,
+}
+
+impl LimitedListener {
+    /// accept at most `max_connections` connections open at same time
+    pub fn new(listener: TcpListener, max_connections: usize) -> Self {
+        Self {
+            listener,
+            gate: Arc::new(Gate {
+                max: max_connections.max(1),
+                state: Mutex::new(GateState::default()),
+            }),
+            fd_backoff: DEFAULT_FD_BACKOFF,
+        }
+    }
+
+    /// wait before accepting again when process is out of file descriptors, default is 100ms
+    pub fn fd_backoff(mut self, backoff: Duration) -> Self {
+        self.fd_backoff = backoff;
+        self
+    }
+
+    pub fn get_ref(&self) -> &TcpListener {
+        &self.listener
+    }
+
+    pub fn max_connections(&self) -> usize {
+        self.gate.max
+    }
+
+    /// connections currently open
+    pub fn active(&self) -> usize {
+        self.gate.state.lock().unwrap().active
+    }
+
+    /// most connections open at same time
+    pub fn peak(&self) -> usize {
+        self.gate.state.lock().unwrap().peak
+    }
+
+    /// wait for free slot, then accept next connection
+    pub async fn accept(&self) -> Result<(LimitedStream, SocketAddr), IoError> {
+        let permit = poll_fn(|cx| self.gate.poll_acquire(cx)).await;
+        loop {
+            match self.listener.accept().await {
+                Ok((stream, addr)) => return Ok((LimitedStream::from_parts(stream, permit), addr)),
+                Err(err) if matches!(err.raw_os_error(), Some(EMFILE) | Some(ENFILE)) => {
+                    warn!(
+                        "out of file descriptors, accept again in {:?}",
+                        self.fd_backoff
+                    );
+                    sleep(self.fd_backoff).await;
+                }
+                Err(err) => return Err(err),
+            }
+        }
+    }
+}
+
+/// accepted stream holding its slot in listener until dropped
+pub struct LimitedStream<S = TcpStream> {
+    inner: S,
+    permit: ConnectionPermit,
+}
+
+impl<S> LimitedStream<S> {
+    pub fn from_parts(inner: S, permit: ConnectionPermit) -> Self {
+        Self { inner, permit }
+    }
+
+    /// stream and its slot, such as to wrap stream by tls acceptor and reattach slot
+    pub fn into_parts(self) -> (S, ConnectionPermit) {
+        (self.inner, self.permit)
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for LimitedStream<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for LimitedStream<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::net::SocketAddr;
+
+    use futures_lite::future::poll_once;
+
+    use crate::net::TcpListener;
+    use crate::net::TcpStream;
+    use crate::test_async;
+
+    use super::LimitedListener;
+
+    #[test_async]
+    async fn test_connection_limit() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8906".parse::<SocketAddr>().expect("parse");
+        let listener = LimitedListener::new(TcpListener::bind(&addr).await?, 2);
+
+        let _clients = [
+            TcpStream::connect(&addr).await?,
+            TcpStream::connect(&addr).await?,
+            TcpStream::connect(&addr).await?,
+        ];
+        let (first, _) = listener.accept().await?;
+        let (second, _) = listener.accept().await?;
+        assert_eq!(listener.active(), 2);
+
+        // third waits in backlog until slot is released
+        let mut third = Box::pin(listener.accept());
+        assert!(poll_once(&mut third).await.is_none());
+        drop(first);
+        let (third, _) = third.await?;
+        assert_eq!(listener.active(), 2);
+        assert_eq!(listener.peak(), 2);
+
+        drop((second, third));
+        assert_eq!(listener.active(), 0);
+        Ok(())
+    }
+}
@@ -0,0 +1,293 @@
+//! lz4 block format, compressor is greedy single probe hash chain like lz4 fast mode
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+
+const MIN_MATCH: usize = 4;
+/// last match must start this far before end of block
+const MFLIMIT: usize = 12;
+/// last bytes of block are always literals
+const LAST_LITERALS: usize = 5;
+const MAX_OFFSET: usize = 65535;
+
+/// largest compressed size of `len` bytes
+pub(crate) fn compress_bound(len: usize) -> usize {
+    len + len / 255 + 16
+}
+
+/// compressor keeping hash table between blocks, `hash_log` sets table size
+pub(crate) struct Compressor {
+    hash_log: u32,
+    table: Vec<u32>,
+}
+
+impl Compressor {
+    pub(crate) fn new(hash_log: u32) -> Self {
+        Self {
+            hash_log,
+            table: vec![0; 1 << hash_log],
+        }
+    }
+
+    fn hash(&self, sequence: u32) -> usize {
+        (sequence.wrapping_mul(2_654_435_761) >> (32 - self.hash_log)) as usize
+    }
+
+    /// append compressed `input` to `out`
+    pub(crate) fn compress(&mut self, input: &[u8], out: &mut Vec<u8>) {
+        out.reserve(compress_bound(input.len()));
+        let mut anchor = 0;
+        if input.len() > MFLIMIT {
+            // positions are stored plus one, so zero is empty slot
+            self.table.iter_mut().for_each(|slot| *slot = 0);
+            let match_limit = input.len() - LAST_LITERALS;
+            let mut pos = 0;
+            while pos + MFLIMIT < input.len() {
+                let sequence = read_u32(input, pos);
+                let hash = self.hash(sequence);
+                let candidate = self.table[hash] as usize;
+                self.table[hash] = (pos + 1) as u32;
+                if candidate > 0 {
+                    let candidate = candidate - 1;
+                    if pos - candidate <= MAX_OFFSET && read_u32(input, candidate) == sequence {
+                        let mut len = MIN_MATCH;
+                        while pos + len < match_limit && input[candidate + len] == input[pos + len]
+                        {
+                            len += 1;
+                        }
+                        write_sequence(out, &input[anchor..pos], Some((pos - candidate, len)));
+                        pos += len;
+                        anchor = pos;
+                        continue;
+                    }
+                }
+                pos += 1;
+            }
+        }
+        write_sequence(out, &input[anchor..], None);
+    }
+}
+
+fn read_u32(input: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([input[pos], input[pos + 1], input[pos + 2], input[pos + 3]])
+}
+
+fn write_length(out: &mut Vec<u8>, mut len: usize) {
+    while len >= 255 {
+        out.push(255);
+        len -= 255;
+    }
+    out.push(len as u8);
+}
+
+fn write_sequence(out: &mut Vec<u8>, literals: &[u8], matched: Option<(usize, usize)>) {
+    let literal_token = literals.len().min(15);
+    let match_token = matched.map_or(0, |(_, len)| (len - MIN_MATCH).min(15));
+    out.push((literal_token << 4 | match_token) as u8);
+    if literals.len() >= 15 {
+        write_length(out, literals.len() - 15);
+    }
+    out.extend_from_slice(literals);
+    if let Some((offset, len)) = matched {
+        out.extend_from_slice(&(offset as u16).to_le_bytes());
+        if len - MIN_MATCH >= 15 {
+            write_length(out, len - MIN_MATCH - 15);
+        }
+    }
+}
+
+fn corrupt() -> IoError {
+    IoError::new(ErrorKind::InvalidData, "corrupt lz4 block")
+}
+
+fn read_length(input: &[u8], pos: &mut usize, mut len: usize) -> Result<usize, IoError> {
+    loop {
+        let byte = *input.get(*pos).ok_or_else(corrupt)?;
+        *pos += 1;
+        len += byte as usize;
+        if byte != 255 {
+            return Ok(len);
+        }
+    }
+}
+
+/// decompress block which decodes to exactly `len` bytes
+pub(crate) fn decompress(input: &[u8], len: usize) -> Result<Vec<u8>, IoError> {
+    let mut out = Vec::with_capacity(len);
+    let mut pos = 0;
+    loop {
+        let token = *input.get(pos).ok_or_else(corrupt)?;
+        pos += 1;
+        let mut literals = (token >> 4) as usize;
+        if literals == 15 {
+            literals = read_length(input, &mut pos, literals)?;
+        }
+        let literal_end = pos.checked_add(literals).ok_or_else(corrupt)?;
+        if literal_end > input.len() || out.len() + literals > len {
+            return Err(corrupt());
+        }
+        out.extend_from_slice(&input[pos..literal_end]);
+        pos = literal_end;
+        if pos == input.len() {
+            break;
+        }
+
+        let offset = input.get(pos..pos + 2).ok_or_else(corrupt)?;
+        let offset = u16::from_le_bytes([offset[0], offset[1]]) as usize;
+        pos += 2;
+        let mut match_len = (token & 0x0f) as usize;
+        if match_len == 15 {
+            match_len = read_length(input, &mut pos, match_len)?;
+        }
+        match_len += MIN_MATCH;
+        if offset == 0 || offset > out.len() || out.len() + match_len > len {
+            return Err(corrupt());
+        }
+        // match may overlap bytes it produces, so copy one at time
+        let start = out.len() - offset;
+        for index in start..start + match_len {
+            let byte = out[index];
+            out.push(byte);
+        }
+    }
+    if out.len() != len {
+        return Err(corrupt());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::compress_bound;
+    use super::decompress;
+    use super::Compressor;
+
+    #[test]
+    fn test_lz4_roundtrip() {
+        let mut compressor = Compressor::new(12);
+        let text = b"fluvio streams records, fluvio streams records again and again. ".repeat(50);
+        let mut noise = vec![0u8; 5000];
+        let mut seed = 7u32;
+        for byte in noise.iter_mut() {
+            seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+            *byte = (seed >> 24) as u8;
+        }
+        let inputs: Vec<Vec<u8>> = vec![
+            vec![],
+            b"short".to_vec(),
+            vec![0; 70_000],
+            text.clone(),
+            noise,
+            [text, vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13]].concat(),
+        ];
+        for input in inputs {
+            let mut compressed = vec![];
+            compressor.compress(&input, &mut compressed);
+            assert!(compressed.len() <= compress_bound(input.len()));
+            assert_eq!(decompress(&compressed, input.len()).unwrap(), input);
+        }
+
+        let mut compressed = vec![];
+        compressor.compress(&[9; 1000], &mut compressed);
+        assert!(compressed.len() < 20);
+        assert!(decompress(&compressed, 999).is_err());
+        assert!(decompress(&compressed[..compressed.len() - 1], 1000).is_err());
+        assert!(decompress(&[0x0f, 0, 0], 100).is_err());
+    }
+
+    /// next byte of fixed pseudo random sequence
+    fn next(seed: &mut u32) -> u8 {
+        *seed = seed.wrapping_mul(1_103_515_245).wrapping_add(12345);
+        (*seed >> 24) as u8
+    }
+
+    #[test]
+    fn test_lz4_reference_blocks() {
+        // blocks taken out of frames written by lz4 1.9.4 cli
+        let vectors: Vec<(Vec<u8>, Vec<u8>)> = vec![
+            (
+                vec![
+                    0x1f, 0x61, 0x01, 0x00, 0x19, 0x50, 0x61, 0x61, 0x61, 0x61, 0x61,
+                ],
+                b"a".repeat(50),
+            ),
+            (
+                [&[0x6f][..], b"hello ", &[0x06, 0x00, 0x05, 0x50], b"hello"].concat(),
+                b"hello ".repeat(6)[..35].to_vec(),
+            ),
+            (
+                [
+                    &[0xff, 0x01][..],
+                    b"0123456789abcdef",
+                    &[0x10, 0x00, 0x09, 0x50],
+                    b"cdef!",
+                ]
+                .concat(),
+                [&b"0123456789abcdef".repeat(3)[..], b"!"].concat(),
+            ),
+            // lengths extended over several bytes
+            (
+                [
+                    &[0xff, 0xf1][..],
+                    &(0..=255).collect::<Vec<u8>>(),
+                    &[0x00, 0x01, 0xff, 0xff, 0xef, 0x7f],
+                    b"fluvio ",
+                    &[0x07, 0x00, 0xf9, 0x50],
+                    b"uvio ",
+                ]
+                .concat(),
+                [
+                    (0..=255).collect::<Vec<u8>>().repeat(4),
+                    b"fluvio ".repeat(40),
+                ]
+                .concat(),
+            ),
+        ];
+        for (block, expected) in vectors {
+            assert_eq!(decompress(&block, expected.len()).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_lz4_corrupt_input() {
+        let mut compressor = Compressor::new(12);
+        let input = b"fluvio streams records, fluvio streams records again and again. ".repeat(20);
+        let mut compressed = vec![];
+        compressor.compress(&input, &mut compressed);
+
+        // truncated block never decodes
+        for len in 0..compressed.len() {
+            assert!(decompress(&compressed[..len], input.len()).is_err());
+        }
+
+        // flipped bytes either fail or decode to exactly expected length, but never panic
+        let mut seed = 11u32;
+        for _ in 0..5000 {
+            let mut corrupted = compressed.clone();
+            for _ in 0..=next(&mut seed) % 4 {
+                let pos = (next(&mut seed) as usize) << 8 | next(&mut seed) as usize;
+                corrupted[pos % compressed.len()] ^= next(&mut seed) | 1;
+            }
+            if let Ok(out) = decompress(&corrupted, input.len()) {
+                assert_eq!(out.len(), input.len());
+            }
+        }
+
+        // random garbage
+        for _ in 0..5000 {
+            let garbage: Vec<u8> = (0..next(&mut seed) % 64).map(|_| next(&mut seed)).collect();
+            let len = next(&mut seed) as usize * 16;
+            if let Ok(out) = decompress(&garbage, len) {
+                assert_eq!(out.len(), len);
+            }
+        }
+
+        // offset pointing before start of output, or zero
+        assert!(decompress(&[0x14, b'a', 0x02, 0x00, 0x50, 1, 2, 3, 4, 5], 14).is_err());
+        assert!(decompress(&[0x14, b'a', 0x00, 0x00, 0x50, 1, 2, 3, 4, 5], 14).is_err());
+        // length extension running past end of block
+        assert!(decompress(&[0xf0, 0xff, 0xff], 1000).is_err());
+        // literals larger than declared length
+        assert!(decompress(&[0x50, 1, 2, 3, 4, 5], 4).is_err());
+    }
+}
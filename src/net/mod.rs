@@ -1,51 +1,372 @@
 pub use async_net::*;
 
 #[cfg(test)]
+#[allow(clippy::never_loop, clippy::assertions_on_constants)]
 mod tcp_stream;
 
+#[cfg(unix)]
+pub use acceptor::*;
+#[cfg(all(unix, feature = "socket"))]
+pub use activation::*;
+#[cfg(unix)]
+pub use balanced::*;
+#[cfg(unix)]
+pub use breaker::*;
+#[cfg(unix)]
+pub use checksum::*;
+#[cfg(unix)]
+pub use compressed::*;
+#[cfg(all(unix, feature = "config"))]
+pub use config::*;
 #[cfg(unix)]
 pub use connector::*;
+#[cfg(unix)]
+pub use deadline::*;
+pub use duplex::*;
+#[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+pub(crate) use env_config::*;
+pub use error::*;
+#[cfg(unix)]
+pub use events::*;
+pub use expiry::*;
+#[cfg(unix)]
+pub use fd::*;
+#[cfg(all(target_os = "linux", feature = "socket"))]
+pub use fd_passing::*;
+#[cfg(unix)]
+pub use framed::*;
+pub use heartbeat::*;
+#[cfg(unix)]
+pub use ip_filter::*;
+#[cfg(unix)]
+pub use limit::*;
+#[cfg(all(target_os = "linux", feature = "ping"))]
+pub use ping::*;
+#[cfg(unix)]
+pub use proxy_protocol::*;
+pub use read_timeout::*;
+pub use readiness::*;
+#[cfg(unix)]
+pub use registry::*;
+pub use resolver::*;
+#[cfg(all(unix, feature = "socket"))]
+pub use reuseport::*;
+#[cfg(all(unix, feature = "secure_dns"))]
+pub use secure_dns::*;
+pub use shared::*;
+pub use sniff::*;
+pub use split::*;
+pub use srv::*;
+pub use transport::*;
+#[cfg(all(target_os = "linux", feature = "tun"))]
+pub use tun::*;
+#[cfg(all(target_os = "linux", feature = "socket"))]
+pub use unix_socket::*;
+#[cfg(all(target_os = "linux", feature = "vsock"))]
+pub use vsock::*;
+
+#[cfg(all(unix, feature = "socket"))]
+mod activation;
+#[cfg(unix)]
+mod balanced;
+#[cfg(unix)]
+mod block;
+#[cfg(unix)]
+mod breaker;
+#[cfg(unix)]
+mod checksum;
+#[cfg(unix)]
+mod compressed;
+#[cfg(all(unix, feature = "config"))]
+mod config;
+#[cfg(unix)]
+mod deadline;
+#[cfg(all(unix, feature = "socket"))]
+pub mod discovery;
+mod duplex;
+#[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+mod env_config;
+mod error;
+#[cfg(unix)]
+mod events;
+mod expiry;
+#[cfg(unix)]
+mod fd;
+#[cfg(all(target_os = "linux", feature = "socket"))]
+mod fd_passing;
+#[cfg(unix)]
+mod framed;
+mod heartbeat;
+#[cfg(unix)]
+mod ip_filter;
+#[cfg(unix)]
+mod limit;
+#[cfg(unix)]
+mod lz4;
+#[cfg(all(target_os = "linux", feature = "ping"))]
+mod ping;
+#[cfg(unix)]
+mod proxy_protocol;
+mod read_timeout;
+mod readiness;
+#[cfg(unix)]
+mod registry;
+mod resolver;
+#[cfg(all(unix, feature = "socket"))]
+mod reuseport;
+#[cfg(all(unix, feature = "secure_dns"))]
+mod secure_dns;
+mod shared;
+mod sniff;
+mod split;
+mod srv;
+mod transport;
+#[cfg(all(target_os = "linux", feature = "tun"))]
+mod tun;
+#[cfg(all(target_os = "linux", feature = "socket"))]
+mod unix_socket;
+#[cfg(all(target_os = "linux", feature = "vsock"))]
+mod vsock;
 
 #[cfg(unix)]
 mod connector {
     use std::io::Error as IoError;
+    use std::io::ErrorKind;
     #[cfg(unix)]
     use std::os::unix::io::AsRawFd;
     #[cfg(unix)]
     use std::os::unix::io::RawFd;
+    use std::time::Duration;
 
     use async_trait::async_trait;
-    use futures_lite::{AsyncRead, AsyncWrite};
+    use futures_lite::{AsyncRead, AsyncWrite, AsyncWriteExt};
     use log::debug;
 
+    use super::encode_proxy_header;
+    use super::resolver;
+    use super::unix::UnixStream;
+    use super::within_deadline;
+    use super::ConnectorError;
+    use super::Deadline;
+    use super::ProxyInfo;
+    use super::ProxyVersion;
     use super::TcpStream;
+    use crate::instrument::instrument_connect;
+    use crate::instrument::record_peer;
 
     /// transform raw tcp stream to another stream
     #[async_trait]
     pub trait TcpDomainConnector {
         type WrapperStream: AsyncRead + AsyncWrite + Unpin + Send;
 
-        async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), IoError>;
+        async fn connect(
+            &self,
+            domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError>;
+    }
+
+    /// resolve and connect to tcp address, dns failure is reported separately from connect failure.
+    /// with current deadline, remaining time is split evenly over addresses not yet tried
+    pub async fn connect_tcp(addr: &str) -> Result<TcpStream, ConnectorError> {
+        let addrs = within_deadline(resolver().resolve(addr))
+            .await?
+            .map_err(|source| ConnectorError::Dns {
+                target: addr.to_owned(),
+                source,
+            })?
+            .addrs;
+        if addrs.is_empty() {
+            return Err(ConnectorError::Dns {
+                target: addr.to_owned(),
+                source: IoError::new(ErrorKind::NotFound, "no address found"),
+            });
+        }
+        let count = addrs.len();
+        let mut last_error = None;
+        for (index, addr) in addrs.into_iter().enumerate() {
+            let attempt = match Deadline::current() {
+                Some(deadline) => {
+                    let share = deadline.remaining() / (count - index) as u32;
+                    Deadline::after(share)
+                        .scope(within_deadline(TcpStream::connect(addr)))
+                        .await
+                }
+                None => Ok(TcpStream::connect(addr).await),
+            };
+            match attempt {
+                Ok(Ok(stream)) => return Ok(stream),
+                Ok(Err(err)) => {
+                    debug!("connect to: {} failed: {}", addr, err);
+                    last_error = Some(ConnectorError::from(err).with_addr(addr));
+                }
+                Err(err) => {
+                    debug!("connect to: {} timed out", addr);
+                    last_error = Some(err.with_addr(addr));
+                }
+            }
+        }
+        Err(last_error.expect("at least one address"))
     }
 
-    #[derive(Clone)]
-    pub struct DefaultTcpDomainConnector {}
+    /// host of `host:port` address, such as for tls server name of anonymous connect.
+    /// brackets of ipv6 host are removed, address without port is returned as is
+    #[cfg(any(feature = "rust_tls", feature = "native2_tls"))]
+    pub(crate) fn server_name(addr: &str) -> &str {
+        let host = match addr.rsplit_once(':') {
+            Some((host, port))
+                if port.parse::<u16>().is_ok() && (!host.contains(':') || host.ends_with(']')) =>
+            {
+                host
+            }
+            _ => addr,
+        };
+        host.trim_start_matches('[').trim_end_matches(']')
+    }
 
-    impl DefaultTcpDomainConnector {
+    /// transport tls connectors can be layered over, such as tcp, unix socket or in memory stream
+    pub trait TransportConnector: TcpDomainConnector + Send + Sync {}
+
+    impl<T: TcpDomainConnector + Send + Sync> TransportConnector for T {}
+
+    /// connect to unix socket, address is path of socket.
+    /// on linux with `socket` feature, `@name` is address in abstract namespace
+    #[derive(Clone, Default)]
+    pub struct UnixDomainConnector {}
+
+    impl UnixDomainConnector {
         pub fn new() -> Self {
             Self {}
         }
     }
 
+    #[async_trait]
+    impl TcpDomainConnector for UnixDomainConnector {
+        type WrapperStream = UnixStream;
+
+        async fn connect(
+            &self,
+            path: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            instrument_connect("unix", path, path, async {
+                debug!("connect to unix socket: {}", path);
+                #[cfg(all(target_os = "linux", feature = "socket"))]
+                let stream = if super::is_abstract_address(path) {
+                    super::connect_unix(path, super::UnixSocketType::Stream).await?
+                } else {
+                    UnixStream::connect(path).await?
+                };
+                #[cfg(not(all(target_os = "linux", feature = "socket")))]
+                let stream = UnixStream::connect(path).await?;
+                let fd = stream.as_raw_fd();
+                Ok((stream, fd)) as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
+        }
+    }
+
+    #[derive(Clone, Default)]
+    pub struct DefaultTcpDomainConnector {
+        connect_timeout: Option<Duration>,
+        proxy: Option<ProxyVersion>,
+    }
+
+    impl DefaultTcpDomainConnector {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// fail with `ConnectorError::Timeout` if dns and tcp connect take longer than `timeout`,
+        /// or than current deadline if it is earlier. without it, timeout of `Runtime` applies
+        pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+            self.connect_timeout = Some(timeout);
+            self
+        }
+
+        /// send PROXY protocol header with addresses of connection, before any other data
+        pub fn proxy_protocol(mut self, version: ProxyVersion) -> Self {
+            self.proxy = Some(version);
+            self
+        }
+
+        /// connect tcp stream with timeout and PROXY header applied,
+        /// tls connectors use this for their tcp stream
+        pub async fn connect_stream(&self, addr: &str) -> Result<TcpStream, ConnectorError> {
+            let timeout = self
+                .connect_timeout
+                .or_else(|| crate::Runtime::current().connect_timeout());
+            let mut stream = match timeout {
+                Some(timeout) => {
+                    Deadline::after(timeout)
+                        .scope(within_deadline(connect_tcp(addr)))
+                        .await??
+                }
+                None => connect_tcp(addr).await?,
+            };
+            if let Some(version) = self.proxy {
+                let info = ProxyInfo {
+                    source: stream.local_addr()?,
+                    destination: stream.peer_addr()?,
+                };
+                within_deadline(stream.write_all(&encode_proxy_header(version, Some(&info))))
+                    .await??;
+            }
+            Ok(stream)
+        }
+    }
+
     #[async_trait]
     impl TcpDomainConnector for DefaultTcpDomainConnector {
         type WrapperStream = TcpStream;
 
-        async fn connect(&self, addr: &str) -> Result<(Self::WrapperStream, RawFd), IoError> {
-            debug!("connect to tcp addr: {}", addr);
-            let tcp_stream = TcpStream::connect(addr).await?;
-            let fd = tcp_stream.as_raw_fd();
-            Ok((tcp_stream, fd))
+        async fn connect(
+            &self,
+            addr: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            instrument_connect("tcp", addr, addr, async {
+                debug!("connect to tcp addr: {}", addr);
+                let tcp_stream = self.connect_stream(addr).await?;
+                record_peer(&tcp_stream);
+                let fd = tcp_stream.as_raw_fd();
+                Ok((tcp_stream, fd)) as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
+        }
+    }
+}
+
+#[cfg(unix)]
+mod acceptor {
+    use std::io::Error as IoError;
+
+    use async_trait::async_trait;
+    use futures_lite::{AsyncRead, AsyncWrite};
+
+    use super::TcpStream;
+
+    /// transform accepted tcp stream to another stream
+    #[async_trait]
+    pub trait TcpDomainAcceptor {
+        type WrapperStream: AsyncRead + AsyncWrite + Unpin + Send;
+
+        async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError>;
+    }
+
+    /// accept as plain tcp
+    #[derive(Clone, Default)]
+    pub struct DefaultTcpDomainAcceptor {}
+
+    impl DefaultTcpDomainAcceptor {
+        pub fn new() -> Self {
+            Self {}
+        }
+    }
+
+    #[async_trait]
+    impl TcpDomainAcceptor for DefaultTcpDomainAcceptor {
+        type WrapperStream = TcpStream;
+
+        async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+            Ok(stream)
         }
     }
 }
@@ -0,0 +1,221 @@
+//! icmp echo, for checking that endpoint is reachable before more expensive tls connect.
+//!
+//! unprivileged `SOCK_DGRAM` icmp socket is used if `net.ipv4.ping_group_range` allows it,
+//! raw socket otherwise, which needs `CAP_NET_RAW`
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::net::SocketAddr;
+use std::net::UdpSocket;
+use std::os::unix::io::FromRawFd;
+use std::sync::atomic::AtomicU16;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_io::Async;
+use futures_lite::future::FutureExt;
+use log::debug;
+
+use crate::timer::sleep;
+
+const PING_TIMEOUT: Duration = Duration::from_secs(2);
+const PAYLOAD: &[u8] = b"fluvio-future-ping";
+
+const ECHO_REQUEST_V4: u8 = 8;
+const ECHO_REPLY_V4: u8 = 0;
+const ECHO_REQUEST_V6: u8 = 128;
+const ECHO_REPLY_V6: u8 = 129;
+
+/// icmp socket, wrapped as udp socket since `send_to` and `recv_from` work same way
+struct IcmpSocket {
+    socket: Async<UdpSocket>,
+    /// raw socket receives all icmp, with ip header for ipv4
+    raw: bool,
+}
+
+impl IcmpSocket {
+    fn open(addr: IpAddr) -> Result<Self, IoError> {
+        let (domain, protocol) = match addr {
+            IpAddr::V4(_) => (libc::AF_INET, libc::IPPROTO_ICMP),
+            IpAddr::V6(_) => (libc::AF_INET6, libc::IPPROTO_ICMPV6),
+        };
+        let open = |kind| {
+            let fd = unsafe { libc::socket(domain, kind | libc::SOCK_CLOEXEC, protocol) };
+            if fd < 0 {
+                Err(IoError::last_os_error())
+            } else {
+                Ok(unsafe { UdpSocket::from_raw_fd(fd) })
+            }
+        };
+        let (socket, raw) = match open(libc::SOCK_DGRAM) {
+            Ok(socket) => (socket, false),
+            Err(err) if matches!(err.kind(), ErrorKind::PermissionDenied) => {
+                debug!("icmp dgram socket not permitted, using raw socket");
+                (open(libc::SOCK_RAW)?, true)
+            }
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            socket: Async::new(socket)?,
+            raw,
+        })
+    }
+}
+
+/// checksum of rfc 1071, kernel computes it for icmpv6
+fn checksum(data: &[u8]) -> u16 {
+    let mut sum: u32 = data
+        .chunks(2)
+        .map(|pair| u16::from_be_bytes([pair[0], *pair.get(1).unwrap_or(&0)]) as u32)
+        .sum();
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+    !(sum as u16)
+}
+
+fn echo_request(addr: IpAddr, ident: u16, seq: u16) -> Vec<u8> {
+    let kind = if addr.is_ipv4() {
+        ECHO_REQUEST_V4
+    } else {
+        ECHO_REQUEST_V6
+    };
+    let mut packet = vec![kind, 0, 0, 0];
+    packet.extend_from_slice(&ident.to_be_bytes());
+    packet.extend_from_slice(&seq.to_be_bytes());
+    packet.extend_from_slice(PAYLOAD);
+    if addr.is_ipv4() {
+        let sum = checksum(&packet);
+        packet[2..4].copy_from_slice(&sum.to_be_bytes());
+    }
+    packet
+}
+
+/// send icmp echo requests and time replies
+#[derive(Debug)]
+pub struct Pinger {
+    timeout: Duration,
+    seq: AtomicU16,
+}
+
+impl Default for Pinger {
+    fn default() -> Self {
+        Self {
+            timeout: PING_TIMEOUT,
+            seq: AtomicU16::new(0),
+        }
+    }
+}
+
+impl Pinger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// time to wait for reply
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// round trip time of one echo, fails with `TimedOut` if no reply came in time
+    pub async fn ping(&self, addr: IpAddr) -> Result<Duration, IoError> {
+        let timed_out = async {
+            sleep(self.timeout).await;
+            Err(IoError::new(
+                ErrorKind::TimedOut,
+                format!("no echo reply from {}", addr),
+            ))
+        };
+        self.echo(addr).or(timed_out).await
+    }
+
+    /// true if `addr` replies in time
+    pub async fn is_reachable(&self, addr: IpAddr) -> bool {
+        match self.ping(addr).await {
+            Ok(rtt) => {
+                debug!("{} replied in {:?}", addr, rtt);
+                true
+            }
+            Err(err) => {
+                debug!("{} not reachable: {}", addr, err);
+                false
+            }
+        }
+    }
+
+    async fn echo(&self, addr: IpAddr) -> Result<Duration, IoError> {
+        let socket = IcmpSocket::open(addr)?;
+        // kernel replaces ident of dgram socket with its own and filters replies by it
+        let ident = std::process::id() as u16;
+        let seq = self.seq.fetch_add(1, Ordering::Relaxed);
+        let request = echo_request(addr, ident, seq);
+        let reply_kind = if addr.is_ipv4() {
+            ECHO_REPLY_V4
+        } else {
+            ECHO_REPLY_V6
+        };
+
+        let start = Instant::now();
+        socket
+            .socket
+            .send_to(&request, SocketAddr::new(addr, 0))
+            .await?;
+        let mut buf = [0; 1500];
+        loop {
+            let (n, from) = socket.socket.recv_from(&mut buf).await?;
+            let mut packet = &buf[..n];
+            if socket.raw && addr.is_ipv4() && !packet.is_empty() {
+                let header = ((packet[0] & 0x0f) as usize) * 4;
+                packet = packet.get(header..).unwrap_or_default();
+            }
+            if from.ip() != addr || packet.len() < 8 || packet[0] != reply_kind {
+                continue;
+            }
+            if u16::from_be_bytes([packet[6], packet[7]]) != seq {
+                continue;
+            }
+            if socket.raw && u16::from_be_bytes([packet[4], packet[5]]) != ident {
+                continue;
+            }
+            return Ok(start.elapsed());
+        }
+    }
+}
+
+/// round trip time of one echo to `addr`, with default timeout
+pub async fn ping(addr: IpAddr) -> Result<Duration, IoError> {
+    Pinger::new().ping(addr).await
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use log::debug;
+
+    use crate::test_async;
+
+    use super::checksum;
+    use super::ping;
+    use super::Pinger;
+
+    #[test_async]
+    async fn test_ping() -> Result<(), IoError> {
+        assert_eq!(checksum(&[0x08, 0, 0, 0, 0, 1, 0, 1]), 0xf7fd);
+
+        // icmp socket needs ping group or raw socket capability
+        match ping("127.0.0.1".parse().expect("ip")).await {
+            Ok(rtt) => {
+                assert!(rtt < Duration::from_secs(2));
+                let pinger = Pinger::new().timeout(Duration::from_millis(500));
+                assert!(pinger.is_reachable("127.0.0.1".parse().expect("ip")).await);
+            }
+            Err(err) => debug!("icmp not permitted: {}", err),
+        }
+        Ok(())
+    }
+}
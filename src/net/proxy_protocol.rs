@@ -0,0 +1,474 @@
+//! haproxy PROXY protocol v1 and v2, keeps real client address behind l4 load balancer.
+//!
+//! see <https://www.haproxy.org/download/2.0/doc/proxy-protocol.txt>
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::IpAddr;
+use std::net::Ipv4Addr;
+use std::net::Ipv6Addr;
+use std::net::SocketAddr;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_trait::async_trait;
+use futures_lite::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use log::debug;
+
+use super::connect_tcp;
+use super::ConnectorError;
+use super::TcpDomainAcceptor;
+use super::TcpDomainConnector;
+use super::TcpStream;
+
+const V1_PREFIX: &[u8] = b"PROXY";
+const V1_MAX_LEN: usize = 107;
+const V2_SIGNATURE: &[u8] = b"\r\n\r\n\0\r\nQUIT\n";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "config",
+    derive(serde::Deserialize),
+    serde(rename_all = "lowercase")
+)]
+pub enum ProxyVersion {
+    V1,
+    V2,
+}
+
+/// addresses of proxied connection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProxyInfo {
+    /// original client
+    pub source: SocketAddr,
+    /// address client connected to
+    pub destination: SocketAddr,
+}
+
+fn invalid(message: &str) -> IoError {
+    IoError::new(
+        ErrorKind::InvalidData,
+        format!("invalid proxy header: {}", message),
+    )
+}
+
+/// both addresses in same family, ipv4 is mapped to ipv6 if families differ
+fn same_family(info: &ProxyInfo) -> (SocketAddr, SocketAddr) {
+    let to_v6 = |addr: SocketAddr| match addr.ip() {
+        IpAddr::V4(ip) => SocketAddr::new(IpAddr::V6(ip.to_ipv6_mapped()), addr.port()),
+        IpAddr::V6(_) => addr,
+    };
+    if info.source.is_ipv4() == info.destination.is_ipv4() {
+        (info.source, info.destination)
+    } else {
+        (to_v6(info.source), to_v6(info.destination))
+    }
+}
+
+/// encode header, none is sent as `UNKNOWN` in v1 and `LOCAL` in v2
+pub fn encode_proxy_header(version: ProxyVersion, info: Option<&ProxyInfo>) -> Vec<u8> {
+    match version {
+        ProxyVersion::V1 => match info {
+            Some(info) => {
+                let (source, destination) = same_family(info);
+                format!(
+                    "PROXY {} {} {} {} {}\r\n",
+                    if source.is_ipv4() { "TCP4" } else { "TCP6" },
+                    source.ip(),
+                    destination.ip(),
+                    source.port(),
+                    destination.port()
+                )
+                .into_bytes()
+            }
+            None => b"PROXY UNKNOWN\r\n".to_vec(),
+        },
+        ProxyVersion::V2 => {
+            let mut header = V2_SIGNATURE.to_vec();
+            match info {
+                Some(info) => {
+                    let (source, destination) = same_family(info);
+                    let mut addrs = vec![];
+                    let family = match (source.ip(), destination.ip()) {
+                        (IpAddr::V4(source), IpAddr::V4(destination)) => {
+                            addrs.extend_from_slice(&source.octets());
+                            addrs.extend_from_slice(&destination.octets());
+                            0x11
+                        }
+                        (source, destination) => {
+                            addrs.extend_from_slice(&to_ipv6(source).octets());
+                            addrs.extend_from_slice(&to_ipv6(destination).octets());
+                            0x21
+                        }
+                    };
+                    addrs.extend_from_slice(&source.port().to_be_bytes());
+                    addrs.extend_from_slice(&destination.port().to_be_bytes());
+                    header.extend_from_slice(&[0x21, family]);
+                    header.extend_from_slice(&(addrs.len() as u16).to_be_bytes());
+                    header.extend(addrs);
+                }
+                None => header.extend_from_slice(&[0x20, 0x00, 0x00, 0x00]),
+            }
+            header
+        }
+    }
+}
+
+fn to_ipv6(ip: IpAddr) -> Ipv6Addr {
+    match ip {
+        IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+        IpAddr::V6(ip) => ip,
+    }
+}
+
+/// read v1 or v2 header, none if peer didn't send addresses of client, such as health check.
+/// nothing after header is read
+pub async fn read_proxy_header<S>(stream: &mut S) -> Result<Option<ProxyInfo>, IoError>
+where
+    S: AsyncRead + Unpin,
+{
+    let mut prefix = [0; 5];
+    stream.read_exact(&mut prefix).await?;
+    if prefix == V1_PREFIX {
+        let mut line = prefix.to_vec();
+        // byte at time, so payload after header is left in stream
+        while !line.ends_with(b"\r\n") {
+            if line.len() >= V1_MAX_LEN {
+                return Err(invalid("v1 header too long"));
+            }
+            let mut byte = [0; 1];
+            stream.read_exact(&mut byte).await?;
+            line.push(byte[0]);
+        }
+        parse_v1(&line[..line.len() - 2])
+    } else if prefix == V2_SIGNATURE[..5] {
+        let mut header = [0; 11];
+        stream.read_exact(&mut header).await?;
+        if header[..7] != V2_SIGNATURE[5..] {
+            return Err(invalid("bad v2 signature"));
+        }
+        let len = u16::from_be_bytes([header[9], header[10]]) as usize;
+        let mut addrs = vec![0; len];
+        stream.read_exact(&mut addrs).await?;
+        parse_v2(header[7], header[8], &addrs)
+    } else {
+        Err(invalid("missing header"))
+    }
+}
+
+fn parse_v1(line: &[u8]) -> Result<Option<ProxyInfo>, IoError> {
+    let line = std::str::from_utf8(line).map_err(|_| invalid("v1 header is not ascii"))?;
+    let parts: Vec<&str> = line.split(' ').collect();
+    match parts.as_slice() {
+        ["PROXY", "UNKNOWN", ..] => Ok(None),
+        ["PROXY", protocol @ ("TCP4" | "TCP6"), source, destination, source_port, destination_port] =>
+        {
+            let addr = |ip: &str, port: &str| -> Result<SocketAddr, IoError> {
+                let ip: IpAddr = ip.parse().map_err(|_| invalid("bad v1 address"))?;
+                let port: u16 = port.parse().map_err(|_| invalid("bad v1 port"))?;
+                if ip.is_ipv4() != (*protocol == "TCP4") {
+                    return Err(invalid("v1 address doesn't match protocol"));
+                }
+                Ok(SocketAddr::new(ip, port))
+            };
+            Ok(Some(ProxyInfo {
+                source: addr(source, source_port)?,
+                destination: addr(destination, destination_port)?,
+            }))
+        }
+        _ => Err(invalid("bad v1 header")),
+    }
+}
+
+fn parse_v2(version_command: u8, family: u8, addrs: &[u8]) -> Result<Option<ProxyInfo>, IoError> {
+    if version_command >> 4 != 2 {
+        return Err(invalid("unsupported version"));
+    }
+    match version_command & 0x0F {
+        // local connection from proxy itself
+        0x0 => return Ok(None),
+        0x1 => {}
+        _ => return Err(invalid("unsupported command")),
+    }
+    let port = |offset: usize| u16::from_be_bytes([addrs[offset], addrs[offset + 1]]);
+    match family {
+        // tcp over ipv4, tlvs after addresses are ignored
+        0x11 if addrs.len() >= 12 => {
+            let ip = |offset: usize| {
+                let mut octets = [0; 4];
+                octets.copy_from_slice(&addrs[offset..offset + 4]);
+                IpAddr::V4(Ipv4Addr::from(octets))
+            };
+            Ok(Some(ProxyInfo {
+                source: SocketAddr::new(ip(0), port(8)),
+                destination: SocketAddr::new(ip(4), port(10)),
+            }))
+        }
+        0x21 if addrs.len() >= 36 => {
+            let ip = |offset: usize| {
+                let mut octets = [0; 16];
+                octets.copy_from_slice(&addrs[offset..offset + 16]);
+                IpAddr::V6(Ipv6Addr::from(octets))
+            };
+            Ok(Some(ProxyInfo {
+                source: SocketAddr::new(ip(0), port(32)),
+                destination: SocketAddr::new(ip(16), port(34)),
+            }))
+        }
+        0x11 | 0x21 => Err(invalid("v2 addresses truncated")),
+        // unix sockets and unspecified families carry no usable client address
+        _ => Ok(None),
+    }
+}
+
+/// stream accepted thru proxy, with addresses from its header
+pub struct ProxiedStream<S> {
+    inner: S,
+    info: Option<ProxyInfo>,
+}
+
+impl<S> ProxiedStream<S> {
+    /// addresses from header, none if proxy didn't send them
+    pub fn proxy_info(&self) -> Option<&ProxyInfo> {
+        self.info.as_ref()
+    }
+
+    /// original client address
+    pub fn source_addr(&self) -> Option<SocketAddr> {
+        self.info.map(|info| info.source)
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ProxiedStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.inner).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ProxiedStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+/// acceptor reading proxy header before passing stream to inner acceptor, such as tls
+#[derive(Clone, Default)]
+pub struct ProxyProtocolAcceptor<A> {
+    inner: A,
+}
+
+impl<A> ProxyProtocolAcceptor<A> {
+    pub fn new(inner: A) -> Self {
+        Self { inner }
+    }
+}
+
+#[async_trait]
+impl<A> TcpDomainAcceptor for ProxyProtocolAcceptor<A>
+where
+    A: TcpDomainAcceptor + Send + Sync,
+{
+    type WrapperStream = ProxiedStream<A::WrapperStream>;
+
+    async fn accept(&self, mut stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+        let info = read_proxy_header(&mut stream).await?;
+        debug!("proxy header: {:?}", info);
+        let inner = self.inner.accept(stream).await?;
+        Ok(ProxiedStream { inner, info })
+    }
+}
+
+/// tcp connector sending proxy header right after connecting.
+/// tls can be layered by passing stream to `TlsConnector::connect`
+#[derive(Clone)]
+pub struct ProxyProtocolConnector {
+    version: ProxyVersion,
+    info: Option<ProxyInfo>,
+}
+
+impl ProxyProtocolConnector {
+    /// header carries local and peer address of connection
+    pub fn new(version: ProxyVersion) -> Self {
+        Self {
+            version,
+            info: None,
+        }
+    }
+
+    /// send addresses of client being proxied
+    pub fn with_info(mut self, info: ProxyInfo) -> Self {
+        self.info = Some(info);
+        self
+    }
+}
+
+#[async_trait]
+impl TcpDomainConnector for ProxyProtocolConnector {
+    type WrapperStream = TcpStream;
+
+    async fn connect(&self, addr: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        let mut stream = connect_tcp(addr).await?;
+        let info = match self.info {
+            Some(info) => info,
+            None => ProxyInfo {
+                source: stream.local_addr()?,
+                destination: stream.peer_addr()?,
+            },
+        };
+        stream
+            .write_all(&encode_proxy_header(self.version, Some(&info)))
+            .await?;
+        let fd = stream.as_raw_fd();
+        Ok((stream, fd))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::net::SocketAddr;
+
+    use futures_lite::io::Cursor;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+    use futures_lite::StreamExt;
+
+    use crate::net::DefaultTcpDomainAcceptor;
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::TcpDomainAcceptor;
+    use crate::net::TcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::test_async;
+
+    use super::encode_proxy_header;
+    use super::read_proxy_header;
+    use super::ProxyInfo;
+    use super::ProxyProtocolAcceptor;
+    use super::ProxyProtocolConnector;
+    use super::ProxyVersion;
+
+    #[test_async]
+    async fn test_proxy_header() -> Result<(), IoError> {
+        let v4 = ProxyInfo {
+            source: "192.168.0.1:56324".parse().unwrap(),
+            destination: "10.0.0.1:443".parse().unwrap(),
+        };
+        let v6 = ProxyInfo {
+            source: "[2001:db8::1]:56324".parse().unwrap(),
+            destination: "[2001:db8::2]:443".parse().unwrap(),
+        };
+        assert_eq!(
+            encode_proxy_header(ProxyVersion::V1, Some(&v4)),
+            b"PROXY TCP4 192.168.0.1 10.0.0.1 56324 443\r\n"
+        );
+
+        for version in [ProxyVersion::V1, ProxyVersion::V2] {
+            for info in [Some(v4), Some(v6), None] {
+                let mut bytes = encode_proxy_header(version, info.as_ref());
+                bytes.extend_from_slice(b"payload");
+                let mut stream = Cursor::new(bytes);
+                assert_eq!(read_proxy_header(&mut stream).await?, info);
+                let mut rest = vec![];
+                stream.read_to_end(&mut rest).await?;
+                assert_eq!(rest, b"payload");
+            }
+        }
+
+        let err = read_proxy_header(&mut Cursor::new(b"GET / HTTP/1.1\r\n".to_vec()))
+            .await
+            .expect_err("no header");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        let err = read_proxy_header(&mut Cursor::new(b"PROXY TCP4 1.1.1.1\r\n".to_vec()))
+            .await
+            .expect_err("bad header");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_proxy_protocol_connection() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8897".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let info = ProxyInfo {
+            source: "203.0.113.7:40000".parse().unwrap(),
+            destination: addr,
+        };
+
+        let server_ft = async {
+            let stream = listener.incoming().next().await.expect("stream")?;
+            let acceptor = ProxyProtocolAcceptor::new(DefaultTcpDomainAcceptor::new());
+            let mut stream = acceptor.accept(stream).await?;
+            assert_eq!(stream.source_addr(), Some(info.source));
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(()) as Result<(), IoError>
+        };
+
+        let client_ft = async {
+            let connector = ProxyProtocolConnector::new(ProxyVersion::V2).with_info(info);
+            let (mut stream, _) = connector.connect("127.0.0.1:8897").await?;
+            stream.write_all(b"ping").await?;
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (server_result, client_result) = futures_lite::future::zip(server_ft, client_ft).await;
+        client_result?;
+        server_result
+    }
+
+    #[test_async]
+    async fn test_tcp_connector_proxy() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8900".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+
+        let server_ft = async {
+            let mut stream = listener.incoming().next().await.expect("stream")?;
+            let info = read_proxy_header(&mut stream).await?.expect("info");
+            assert_eq!(info.source, stream.peer_addr()?);
+            assert_eq!(info.destination, addr);
+            Ok(()) as Result<(), IoError>
+        };
+
+        let client_ft = async {
+            let connector = DefaultTcpDomainConnector::new().proxy_protocol(ProxyVersion::V1);
+            connector.connect("127.0.0.1:8900").await?;
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (server_result, client_result) = futures_lite::future::zip(server_ft, client_ft).await;
+        client_result?;
+        server_result
+    }
+}
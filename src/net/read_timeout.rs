@@ -0,0 +1,164 @@
+//! per read timeout, independent from writes, so client can bound wait for each response
+use std::future::Future;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use crate::timer::{sleep, Sleeper};
+
+/// stream whose reads fail with `ErrorKind::TimedOut` once read has waited for `timeout`
+/// without receiving anything. stream stays usable, next read waits again for full timeout.
+/// writes are not bounded
+pub fn with_read_timeout<S>(stream: S, timeout: Duration) -> ReadTimeout<S> {
+    ReadTimeout {
+        inner: stream,
+        timeout,
+        timer: None,
+    }
+}
+
+pub struct ReadTimeout<S> {
+    inner: S,
+    timeout: Duration,
+    /// armed while read is pending
+    timer: Option<Sleeper>,
+}
+
+impl<S> ReadTimeout<S> {
+    pub fn read_timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// applies from next read which isn't already waiting
+    pub fn set_read_timeout(&mut self, timeout: Duration) {
+        self.timeout = timeout;
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> S {
+        self.inner
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for ReadTimeout<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = self.get_mut();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(result) => {
+                this.timer = None;
+                Poll::Ready(result)
+            }
+            Poll::Pending => {
+                let timeout = this.timeout;
+                let timer = this.timer.get_or_insert_with(|| sleep(timeout));
+                if Pin::new(timer).poll(cx).is_ready() {
+                    debug!("nothing read within {:?}", timeout);
+                    this.timer = None;
+                    return Poll::Ready(Err(IoError::new(
+                        ErrorKind::TimedOut,
+                        format!("nothing read within {:?}", timeout),
+                    )));
+                }
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for ReadTimeout<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.get_mut().inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use futures_lite::future::{yield_now, zip};
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::duplex;
+    use crate::test_async;
+    use crate::timer::{advance, pause, resume};
+
+    use super::with_read_timeout;
+
+    #[test_async]
+    async fn test_read_timeout() -> Result<(), IoError> {
+        let (client, mut server) = duplex(64);
+        let mut client = with_read_timeout(client, Duration::from_millis(50));
+
+        // writes are not affected
+        client.write_all(b"request").await?;
+        let mut buf = [0; 7];
+        server.read_exact(&mut buf).await?;
+
+        let start = Instant::now();
+        let err = client.read(&mut buf).await.expect_err("timed out");
+        assert_eq!(err.kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // stream is still usable after timeout
+        server.write_all(b"reply").await?;
+        let n = client.read(&mut buf).await?;
+        assert_eq!(&buf[..n], b"reply");
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_read_timeout_mock_clock() -> Result<(), IoError> {
+        let (client, _server) = duplex(64);
+        let mut client = with_read_timeout(client, Duration::from_secs(60));
+        let start = Instant::now();
+        pause();
+
+        let mut buf = [0; 8];
+        let read_ft = client.read(&mut buf);
+        let clock = async {
+            yield_now().await;
+            advance(Duration::from_secs(30));
+            yield_now().await;
+            advance(Duration::from_secs(30));
+        };
+        let (result, _) = zip(read_ft, clock).await;
+        resume();
+
+        assert_eq!(result.expect_err("timed out").kind(), ErrorKind::TimedOut);
+        assert!(start.elapsed() < Duration::from_secs(1));
+        Ok(())
+    }
+}
@@ -0,0 +1,93 @@
+//! readiness of streams, for callers managing their own buffers who want to read
+//! only when socket has data instead of issuing speculative reads
+use std::io::Error as IoError;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use async_io::Async;
+use futures_lite::future::poll_fn;
+
+use super::TcpStream;
+
+/// readiness is hint, read or write after it can still return `Pending`
+/// and caller must then wait for readiness again
+pub trait PollReady {
+    /// ready when read is likely to make progress
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>>;
+
+    /// ready when write is likely to make progress
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>>;
+}
+
+/// wait until `stream` is ready for read
+pub async fn read_ready<S: PollReady>(stream: &S) -> Result<(), IoError> {
+    poll_fn(|cx| stream.poll_read_ready(cx)).await
+}
+
+/// wait until `stream` is ready for write
+pub async fn write_ready<S: PollReady>(stream: &S) -> Result<(), IoError> {
+    poll_fn(|cx| stream.poll_write_ready(cx)).await
+}
+
+// stream only hands out its socket by conversion, clone is just reference count
+impl PollReady for TcpStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let socket: Arc<Async<std::net::TcpStream>> = self.clone().into();
+        socket.poll_readable(cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let socket: Arc<Async<std::net::TcpStream>> = self.clone().into();
+        socket.poll_writable(cx)
+    }
+}
+
+#[cfg(unix)]
+impl PollReady for super::unix::UnixStream {
+    fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let socket: Arc<Async<std::os::unix::net::UnixStream>> = self.clone().into();
+        socket.poll_readable(cx)
+    }
+
+    fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let socket: Arc<Async<std::os::unix::net::UnixStream>> = self.clone().into();
+        socket.poll_writable(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::net::SocketAddr;
+
+    use futures_lite::future::poll_once;
+    use futures_lite::stream::StreamExt;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::TcpListener;
+    use crate::net::TcpStream;
+    use crate::test_async;
+
+    use super::read_ready;
+    use super::write_ready;
+
+    #[test_async]
+    async fn test_read_ready() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8903".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let mut client = TcpStream::connect(&addr).await?;
+        let mut server = listener.incoming().next().await.expect("stream")?;
+
+        write_ready(&client).await?;
+        assert!(poll_once(read_ready(&server)).await.is_none());
+
+        client.write_all(b"ping").await?;
+        read_ready(&server).await?;
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! connectors keyed by uri scheme, so `tcp://host:9003` or `unix:///tmp/socket`
+//! is dispatched to connector registered for its scheme
+use std::collections::HashMap;
+use std::os::unix::io::RawFd;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures_lite::{AsyncRead, AsyncWrite};
+
+use super::ConnectorError;
+use super::DefaultTcpDomainConnector;
+use super::TcpDomainConnector;
+use super::UnixDomainConnector;
+
+/// stream of any connector in registry
+pub trait AsyncStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> AsyncStream for S {}
+
+pub type BoxedStream = Box<dyn AsyncStream>;
+
+/// connector with its stream type erased, implemented for every `TcpDomainConnector`
+#[async_trait]
+pub trait BoxedConnector: Send + Sync {
+    async fn connect_boxed(&self, addr: &str) -> Result<(BoxedStream, RawFd), ConnectorError>;
+}
+
+#[async_trait]
+impl<C> BoxedConnector for C
+where
+    C: TcpDomainConnector + Send + Sync,
+    C::WrapperStream: 'static,
+{
+    async fn connect_boxed(&self, addr: &str) -> Result<(BoxedStream, RawFd), ConnectorError> {
+        let (stream, fd) = self.connect(addr).await?;
+        Ok((Box::new(stream), fd))
+    }
+}
+
+/// split `scheme://address` into lowercase scheme and address
+pub fn parse_target(target: &str) -> Result<(String, &str), ConnectorError> {
+    match target.split_once("://") {
+        Some((scheme, addr)) if !scheme.is_empty() && !addr.is_empty() => {
+            Ok((scheme.to_ascii_lowercase(), addr))
+        }
+        _ => Err(ConnectorError::InvalidTarget(target.to_owned())),
+    }
+}
+
+#[derive(Clone, Default)]
+pub struct ConnectorRegistry {
+    connectors: HashMap<String, Arc<dyn BoxedConnector>>,
+}
+
+impl ConnectorRegistry {
+    /// registry without any scheme
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// registry with `tcp` and `unix` schemes, tls and others are added with `register`
+    pub fn with_defaults() -> Self {
+        Self::new()
+            .register("tcp", DefaultTcpDomainConnector::new())
+            .register("unix", UnixDomainConnector::new())
+    }
+
+    /// use `connector` for `scheme`, replacing connector registered before
+    pub fn register<C>(mut self, scheme: &str, connector: C) -> Self
+    where
+        C: BoxedConnector + 'static,
+    {
+        self.connectors
+            .insert(scheme.to_ascii_lowercase(), Arc::new(connector));
+        self
+    }
+
+    pub fn contains(&self, scheme: &str) -> bool {
+        self.connectors.contains_key(&scheme.to_ascii_lowercase())
+    }
+
+    /// connect to `target` in `scheme://address` form, address is passed to connector as is
+    pub async fn connect(&self, target: &str) -> Result<(BoxedStream, RawFd), ConnectorError> {
+        let (scheme, addr) = parse_target(target)?;
+        let connector = self
+            .connectors
+            .get(&scheme)
+            .ok_or(ConnectorError::UnknownScheme(scheme))?;
+        connector.connect_boxed(addr).await
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::os::unix::io::RawFd;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use async_trait::async_trait;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::duplex;
+    use crate::net::ConnectorError;
+    use crate::net::DuplexStream;
+    use crate::net::TcpDomainConnector;
+    use crate::test_async;
+
+    use super::parse_target;
+    use super::ConnectorRegistry;
+
+    /// connector handing out in memory streams, keeping peer ends
+    #[derive(Clone, Default)]
+    struct MemoryConnector(Arc<Mutex<Vec<(String, DuplexStream)>>>);
+
+    #[async_trait]
+    impl TcpDomainConnector for MemoryConnector {
+        type WrapperStream = DuplexStream;
+
+        async fn connect(
+            &self,
+            addr: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            let (local, peer) = duplex(64);
+            self.0.lock().unwrap().push((addr.to_owned(), peer));
+            Ok((local, 0))
+        }
+    }
+
+    #[test_async]
+    async fn test_connector_registry() -> Result<(), IoError> {
+        assert_eq!(
+            parse_target("TLS://broker:9005").ok(),
+            Some(("tls".to_owned(), "broker:9005"))
+        );
+        assert!(parse_target("broker:9005").is_err());
+
+        let memory = MemoryConnector::default();
+        let registry = ConnectorRegistry::with_defaults().register("mem", memory.clone());
+        assert!(registry.contains("tcp") && registry.contains("unix"));
+
+        let (mut stream, _) = registry.connect("mem://broker:9005").await?;
+        stream.write_all(b"ping").await?;
+        let (addr, mut peer) = memory.0.lock().unwrap().pop().expect("connected");
+        assert_eq!(addr, "broker:9005");
+        let mut buf = [0; 4];
+        peer.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+
+        let err = registry
+            .connect("ws://broker:9005")
+            .await
+            .err()
+            .expect("ws");
+        assert!(matches!(err, ConnectorError::UnknownScheme(scheme) if scheme == "ws"));
+        Ok(())
+    }
+}
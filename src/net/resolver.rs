@@ -0,0 +1,318 @@
+//! resolution of connect targets to socket addresses.
+//!
+//! `connect_tcp` resolves thru process wide resolver, which is `SystemResolver` unless replaced
+//! with `set_resolver`. `CachingResolver` caches results of another resolver
+use std::collections::HashMap;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::time::Duration;
+use std::time::Instant;
+
+use async_trait::async_trait;
+use log::trace;
+
+/// addresses of target, ttl is none if resolver doesn't know it
+#[derive(Debug, Clone)]
+pub struct Resolved {
+    pub addrs: Vec<SocketAddr>,
+    pub ttl: Option<Duration>,
+}
+
+#[async_trait]
+pub trait Resolver: Send + Sync {
+    /// resolve target in `host:port` form
+    async fn resolve(&self, target: &str) -> Result<Resolved, IoError>;
+}
+
+pub type SharedResolver = Arc<dyn Resolver>;
+
+/// resolve using system resolver, which doesn't report ttl
+#[derive(Debug, Clone, Default)]
+pub struct SystemResolver {}
+
+impl SystemResolver {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl Resolver for SystemResolver {
+    async fn resolve(&self, target: &str) -> Result<Resolved, IoError> {
+        let addrs = super::resolve(target).await?;
+        Ok(Resolved { addrs, ttl: None })
+    }
+}
+
+static RESOLVER: RwLock<Option<SharedResolver>> = RwLock::new(None);
+
+/// replace resolver used by `connect_tcp`
+pub fn set_resolver(resolver: SharedResolver) {
+    *RESOLVER.write().unwrap() = Some(resolver);
+}
+
+/// resolver used by `connect_tcp`
+pub fn resolver() -> SharedResolver {
+    match RESOLVER.read().unwrap().as_ref() {
+        Some(resolver) => resolver.clone(),
+        None => Arc::new(SystemResolver::new()),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// ttl for results without ttl
+    pub default_ttl: Duration,
+    pub min_ttl: Duration,
+    pub max_ttl: Duration,
+    /// how long failed resolution is cached
+    pub negative_ttl: Duration,
+    pub max_entries: usize,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl: Duration::from_secs(30),
+            min_ttl: Duration::from_secs(1),
+            max_ttl: Duration::from_secs(300),
+            negative_ttl: Duration::from_secs(5),
+            max_entries: 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CacheStats {
+    pub hits: u64,
+    pub negative_hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+    pub entries: usize,
+}
+
+enum Cached {
+    Found(Vec<SocketAddr>),
+    Failed(ErrorKind, String),
+}
+
+struct Entry {
+    cached: Cached,
+    expires: Instant,
+}
+
+/// cache results of inner resolver, honoring their ttl
+pub struct CachingResolver<R> {
+    inner: R,
+    config: CacheConfig,
+    entries: Mutex<HashMap<String, Entry>>,
+    hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+}
+
+impl<R> CachingResolver<R> {
+    pub fn new(inner: R, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            config,
+            entries: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            negative_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+        }
+    }
+
+    pub fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            entries: self.entries.lock().unwrap().len(),
+        }
+    }
+
+    /// drop all cached entries
+    pub fn clear(&self) {
+        self.entries.lock().unwrap().clear();
+    }
+
+    /// cached result, if it has not expired
+    fn lookup(&self, target: &str) -> Option<Result<Resolved, IoError>> {
+        let now = Instant::now();
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(target).filter(|entry| entry.expires > now)?;
+        match &entry.cached {
+            Cached::Found(addrs) => {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                Some(Ok(Resolved {
+                    addrs: addrs.clone(),
+                    ttl: Some(entry.expires - now),
+                }))
+            }
+            Cached::Failed(kind, message) => {
+                self.negative_hits.fetch_add(1, Ordering::Relaxed);
+                Some(Err(IoError::new(*kind, message.clone())))
+            }
+        }
+    }
+
+    fn insert(&self, target: &str, cached: Cached, ttl: Duration) {
+        let now = Instant::now();
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.config.max_entries && !entries.contains_key(target) {
+            let before = entries.len();
+            entries.retain(|_, entry| entry.expires > now);
+            if entries.len() == before {
+                // nothing expired, evict entry closest to expiring
+                let oldest = entries
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    entries.remove(&oldest);
+                }
+            }
+            self.evictions
+                .fetch_add((before - entries.len()) as u64, Ordering::Relaxed);
+        }
+        if self.config.max_entries > 0 {
+            entries.insert(
+                target.to_owned(),
+                Entry {
+                    cached,
+                    expires: now + ttl,
+                },
+            );
+        }
+    }
+}
+
+#[async_trait]
+impl<R: Resolver> Resolver for CachingResolver<R> {
+    async fn resolve(&self, target: &str) -> Result<Resolved, IoError> {
+        if let Some(result) = self.lookup(target) {
+            trace!("resolve cache hit: {}", target);
+            return result;
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        match self.inner.resolve(target).await {
+            Ok(mut resolved) => {
+                let ttl = resolved
+                    .ttl
+                    .unwrap_or(self.config.default_ttl)
+                    .max(self.config.min_ttl)
+                    .min(self.config.max_ttl);
+                self.insert(target, Cached::Found(resolved.addrs.clone()), ttl);
+                resolved.ttl = Some(ttl);
+                Ok(resolved)
+            }
+            Err(err) => {
+                self.insert(
+                    target,
+                    Cached::Failed(err.kind(), err.to_string()),
+                    self.config.negative_ttl,
+                );
+                Err(err)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::sync::atomic::AtomicU64;
+    use std::sync::atomic::Ordering;
+    use std::time::Duration;
+
+    use async_trait::async_trait;
+
+    use crate::test_async;
+
+    use super::CacheConfig;
+    use super::CacheStats;
+    use super::CachingResolver;
+    use super::Resolved;
+    use super::Resolver;
+
+    #[derive(Default)]
+    struct CountingResolver(AtomicU64);
+
+    #[async_trait]
+    impl Resolver for CountingResolver {
+        async fn resolve(&self, target: &str) -> Result<Resolved, IoError> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            if target.starts_with("bad") {
+                return Err(IoError::new(ErrorKind::NotFound, "no such host"));
+            }
+            Ok(Resolved {
+                addrs: vec!["127.0.0.1:9092".parse().unwrap()],
+                ttl: Some(Duration::from_secs(3600)),
+            })
+        }
+    }
+
+    #[test_async]
+    async fn test_caching_resolver() -> Result<(), IoError> {
+        let resolver = CachingResolver::new(
+            CountingResolver::default(),
+            CacheConfig {
+                max_entries: 2,
+                ..Default::default()
+            },
+        );
+
+        let resolved = resolver.resolve("good:9092").await?;
+        // ttl is capped by max ttl
+        assert_eq!(resolved.ttl, Some(Duration::from_secs(300)));
+        resolver.resolve("good:9092").await?;
+        for _ in 0..2u16 {
+            let err = resolver.resolve("bad:9092").await.expect_err("failed");
+            assert_eq!(err.kind(), ErrorKind::NotFound);
+        }
+        assert_eq!(resolver.inner.0.load(Ordering::Relaxed), 2);
+
+        // cache is full, entry closest to expiring is evicted
+        resolver.resolve("other:9092").await?;
+        assert_eq!(
+            resolver.stats(),
+            CacheStats {
+                hits: 1,
+                negative_hits: 1,
+                misses: 3,
+                evictions: 1,
+                entries: 2,
+            }
+        );
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_cache_expiry() -> Result<(), IoError> {
+        let resolver = CachingResolver::new(
+            CountingResolver::default(),
+            CacheConfig {
+                min_ttl: Duration::ZERO,
+                max_ttl: Duration::ZERO,
+                ..Default::default()
+            },
+        );
+        resolver.resolve("good:9092").await?;
+        resolver.resolve("good:9092").await?;
+        assert_eq!(resolver.stats().misses, 2);
+        Ok(())
+    }
+}
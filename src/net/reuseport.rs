@@ -0,0 +1,153 @@
+//! listeners sharing port with `SO_REUSEPORT`, kernel balances connections between them
+use std::convert::TryFrom;
+use std::io::Error as IoError;
+use std::net::SocketAddr;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd;
+
+use nix::sys::socket::bind;
+use nix::sys::socket::listen;
+use nix::sys::socket::setsockopt;
+use nix::sys::socket::socket;
+use nix::sys::socket::sockopt;
+use nix::sys::socket::AddressFamily;
+use nix::sys::socket::InetAddr;
+use nix::sys::socket::SockAddr;
+use nix::sys::socket::SockFlag;
+use nix::sys::socket::SockType;
+
+use super::nix_error;
+use super::TcpListener;
+
+const BACKLOG: usize = 1024;
+
+/// bind listener with `SO_REUSEPORT`, so multiple listeners can bind to same address
+pub fn bind_reuse_port(addr: SocketAddr) -> Result<TcpListener, IoError> {
+    let family = match addr {
+        SocketAddr::V4(_) => AddressFamily::Inet,
+        SocketAddr::V6(_) => AddressFamily::Inet6,
+    };
+    let fd = socket(family, SockType::Stream, SockFlag::SOCK_CLOEXEC, None).map_err(nix_error)?;
+    // owned right away so fd is closed on error
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    setsockopt(fd, sockopt::ReuseAddr, &true).map_err(nix_error)?;
+    setsockopt(fd, sockopt::ReusePort, &true).map_err(nix_error)?;
+    bind(fd, &SockAddr::new_inet(InetAddr::from_std(&addr))).map_err(nix_error)?;
+    listen(listener.as_raw_fd(), BACKLOG).map_err(nix_error)?;
+    TcpListener::try_from(listener)
+}
+
+#[cfg(feature = "task")]
+pub use acceptors::*;
+
+#[cfg(feature = "task")]
+mod acceptors {
+    use std::future::Future;
+    use std::io::Error as IoError;
+    use std::net::SocketAddr;
+
+    use futures_lite::StreamExt;
+    use log::debug;
+
+    use super::bind_reuse_port;
+    use crate::net::TcpDomainAcceptor;
+    use crate::task::spawn_task;
+    use crate::task::Task;
+
+    /// spawn `count` accept loops, each with own listener bound to `addr`, such as one per core
+    /// from `std::thread::available_parallelism`. accepted streams are transformed by
+    /// `acceptor` and passed to `handler` in their own task
+    pub fn spawn_acceptors<A, F, Fut>(
+        addr: SocketAddr,
+        count: usize,
+        acceptor: A,
+        handler: F,
+    ) -> Result<Vec<Task<()>>, IoError>
+    where
+        A: TcpDomainAcceptor + Clone + Send + Sync + 'static,
+        A::WrapperStream: 'static,
+        F: Fn(A::WrapperStream) -> Fut + Clone + Send + Sync + 'static,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        // bind all before spawning, so bind failure is reported to caller
+        let listeners = (0..count)
+            .map(|_| bind_reuse_port(addr))
+            .collect::<Result<Vec<_>, IoError>>()?;
+
+        Ok(listeners
+            .into_iter()
+            .enumerate()
+            .map(|(id, listener)| {
+                let acceptor = acceptor.clone();
+                let handler = handler.clone();
+                spawn_task(async move {
+                    debug!("acceptor: {} listening on: {}", id, addr);
+                    let mut incoming = listener.incoming();
+                    while let Some(stream) = incoming.next().await {
+                        let stream = match stream {
+                            Ok(stream) => stream,
+                            Err(err) => {
+                                debug!("acceptor: {} accept failed: {}", id, err);
+                                continue;
+                            }
+                        };
+                        let acceptor = acceptor.clone();
+                        let handler = handler.clone();
+                        spawn_task(async move {
+                            match acceptor.accept(stream).await {
+                                Ok(stream) => handler(stream).await,
+                                Err(err) => debug!("acceptor: {} failed: {}", id, err),
+                            }
+                        });
+                    }
+                })
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::net::SocketAddr;
+
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::DefaultTcpDomainAcceptor;
+    use crate::net::TcpStream;
+    use crate::test_async;
+
+    use super::bind_reuse_port;
+    use super::spawn_acceptors;
+
+    #[test_async]
+    async fn test_reuse_port() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8896".parse::<SocketAddr>().expect("parse");
+        // listeners can share port
+        drop((bind_reuse_port(addr)?, bind_reuse_port(addr)?));
+
+        let handles = spawn_acceptors(
+            addr,
+            2,
+            DefaultTcpDomainAcceptor::new(),
+            |mut stream: TcpStream| async move {
+                let _ = stream.write_all(b"hello").await;
+            },
+        )?;
+        assert_eq!(handles.len(), 2);
+
+        for _ in 0..4u16 {
+            let mut stream = TcpStream::connect(&addr).await?;
+            let mut buf = vec![];
+            stream.read_to_end(&mut buf).await?;
+            assert_eq!(buf, b"hello");
+        }
+
+        for handle in handles {
+            handle.cancel().await;
+        }
+        Ok(())
+    }
+}
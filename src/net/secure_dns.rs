@@ -0,0 +1,486 @@
+//! private resolution of host names over dns-over-tls (rfc 7858) and dns-over-https (rfc 8484).
+//!
+//! queries are sent thru connector of this crate, usually tls connector verifying certificate
+//! of nameserver. nameserver should be ip address, so connecting to it needs no resolution
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_lite::future::FutureExt;
+use futures_lite::{AsyncReadExt, AsyncWriteExt};
+use log::debug;
+
+use super::srv::dns;
+use super::Resolved;
+use super::Resolver;
+use super::TcpDomainConnector;
+use crate::timer::sleep;
+
+const QUERY_TIMEOUT: Duration = Duration::from_secs(5);
+const DEFAULT_DOH_PATH: &str = "/dns-query";
+/// largest response accepted from nameserver
+const MAX_RESPONSE: usize = 64 * 1024;
+
+fn invalid(message: &str) -> IoError {
+    IoError::new(ErrorKind::InvalidData, message.to_owned())
+}
+
+/// host and port of `host:port` target, brackets of ipv6 host are removed
+fn split_target(target: &str) -> Result<(&str, u16), IoError> {
+    let (host, port) = target
+        .rsplit_once(':')
+        .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, format!("no port in {}", target)))?;
+    let port = port.parse().map_err(|_| {
+        IoError::new(
+            ErrorKind::InvalidInput,
+            format!("invalid port in {}", target),
+        )
+    })?;
+    Ok((host.trim_start_matches('[').trim_end_matches(']'), port))
+}
+
+/// a and aaaa query for host
+fn address_queries(id: u16, host: &str) -> Result<Vec<Vec<u8>>, IoError> {
+    Ok(vec![
+        dns::query(id, host, dns::TYPE_A)?,
+        dns::query(id, host, dns::TYPE_AAAA)?,
+    ])
+}
+
+/// addresses from responses to `address_queries`
+fn collect_addresses(
+    host: &str,
+    port: u16,
+    id: u16,
+    responses: Vec<Vec<u8>>,
+) -> Result<Resolved, IoError> {
+    let mut addrs = vec![];
+    let mut ttl: Option<Duration> = None;
+    for response in responses {
+        if response.len() < 2 || u16::from_be_bytes([response[0], response[1]]) != id {
+            return Err(invalid("response to other query"));
+        }
+        match dns::parse_address_response(&response) {
+            Ok((ips, record_ttl)) => {
+                addrs.extend(ips.into_iter().map(|ip| SocketAddr::new(ip, port)));
+                if let Some(record_ttl) = record_ttl {
+                    ttl = Some(ttl.map_or(record_ttl, |ttl| ttl.min(record_ttl)));
+                }
+            }
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => return Err(err),
+        }
+    }
+    if addrs.is_empty() {
+        return Err(IoError::new(
+            ErrorKind::NotFound,
+            format!("no address of {}", host),
+        ));
+    }
+    Ok(Resolved { addrs, ttl })
+}
+
+async fn with_timeout<T, F>(timeout: Duration, future: F) -> Result<T, IoError>
+where
+    F: std::future::Future<Output = Result<T, IoError>>,
+{
+    let timed_out = async {
+        sleep(timeout).await;
+        Err(IoError::new(ErrorKind::TimedOut, "dns query timed out"))
+    };
+    future.or(timed_out).await
+}
+
+/// resolve over dns-over-tls, queries are framed by length on one connection per resolution
+pub struct DotResolver<C> {
+    connector: C,
+    nameserver: String,
+    timeout: Duration,
+}
+
+impl<C> DotResolver<C> {
+    /// `nameserver` is connect target of connector, such as `9.9.9.9:853`
+    pub fn new(connector: C, nameserver: impl Into<String>) -> Self {
+        Self {
+            connector,
+            nameserver: nameserver.into(),
+            timeout: QUERY_TIMEOUT,
+        }
+    }
+
+    /// timeout of exchange with nameserver, including connect
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<C: TcpDomainConnector> DotResolver<C> {
+    async fn exchange(&self, queries: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, IoError> {
+        let (mut stream, _) = self.connector.connect(&self.nameserver).await?;
+        let mut responses = vec![];
+        for query in queries {
+            let mut request = (query.len() as u16).to_be_bytes().to_vec();
+            request.extend_from_slice(&query);
+            stream.write_all(&request).await?;
+            stream.flush().await?;
+
+            let mut len = [0; 2];
+            stream.read_exact(&mut len).await?;
+            let mut response = vec![0; u16::from_be_bytes(len) as usize];
+            stream.read_exact(&mut response).await?;
+            responses.push(response);
+        }
+        Ok(responses)
+    }
+}
+
+#[async_trait]
+impl<C> Resolver for DotResolver<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    async fn resolve(&self, target: &str) -> Result<Resolved, IoError> {
+        if let Ok(addr) = target.parse::<SocketAddr>() {
+            return Ok(Resolved {
+                addrs: vec![addr],
+                ttl: None,
+            });
+        }
+        let (host, port) = split_target(target)?;
+        let id = fastrand::u16(..);
+        let queries = address_queries(id, host)?;
+        debug!("resolving: {} over tls: {}", host, self.nameserver);
+        let responses = with_timeout(self.timeout, self.exchange(queries)).await?;
+        collect_addresses(host, port, id, responses)
+    }
+}
+
+/// resolve over dns-over-https, each query is posted on its own http/1.1 connection
+pub struct DohResolver<C> {
+    connector: C,
+    nameserver: String,
+    host: String,
+    path: String,
+    timeout: Duration,
+}
+
+impl<C> DohResolver<C> {
+    /// `nameserver` is connect target of connector, such as `9.9.9.9:443`.
+    /// host header is host of nameserver unless set by `host`
+    pub fn new(connector: C, nameserver: impl Into<String>) -> Self {
+        let nameserver = nameserver.into();
+        let host = split_target(&nameserver)
+            .map(|(host, _)| host.to_owned())
+            .unwrap_or_else(|_| nameserver.clone());
+        Self {
+            connector,
+            nameserver,
+            host,
+            path: DEFAULT_DOH_PATH.to_owned(),
+            timeout: QUERY_TIMEOUT,
+        }
+    }
+
+    /// host header, such as `dns.quad9.net`
+    pub fn host(mut self, host: impl Into<String>) -> Self {
+        self.host = host.into();
+        self
+    }
+
+    /// path of query endpoint, default is `/dns-query`
+    pub fn path(mut self, path: impl Into<String>) -> Self {
+        self.path = path.into();
+        self
+    }
+
+    /// timeout of all exchanges with nameserver, including connects
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+impl<C: TcpDomainConnector> DohResolver<C> {
+    async fn post(&self, query: &[u8]) -> Result<Vec<u8>, IoError> {
+        let (mut stream, _) = self.connector.connect(&self.nameserver).await?;
+        let mut request = format!(
+            "POST {} HTTP/1.1\r\nHost: {}\r\nAccept: application/dns-message\r\n\
+             Content-Type: application/dns-message\r\nContent-Length: {}\r\n\
+             Connection: close\r\n\r\n",
+            self.path,
+            self.host,
+            query.len()
+        )
+        .into_bytes();
+        request.extend_from_slice(query);
+        stream.write_all(&request).await?;
+        stream.flush().await?;
+
+        let mut response = vec![];
+        (&mut stream)
+            .take(MAX_RESPONSE as u64 + 1)
+            .read_to_end(&mut response)
+            .await?;
+        if response.len() > MAX_RESPONSE {
+            return Err(invalid("http response too large"));
+        }
+        parse_http_response(&response)
+    }
+
+    async fn exchange(&self, queries: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>, IoError> {
+        let mut responses = vec![];
+        for query in queries {
+            responses.push(self.post(&query).await?);
+        }
+        Ok(responses)
+    }
+}
+
+#[async_trait]
+impl<C> Resolver for DohResolver<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    async fn resolve(&self, target: &str) -> Result<Resolved, IoError> {
+        if let Ok(addr) = target.parse::<SocketAddr>() {
+            return Ok(Resolved {
+                addrs: vec![addr],
+                ttl: None,
+            });
+        }
+        let (host, port) = split_target(target)?;
+        // id is zero so responses can be cached by http caches
+        let queries = address_queries(0, host)?;
+        debug!("resolving: {} over https: {}", host, self.nameserver);
+        let responses = with_timeout(self.timeout, self.exchange(queries)).await?;
+        collect_addresses(host, port, 0, responses)
+    }
+}
+
+/// body of successful http/1.1 response, with content length or chunked encoding
+fn parse_http_response(response: &[u8]) -> Result<Vec<u8>, IoError> {
+    let header_end = response
+        .windows(4)
+        .position(|window| window == b"\r\n\r\n")
+        .ok_or_else(|| invalid("incomplete http response"))?;
+    let head =
+        std::str::from_utf8(&response[..header_end]).map_err(|_| invalid("invalid http header"))?;
+    let body = &response[header_end + 4..];
+
+    let mut lines = head.split("\r\n");
+    let status = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .ok_or_else(|| invalid("invalid http status line"))?;
+    if status != "200" {
+        return Err(IoError::other(format!(
+            "nameserver replied with http status {}",
+            status
+        )));
+    }
+
+    let mut content_length = None;
+    let mut chunked = false;
+    for line in lines {
+        if let Some((name, value)) = line.split_once(':') {
+            let value = value.trim();
+            if name.eq_ignore_ascii_case("content-length") {
+                content_length = Some(
+                    value
+                        .parse::<usize>()
+                        .map_err(|_| invalid("invalid content length"))?,
+                );
+            } else if name.eq_ignore_ascii_case("transfer-encoding") {
+                chunked = value.eq_ignore_ascii_case("chunked");
+            }
+        }
+    }
+
+    if chunked {
+        return decode_chunked(body);
+    }
+    match content_length {
+        Some(len) if len > body.len() => Err(invalid("truncated http body")),
+        Some(len) => Ok(body[..len].to_vec()),
+        None => Ok(body.to_vec()),
+    }
+}
+
+fn decode_chunked(mut body: &[u8]) -> Result<Vec<u8>, IoError> {
+    let mut decoded = vec![];
+    loop {
+        let line_end = body
+            .windows(2)
+            .position(|window| window == b"\r\n")
+            .ok_or_else(|| invalid("truncated http chunk"))?;
+        let size = std::str::from_utf8(&body[..line_end])
+            .ok()
+            .and_then(|line| line.split(';').next())
+            .and_then(|size| usize::from_str_radix(size.trim(), 16).ok())
+            .ok_or_else(|| invalid("invalid http chunk size"))?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Ok(decoded);
+        }
+        // size is sent by nameserver, so it can't be trusted not to overflow
+        if size > body.len().saturating_sub(2) {
+            return Err(invalid("truncated http chunk"));
+        }
+        decoded.extend_from_slice(&body[..size]);
+        body = &body[size + 2..];
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::net::SocketAddr;
+
+    use futures_lite::future::zip;
+    use futures_lite::stream::StreamExt;
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::Resolver;
+    use crate::net::TcpListener;
+    use crate::test_async;
+
+    use super::decode_chunked;
+    use super::DohResolver;
+    use super::DotResolver;
+
+    /// answer to query with one a record of 10.0.0.1, or one aaaa record of ::1
+    fn answer(query: &[u8]) -> Vec<u8> {
+        let mut response = query.to_vec();
+        response[2] |= 0x80;
+        response[6..8].copy_from_slice(&1u16.to_be_bytes());
+        let record_type = u16::from_be_bytes([query[query.len() - 4], query[query.len() - 3]]);
+        // name is pointer to question
+        response.extend_from_slice(&[0xC0, 12]);
+        response.extend_from_slice(&record_type.to_be_bytes());
+        response.extend_from_slice(&1u16.to_be_bytes());
+        response.extend_from_slice(&(if record_type == 1 { 60u32 } else { 30 }).to_be_bytes());
+        if record_type == 1 {
+            response.extend_from_slice(&4u16.to_be_bytes());
+            response.extend_from_slice(&[10, 0, 0, 1]);
+        } else {
+            response.extend_from_slice(&16u16.to_be_bytes());
+            let mut ip = [0; 16];
+            ip[15] = 1;
+            response.extend_from_slice(&ip);
+        }
+        response
+    }
+
+    #[test_async]
+    async fn test_secure_dns() -> Result<(), IoError> {
+        let expected: Vec<SocketAddr> = vec![
+            "10.0.0.1:9092".parse().expect("addr"),
+            "[::1]:9092".parse().expect("addr"),
+        ];
+
+        let addr = "127.0.0.1:8909";
+        let listener = TcpListener::bind(addr).await?;
+        let server = async {
+            let mut stream = listener.incoming().next().await.expect("stream")?;
+            for _ in 0..2 {
+                let mut len = [0; 2];
+                stream.read_exact(&mut len).await?;
+                let mut query = vec![0; u16::from_be_bytes(len) as usize];
+                stream.read_exact(&mut query).await?;
+                let response = answer(&query);
+                stream
+                    .write_all(&(response.len() as u16).to_be_bytes())
+                    .await?;
+                stream.write_all(&response).await?;
+            }
+            Ok(()) as Result<(), IoError>
+        };
+        let resolver = DotResolver::new(DefaultTcpDomainConnector::new(), addr);
+        let (server_result, resolved) = zip(server, resolver.resolve("broker.local:9092")).await;
+        server_result?;
+        let resolved = resolved?;
+        assert_eq!(resolved.addrs, expected);
+        assert_eq!(resolved.ttl, Some(std::time::Duration::from_secs(30)));
+
+        let addr = "127.0.0.1:8910";
+        let listener = TcpListener::bind(addr).await?;
+        let server = async {
+            for chunked in [false, true].iter() {
+                let mut stream = listener.incoming().next().await.expect("stream")?;
+                let mut request = vec![];
+                while !request.ends_with(b"\r\n\r\n") {
+                    let mut byte = [0; 1];
+                    stream.read_exact(&mut byte).await?;
+                    request.push(byte[0]);
+                }
+                let head = String::from_utf8_lossy(&request).to_string();
+                assert!(head.starts_with("POST /dns-query HTTP/1.1\r\n"));
+                let len: usize = head
+                    .lines()
+                    .find_map(|line| line.strip_prefix("Content-Length: "))
+                    .expect("length")
+                    .parse()
+                    .expect("number");
+                let mut query = vec![0; len];
+                stream.read_exact(&mut query).await?;
+                let response = answer(&query);
+                let reply = if *chunked {
+                    let mut reply = format!(
+                        "HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n{:x}\r\n",
+                        response.len()
+                    )
+                    .into_bytes();
+                    reply.extend_from_slice(&response);
+                    reply.extend_from_slice(b"\r\n0\r\n\r\n");
+                    reply
+                } else {
+                    let mut reply = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n",
+                        response.len()
+                    )
+                    .into_bytes();
+                    reply.extend_from_slice(&response);
+                    reply
+                };
+                stream.write_all(&reply).await?;
+            }
+            Ok(()) as Result<(), IoError>
+        };
+        let resolver = DohResolver::new(DefaultTcpDomainConnector::new(), addr);
+        let (server_result, resolved) = zip(server, resolver.resolve("broker.local:9092")).await;
+        server_result?;
+        assert_eq!(resolved?.addrs, expected);
+
+        // literal address is not sent to nameserver
+        let resolved = resolver.resolve("127.0.0.1:9092").await?;
+        assert_eq!(
+            resolved.addrs,
+            vec!["127.0.0.1:9092".parse().expect("addr")]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_chunked() {
+        assert_eq!(
+            decode_chunked(b"3\r\nabc\r\n2;ext=1\r\nde\r\n0\r\n\r\n").expect("decode"),
+            b"abcde"
+        );
+        for body in [
+            &b"ffffffffffffffff\r\nabc\r\n0\r\n\r\n"[..],
+            b"fffffffffffffffe\r\n",
+            b"4\r\nabc\r\n0\r\n\r\n",
+            b"3\r\nabc",
+            b"zz\r\nabc\r\n",
+        ] {
+            let err = decode_chunked(body).expect_err("corrupt chunk");
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
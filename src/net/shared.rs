@@ -0,0 +1,220 @@
+//! stream shared by many writers, each frame is written whole in order writers asked for it
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::{Poll, Waker};
+
+use futures_lite::future::poll_fn;
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use super::split;
+use super::OwnedReadHalf;
+use super::OwnedWriteHalf;
+
+/// writers take tickets and write when their ticket is served, so frames are written first come first
+#[derive(Default)]
+struct Turns {
+    serving: u64,
+    next: u64,
+    /// tickets of writers which gave up before their turn
+    cancelled: BTreeSet<u64>,
+    wakers: HashMap<u64, Waker>,
+}
+
+impl Turns {
+    fn advance(&mut self) {
+        self.serving += 1;
+        while self.cancelled.remove(&self.serving) {
+            self.serving += 1;
+        }
+        if let Some(waker) = self.wakers.remove(&self.serving) {
+            waker.wake();
+        }
+    }
+}
+
+struct Shared<S> {
+    writer: Mutex<OwnedWriteHalf<S>>,
+    turns: Mutex<Turns>,
+    /// frame was partially written, stream can't be used for writing anymore
+    poisoned: AtomicBool,
+}
+
+/// turn of writer, next writer is let in when dropped
+struct Turn<'a, S> {
+    shared: &'a Shared<S>,
+    ticket: u64,
+    writing: bool,
+    done: bool,
+}
+
+impl<S> Drop for Turn<'_, S> {
+    fn drop(&mut self) {
+        if self.writing && !self.done {
+            debug!("frame write was cancelled or failed, poisoning stream");
+            self.shared.poisoned.store(true, Ordering::SeqCst);
+        }
+        let mut turns = self.shared.turns.lock().unwrap();
+        turns.wakers.remove(&self.ticket);
+        if turns.serving == self.ticket {
+            turns.advance();
+        } else {
+            turns.cancelled.insert(self.ticket);
+        }
+    }
+}
+
+/// clone-able handle writing whole frames to stream from many tasks.
+/// reads are done thru read half, independent of writes
+pub struct SharedStream<S> {
+    shared: Arc<Shared<S>>,
+}
+
+impl<S> Clone for SharedStream<S> {
+    fn clone(&self) -> Self {
+        Self {
+            shared: self.shared.clone(),
+        }
+    }
+}
+
+impl<S> SharedStream<S>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    pub fn new(stream: S) -> (Self, OwnedReadHalf<S>) {
+        let (read, write) = split(stream);
+        let shared = Shared {
+            writer: Mutex::new(write),
+            turns: Mutex::new(Turns::default()),
+            poisoned: AtomicBool::new(false),
+        };
+        (
+            Self {
+                shared: Arc::new(shared),
+            },
+            read,
+        )
+    }
+
+    /// write and flush frame after frames of earlier callers.
+    /// if this is cancelled while writing, stream is left with partial frame and further writes fail
+    pub async fn write_frame(&self, frame: &[u8]) -> Result<(), IoError> {
+        let mut turn = Turn {
+            shared: &self.shared,
+            ticket: {
+                let mut turns = self.shared.turns.lock().unwrap();
+                turns.next += 1;
+                turns.next - 1
+            },
+            writing: false,
+            done: false,
+        };
+
+        poll_fn(|cx| {
+            let mut turns = self.shared.turns.lock().unwrap();
+            if turns.serving == turn.ticket {
+                Poll::Ready(())
+            } else {
+                turns.wakers.insert(turn.ticket, cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await;
+
+        if self.shared.poisoned.load(Ordering::SeqCst) {
+            return Err(IoError::new(
+                ErrorKind::BrokenPipe,
+                "earlier frame was partially written",
+            ));
+        }
+        turn.writing = true;
+        let mut written = 0;
+        while written < frame.len() {
+            let n = poll_fn(|cx| {
+                let mut writer = self.shared.writer.lock().unwrap();
+                Pin::new(&mut *writer).poll_write(cx, &frame[written..])
+            })
+            .await?;
+            if n == 0 {
+                return Err(ErrorKind::WriteZero.into());
+            }
+            written += n;
+        }
+        poll_fn(|cx| {
+            let mut writer = self.shared.writer.lock().unwrap();
+            Pin::new(&mut *writer).poll_flush(cx)
+        })
+        .await?;
+        turn.done = true;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::duplex;
+    use crate::test_async;
+
+    use super::SharedStream;
+
+    const FRAMES: u8 = 20;
+    const FRAME_LEN: usize = 24;
+
+    #[test_async]
+    async fn test_shared_stream() -> Result<(), IoError> {
+        // buffer smaller than frame, so frames are written in many writes
+        let (local, mut peer) = duplex(16);
+        let (shared, mut reader) = SharedStream::new(local);
+
+        let writer = |id: u8| {
+            let shared = shared.clone();
+            async move {
+                for _ in 0..FRAMES {
+                    shared.write_frame(&[id; FRAME_LEN]).await?;
+                }
+                Ok(()) as Result<(), IoError>
+            }
+        };
+        let peer_ft = async {
+            let mut counts = [0; 2];
+            for _ in 0..FRAMES * 2 {
+                let mut frame = [0; FRAME_LEN];
+                peer.read_exact(&mut frame).await?;
+                assert!(frame.iter().all(|b| *b == frame[0]), "interleaved frame");
+                counts[frame[0] as usize] += 1;
+            }
+            assert_eq!(counts, [FRAMES, FRAMES]);
+            // reads are independent from writers
+            peer.write_all(b"done").await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let read_ft = async {
+            let mut buf = [0; 4];
+            reader.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"done");
+            Ok(()) as Result<(), IoError>
+        };
+
+        let ((first, second), (peer_result, read_result)) =
+            zip(zip(writer(0), writer(1)), zip(peer_ft, read_ft)).await;
+        first?;
+        second?;
+        peer_result?;
+        read_result
+    }
+}
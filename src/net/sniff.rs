@@ -0,0 +1,43 @@
+//! protocol detection from first bytes of accepted connection, without consuming them
+use std::io::Error as IoError;
+
+use super::TcpStream;
+
+/// content type of tls record carrying handshake, first byte sent by tls client
+const TLS_HANDSHAKE: u8 = 0x16;
+/// major version of ssl 3.0 and all tls versions
+const TLS_MAJOR: u8 = 0x03;
+
+/// true if `prefix` can start tls handshake record, such as client hello.
+/// empty prefix is never tls
+pub fn is_tls_handshake(prefix: &[u8]) -> bool {
+    match prefix {
+        [TLS_HANDSHAKE] | [TLS_HANDSHAKE, TLS_MAJOR] => true,
+        [TLS_HANDSHAKE, TLS_MAJOR, minor, ..] => *minor <= 0x04,
+        _ => false,
+    }
+}
+
+/// wait for first bytes from peer and check if it starts tls handshake.
+/// stream is only peeked, so it can be passed to tls or plain handler after
+pub async fn sniff_tls(stream: &TcpStream) -> Result<bool, IoError> {
+    let mut prefix = [0; 3];
+    let len = stream.peek(&mut prefix).await?;
+    Ok(is_tls_handshake(&prefix[..len]))
+}
+
+#[cfg(test)]
+mod test {
+
+    use super::is_tls_handshake;
+
+    #[test]
+    fn test_is_tls_handshake() {
+        assert!(is_tls_handshake(&[0x16, 0x03, 0x01, 0x02, 0x00]));
+        assert!(is_tls_handshake(&[0x16]));
+        assert!(!is_tls_handshake(&[]));
+        assert!(!is_tls_handshake(b"GET / HTTP/1.1"));
+        assert!(!is_tls_handshake(&[0x16, 0x03, 0x09]));
+        assert!(!is_tls_handshake(&[0x00, 0x00, 0x00, 0x10]));
+    }
+}
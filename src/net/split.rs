@@ -0,0 +1,136 @@
+//! owned read and write halves of stream, so reader and writer loops can run in separate tasks
+use std::io::Error as IoError;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use futures_lite::{AsyncRead, AsyncWrite};
+
+/// split stream into halves. stream is locked only while half is polled,
+/// so pending read doesn't block writes.
+///
+/// plain `TcpStream` can also be cloned, with each clone used for one direction
+pub fn split<S>(stream: S) -> (OwnedReadHalf<S>, OwnedWriteHalf<S>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let inner = Arc::new(Mutex::new(stream));
+    (
+        OwnedReadHalf {
+            inner: inner.clone(),
+        },
+        OwnedWriteHalf { inner },
+    )
+}
+
+/// `into_split` for tls, tcp and other streams
+pub trait IntoSplit: AsyncRead + AsyncWrite + Unpin + Sized {
+    fn into_split(self) -> (OwnedReadHalf<Self>, OwnedWriteHalf<Self>) {
+        split(self)
+    }
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin> IntoSplit for S {}
+
+pub struct OwnedReadHalf<S> {
+    inner: Arc<Mutex<S>>,
+}
+
+impl<S> OwnedReadHalf<S> {
+    /// join with write half of same stream, halves are returned if they don't match
+    pub fn reunite(
+        self,
+        write: OwnedWriteHalf<S>,
+    ) -> Result<S, (OwnedReadHalf<S>, OwnedWriteHalf<S>)> {
+        if !Arc::ptr_eq(&self.inner, &write.inner) {
+            return Err((self, write));
+        }
+        drop(write);
+        match Arc::try_unwrap(self.inner) {
+            Ok(stream) => Ok(stream.into_inner().unwrap()),
+            Err(_) => unreachable!("only halves share stream"),
+        }
+    }
+}
+
+pub struct OwnedWriteHalf<S> {
+    inner: Arc<Mutex<S>>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for OwnedReadHalf<S> {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let mut stream = self.inner.lock().unwrap();
+        Pin::new(&mut *stream).poll_read(cx, buf)
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for OwnedWriteHalf<S> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let mut stream = self.inner.lock().unwrap();
+        Pin::new(&mut *stream).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let mut stream = self.inner.lock().unwrap();
+        Pin::new(&mut *stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let mut stream = self.inner.lock().unwrap();
+        Pin::new(&mut *stream).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::duplex;
+    use crate::test_async;
+
+    use super::IntoSplit;
+
+    #[test_async]
+    async fn test_split() -> Result<(), IoError> {
+        let (local, mut peer) = duplex(64);
+        let (mut reader, mut writer) = local.into_split();
+
+        // reader waits for data while writer sends request
+        let read_ft = async {
+            let mut buf = [0; 5];
+            reader.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"reply");
+            Ok(()) as Result<(), IoError>
+        };
+        let write_ft = async {
+            writer.write_all(b"ping").await?;
+            let mut buf = [0; 4];
+            peer.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            peer.write_all(b"reply").await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let (read_result, write_result) = zip(read_ft, write_ft).await;
+        read_result?;
+        write_result?;
+
+        let (other_reader, _other_writer) = duplex(64).0.into_split();
+        let (_other_reader, writer) = other_reader.reunite(writer).err().expect("mismatch");
+        assert!(reader.reunite(writer).is_ok());
+        Ok(())
+    }
+}
@@ -0,0 +1,494 @@
+//! dns srv resolution, targets like `_fluvio._tcp.example.com` resolve to host:port candidates.
+//!
+//! candidates are ordered by priority and by weighted random choice within same priority,
+//! as described in rfc 2782, so `connect_tcp` tries them in that order
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_lite::future::FutureExt;
+use log::debug;
+
+use super::Resolved;
+use super::Resolver;
+use super::UdpSocket;
+use crate::timer::sleep;
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+const DNS_PORT: u16 = 53;
+const QUERY_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// one srv record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub priority: u16,
+    pub weight: u16,
+    pub port: u16,
+    pub target: String,
+    pub ttl: Duration,
+}
+
+/// true if target is srv name, which starts with `_service._proto` and has no port
+pub fn is_srv_target(target: &str) -> bool {
+    let mut labels = target.split('.');
+    matches!(
+        (labels.next(), labels.next()),
+        (Some(service), Some(proto)) if service.starts_with('_') && proto.starts_with('_')
+    ) && !target.contains(':')
+}
+
+/// resolver looking up srv records for srv targets, other targets go to inner resolver.
+/// host of each record is resolved thru inner resolver
+pub struct SrvResolver<R> {
+    inner: R,
+    nameservers: Vec<SocketAddr>,
+    timeout: Duration,
+}
+
+impl<R> SrvResolver<R> {
+    /// use nameservers from resolv.conf
+    pub fn new(inner: R) -> Self {
+        let nameservers = std::fs::read_to_string(RESOLV_CONF)
+            .map(|conf| parse_resolv_conf(&conf))
+            .unwrap_or_default();
+        Self::with_nameservers(inner, nameservers)
+    }
+
+    pub fn with_nameservers(inner: R, nameservers: Vec<SocketAddr>) -> Self {
+        Self {
+            inner,
+            nameservers,
+            timeout: QUERY_TIMEOUT,
+        }
+    }
+
+    /// timeout of each query to nameserver
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// srv records of name, ordered in which they should be tried
+    pub async fn lookup_srv(&self, name: &str) -> Result<Vec<SrvRecord>, IoError> {
+        if self.nameservers.is_empty() {
+            return Err(IoError::new(
+                ErrorKind::NotFound,
+                "no nameserver configured",
+            ));
+        }
+        let mut last_error = None;
+        for nameserver in &self.nameservers {
+            match query(*nameserver, name, self.timeout).await {
+                Ok(records) => return Ok(order_records(records)),
+                Err(err) if err.kind() == ErrorKind::NotFound => return Err(err),
+                Err(err) => {
+                    debug!("srv query to: {} failed: {}", nameserver, err);
+                    last_error = Some(err);
+                }
+            }
+        }
+        Err(last_error.expect("at least one nameserver"))
+    }
+}
+
+#[async_trait]
+impl<R: Resolver> Resolver for SrvResolver<R> {
+    async fn resolve(&self, target: &str) -> Result<Resolved, IoError> {
+        if !is_srv_target(target) {
+            return self.inner.resolve(target).await;
+        }
+
+        let records = self.lookup_srv(target).await?;
+        let mut addrs = vec![];
+        let mut ttl = records.iter().map(|record| record.ttl).min();
+        for record in records {
+            let host = format!("{}:{}", record.target, record.port);
+            match self.inner.resolve(&host).await {
+                Ok(resolved) => {
+                    addrs.extend(resolved.addrs);
+                    if let Some(host_ttl) = resolved.ttl {
+                        ttl = ttl.map(|ttl| ttl.min(host_ttl));
+                    }
+                }
+                Err(err) => debug!("resolving srv target: {} failed: {}", host, err),
+            }
+        }
+        if addrs.is_empty() {
+            return Err(IoError::new(
+                ErrorKind::NotFound,
+                format!("no srv target of {} resolved", target),
+            ));
+        }
+        Ok(Resolved { addrs, ttl })
+    }
+}
+
+fn parse_resolv_conf(conf: &str) -> Vec<SocketAddr> {
+    conf.lines()
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            match (parts.next(), parts.next()) {
+                (Some("nameserver"), Some(ip)) => ip.parse().ok(),
+                _ => None,
+            }
+        })
+        .map(|ip| SocketAddr::new(ip, DNS_PORT))
+        .collect()
+}
+
+/// order by priority, then by weighted random choice within priority
+fn order_records(mut records: Vec<SrvRecord>) -> Vec<SrvRecord> {
+    // "." means service is not available
+    records.retain(|record| record.target != ".");
+    records.sort_by_key(|record| (record.priority, record.weight));
+
+    let mut ordered = Vec::with_capacity(records.len());
+    while !records.is_empty() {
+        let priority = records[0].priority;
+        let mut group: Vec<SrvRecord> = vec![];
+        while !records.is_empty() && records[0].priority == priority {
+            group.push(records.remove(0));
+        }
+        // zero weight records come first so they can still be chosen
+        while !group.is_empty() {
+            let total: u32 = group.iter().map(|record| record.weight as u32).sum();
+            let pick = fastrand::u32(0..=total);
+            let mut running = 0;
+            let index = group
+                .iter()
+                .position(|record| {
+                    running += record.weight as u32;
+                    running >= pick
+                })
+                .unwrap_or(0);
+            ordered.push(group.remove(index));
+        }
+    }
+    ordered
+}
+
+async fn query(
+    nameserver: SocketAddr,
+    name: &str,
+    timeout: Duration,
+) -> Result<Vec<SrvRecord>, IoError> {
+    let id = fastrand::u16(..);
+    let request = dns::srv_query(id, name)?;
+    let bind_addr: SocketAddr = if nameserver.is_ipv4() {
+        ([0, 0, 0, 0], 0).into()
+    } else {
+        ([0u16; 8], 0).into()
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(nameserver).await?;
+    socket.send(&request).await?;
+
+    let mut buf = vec![0; 4096];
+    let receive = async {
+        loop {
+            let len = socket.recv(&mut buf).await?;
+            // response to other query is ignored
+            if len >= 2 && u16::from_be_bytes([buf[0], buf[1]]) == id {
+                return Ok(len);
+            }
+        }
+    };
+    let timed_out = async {
+        sleep(timeout).await;
+        Err(IoError::new(ErrorKind::TimedOut, "srv query timed out"))
+    };
+    let len = receive.or(timed_out).await?;
+    dns::parse_srv_response(&buf[..len])
+}
+
+/// minimal dns message encoding, enough for srv and address queries
+pub(super) mod dns {
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    #[cfg(feature = "secure_dns")]
+    use std::net::IpAddr;
+    use std::ops::Range;
+    use std::time::Duration;
+
+    use super::SrvRecord;
+
+    #[cfg(feature = "secure_dns")]
+    pub(in crate::net) const TYPE_A: u16 = 1;
+    #[cfg(feature = "secure_dns")]
+    pub(in crate::net) const TYPE_AAAA: u16 = 28;
+    const TYPE_SRV: u16 = 33;
+    const CLASS_IN: u16 = 1;
+    const FLAG_RD: u16 = 0x0100;
+    const FLAG_TC: u16 = 0x0200;
+    const RCODE_NXDOMAIN: u16 = 3;
+    const MAX_POINTERS: usize = 32;
+
+    fn invalid(message: &str) -> IoError {
+        IoError::new(
+            ErrorKind::InvalidData,
+            format!("invalid dns response: {}", message),
+        )
+    }
+
+    pub(super) fn srv_query(id: u16, name: &str) -> Result<Vec<u8>, IoError> {
+        query(id, name, TYPE_SRV)
+    }
+
+    pub(in crate::net) fn query(id: u16, name: &str, record_type: u16) -> Result<Vec<u8>, IoError> {
+        let mut message = Vec::with_capacity(18 + name.len());
+        message.extend_from_slice(&id.to_be_bytes());
+        message.extend_from_slice(&FLAG_RD.to_be_bytes());
+        // one question, no answer, authority or additional records
+        message.extend_from_slice(&[0, 1, 0, 0, 0, 0, 0, 0]);
+        for label in name.trim_end_matches('.').split('.') {
+            if label.is_empty() || label.len() > 63 {
+                return Err(IoError::new(
+                    ErrorKind::InvalidInput,
+                    format!("invalid dns name: {}", name),
+                ));
+            }
+            message.push(label.len() as u8);
+            message.extend_from_slice(label.as_bytes());
+        }
+        message.push(0);
+        message.extend_from_slice(&record_type.to_be_bytes());
+        message.extend_from_slice(&CLASS_IN.to_be_bytes());
+        Ok(message)
+    }
+
+    fn read_u16(message: &[u8], pos: usize) -> Result<u16, IoError> {
+        message
+            .get(pos..pos + 2)
+            .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+            .ok_or_else(|| invalid("truncated"))
+    }
+
+    fn read_u32(message: &[u8], pos: usize) -> Result<u32, IoError> {
+        Ok((read_u16(message, pos)? as u32) << 16 | read_u16(message, pos + 2)? as u32)
+    }
+
+    /// read possibly compressed name, returns name and position after it
+    fn read_name(message: &[u8], mut pos: usize) -> Result<(String, usize), IoError> {
+        let mut labels: Vec<String> = vec![];
+        let mut end = None;
+        let mut pointers = 0;
+        loop {
+            let len = *message.get(pos).ok_or_else(|| invalid("truncated name"))? as usize;
+            if len == 0 {
+                pos += 1;
+                break;
+            }
+            if len & 0xC0 == 0xC0 {
+                pointers += 1;
+                if pointers > MAX_POINTERS {
+                    return Err(invalid("name pointer loop"));
+                }
+                let offset = (read_u16(message, pos)? & 0x3FFF) as usize;
+                end.get_or_insert(pos + 2);
+                pos = offset;
+                continue;
+            }
+            let label = message
+                .get(pos + 1..pos + 1 + len)
+                .ok_or_else(|| invalid("truncated label"))?;
+            labels.push(String::from_utf8_lossy(label).into_owned());
+            pos += 1 + len;
+        }
+        let name = if labels.is_empty() {
+            ".".to_owned()
+        } else {
+            labels.join(".")
+        };
+        Ok((name, end.unwrap_or(pos)))
+    }
+
+    /// answer record, data is range in message
+    struct Answer {
+        record_type: u16,
+        ttl: Duration,
+        data: Range<usize>,
+    }
+
+    fn parse_answers(message: &[u8], not_found: &str) -> Result<Vec<Answer>, IoError> {
+        let flags = read_u16(message, 2)?;
+        match flags & 0x000F {
+            0 => {}
+            RCODE_NXDOMAIN => return Err(IoError::new(ErrorKind::NotFound, not_found.to_owned())),
+            rcode => return Err(invalid(&format!("error code {}", rcode))),
+        }
+        if flags & FLAG_TC != 0 {
+            log::debug!("dns response truncated, using records received");
+        }
+        let questions = read_u16(message, 4)?;
+        let answers = read_u16(message, 6)?;
+
+        let mut pos = 12;
+        for _ in 0..questions {
+            pos = read_name(message, pos)?.1 + 4;
+        }
+
+        let mut records = vec![];
+        for _ in 0..answers {
+            pos = read_name(message, pos)?.1;
+            let record_type = read_u16(message, pos)?;
+            let ttl = read_u32(message, pos + 4)?;
+            let len = read_u16(message, pos + 8)? as usize;
+            let data = pos + 10;
+            if data + len > message.len() {
+                return Err(invalid("truncated record"));
+            }
+            records.push(Answer {
+                record_type,
+                ttl: Duration::from_secs(ttl as u64),
+                data: data..data + len,
+            });
+            pos = data + len;
+        }
+        Ok(records)
+    }
+
+    pub(super) fn parse_srv_response(message: &[u8]) -> Result<Vec<SrvRecord>, IoError> {
+        let mut records = vec![];
+        for answer in parse_answers(message, "no such srv name")? {
+            if answer.record_type == TYPE_SRV {
+                let data = answer.data.start;
+                records.push(SrvRecord {
+                    priority: read_u16(message, data)?,
+                    weight: read_u16(message, data + 2)?,
+                    port: read_u16(message, data + 4)?,
+                    target: read_name(message, data + 6)?.0,
+                    ttl: answer.ttl,
+                });
+            }
+        }
+        Ok(records)
+    }
+
+    /// a and aaaa records with lowest ttl of them, cname records are skipped
+    #[cfg(feature = "secure_dns")]
+    pub(in crate::net) fn parse_address_response(
+        message: &[u8],
+    ) -> Result<(Vec<IpAddr>, Option<Duration>), IoError> {
+        let mut addrs = vec![];
+        let mut ttl: Option<Duration> = None;
+        for answer in parse_answers(message, "no such host")? {
+            let data = &message[answer.data.clone()];
+            let addr = match (answer.record_type, data.len()) {
+                (TYPE_A, 4) => IpAddr::from([data[0], data[1], data[2], data[3]]),
+                (TYPE_AAAA, 16) => {
+                    let mut octets = [0; 16];
+                    octets.copy_from_slice(data);
+                    IpAddr::from(octets)
+                }
+                _ => continue,
+            };
+            addrs.push(addr);
+            ttl = Some(ttl.map_or(answer.ttl, |ttl| ttl.min(answer.ttl)));
+        }
+        Ok((addrs, ttl))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use super::dns;
+    use super::is_srv_target;
+    use super::order_records;
+    use super::parse_resolv_conf;
+    use super::SrvRecord;
+
+    /// response answering query with two records, second target is compressed
+    fn srv_response(query: &[u8]) -> Vec<u8> {
+        let mut message = query.to_vec();
+        // answer count
+        message[6..8].copy_from_slice(&[0, 2]);
+        let records: [(u16, u16, &[u8]); 2] = [
+            // broker1.example.com
+            (10, 5, b"\x07broker1\x07example\x03com\x00"),
+            // broker2 + pointer to example.com in first answer
+            (20, 0, b"\x07broker2\xc0\x00"),
+        ];
+        let mut example_offset = 0;
+        for (priority, weight, target) in records.iter() {
+            // name pointer to question
+            message.extend_from_slice(&[0xc0, 12]);
+            message.extend_from_slice(&[0, 33, 0, 1, 0, 0, 0, 60]);
+            message.extend_from_slice(&((6 + target.len()) as u16).to_be_bytes());
+            message.extend_from_slice(&priority.to_be_bytes());
+            message.extend_from_slice(&weight.to_be_bytes());
+            message.extend_from_slice(&9092u16.to_be_bytes());
+            if example_offset == 0 {
+                example_offset = message.len() + 8;
+                message.extend_from_slice(target);
+            } else {
+                let mut target = target.to_vec();
+                let len = target.len();
+                target[len - 1] = example_offset as u8;
+                message.extend_from_slice(&target);
+            }
+        }
+        message
+    }
+
+    #[test]
+    fn test_parse_srv_response() {
+        let query = dns::srv_query(7, "_fluvio._tcp.example.com").expect("query");
+        let records = dns::parse_srv_response(&srv_response(&query)).expect("parse");
+        assert_eq!(
+            records,
+            vec![
+                SrvRecord {
+                    priority: 10,
+                    weight: 5,
+                    port: 9092,
+                    target: "broker1.example.com".to_owned(),
+                    ttl: Duration::from_secs(60),
+                },
+                SrvRecord {
+                    priority: 20,
+                    weight: 0,
+                    port: 9092,
+                    target: "broker2.example.com".to_owned(),
+                    ttl: Duration::from_secs(60),
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_order_records() {
+        let record = |priority, weight, target: &str| SrvRecord {
+            priority,
+            weight,
+            port: 9092,
+            target: target.to_owned(),
+            ttl: Duration::from_secs(60),
+        };
+        let ordered = order_records(vec![
+            record(20, 1, "c"),
+            record(10, 1, "a"),
+            record(10, 0, "."),
+            record(10, 1, "b"),
+        ]);
+        let targets: Vec<&str> = ordered.iter().map(|r| r.target.as_str()).collect();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[2], "c");
+        assert!(targets[..2].contains(&"a") && targets[..2].contains(&"b"));
+    }
+
+    #[test]
+    fn test_srv_target() {
+        assert!(is_srv_target("_fluvio._tcp.example.com"));
+        assert!(!is_srv_target("example.com:9092"));
+        assert!(!is_srv_target("_fluvio.example.com"));
+        assert_eq!(
+            parse_resolv_conf("# comment\nnameserver 10.0.0.1\nsearch local\n"),
+            vec!["10.0.0.1:53".parse().unwrap()]
+        );
+    }
+}
@@ -0,0 +1,235 @@
+//! async tun and tap devices on crate reactor, for overlay network experiments.
+//!
+//! each read returns one packet, ip packet for tun and ethernet frame for tap,
+//! without packet information header. opening device needs `CAP_NET_ADMIN`,
+//! unprivileged process can get fd of opened device from supervisor instead
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::io::RawFd;
+
+use async_io::Async;
+use futures_lite::stream;
+use futures_lite::Stream;
+use log::debug;
+
+const TUN_PATH: &str = "/dev/net/tun";
+/// `_IOW('T', 202, int)`
+const TUNSETIFF: libc::c_ulong = 0x4004_54ca;
+const IFF_TUN: libc::c_short = 0x0001;
+const IFF_TAP: libc::c_short = 0x0002;
+const IFF_NO_PI: libc::c_short = 0x1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TunKind {
+    /// ip packets
+    Tun,
+    /// ethernet frames
+    Tap,
+}
+
+/// `struct ifreq` with flags member of its union
+#[repr(C)]
+struct IfReq {
+    name: [libc::c_char; libc::IFNAMSIZ],
+    flags: libc::c_short,
+    _pad: [u8; 22],
+}
+
+/// fd of device, read and write transfer one packet
+#[derive(Debug)]
+struct TunFd(OwnedFd);
+
+impl AsRawFd for TunFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl AsFd for TunFd {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl Read for &TunFd {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let n = unsafe { libc::read(self.as_raw_fd(), buf.as_mut_ptr() as *mut _, buf.len()) };
+        if n < 0 {
+            Err(IoError::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+}
+
+impl Write for &TunFd {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        let n = unsafe { libc::write(self.as_raw_fd(), buf.as_ptr() as *const _, buf.len()) };
+        if n < 0 {
+            Err(IoError::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+/// tun or tap device
+#[derive(Debug)]
+pub struct TunDevice {
+    fd: Async<TunFd>,
+    name: String,
+}
+
+impl TunDevice {
+    /// create or attach to device `name`, empty name lets kernel pick one such as `tun0`
+    pub fn open(name: &str, kind: TunKind) -> Result<Self, IoError> {
+        if name.len() >= libc::IFNAMSIZ || name.contains('\0') {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("invalid device name: {}", name),
+            ));
+        }
+        let path = std::ffi::CString::new(TUN_PATH).expect("path");
+        let fd = unsafe { libc::open(path.as_ptr(), libc::O_RDWR | libc::O_CLOEXEC) };
+        if fd < 0 {
+            return Err(IoError::last_os_error());
+        }
+        let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+        let mut request = IfReq {
+            name: [0; libc::IFNAMSIZ],
+            flags: IFF_NO_PI
+                | match kind {
+                    TunKind::Tun => IFF_TUN,
+                    TunKind::Tap => IFF_TAP,
+                },
+            _pad: [0; 22],
+        };
+        for (dst, src) in request.name.iter_mut().zip(name.as_bytes()) {
+            *dst = *src as libc::c_char;
+        }
+        if unsafe { libc::ioctl(fd.as_raw_fd(), TUNSETIFF as _, &mut request) } < 0 {
+            return Err(IoError::last_os_error());
+        }
+        let name: Vec<u8> = request
+            .name
+            .iter()
+            .take_while(|c| **c != 0)
+            .map(|c| *c as u8)
+            .collect();
+        let name = String::from_utf8_lossy(&name).into_owned();
+        debug!("opened {:?} device: {}", kind, name);
+        Ok(Self {
+            fd: Async::new(TunFd(fd))?,
+            name,
+        })
+    }
+
+    /// wrap fd of device opened elsewhere, such as received with `FdPassing`
+    ///
+    /// # Safety
+    /// `fd` must be open device which is not owned by anything else
+    pub unsafe fn from_raw_fd(fd: RawFd, name: &str) -> Result<Self, IoError> {
+        Ok(Self {
+            fd: Async::new(TunFd(OwnedFd::from_raw_fd(fd)))?,
+            name: name.to_owned(),
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// read one packet, which is truncated if longer than `buf`
+    pub async fn recv(&self, buf: &mut [u8]) -> Result<usize, IoError> {
+        self.fd.read_with(|fd| { fd }.read(buf)).await
+    }
+
+    /// write one packet
+    pub async fn send(&self, packet: &[u8]) -> Result<usize, IoError> {
+        self.fd.write_with(|fd| { fd }.write(packet)).await
+    }
+
+    /// packets read from device, each up to `mtu` bytes
+    pub fn packets(&self, mtu: usize) -> impl Stream<Item = Result<Vec<u8>, IoError>> + '_ {
+        stream::unfold(vec![0; mtu], move |mut buf| async move {
+            let packet = self.recv(&mut buf).await.map(|n| buf[..n].to_vec());
+            Some((packet, buf))
+        })
+    }
+
+    /// release ownership of fd, caller must close it
+    pub fn into_raw_fd(self) -> Result<RawFd, IoError> {
+        Ok(self.fd.into_inner()?.0.into_raw_fd())
+    }
+}
+
+impl AsRawFd for TunDevice {
+    fn as_raw_fd(&self) -> RawFd {
+        self.fd.as_raw_fd()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::os::unix::io::FromRawFd;
+    use std::os::unix::io::OwnedFd;
+
+    use futures_lite::StreamExt;
+    use log::debug;
+
+    use crate::test_async;
+
+    use super::TunDevice;
+    use super::TunKind;
+
+    #[test_async]
+    async fn test_tun_device() -> Result<(), IoError> {
+        match TunDevice::open("fluviotest0", TunKind::Tun) {
+            Ok(device) => assert_eq!(device.name(), "fluviotest0"),
+            Err(err) => debug!("tun device not available: {}", err),
+        }
+
+        // seqpacket pair keeps packet boundaries like device does
+        let mut fds = [0; 2];
+        let result = unsafe {
+            libc::socketpair(
+                libc::AF_UNIX,
+                libc::SOCK_SEQPACKET | libc::SOCK_CLOEXEC,
+                0,
+                fds.as_mut_ptr(),
+            )
+        };
+        assert_eq!(result, 0);
+        let device = unsafe { TunDevice::from_raw_fd(fds[0], "fake0")? };
+        let peer = unsafe { TunDevice::from_raw_fd(fds[1], "peer0")? };
+        assert_eq!(device.name(), "fake0");
+
+        peer.send(b"first packet").await?;
+        peer.send(b"second").await?;
+        {
+            let packets = device.packets(1500);
+            futures_lite::pin!(packets);
+            assert_eq!(packets.next().await.expect("packet")?, b"first packet");
+            assert_eq!(packets.next().await.expect("packet")?, b"second");
+        }
+
+        let fd = device.into_raw_fd()?;
+        drop(unsafe { OwnedFd::from_raw_fd(fd) });
+        Ok(())
+    }
+}
@@ -0,0 +1,253 @@
+//! linux unix sockets beyond stream sockets bound to path, for container runtimes and
+//! systemd services: abstract namespace addresses, written as `@name`, and `SOCK_SEQPACKET`.
+//!
+//! seqpacket socket is wrapped as `UnixStream`, each write is sent as one message
+//! and each read returns at most one message, rest of message is discarded if buffer is short
+use std::convert::TryFrom;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::mem;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::IntoRawFd;
+use std::os::unix::io::RawFd;
+
+use async_io::Async;
+use async_trait::async_trait;
+use log::debug;
+use nix::sys::socket::listen;
+use nix::sys::socket::socket;
+use nix::sys::socket::AddressFamily;
+use nix::sys::socket::SockFlag;
+use nix::sys::socket::SockType;
+
+use super::nix_error;
+use super::unix::UnixListener;
+use super::unix::UnixStream;
+use super::ConnectorError;
+use super::TcpDomainConnector;
+use crate::instrument::instrument_connect;
+
+const BACKLOG: usize = 128;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum UnixSocketType {
+    #[default]
+    Stream,
+    /// connection oriented, with message boundaries kept
+    SeqPacket,
+}
+
+impl UnixSocketType {
+    fn sock_type(&self) -> SockType {
+        match self {
+            Self::Stream => SockType::Stream,
+            Self::SeqPacket => SockType::SeqPacket,
+        }
+    }
+}
+
+/// true if address is in abstract namespace, which starts with `@`
+pub fn is_abstract_address(address: &str) -> bool {
+    address.starts_with('@')
+}
+
+/// sockaddr of address, built here since sockaddr of nix 0.17 computes its length
+/// thru null pointer, which panics on current compilers
+struct UnixAddr {
+    addr: libc::sockaddr_un,
+    len: libc::socklen_t,
+}
+
+impl UnixAddr {
+    fn new(address: &str) -> Result<Self, IoError> {
+        let mut addr: libc::sockaddr_un = unsafe { mem::zeroed() };
+        addr.sun_family = libc::AF_UNIX as libc::sa_family_t;
+        // abstract name starts with nul byte in place of `@`
+        let (path, offset) = match address.strip_prefix('@') {
+            Some(name) => (name.as_bytes(), 1),
+            None => (address.as_bytes(), 0),
+        };
+        // path needs trailing nul, abstract name doesn't
+        if path.contains(&0) || path.len() + 1 > addr.sun_path.len() {
+            return Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("invalid unix socket address: {}", address),
+            ));
+        }
+        for (dst, src) in addr.sun_path[offset..].iter_mut().zip(path) {
+            *dst = *src as libc::c_char;
+        }
+        let path_len = if offset == 1 {
+            path.len() + 1
+        } else {
+            path.len()
+        };
+        let len = mem::size_of::<libc::sa_family_t>() + path_len;
+        Ok(Self {
+            addr,
+            len: len as libc::socklen_t,
+        })
+    }
+
+    fn as_ptr(&self) -> *const libc::sockaddr {
+        &self.addr as *const libc::sockaddr_un as *const libc::sockaddr
+    }
+}
+
+/// new socket, owned by std stream so fd is closed on error
+fn unix_socket(
+    socket_type: UnixSocketType,
+    flags: SockFlag,
+) -> Result<std::os::unix::net::UnixStream, IoError> {
+    let fd = socket(
+        AddressFamily::Unix,
+        socket_type.sock_type(),
+        SockFlag::SOCK_CLOEXEC | flags,
+        None,
+    )
+    .map_err(nix_error)?;
+    Ok(unsafe { std::os::unix::net::UnixStream::from_raw_fd(fd) })
+}
+
+/// connect to socket at path or `@name` address.
+/// socket is non blocking, so connect waits for listener without blocking executor
+pub async fn connect_unix(
+    address: &str,
+    socket_type: UnixSocketType,
+) -> Result<UnixStream, IoError> {
+    let addr = UnixAddr::new(address)?;
+    let stream = unix_socket(socket_type, SockFlag::SOCK_NONBLOCK)?;
+    let pending = unsafe { libc::connect(stream.as_raw_fd(), addr.as_ptr(), addr.len) } < 0 && {
+        let err = IoError::last_os_error();
+        // EAGAIN if backlog of listener is full
+        if !matches!(
+            err.raw_os_error(),
+            Some(libc::EINPROGRESS) | Some(libc::EAGAIN)
+        ) {
+            return Err(err);
+        }
+        true
+    };
+    let stream = Async::new(stream)?;
+    if pending {
+        stream.writable().await?;
+        if let Some(err) = stream.get_ref().take_error()? {
+            return Err(err);
+        }
+    }
+    Ok(UnixStream::from(stream))
+}
+
+/// listen at path or `@name` address, abstract address is released once listener is closed
+pub fn bind_unix(address: &str, socket_type: UnixSocketType) -> Result<UnixListener, IoError> {
+    let addr = UnixAddr::new(address)?;
+    let socket = unix_socket(socket_type, SockFlag::empty())?;
+    let fd = socket.as_raw_fd();
+    if unsafe { libc::bind(fd, addr.as_ptr(), addr.len) } < 0 {
+        return Err(IoError::last_os_error());
+    }
+    listen(fd, BACKLOG).map_err(nix_error)?;
+    let listener = unsafe { std::os::unix::net::UnixListener::from_raw_fd(socket.into_raw_fd()) };
+    UnixListener::try_from(listener)
+}
+
+/// connect to unix sockets by path or `@name` address, as stream or seqpacket socket
+#[derive(Debug, Clone, Default)]
+pub struct UnixSocketConnector {
+    socket_type: UnixSocketType,
+}
+
+impl UnixSocketConnector {
+    pub fn new(socket_type: UnixSocketType) -> Self {
+        Self { socket_type }
+    }
+
+    pub fn seqpacket() -> Self {
+        Self::new(UnixSocketType::SeqPacket)
+    }
+}
+
+#[async_trait]
+impl TcpDomainConnector for UnixSocketConnector {
+    type WrapperStream = UnixStream;
+
+    async fn connect(&self, address: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        instrument_connect("unix", address, address, async {
+            debug!("connect to {:?} unix socket: {}", self.socket_type, address);
+            let stream = connect_unix(address, self.socket_type).await?;
+            let fd = stream.as_raw_fd();
+            Ok((stream, fd)) as Result<(Self::WrapperStream, RawFd), ConnectorError>
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+
+    use crate::net::TcpDomainConnector;
+    use crate::net::UnixDomainConnector;
+    use crate::test_async;
+
+    use super::bind_unix;
+    use super::connect_unix;
+    use super::UnixSocketConnector;
+    use super::UnixSocketType;
+
+    #[test_async]
+    async fn test_abstract_seqpacket() -> Result<(), IoError> {
+        let address = format!("@fluvio-future-test-{}", std::process::id());
+        let listener = bind_unix(&address, UnixSocketType::SeqPacket)?;
+        let client_ft = async {
+            let (mut stream, _) = UnixSocketConnector::seqpacket()
+                .connect(&address)
+                .await
+                .map_err(IoError::from)?;
+            stream.write_all(b"first").await?;
+            stream.write_all(b"second").await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let server_ft = async {
+            let (mut stream, _) = listener.accept().await?;
+            // messages are not merged
+            let mut buf = [0; 64];
+            let first = stream.read(&mut buf).await?;
+            assert_eq!(&buf[..first], b"first");
+            let second = stream.read(&mut buf).await?;
+            assert_eq!(&buf[..second], b"second");
+            Ok(()) as Result<(), IoError>
+        };
+        let (client_result, server_result) = zip(client_ft, server_ft).await;
+        client_result?;
+        server_result?;
+
+        // stream socket in abstract namespace, thru default unix connector
+        let address = format!("@fluvio-future-stream-{}", std::process::id());
+        let listener = bind_unix(&address, UnixSocketType::Stream)?;
+        let (mut client, _) = UnixDomainConnector::new()
+            .connect(&address)
+            .await
+            .map_err(IoError::from)?;
+        let (mut server, _) = listener.accept().await?;
+        client.write_all(b"ping").await?;
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+
+        // nobody listens at address once listener is closed
+        drop(listener);
+        let err = connect_unix(&address, UnixSocketType::Stream)
+            .await
+            .expect_err("refused");
+        assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+        Ok(())
+    }
+}
@@ -0,0 +1,412 @@
+//! vsock transport, for services in micro vm such as firecracker or cloud hypervisor
+//! talking to host without network interface.
+//!
+//! address is written as `cid:port`, where cid `host` stands for `VMADDR_CID_HOST`
+use std::fmt;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::mem;
+use std::os::unix::io::AsFd;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::BorrowedFd;
+use std::os::unix::io::FromRawFd;
+use std::os::unix::io::OwnedFd;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::task::{Context, Poll};
+
+use async_io::Async;
+use async_trait::async_trait;
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use super::ConnectorError;
+use super::TcpDomainConnector;
+use crate::instrument::instrument_connect;
+
+const BACKLOG: libc::c_int = 128;
+
+/// context id and port of vsock endpoint
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct VsockAddr {
+    cid: u32,
+    port: u32,
+}
+
+impl VsockAddr {
+    pub const CID_ANY: u32 = libc::VMADDR_CID_ANY;
+    pub const CID_HOST: u32 = libc::VMADDR_CID_HOST;
+    pub const CID_LOCAL: u32 = libc::VMADDR_CID_LOCAL;
+
+    pub fn new(cid: u32, port: u32) -> Self {
+        Self { cid, port }
+    }
+
+    pub fn cid(&self) -> u32 {
+        self.cid
+    }
+
+    pub fn port(&self) -> u32 {
+        self.port
+    }
+
+    fn to_raw(self) -> libc::sockaddr_vm {
+        let mut addr: libc::sockaddr_vm = unsafe { mem::zeroed() };
+        addr.svm_family = libc::AF_VSOCK as libc::sa_family_t;
+        addr.svm_cid = self.cid;
+        addr.svm_port = self.port;
+        addr
+    }
+
+    fn from_raw(addr: &libc::sockaddr_vm) -> Self {
+        Self::new(addr.svm_cid, addr.svm_port)
+    }
+}
+
+impl FromStr for VsockAddr {
+    type Err = IoError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || {
+            IoError::new(
+                ErrorKind::InvalidInput,
+                format!("invalid vsock addr: {}", s),
+            )
+        };
+        let (cid, port) = s.rsplit_once(':').ok_or_else(invalid)?;
+        let cid = match cid {
+            "host" => Self::CID_HOST,
+            "any" => Self::CID_ANY,
+            cid => cid.parse().map_err(|_| invalid())?,
+        };
+        Ok(Self::new(cid, port.parse().map_err(|_| invalid())?))
+    }
+}
+
+impl fmt::Display for VsockAddr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.cid, self.port)
+    }
+}
+
+/// owned vsock fd, read and write go straight to socket
+#[derive(Debug)]
+struct VsockSocket(OwnedFd);
+
+impl VsockSocket {
+    fn new() -> Result<Self, IoError> {
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_VSOCK,
+                libc::SOCK_STREAM | libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                0,
+            )
+        };
+        if fd < 0 {
+            return Err(IoError::last_os_error());
+        }
+        Ok(Self(unsafe { OwnedFd::from_raw_fd(fd) }))
+    }
+
+    fn addr(&self, peer: bool) -> Result<VsockAddr, IoError> {
+        let mut addr: libc::sockaddr_vm = unsafe { mem::zeroed() };
+        let mut len = mem::size_of::<libc::sockaddr_vm>() as libc::socklen_t;
+        let raw = &mut addr as *mut libc::sockaddr_vm as *mut libc::sockaddr;
+        let result = unsafe {
+            if peer {
+                libc::getpeername(self.as_raw_fd(), raw, &mut len)
+            } else {
+                libc::getsockname(self.as_raw_fd(), raw, &mut len)
+            }
+        };
+        if result < 0 {
+            return Err(IoError::last_os_error());
+        }
+        Ok(VsockAddr::from_raw(&addr))
+    }
+
+    /// pending error of socket, such as result of non blocking connect
+    fn take_error(&self) -> Result<Option<IoError>, IoError> {
+        let mut error: libc::c_int = 0;
+        let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+        let result = unsafe {
+            libc::getsockopt(
+                self.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_ERROR,
+                &mut error as *mut libc::c_int as *mut libc::c_void,
+                &mut len,
+            )
+        };
+        if result < 0 {
+            return Err(IoError::last_os_error());
+        }
+        Ok((error != 0).then(|| IoError::from_raw_os_error(error)))
+    }
+}
+
+impl AsRawFd for VsockSocket {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl AsFd for VsockSocket {
+    fn as_fd(&self) -> BorrowedFd<'_> {
+        self.0.as_fd()
+    }
+}
+
+impl Read for &VsockSocket {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        let n = unsafe { libc::read(self.as_raw_fd(), buf.as_mut_ptr() as *mut _, buf.len()) };
+        if n < 0 {
+            Err(IoError::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+}
+
+impl Read for VsockSocket {
+    fn read(&mut self, buf: &mut [u8]) -> Result<usize, IoError> {
+        (&*self).read(buf)
+    }
+}
+
+impl Write for &VsockSocket {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        let n = unsafe {
+            libc::send(
+                self.as_raw_fd(),
+                buf.as_ptr() as *const _,
+                buf.len(),
+                libc::MSG_NOSIGNAL,
+            )
+        };
+        if n < 0 {
+            Err(IoError::last_os_error())
+        } else {
+            Ok(n as usize)
+        }
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+impl Write for VsockSocket {
+    fn write(&mut self, buf: &[u8]) -> Result<usize, IoError> {
+        (&*self).write(buf)
+    }
+
+    fn flush(&mut self) -> Result<(), IoError> {
+        Ok(())
+    }
+}
+
+/// connected vsock stream
+#[derive(Debug)]
+pub struct VsockStream(Async<VsockSocket>);
+
+impl VsockStream {
+    pub async fn connect(addr: VsockAddr) -> Result<Self, IoError> {
+        let socket = VsockSocket::new()?;
+        let raw = addr.to_raw();
+        let result = unsafe {
+            libc::connect(
+                socket.as_raw_fd(),
+                &raw as *const libc::sockaddr_vm as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_vm>() as libc::socklen_t,
+            )
+        };
+        let pending = result < 0 && {
+            let err = IoError::last_os_error();
+            if err.raw_os_error() != Some(libc::EINPROGRESS) {
+                return Err(err);
+            }
+            true
+        };
+        let socket = Async::new(socket)?;
+        if pending {
+            socket.writable().await?;
+            if let Some(err) = socket.get_ref().take_error()? {
+                return Err(err);
+            }
+        }
+        Ok(Self(socket))
+    }
+
+    pub fn local_addr(&self) -> Result<VsockAddr, IoError> {
+        self.0.get_ref().addr(false)
+    }
+
+    pub fn peer_addr(&self) -> Result<VsockAddr, IoError> {
+        self.0.get_ref().addr(true)
+    }
+}
+
+impl AsRawFd for VsockStream {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0.as_raw_fd()
+    }
+}
+
+impl AsyncRead for VsockStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.0).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for VsockStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        Pin::new(&mut self.0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.0).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        let result = unsafe { libc::shutdown(self.as_raw_fd(), libc::SHUT_WR) };
+        if result < 0 {
+            Poll::Ready(Err(IoError::last_os_error()))
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+}
+
+/// vsock listener, `any:port` accepts from every cid
+#[derive(Debug)]
+pub struct VsockListener(Async<VsockSocket>);
+
+impl VsockListener {
+    pub fn bind(addr: VsockAddr) -> Result<Self, IoError> {
+        let socket = VsockSocket::new()?;
+        let raw = addr.to_raw();
+        unsafe {
+            if libc::bind(
+                socket.as_raw_fd(),
+                &raw as *const libc::sockaddr_vm as *const libc::sockaddr,
+                mem::size_of::<libc::sockaddr_vm>() as libc::socklen_t,
+            ) < 0
+            {
+                return Err(IoError::last_os_error());
+            }
+            if libc::listen(socket.as_raw_fd(), BACKLOG) < 0 {
+                return Err(IoError::last_os_error());
+            }
+        }
+        Ok(Self(Async::new(socket)?))
+    }
+
+    pub fn local_addr(&self) -> Result<VsockAddr, IoError> {
+        self.0.get_ref().addr(false)
+    }
+
+    pub async fn accept(&self) -> Result<(VsockStream, VsockAddr), IoError> {
+        let socket = self
+            .0
+            .read_with(|listener| {
+                let fd = unsafe {
+                    libc::accept4(
+                        listener.as_raw_fd(),
+                        std::ptr::null_mut(),
+                        std::ptr::null_mut(),
+                        libc::SOCK_CLOEXEC | libc::SOCK_NONBLOCK,
+                    )
+                };
+                if fd < 0 {
+                    Err(IoError::last_os_error())
+                } else {
+                    Ok(VsockSocket(unsafe { OwnedFd::from_raw_fd(fd) }))
+                }
+            })
+            .await?;
+        let peer = socket.addr(true)?;
+        debug!("accepted vsock peer: {}", peer);
+        Ok((VsockStream(Async::new(socket)?), peer))
+    }
+}
+
+/// connect to `cid:port` vsock address
+#[derive(Debug, Clone, Default)]
+pub struct VsockConnector {}
+
+impl VsockConnector {
+    pub fn new() -> Self {
+        Self {}
+    }
+}
+
+#[async_trait]
+impl TcpDomainConnector for VsockConnector {
+    type WrapperStream = VsockStream;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        instrument_connect("vsock", domain, domain, async {
+            let addr: VsockAddr = domain.parse()?;
+            debug!("connect to vsock: {}", addr);
+            let stream = VsockStream::connect(addr).await?;
+            let fd = stream.as_raw_fd();
+            Ok((stream, fd)) as Result<(Self::WrapperStream, RawFd), ConnectorError>
+        })
+        .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+    use log::debug;
+
+    use crate::net::TcpDomainConnector;
+    use crate::test_async;
+
+    use super::VsockAddr;
+    use super::VsockConnector;
+    use super::VsockListener;
+
+    #[test_async]
+    async fn test_vsock() -> Result<(), IoError> {
+        assert_eq!("host:5000".parse::<VsockAddr>()?, VsockAddr::new(2, 5000));
+        assert_eq!("3:80".parse::<VsockAddr>()?.to_string(), "3:80");
+        assert!("3".parse::<VsockAddr>().is_err());
+
+        // loopback needs vsock_loopback module, missing in most containers
+        let listener = match VsockListener::bind(VsockAddr::new(VsockAddr::CID_LOCAL, 8911)) {
+            Ok(listener) => listener,
+            Err(err) => {
+                debug!("vsock loopback not available: {}", err);
+                return Ok(());
+            }
+        };
+        let (mut client, _) = VsockConnector::new()
+            .connect("1:8911")
+            .await
+            .map_err(IoError::from)?;
+        let (mut server, _) = listener.accept().await?;
+        client.write_all(b"ping").await?;
+        let mut buf = [0; 4];
+        server.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"ping");
+        Ok(())
+    }
+}
@@ -0,0 +1,89 @@
+//! child processes with async stdio, so tools can shell out, such as to external compressor,
+//! without blocking executor
+use std::io::Error as IoError;
+
+use futures_lite::future::zip;
+use futures_lite::{AsyncReadExt, AsyncWriteExt};
+
+pub use async_process::*;
+
+/// run `command` with `input` on its stdin and collect its output.
+/// input is written while output is read, so large input can't deadlock on full pipe
+pub async fn pipe_through(command: &mut Command, input: &[u8]) -> Result<Output, IoError> {
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdin = child.stdin.take().expect("piped stdin");
+    let mut stdout = child.stdout.take().expect("piped stdout");
+    let mut stderr = child.stderr.take().expect("piped stderr");
+
+    let write_ft = async {
+        stdin.write_all(input).await?;
+        // closing stdin ends input of child
+        drop(stdin);
+        Ok(()) as Result<(), IoError>
+    };
+    let read_ft = async {
+        let mut out = vec![];
+        let mut err = vec![];
+        let (out_result, err_result) =
+            zip(stdout.read_to_end(&mut out), stderr.read_to_end(&mut err)).await;
+        out_result?;
+        err_result?;
+        Ok((out, err)) as Result<(Vec<u8>, Vec<u8>), IoError>
+    };
+    let (write_result, read_result) = zip(write_ft, read_ft).await;
+    let (stdout, stderr) = read_result?;
+    let status = child.status().await?;
+    // child may exit without reading all input, its status tells more than broken pipe
+    if let Err(err) = write_result {
+        if status.success() {
+            return Err(err);
+        }
+    }
+    Ok(Output {
+        status,
+        stdout,
+        stderr,
+    })
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+
+    use futures_lite::AsyncReadExt;
+
+    use crate::test_async;
+
+    use super::pipe_through;
+    use super::Command;
+    use super::Stdio;
+
+    #[test_async]
+    async fn test_process() -> Result<(), IoError> {
+        let input = b"record ".repeat(100_000);
+        let output = pipe_through(&mut Command::new("cat"), &input).await?;
+        assert!(output.status.success());
+        assert_eq!(output.stdout, input);
+
+        let mut child = Command::new("sh")
+            .arg("-c")
+            .arg("echo hello; exit 3")
+            .stdout(Stdio::piped())
+            .spawn()?;
+        let mut stdout = String::new();
+        child
+            .stdout
+            .take()
+            .expect("stdout")
+            .read_to_string(&mut stdout)
+            .await?;
+        assert_eq!(stdout, "hello\n");
+        assert_eq!(child.status().await?.code(), Some(3));
+        Ok(())
+    }
+}
@@ -0,0 +1,162 @@
+//! defaults consulted by modules of crate, configured once at startup of process.
+//!
+//! without installed runtime, `Runtime::current` returns defaults which are same as
+//! built by `Runtime::builder`
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use log::debug;
+
+/// chunk read at once into frame buffer, such as by `Framed`
+pub const DEFAULT_READ_BUFFER_SIZE: usize = 8 * 1024;
+
+/// small writes are batched up to this, such as by `StreamSink`
+pub const DEFAULT_WRITE_BUFFER_SIZE: usize = 4 * 1024;
+
+/// chunk read ahead when streaming files, or copied when zero copy isn't available
+pub const DEFAULT_FILE_CHUNK_SIZE: usize = 64 * 1024;
+
+static CURRENT: RwLock<Option<Arc<Runtime>>> = RwLock::new(None);
+
+/// copy of `Runtime::metrics`, checked on every metric update
+static METRICS_ENABLED: AtomicBool = AtomicBool::new(true);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Runtime {
+    connect_timeout: Option<Duration>,
+    read_buffer_size: usize,
+    write_buffer_size: usize,
+    file_chunk_size: usize,
+    metrics: bool,
+}
+
+impl Default for Runtime {
+    fn default() -> Self {
+        Self {
+            connect_timeout: None,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            write_buffer_size: DEFAULT_WRITE_BUFFER_SIZE,
+            file_chunk_size: DEFAULT_FILE_CHUNK_SIZE,
+            metrics: true,
+        }
+    }
+}
+
+impl Runtime {
+    pub fn builder() -> RuntimeBuilder {
+        RuntimeBuilder(Self::default())
+    }
+
+    /// runtime installed for process, or defaults
+    pub fn current() -> Arc<Runtime> {
+        CURRENT
+            .read()
+            .unwrap()
+            .clone()
+            .unwrap_or_else(|| Arc::new(Self::default()))
+    }
+
+    /// make this runtime current for whole process, replacing previous one.
+    /// streams and files read it when they are created, so it should be done at startup.
+    /// size of blocking pool isn't covered, it is configured by `BLOCKING_MAX_THREADS`
+    /// env var of `blocking` crate before process starts
+    pub fn install(self) {
+        debug!("installing runtime: {:?}", self);
+        METRICS_ENABLED.store(self.metrics, Ordering::Relaxed);
+        *CURRENT.write().unwrap() = Some(Arc::new(self));
+    }
+
+    /// timeout of tcp connect for connectors which don't set their own
+    pub fn connect_timeout(&self) -> Option<Duration> {
+        self.connect_timeout
+    }
+
+    pub fn read_buffer_size(&self) -> usize {
+        self.read_buffer_size
+    }
+
+    pub fn write_buffer_size(&self) -> usize {
+        self.write_buffer_size
+    }
+
+    pub fn file_chunk_size(&self) -> usize {
+        self.file_chunk_size
+    }
+
+    pub fn metrics(&self) -> bool {
+        self.metrics
+    }
+}
+
+pub struct RuntimeBuilder(Runtime);
+
+impl RuntimeBuilder {
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.0.connect_timeout = Some(timeout);
+        self
+    }
+
+    pub fn read_buffer_size(mut self, size: usize) -> Self {
+        self.0.read_buffer_size = size.max(1);
+        self
+    }
+
+    pub fn write_buffer_size(mut self, size: usize) -> Self {
+        self.0.write_buffer_size = size.max(1);
+        self
+    }
+
+    pub fn file_chunk_size(mut self, size: usize) -> Self {
+        self.0.file_chunk_size = size.max(1);
+        self
+    }
+
+    /// disable to skip counting connects, handshakes, bytes, connections and certificates
+    pub fn metrics(mut self, enabled: bool) -> Self {
+        self.0.metrics = enabled;
+        self
+    }
+
+    pub fn build(self) -> Runtime {
+        self.0
+    }
+}
+
+#[cfg(feature = "metrics")]
+pub(crate) fn metrics_enabled() -> bool {
+    METRICS_ENABLED.load(Ordering::Relaxed)
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::time::Duration;
+
+    use super::Runtime;
+    use super::DEFAULT_READ_BUFFER_SIZE;
+    use super::DEFAULT_WRITE_BUFFER_SIZE;
+
+    #[test]
+    fn test_runtime() {
+        assert_eq!(*Runtime::current(), Runtime::default());
+        let runtime = Runtime::builder()
+            .connect_timeout(Duration::from_secs(60))
+            .read_buffer_size(0)
+            .build();
+        assert_eq!(runtime.read_buffer_size(), 1);
+        assert!(runtime.metrics());
+        assert_eq!(runtime.write_buffer_size(), DEFAULT_WRITE_BUFFER_SIZE);
+
+        // other tests run meanwhile, so only install what they don't notice
+        Runtime::builder()
+            .connect_timeout(Duration::from_secs(60))
+            .build()
+            .install();
+        let current = Runtime::current();
+        assert_eq!(current.connect_timeout(), Some(Duration::from_secs(60)));
+        assert_eq!(current.read_buffer_size(), DEFAULT_READ_BUFFER_SIZE);
+        Runtime::default().install();
+        assert_eq!(Runtime::current().connect_timeout(), None);
+    }
+}
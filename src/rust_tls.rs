@@ -2,8 +2,13 @@ use crate::net::TcpStream;
 
 pub use fluvio_async_tls::client::TlsStream as ClientTlsStream;
 pub use fluvio_async_tls::server::TlsStream as ServerTlsStream;
+pub use fluvio_async_tls::Connect;
 pub use fluvio_async_tls::TlsAcceptor;
-pub use fluvio_async_tls::TlsConnector;
+pub use rustls::internal::msgs::enums::SignatureAlgorithm;
+pub use rustls::sign::CertifiedKey;
+pub use rustls::sign::Signer;
+pub use rustls::sign::SigningKey;
+pub use rustls::SignatureScheme;
 
 pub type DefaultServerTlsStream = ServerTlsStream<TcpStream>;
 pub type DefaultClientTlsStream = ClientTlsStream<TcpStream>;
@@ -17,6 +22,7 @@ use rustls::ServerConfig;
 pub use builder::*;
 pub use cert::*;
 pub use connector::*;
+pub use tls_connector::*;
 
 mod cert {
     use std::fs::File;
@@ -25,14 +31,54 @@ mod cert {
     use std::io::Error as IoError;
     use std::io::ErrorKind;
     use std::path::Path;
+    use std::sync::Arc;
 
+    use ring::digest::digest;
+    use ring::digest::SHA256;
     use rustls::internal::pemfile::certs;
     use rustls::internal::pemfile::rsa_private_keys;
+    use rustls::sign::any_supported_type;
+
+    use crate::net::cert_spki;
 
     use super::Certificate;
+    use super::CertifiedKey;
     use super::PrivateKey;
     use super::RootCertStore;
 
+    /// sha256 fingerprint of pinned certificate, or of its public key,
+    /// which stays same when certificate is renewed with same key
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Fingerprint {
+        Cert([u8; 32]),
+        Spki([u8; 32]),
+    }
+
+    impl Fingerprint {
+        pub fn of_cert(cert: &Certificate) -> Self {
+            Self::Cert(sha256(&cert.0))
+        }
+
+        pub fn of_spki(cert: &Certificate) -> Result<Self, IoError> {
+            Ok(Self::Spki(sha256(cert_spki(&cert.0)?)))
+        }
+
+        pub fn matches(&self, cert: &Certificate) -> bool {
+            match self {
+                Self::Cert(fingerprint) => sha256(&cert.0) == *fingerprint,
+                Self::Spki(fingerprint) => cert_spki(&cert.0)
+                    .map(|spki| sha256(spki) == *fingerprint)
+                    .unwrap_or(false),
+            }
+        }
+    }
+
+    fn sha256(data: &[u8]) -> [u8; 32] {
+        let mut out = [0; 32];
+        out.copy_from_slice(digest(&SHA256, data).as_ref());
+        out
+    }
+
     pub fn load_certs<P: AsRef<Path>>(path: P) -> Result<Vec<Certificate>, IoError> {
         load_certs_from_reader(&mut BufReader::new(File::open(path)?))
     }
@@ -59,35 +105,931 @@ mod cert {
 
         Ok(root_store)
     }
+
+    /// load certificate chain and its key, for serving selected certificate
+    pub fn load_certified_key<P: AsRef<Path>>(
+        cert_path: P,
+        key_path: P,
+    ) -> Result<CertifiedKey, IoError> {
+        let certs = load_certs(cert_path)?;
+        if certs.is_empty() {
+            return Err(IoError::new(ErrorKind::InvalidInput, "no cert found"));
+        }
+        let key = load_keys(key_path)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "no key found"))?;
+        let key = any_supported_type(&key)
+            .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid key"))?;
+        Ok(CertifiedKey::new(certs, Arc::new(key)))
+    }
+}
+
+pub use generate::*;
+
+/// in memory certificates for tests and development, without fixture files
+mod generate {
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::net::IpAddr;
+    use std::time::Duration;
+    use std::time::SystemTime;
+    use std::time::UNIX_EPOCH;
+
+    use ring::rand::SecureRandom;
+    use ring::rand::SystemRandom;
+    use ring::signature::EcdsaKeyPair;
+    use ring::signature::KeyPair;
+    use ring::signature::ECDSA_P256_SHA256_ASN1_SIGNING;
+
+    use super::Certificate;
+    use super::PrivateKey;
+
+    const VALIDITY: Duration = Duration::from_secs(365 * 24 * 3600);
+    /// tolerate clock skew between peers
+    const BACKDATE: Duration = Duration::from_secs(3600);
+
+    const OID_EC_PUBLIC_KEY: &[u64] = &[1, 2, 840, 10045, 2, 1];
+    const OID_PRIME256V1: &[u64] = &[1, 2, 840, 10045, 3, 1, 7];
+    const OID_ECDSA_SHA256: &[u64] = &[1, 2, 840, 10045, 4, 3, 2];
+    const OID_COMMON_NAME: &[u64] = &[2, 5, 4, 3];
+    const OID_BASIC_CONSTRAINTS: &[u64] = &[2, 5, 29, 19];
+    const OID_KEY_USAGE: &[u64] = &[2, 5, 29, 15];
+    const OID_SUBJECT_ALT_NAME: &[u64] = &[2, 5, 29, 17];
+
+    /// generated certificate and its pkcs8 ECDSA P-256 key
+    #[derive(Clone)]
+    pub struct CertKeyPair {
+        pub cert: Certificate,
+        pub key: PrivateKey,
+    }
+
+    /// self signed certificate valid for `domains`, first domain is used as common name
+    pub fn generate_self_signed(domains: &[&str]) -> Result<CertKeyPair, IoError> {
+        let common_name = domains
+            .first()
+            .ok_or_else(|| IoError::new(ErrorKind::InvalidInput, "no domain"))?;
+        let (key_pair, key) = generate_key()?;
+        let name = name(common_name);
+        let cert = sign_cert(
+            &key_pair,
+            &name,
+            &name,
+            key_pair.public_key().as_ref(),
+            leaf_extensions(domains),
+        )?;
+        Ok(CertKeyPair { cert, key })
+    }
+
+    /// certificate authority issuing certificates, such as for server and client of test
+    pub fn generate_ca() -> Result<CertificateAuthority, IoError> {
+        let (key_pair, key) = generate_key()?;
+        let name = name("fluvio test ca");
+        let extensions = vec![
+            extension(OID_BASIC_CONSTRAINTS, true, &seq(&[der(0x01, &[0xff])])),
+            // keyCertSign and cRLSign
+            extension(OID_KEY_USAGE, true, &der(0x03, &[0x01, 0x06])),
+        ];
+        let cert = sign_cert(
+            &key_pair,
+            &name,
+            &name,
+            key_pair.public_key().as_ref(),
+            extensions,
+        )?;
+        Ok(CertificateAuthority {
+            ca: CertKeyPair { cert, key },
+            key_pair,
+            name,
+        })
+    }
+
+    pub struct CertificateAuthority {
+        ca: CertKeyPair,
+        key_pair: EcdsaKeyPair,
+        /// encoded subject, issuer of issued certificates
+        name: Vec<u8>,
+    }
+
+    impl CertificateAuthority {
+        /// certificate to trust as root
+        pub fn cert(&self) -> &Certificate {
+            &self.ca.cert
+        }
+
+        pub fn cert_key(&self) -> &CertKeyPair {
+            &self.ca
+        }
+
+        /// certificate for `domain` signed by this authority
+        pub fn issue_cert(&self, domain: &str) -> Result<CertKeyPair, IoError> {
+            let (key_pair, key) = generate_key()?;
+            let cert = sign_cert(
+                &self.key_pair,
+                &self.name,
+                &name(domain),
+                key_pair.public_key().as_ref(),
+                leaf_extensions(&[domain]),
+            )?;
+            Ok(CertKeyPair { cert, key })
+        }
+    }
+
+    fn generate_error<E>(_: E) -> IoError {
+        IoError::new(ErrorKind::InvalidData, "certificate generation failed")
+    }
+
+    fn generate_key() -> Result<(EcdsaKeyPair, PrivateKey), IoError> {
+        let pkcs8 =
+            EcdsaKeyPair::generate_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, &SystemRandom::new())
+                .map_err(generate_error)?;
+        let key_pair = EcdsaKeyPair::from_pkcs8(&ECDSA_P256_SHA256_ASN1_SIGNING, pkcs8.as_ref())
+            .map_err(generate_error)?;
+        Ok((key_pair, PrivateKey(pkcs8.as_ref().to_vec())))
+    }
+
+    fn leaf_extensions(domains: &[&str]) -> Vec<Vec<u8>> {
+        let names: Vec<Vec<u8>> = domains
+            .iter()
+            .map(|domain| match domain.parse::<IpAddr>() {
+                Ok(IpAddr::V4(ip)) => der(0x87, &ip.octets()),
+                Ok(IpAddr::V6(ip)) => der(0x87, &ip.octets()),
+                Err(_) => der(0x82, domain.as_bytes()),
+            })
+            .collect();
+        vec![
+            extension(OID_BASIC_CONSTRAINTS, true, &seq(&[])),
+            extension(OID_SUBJECT_ALT_NAME, false, &seq(&names)),
+        ]
+    }
+
+    fn sign_cert(
+        issuer_key: &EcdsaKeyPair,
+        issuer: &[u8],
+        subject: &[u8],
+        public_key: &[u8],
+        extensions: Vec<Vec<u8>>,
+    ) -> Result<Certificate, IoError> {
+        let rng = SystemRandom::new();
+        let mut serial = [0; 16];
+        rng.fill(&mut serial).map_err(generate_error)?;
+        // positive and without leading zero
+        serial[0] = serial[0] & 0x7f | 0x40;
+
+        let now = SystemTime::now();
+        let algorithm = seq(&[oid(OID_ECDSA_SHA256)]);
+        let tbs = seq(&[
+            // v3
+            der(0xa0, &der(0x02, &[2])),
+            der(0x02, &serial),
+            algorithm.clone(),
+            issuer.to_vec(),
+            seq(&[time(now - BACKDATE), time(now + VALIDITY)]),
+            subject.to_vec(),
+            seq(&[
+                seq(&[oid(OID_EC_PUBLIC_KEY), oid(OID_PRIME256V1)]),
+                bit_string(public_key),
+            ]),
+            der(0xa3, &seq(&extensions)),
+        ]);
+        let signature = issuer_key.sign(&rng, &tbs).map_err(generate_error)?;
+        Ok(Certificate(seq(&[
+            tbs,
+            algorithm,
+            bit_string(signature.as_ref()),
+        ])))
+    }
+
+    fn name(common_name: &str) -> Vec<u8> {
+        seq(&[der(
+            0x31,
+            &seq(&[oid(OID_COMMON_NAME), der(0x0c, common_name.as_bytes())]),
+        )])
+    }
+
+    fn extension(id: &[u64], critical: bool, value: &[u8]) -> Vec<u8> {
+        let mut parts = vec![oid(id)];
+        if critical {
+            parts.push(der(0x01, &[0xff]));
+        }
+        parts.push(der(0x04, value));
+        seq(&parts)
+    }
+
+    fn der(tag: u8, content: &[u8]) -> Vec<u8> {
+        let mut out = vec![tag];
+        let len = content.len();
+        if len < 0x80 {
+            out.push(len as u8);
+        } else {
+            let bytes = len.to_be_bytes();
+            let skip = bytes.iter().take_while(|byte| **byte == 0).count();
+            out.push(0x80 | (bytes.len() - skip) as u8);
+            out.extend_from_slice(&bytes[skip..]);
+        }
+        out.extend_from_slice(content);
+        out
+    }
+
+    fn seq(parts: &[Vec<u8>]) -> Vec<u8> {
+        der(0x30, &parts.concat())
+    }
+
+    fn bit_string(bytes: &[u8]) -> Vec<u8> {
+        let mut content = vec![0];
+        content.extend_from_slice(bytes);
+        der(0x03, &content)
+    }
+
+    fn oid(arcs: &[u64]) -> Vec<u8> {
+        let mut content = vec![(arcs[0] * 40 + arcs[1]) as u8];
+        for arc in &arcs[2..] {
+            let mut encoded = vec![(arc & 0x7f) as u8];
+            let mut rest = arc >> 7;
+            while rest > 0 {
+                encoded.push((rest & 0x7f) as u8 | 0x80);
+                rest >>= 7;
+            }
+            content.extend(encoded.iter().rev());
+        }
+        der(0x06, &content)
+    }
+
+    /// UTCTime until 2050, GeneralizedTime after as rfc 5280 requires
+    fn time(time: SystemTime) -> Vec<u8> {
+        let secs = time
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_secs())
+            .unwrap_or(0);
+        let (year, month, day) = civil_from_days((secs / 86400) as i64);
+        let secs = secs % 86400;
+        let clock = format!(
+            "{:02}{:02}{:02}{:02}{:02}Z",
+            month,
+            day,
+            secs / 3600,
+            secs / 60 % 60,
+            secs % 60
+        );
+        if year < 2050 {
+            der(0x17, format!("{:02}{}", year % 100, clock).as_bytes())
+        } else {
+            der(0x18, format!("{:04}{}", year, clock).as_bytes())
+        }
+    }
+
+    /// date from days since unix epoch, see <http://howardhinnant.github.io/date_algorithms.html>
+    fn civil_from_days(days: i64) -> (i64, u32, u32) {
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era =
+            (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let mp = (5 * day_of_year + 2) / 153;
+        let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+        (year, month, day)
+    }
+
+    #[cfg(test)]
+    mod test {
+
+        use std::io::Error as IoError;
+        use std::time::SystemTime;
+
+        use futures_lite::future::zip;
+        use webpki::trust_anchor_util::cert_der_as_trust_anchor;
+        use webpki::DNSNameRef;
+        use webpki::EndEntityCert;
+        use webpki::TLSServerTrustAnchors;
+        use webpki::Time;
+        use webpki::ECDSA_P256_SHA256;
+
+        use crate::net::duplex;
+        use crate::test_async;
+
+        use super::super::{AcceptorBuilder, ConnectorBuilder};
+        use super::civil_from_days;
+        use super::generate_ca;
+        use super::generate_self_signed;
+        use super::oid;
+        use super::Certificate;
+        use super::OID_ECDSA_SHA256;
+
+        /// parse `cert` as webpki does and verify it for `domain` against `anchor`
+        fn verify(cert: &Certificate, anchor: &Certificate, domain: &str) -> bool {
+            let cert = EndEntityCert::from(&cert.0).expect("parse end entity");
+            let anchors = [cert_der_as_trust_anchor(&anchor.0).expect("parse anchor")];
+            let now = Time::try_from(SystemTime::now()).expect("time");
+            cert.verify_is_valid_tls_server_cert(
+                &[&ECDSA_P256_SHA256],
+                &TLSServerTrustAnchors(&anchors),
+                &[],
+                now,
+            )
+            .is_ok()
+                && cert
+                    .verify_is_valid_for_dns_name(
+                        DNSNameRef::try_from_ascii_str(domain).expect("domain"),
+                    )
+                    .is_ok()
+        }
+
+        #[test_async]
+        async fn test_generated_chain() -> Result<(), IoError> {
+            let ca = generate_ca()?;
+            let issued = ca.issue_cert("localhost")?;
+            assert!(verify(&issued.cert, ca.cert(), "localhost"));
+            assert!(!verify(&issued.cert, ca.cert(), "other.example"));
+            assert!(!verify(&issued.cert, generate_ca()?.cert(), "localhost"));
+            let self_signed = generate_self_signed(&["localhost", "127.0.0.1"])?;
+            assert!(verify(&self_signed.cert, &self_signed.cert, "localhost"));
+
+            // handshake verifying chain and host name by default
+            let acceptor = AcceptorBuilder::new_no_client_authentication()
+                .set_server_cert(vec![issued.cert], issued.key)?
+                .build();
+            let connector = ConnectorBuilder::new().add_ca_cert(ca.cert())?.build();
+            let (client_stream, server_stream) = duplex(16 * 1024);
+            let (server_result, client_result) = zip(
+                acceptor.accept(server_stream),
+                connector.connect("localhost", client_stream),
+            )
+            .await;
+            client_result?;
+            server_result?;
+            Ok(())
+        }
+
+        #[test]
+        fn test_der_encoding() {
+            assert_eq!(civil_from_days(0), (1970, 1, 1));
+            assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+            assert_eq!(
+                oid(OID_ECDSA_SHA256),
+                vec![0x06, 0x08, 0x2a, 0x86, 0x48, 0xce, 0x3d, 0x04, 0x03, 0x02]
+            );
+        }
+    }
+}
+
+pub use ticket::*;
+
+/// session ticket keys, so tickets issued by one server resume on others sharing keys
+mod ticket {
+    use std::io::Error as IoError;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use ring::aead::Aad;
+    use ring::aead::LessSafeKey;
+    use ring::aead::Nonce;
+    use ring::aead::UnboundKey;
+    use ring::aead::CHACHA20_POLY1305;
+    use ring::aead::NONCE_LEN;
+    use ring::rand::SecureRandom;
+    use ring::rand::SystemRandom;
+    use rustls::ProducesTickets;
+
+    const NAME_LEN: usize = 16;
+
+    /// key encrypting session tickets, `name` is sent in ticket to find key decrypting it
+    #[derive(Clone, PartialEq, Eq)]
+    pub struct TicketKey {
+        pub name: [u8; NAME_LEN],
+        pub key: [u8; 32],
+    }
+
+    impl std::fmt::Debug for TicketKey {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            f.debug_struct("TicketKey")
+                .field("name", &self.name)
+                .finish()
+        }
+    }
+
+    impl TicketKey {
+        pub fn generate() -> Result<Self, IoError> {
+            let rng = SystemRandom::new();
+            let mut key = Self {
+                name: [0; NAME_LEN],
+                key: [0; 32],
+            };
+            rng.fill(&mut key.name).map_err(random_error)?;
+            rng.fill(&mut key.key).map_err(random_error)?;
+            Ok(key)
+        }
+    }
+
+    fn random_error<E>(_: E) -> IoError {
+        IoError::other("random generation failed")
+    }
+
+    /// source of ticket keys, such as store shared by fleet of servers
+    pub trait TicketKeyProvider: Send + Sync {
+        /// key encrypting new tickets, none disables issuing tickets
+        fn current(&self) -> Option<TicketKey>;
+
+        /// key with `name`, none if it is unknown or retired
+        fn lookup(&self, name: &[u8; NAME_LEN]) -> Option<TicketKey>;
+    }
+
+    struct Rotation {
+        current: TicketKey,
+        previous: Option<TicketKey>,
+        rotated_at: Instant,
+    }
+
+    /// keys kept in memory and replaced every `interval`.
+    /// previous key still decrypts, so tickets are valid for up to two intervals
+    pub struct RotatingTicketKeys {
+        interval: Duration,
+        rotation: Mutex<Rotation>,
+    }
+
+    impl RotatingTicketKeys {
+        pub fn new(interval: Duration) -> Result<Self, IoError> {
+            Ok(Self {
+                interval,
+                rotation: Mutex::new(Rotation {
+                    current: TicketKey::generate()?,
+                    previous: None,
+                    rotated_at: Instant::now(),
+                }),
+            })
+        }
+
+        pub fn interval(&self) -> Duration {
+            self.interval
+        }
+
+        /// replace current key now, retiring previous one
+        pub fn rotate(&self) -> Result<(), IoError> {
+            let key = TicketKey::generate()?;
+            let mut rotation = self.rotation.lock().unwrap();
+            Self::replace(&mut rotation, key);
+            Ok(())
+        }
+
+        fn replace(rotation: &mut Rotation, key: TicketKey) {
+            let previous = std::mem::replace(&mut rotation.current, key);
+            rotation.previous = Some(previous);
+            rotation.rotated_at = Instant::now();
+        }
+
+        fn rotation(&self) -> std::sync::MutexGuard<'_, Rotation> {
+            let mut rotation = self.rotation.lock().unwrap();
+            if rotation.rotated_at.elapsed() >= self.interval {
+                match TicketKey::generate() {
+                    Ok(key) => Self::replace(&mut rotation, key),
+                    Err(err) => log::error!("ticket key rotation failed: {}", err),
+                }
+            }
+            rotation
+        }
+    }
+
+    impl TicketKeyProvider for RotatingTicketKeys {
+        fn current(&self) -> Option<TicketKey> {
+            Some(self.rotation().current.clone())
+        }
+
+        fn lookup(&self, name: &[u8; NAME_LEN]) -> Option<TicketKey> {
+            let rotation = self.rotation();
+            std::iter::once(&rotation.current)
+                .chain(rotation.previous.as_ref())
+                .find(|key| key.name == *name)
+                .cloned()
+        }
+    }
+
+    /// encrypts tickets as key name, nonce and ChaCha20-Poly1305 sealed session
+    pub(crate) struct ProviderTicketer {
+        provider: Arc<dyn TicketKeyProvider>,
+        lifetime: u32,
+    }
+
+    impl ProviderTicketer {
+        pub(crate) fn new(provider: Arc<dyn TicketKeyProvider>, lifetime: Duration) -> Self {
+            Self {
+                provider,
+                lifetime: lifetime.as_secs() as u32,
+            }
+        }
+
+        fn sealing_key(key: &TicketKey) -> Option<LessSafeKey> {
+            UnboundKey::new(&CHACHA20_POLY1305, &key.key)
+                .ok()
+                .map(LessSafeKey::new)
+        }
+    }
+
+    impl ProducesTickets for ProviderTicketer {
+        fn enabled(&self) -> bool {
+            true
+        }
+
+        fn get_lifetime(&self) -> u32 {
+            self.lifetime
+        }
+
+        fn encrypt(&self, plain: &[u8]) -> Option<Vec<u8>> {
+            let key = self.provider.current()?;
+            let mut nonce = [0; NONCE_LEN];
+            SystemRandom::new().fill(&mut nonce).ok()?;
+
+            let mut sealed = plain.to_vec();
+            Self::sealing_key(&key)?
+                .seal_in_place_append_tag(
+                    Nonce::assume_unique_for_key(nonce),
+                    Aad::from(key.name),
+                    &mut sealed,
+                )
+                .ok()?;
+
+            let mut ticket = Vec::with_capacity(NAME_LEN + NONCE_LEN + sealed.len());
+            ticket.extend_from_slice(&key.name);
+            ticket.extend_from_slice(&nonce);
+            ticket.extend_from_slice(&sealed);
+            Some(ticket)
+        }
+
+        fn decrypt(&self, cipher: &[u8]) -> Option<Vec<u8>> {
+            if cipher.len() < NAME_LEN + NONCE_LEN {
+                return None;
+            }
+            let (name, rest) = cipher.split_at(NAME_LEN);
+            let (nonce, sealed) = rest.split_at(NONCE_LEN);
+            let mut key_name = [0; NAME_LEN];
+            key_name.copy_from_slice(name);
+            let key = match self.provider.lookup(&key_name) {
+                Some(key) => key,
+                None => {
+                    log::debug!("no ticket key for presented ticket");
+                    return None;
+                }
+            };
+
+            let mut sealed = sealed.to_vec();
+            let plain = Self::sealing_key(&key)?
+                .open_in_place(
+                    Nonce::try_assume_unique_for_key(nonce).ok()?,
+                    Aad::from(key.name),
+                    &mut sealed,
+                )
+                .ok()?;
+            Some(plain.to_vec())
+        }
+    }
+
+    #[cfg(test)]
+    mod test {
+
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        use rustls::ProducesTickets;
+
+        use super::ProviderTicketer;
+        use super::RotatingTicketKeys;
+
+        #[test]
+        fn test_ticket_rotation() {
+            let keys = Arc::new(RotatingTicketKeys::new(Duration::from_secs(3600)).unwrap());
+            let ticketer = ProviderTicketer::new(keys.clone(), keys.interval());
+            let ticket = ticketer.encrypt(b"session").expect("encrypt");
+
+            // other server sharing keys resumes ticket
+            let other = ProviderTicketer::new(keys.clone(), keys.interval());
+            assert_eq!(other.decrypt(&ticket).as_deref(), Some(&b"session"[..]));
+
+            keys.rotate().unwrap();
+            assert_eq!(ticketer.decrypt(&ticket).as_deref(), Some(&b"session"[..]));
+            keys.rotate().unwrap();
+            assert!(ticketer.decrypt(&ticket).is_none());
+
+            // tampered ticket and ticket of unrelated keys are rejected
+            let mut ticket = ticketer.encrypt(b"session").expect("encrypt");
+            let last = ticket.len() - 1;
+            ticket[last] ^= 1;
+            assert!(ticketer.decrypt(&ticket).is_none());
+            let unrelated = RotatingTicketKeys::new(Duration::from_secs(3600)).unwrap();
+            let unrelated = ProviderTicketer::new(Arc::new(unrelated), Duration::from_secs(1));
+            let ticket = ticketer.encrypt(b"session").expect("encrypt");
+            assert!(unrelated.decrypt(&ticket).is_none());
+        }
+    }
+}
+
+mod tls_connector {
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use std::sync::Mutex;
+
+    use futures_lite::{AsyncRead, AsyncWrite};
+    use rustls::ServerCertVerified;
+    use rustls::ServerCertVerifier;
+    use rustls::TLSError;
+    use rustls::WebPKIVerifier;
+    use webpki::DNSNameRef;
+
+    use super::Certificate;
+    use super::ClientConfig;
+    use super::Connect;
+    use super::RootCertStore;
+
+    /// overrides of connector settings for single connection
+    #[derive(Debug, Clone, Default, Hash, PartialEq, Eq)]
+    pub struct ConnectOpts {
+        skip_hostname_verification: bool,
+        sni: Option<bool>,
+        alpn: Option<Vec<Vec<u8>>>,
+    }
+
+    impl ConnectOpts {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// verify certificate chain but not that certificate is for domain
+        pub fn verify_hostname(mut self, verify: bool) -> Self {
+            self.skip_hostname_verification = !verify;
+            self
+        }
+
+        /// send or omit server name indication
+        pub fn sni(mut self, enable: bool) -> Self {
+            self.sni = Some(enable);
+            self
+        }
+
+        /// protocols offered by alpn, replacing ones of connector
+        pub fn alpn(mut self, protocols: Vec<Vec<u8>>) -> Self {
+            self.alpn = Some(protocols);
+            self
+        }
+
+        fn is_empty(&self) -> bool {
+            !self.skip_hostname_verification && self.sni.is_none() && self.alpn.is_none()
+        }
+    }
+
+    /// tls client connector, keeps config so it can be adjusted per connection
+    #[derive(Clone)]
+    pub struct TlsConnector {
+        config: Arc<ClientConfig>,
+        /// custom verifier set by builder, none if config verifies with webpki
+        verifier: Option<Arc<dyn ServerCertVerifier>>,
+        /// built without certificate verification, carried to connectors made from this
+        insecure: bool,
+        inner: fluvio_async_tls::TlsConnector,
+        /// connectors built by `connect_with_opts` for each set of options, shared by clones
+        with_opts: Arc<Mutex<HashMap<ConnectOpts, fluvio_async_tls::TlsConnector>>>,
+    }
+
+    impl From<Arc<ClientConfig>> for TlsConnector {
+        fn from(config: Arc<ClientConfig>) -> Self {
+            Self {
+                inner: config.clone().into(),
+                config,
+                verifier: None,
+                insecure: false,
+                with_opts: Arc::new(Mutex::new(HashMap::new())),
+            }
+        }
+    }
+
+    impl From<ClientConfig> for TlsConnector {
+        fn from(config: ClientConfig) -> Self {
+            Arc::new(config).into()
+        }
+    }
+
+    impl Default for TlsConnector {
+        fn default() -> Self {
+            let mut config = ClientConfig::new();
+            config
+                .root_store
+                .add_server_trust_anchors(&webpki_roots::TLS_SERVER_ROOTS);
+            config.into()
+        }
+    }
+
+    impl TlsConnector {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        pub(crate) fn with_verifier(
+            config: ClientConfig,
+            verifier: Option<Arc<dyn ServerCertVerifier>>,
+            insecure: bool,
+        ) -> Self {
+            let mut connector = Self::from(config);
+            connector.verifier = verifier;
+            connector.insecure = insecure;
+            connector
+        }
+
+        /// true if connector was built without certificate verification
+        pub fn is_insecure(&self) -> bool {
+            self.insecure
+        }
+
+        pub fn connect<IO>(&self, domain: impl AsRef<str>, stream: IO) -> Connect<IO>
+        where
+            IO: AsyncRead + AsyncWrite + Unpin,
+        {
+            self.inner.connect(domain, stream)
+        }
+
+        /// connect with `opts` overriding settings of this connector for this connection only,
+        /// such as skipping hostname verification for single self signed endpoint.
+        /// for connector created directly from `ClientConfig`, certificate chain is then verified with webpki
+        pub fn connect_with_opts<IO>(
+            &self,
+            domain: impl AsRef<str>,
+            stream: IO,
+            opts: &ConnectOpts,
+        ) -> Connect<IO>
+        where
+            IO: AsyncRead + AsyncWrite + Unpin,
+        {
+            if opts.is_empty() {
+                return self.connect(domain, stream);
+            }
+            let connector = self
+                .with_opts
+                .lock()
+                .unwrap()
+                .entry(opts.clone())
+                .or_insert_with(|| self.build_with_opts(opts))
+                .clone();
+            connector.connect(domain, stream)
+        }
+
+        #[cfg(test)]
+        pub(crate) fn connectors_with_opts(&self) -> usize {
+            self.with_opts.lock().unwrap().len()
+        }
+
+        fn build_with_opts(&self, opts: &ConnectOpts) -> fluvio_async_tls::TlsConnector {
+            let mut config = ClientConfig::clone(&self.config);
+            if let Some(sni) = opts.sni {
+                config.enable_sni = sni;
+            }
+            if let Some(alpn) = &opts.alpn {
+                config.alpn_protocols = alpn.clone();
+            }
+            if opts.skip_hostname_verification {
+                let verifier = self
+                    .verifier
+                    .clone()
+                    .unwrap_or_else(|| Arc::new(WebPKIVerifier::new()));
+                config
+                    .dangerous()
+                    .set_certificate_verifier(Arc::new(NoHostnameVerification(verifier)));
+            }
+            config.into()
+        }
+    }
+
+    /// accept certificate which is valid except for its names
+    struct NoHostnameVerification(Arc<dyn ServerCertVerifier>);
+
+    impl ServerCertVerifier for NoHostnameVerification {
+        fn verify_server_cert(
+            &self,
+            roots: &RootCertStore,
+            presented_certs: &[Certificate],
+            dns_name: DNSNameRef<'_>,
+            ocsp: &[u8],
+        ) -> Result<ServerCertVerified, TLSError> {
+            match self
+                .0
+                .verify_server_cert(roots, presented_certs, dns_name, ocsp)
+            {
+                Err(TLSError::WebPKIError(webpki::Error::CertNotValidForName)) => {
+                    log::debug!("ignoring server cert name");
+                    Ok(ServerCertVerified::assertion())
+                }
+                result => result,
+            }
+        }
+    }
 }
 
 mod connector {
 
     use std::io::Error as IoError;
 
+    use std::net::SocketAddr;
     use std::os::unix::io::AsRawFd;
     use std::os::unix::io::RawFd;
 
     use async_trait::async_trait;
+    use rustls::TLSError;
 
+    use crate::instrument::handshake_done;
+    use crate::instrument::handshake_start;
+    use crate::instrument::instrument_connect;
+    use crate::instrument::record_peer;
+    use crate::net::server_name;
+    use crate::net::within_deadline;
+    use crate::net::ConnectorError;
     use crate::net::DefaultTcpDomainConnector;
+    use crate::net::EventConnector;
+    use crate::net::SharedEventListener;
+    use crate::net::TcpDomainAcceptor;
     use crate::net::TcpDomainConnector;
+    use crate::net::TransportConnector;
+    use crate::net::UnixDomainConnector;
     use log::debug;
 
     use super::AllTcpStream;
+    use super::ClientTlsStream;
     use super::DefaultClientTlsStream;
+    use super::DefaultServerTlsStream;
     use super::TcpStream;
+    use super::TlsAcceptor;
     use super::TlsConnector;
 
     pub type TlsError = IoError;
 
+    /// keep reason of certificate rejection, rustls reports it as inner error of io error
+    fn handshake_error(err: IoError, peer: Option<SocketAddr>) -> ConnectorError {
+        let verify_result = err
+            .get_ref()
+            .and_then(|inner| inner.downcast_ref::<TLSError>())
+            .and_then(|tls_error| match tls_error {
+                TLSError::WebPKIError(webpki_error) => Some(format!("{:?}", webpki_error)),
+                _ => None,
+            });
+        let err = ConnectorError::tls_handshake(verify_result, err);
+        match peer {
+            Some(addr) => err.with_addr(addr),
+            None => err,
+        }
+    }
+
+    /// whether connector skips certificate verification, and if application allowed that
+    #[derive(Clone, Copy)]
+    struct Insecure {
+        tagged: bool,
+        allowed: bool,
+    }
+
+    impl Insecure {
+        fn of(connector: &TlsConnector) -> Self {
+            Self {
+                tagged: connector.is_insecure(),
+                allowed: false,
+            }
+        }
+
+        fn check(&self, domain: &str) -> Result<(), ConnectorError> {
+            match (self.tagged, self.allowed) {
+                (true, false) => Err(ConnectorError::InsecureNotAllowed),
+                (true, true) => {
+                    log::warn!("connecting to {} without certificate verification", domain);
+                    Ok(())
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
     /// connect as anonymous client
     #[derive(Clone)]
-    pub struct TlsAnonymousConnector(TlsConnector);
+    pub struct TlsAnonymousConnector(TlsConnector, DefaultTcpDomainConnector, Insecure);
 
     impl From<TlsConnector> for TlsAnonymousConnector {
         fn from(connector: TlsConnector) -> Self {
-            Self(connector)
+            let insecure = Insecure::of(&connector);
+            Self(connector, DefaultTcpDomainConnector::new(), insecure)
+        }
+    }
+
+    impl TlsAnonymousConnector {
+        /// connector for tcp stream under tls, such as one with connect timeout
+        pub fn with_tcp(mut self, tcp: DefaultTcpDomainConnector) -> Self {
+            self.1 = tcp;
+            self
+        }
+
+        /// true if connector was built without certificate verification
+        pub fn is_insecure(&self) -> bool {
+            self.2.tagged
+        }
+
+        /// let `AllDomainConnector` use this even if it is insecure
+        pub fn allow_insecure(mut self) -> Self {
+            self.2.allowed = true;
+            self
         }
     }
 
@@ -95,10 +1037,23 @@ mod connector {
     impl TcpDomainConnector for TlsAnonymousConnector {
         type WrapperStream = DefaultClientTlsStream;
 
-        async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), IoError> {
-            let tcp_stream = TcpStream::connect(domain).await?;
-            let fd = tcp_stream.as_raw_fd();
-            Ok((self.0.connect(domain, tcp_stream).await?, fd))
+        async fn connect(
+            &self,
+            domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            // address is host and port, only host is server name
+            let server_name = server_name(domain);
+            instrument_connect("tls_anonymous", domain, server_name, async {
+                let tcp_stream = self.1.connect_stream(domain).await?;
+                let peer = record_peer(&tcp_stream);
+                let fd = tcp_stream.as_raw_fd();
+                let start = handshake_start(server_name);
+                let result = within_deadline(self.0.connect(server_name, tcp_stream)).await?;
+                handshake_done("tls_anonymous", start, &result);
+                Ok((result.map_err(|err| handshake_error(err, peer))?, fd))
+                    as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
         }
     }
 
@@ -106,11 +1061,35 @@ mod connector {
     pub struct TlsDomainConnector {
         domain: String,
         connector: TlsConnector,
+        tcp: DefaultTcpDomainConnector,
+        insecure: Insecure,
     }
 
     impl TlsDomainConnector {
         pub fn new(connector: TlsConnector, domain: String) -> Self {
-            Self { domain, connector }
+            Self {
+                domain,
+                insecure: Insecure::of(&connector),
+                connector,
+                tcp: DefaultTcpDomainConnector::new(),
+            }
+        }
+
+        /// connector for tcp stream under tls, such as one with connect timeout
+        pub fn with_tcp(mut self, tcp: DefaultTcpDomainConnector) -> Self {
+            self.tcp = tcp;
+            self
+        }
+
+        /// true if connector was built without certificate verification
+        pub fn is_insecure(&self) -> bool {
+            self.insecure.tagged
+        }
+
+        /// let `AllDomainConnector` use this even if it is insecure
+        pub fn allow_insecure(mut self) -> Self {
+            self.insecure.allowed = true;
+            self
         }
     }
 
@@ -118,19 +1097,82 @@ mod connector {
     impl TcpDomainConnector for TlsDomainConnector {
         type WrapperStream = DefaultClientTlsStream;
 
-        async fn connect(&self, addr: &str) -> Result<(Self::WrapperStream, RawFd), IoError> {
-            debug!("connect to tls addr: {}", addr);
-            let tcp_stream = TcpStream::connect(addr).await?;
-            let fd = tcp_stream.as_raw_fd();
+        async fn connect(
+            &self,
+            addr: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            instrument_connect("tls_domain", addr, &self.domain, async {
+                debug!("connect to tls addr: {}", addr);
+                let tcp_stream = self.tcp.connect_stream(addr).await?;
+                let peer = record_peer(&tcp_stream);
+                let fd = tcp_stream.as_raw_fd();
+
+                debug!("connect to tls domain: {}", self.domain);
+                let start = handshake_start(&self.domain);
+                let result =
+                    within_deadline(self.connector.connect(&self.domain, tcp_stream)).await?;
+                handshake_done("tls_domain", start, &result);
+                Ok((result.map_err(|err| handshake_error(err, peer))?, fd))
+                    as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
+        }
+    }
+
+    /// tls over transport other than tcp, such as unix socket or in memory stream
+    pub struct TlsOverTransportConnector<T> {
+        transport: T,
+        connector: TlsConnector,
+        domain: Option<String>,
+    }
+
+    impl<T> TlsOverTransportConnector<T> {
+        /// connect tls using address passed to `connect` as domain
+        pub fn new(transport: T, connector: TlsConnector) -> Self {
+            Self {
+                transport,
+                connector,
+                domain: None,
+            }
+        }
+
+        /// domain for server name and certificate verification
+        pub fn domain(mut self, domain: impl Into<String>) -> Self {
+            self.domain = Some(domain.into());
+            self
+        }
+    }
+
+    #[async_trait]
+    impl<T: TransportConnector> TcpDomainConnector for TlsOverTransportConnector<T> {
+        type WrapperStream = ClientTlsStream<T::WrapperStream>;
 
-            debug!("connect to tls domain: {}", self.domain);
-            Ok((self.connector.connect(&self.domain, tcp_stream).await?, fd))
+        async fn connect(
+            &self,
+            addr: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+            let domain = self.domain.as_deref().unwrap_or(addr);
+            instrument_connect("tls_transport", addr, domain, async {
+                let (stream, fd) = self.transport.connect(addr).await?;
+
+                debug!("connect to tls domain: {}", domain);
+                let start = handshake_start(domain);
+                let result = within_deadline(self.connector.connect(domain, stream)).await?;
+                handshake_done("tls_transport", start, &result);
+                Ok((result.map_err(|err| handshake_error(err, None))?, fd))
+                    as Result<(Self::WrapperStream, RawFd), ConnectorError>
+            })
+            .await
         }
     }
 
+    /// connector of any transport, more may be added
     #[derive(Clone)]
+    #[non_exhaustive]
     pub enum AllDomainConnector {
         Tcp(DefaultTcpDomainConnector),
+        /// unix socket, domain is path or `@name` address of socket
+        Unix(UnixDomainConnector),
         TlsDomain(TlsDomainConnector),
         TlsAnonymous(TlsAnonymousConnector),
     }
@@ -146,6 +1188,10 @@ mod connector {
             Self::Tcp(DefaultTcpDomainConnector::new())
         }
 
+        pub fn new_unix() -> Self {
+            Self::Unix(UnixDomainConnector::new())
+        }
+
         pub fn new_tls_domain(connector: TlsDomainConnector) -> Self {
             Self::TlsDomain(connector)
         }
@@ -153,30 +1199,173 @@ mod connector {
         pub fn new_tls_anonymous(connector: TlsAnonymousConnector) -> Self {
             Self::TlsAnonymous(connector)
         }
+
+        /// report connection lifecycle events to listener
+        pub fn with_listener(self, listener: SharedEventListener) -> EventConnector<Self> {
+            EventConnector::new(self, listener)
+        }
+
+        /// use tls connector even if it was built without certificate verification,
+        /// otherwise connect fails with `ConnectorError::InsecureNotAllowed`
+        pub fn allow_insecure(self) -> Self {
+            match self {
+                Self::TlsDomain(connector) => Self::TlsDomain(connector.allow_insecure()),
+                Self::TlsAnonymous(connector) => Self::TlsAnonymous(connector.allow_insecure()),
+                tcp => tcp,
+            }
+        }
     }
 
     #[async_trait]
     impl TcpDomainConnector for AllDomainConnector {
         type WrapperStream = AllTcpStream;
 
-        async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), IoError> {
+        async fn connect(
+            &self,
+            domain: &str,
+        ) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
             match self {
                 Self::Tcp(connector) => {
                     let (stream, fd) = connector.connect(domain).await?;
                     Ok((AllTcpStream::tcp(stream), fd))
                 }
+                Self::Unix(connector) => {
+                    let (stream, fd) = connector.connect(domain).await?;
+                    Ok((AllTcpStream::unix(stream), fd))
+                }
 
                 Self::TlsDomain(connector) => {
+                    connector.insecure.check(domain)?;
                     let (stream, fd) = connector.connect(domain).await?;
                     Ok((AllTcpStream::tls(stream), fd))
                 }
                 Self::TlsAnonymous(connector) => {
+                    connector.2.check(domain)?;
                     let (stream, fd) = connector.connect(domain).await?;
                     Ok((AllTcpStream::tls(stream), fd))
                 }
             }
         }
     }
+
+    #[async_trait]
+    impl TcpDomainAcceptor for TlsAcceptor {
+        type WrapperStream = DefaultServerTlsStream;
+
+        async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+            TlsAcceptor::accept(self, stream).await
+        }
+    }
+}
+
+pub use dual_mode::*;
+
+mod dual_mode {
+
+    use std::io::Error as IoError;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use async_trait::async_trait;
+    use futures_lite::{AsyncRead, AsyncWrite};
+    use log::debug;
+
+    use crate::net::sniff_tls;
+    use crate::net::TcpDomainAcceptor;
+    use crate::net::TransportKind;
+
+    use super::DefaultServerTlsStream;
+    use super::TcpStream;
+    use super::TlsAcceptor;
+
+    /// accepted stream, tls if client started with handshake, otherwise plain tcp
+    #[allow(clippy::large_enum_variant)]
+    pub enum DualModeStream {
+        Tcp(TcpStream),
+        Tls(DefaultServerTlsStream),
+    }
+
+    impl DualModeStream {
+        pub fn transport_kind(&self) -> TransportKind {
+            match self {
+                Self::Tcp(_) => TransportKind::Tcp,
+                Self::Tls(_) => TransportKind::Tls,
+            }
+        }
+
+        pub fn is_tls(&self) -> bool {
+            matches!(self, Self::Tls(_))
+        }
+    }
+
+    impl AsyncRead for DualModeStream {
+        fn poll_read(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &mut [u8],
+        ) -> Poll<Result<usize, IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_read(cx, buf),
+                Self::Tls(stream) => Pin::new(stream).poll_read(cx, buf),
+            }
+        }
+    }
+
+    impl AsyncWrite for DualModeStream {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_write(cx, buf),
+                Self::Tls(stream) => Pin::new(stream).poll_write(cx, buf),
+            }
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_flush(cx),
+                Self::Tls(stream) => Pin::new(stream).poll_flush(cx),
+            }
+        }
+
+        fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            match self.get_mut() {
+                Self::Tcp(stream) => Pin::new(stream).poll_close(cx),
+                Self::Tls(stream) => Pin::new(stream).poll_close(cx),
+            }
+        }
+    }
+
+    /// serve tls and plaintext clients on one port, such as during migration to tls.
+    /// connection is tls if its first bytes are tls handshake, they are only peeked
+    #[derive(Clone)]
+    pub struct DualModeAcceptor {
+        tls: TlsAcceptor,
+    }
+
+    impl DualModeAcceptor {
+        pub fn new(tls: TlsAcceptor) -> Self {
+            Self { tls }
+        }
+    }
+
+    #[async_trait]
+    impl TcpDomainAcceptor for DualModeAcceptor {
+        type WrapperStream = DualModeStream;
+
+        async fn accept(&self, stream: TcpStream) -> Result<Self::WrapperStream, IoError> {
+            if sniff_tls(&stream).await? {
+                debug!("client started tls handshake");
+                let stream = TcpDomainAcceptor::accept(&self.tls, stream).await?;
+                Ok(DualModeStream::Tls(stream))
+            } else {
+                debug!("plaintext client");
+                Ok(DualModeStream::Tcp(stream))
+            }
+        }
+    }
 }
 
 mod builder {
@@ -188,47 +1377,83 @@ mod builder {
     use std::io::ErrorKind;
     use std::path::Path;
     use std::sync::Arc;
+    use std::time::Duration;
 
     use rustls::AllowAnyAuthenticatedClient;
+    use rustls::ClientHello;
+    use rustls::ResolvesClientCert;
+    use rustls::ResolvesServerCert;
     use rustls::ServerCertVerified;
     use rustls::ServerCertVerifier;
     use rustls::TLSError;
     use webpki::DNSNameRef;
 
+    use crate::net::cert_issuer;
+    use crate::net::CertExpiryMonitor;
+    use crate::net::TlsEnv;
+
     use super::load_certs;
     use super::load_certs_from_reader;
     use super::load_keys;
     use super::load_keys_from_reader;
     use super::load_root_ca;
     use super::Certificate;
+    use super::CertifiedKey;
     use super::ClientConfig;
+    use super::Fingerprint;
+    use super::PrivateKey;
+    use super::ProviderTicketer;
     use super::RootCertStore;
     use super::ServerConfig;
+    use super::SignatureScheme;
+    use super::SigningKey;
+    use super::TicketKeyProvider;
     use super::TlsAcceptor;
+    use super::TlsAnonymousConnector;
     use super::TlsConnector;
-
-    pub struct ConnectorBuilder(ClientConfig);
+    use super::TlsDomainConnector;
+
+    pub struct ConnectorBuilder {
+        config: ClientConfig,
+        /// loaded certificates with their label, kept for expiry monitoring
+        certs: Vec<(&'static str, Certificate)>,
+        /// certificate verification is disabled
+        insecure: bool,
+        /// custom verifier, none if config verifies with webpki
+        verifier: Option<Arc<dyn ServerCertVerifier>>,
+    }
 
     impl ConnectorBuilder {
         pub fn new() -> Self {
-            Self(ClientConfig::new())
+            Self {
+                config: ClientConfig::new(),
+                certs: vec![],
+                insecure: false,
+                verifier: None,
+            }
         }
 
         pub fn load_ca_cert<P: AsRef<Path>>(mut self, path: P) -> Result<Self, IoError> {
-            self.0
+            let ca_certs = load_certs(&path)?;
+            self.config
                 .root_store
                 .add_pem_file(&mut BufReader::new(File::open(path)?))
                 .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid ca crt"))?;
+            self.certs
+                .extend(ca_certs.into_iter().map(|cert| ("ca", cert)));
 
             Ok(self)
         }
 
         pub fn load_ca_cert_from_bytes(mut self, buffer: &[u8]) -> Result<Self, IoError> {
+            let ca_certs = load_certs_from_reader(&mut Cursor::new(buffer))?;
             let mut bytes = Cursor::new(buffer);
-            self.0
+            self.config
                 .root_store
                 .add_pem_file(&mut bytes)
                 .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid ca crt"))?;
+            self.certs
+                .extend(ca_certs.into_iter().map(|cert| ("ca", cert)));
 
             Ok(self)
         }
@@ -240,7 +1465,9 @@ mod builder {
         ) -> Result<Self, IoError> {
             let client_certs = load_certs(cert_path)?;
             let mut client_keys = load_keys(key_path)?;
-            self.0
+            self.certs
+                .extend(client_certs.iter().cloned().map(|cert| ("client", cert)));
+            self.config
                 .set_single_client_cert(client_certs, client_keys.remove(0))
                 .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid cert"))?;
 
@@ -254,23 +1481,166 @@ mod builder {
         ) -> Result<Self, IoError> {
             let client_certs = load_certs_from_reader(&mut Cursor::new(cert_buf))?;
             let mut client_keys = load_keys_from_reader(&mut Cursor::new(key_buf))?;
-            self.0
+            self.certs
+                .extend(client_certs.iter().cloned().map(|cert| ("client", cert)));
+            self.config
                 .set_single_client_cert(client_certs, client_keys.remove(0))
                 .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid cert"))?;
 
             Ok(self)
         }
 
-        pub fn no_cert_verification(mut self) -> Self {
-            self.0
-                .dangerous()
-                .set_certificate_verifier(Arc::new(NoCertificateVerification {}));
+        /// trust certificate as root, such as one from `generate_ca`
+        pub fn add_ca_cert(mut self, cert: &Certificate) -> Result<Self, IoError> {
+            self.config
+                .root_store
+                .add(cert)
+                .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid ca crt"))?;
+            self.certs.push(("ca", cert.clone()));
+            Ok(self)
+        }
+
+        pub fn set_client_cert(
+            mut self,
+            client_certs: Vec<Certificate>,
+            key: PrivateKey,
+        ) -> Result<Self, IoError> {
+            self.certs
+                .extend(client_certs.iter().cloned().map(|cert| ("client", cert)));
+            self.config
+                .set_single_client_cert(client_certs, key)
+                .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid cert"))?;
+            Ok(self)
+        }
+
+        /// authenticate with key kept outside of process, such as on hsm or pkcs#11 token.
+        /// `key` signs handshake, so private key never has to be loaded from disk
+        pub fn client_signing_key(
+            mut self,
+            client_certs: Vec<Certificate>,
+            key: Box<dyn SigningKey>,
+        ) -> Self {
+            self.certs
+                .extend(client_certs.iter().cloned().map(|cert| ("client", cert)));
+            self.config.client_auth_cert_resolver = Arc::new(ClientKeyResolver(CertifiedKey::new(
+                client_certs,
+                Arc::new(key),
+            )));
+            self
+        }
+
+        /// select client identity when server requests certificate. `resolver` gets der
+        /// encoded names of ca server accepts, which is empty if server didn't send any
+        pub fn client_cert_resolver<F>(mut self, resolver: F) -> Self
+        where
+            F: Fn(&[&[u8]]) -> Option<CertifiedKey> + Send + Sync + 'static,
+        {
+            self.config.client_auth_cert_resolver = Arc::new(HintResolver(resolver));
+            self
+        }
+
+        /// present first identity with certificate issued by ca server accepts,
+        /// for client holding identities of many clusters. first one is used if server sent no hints
+        pub fn client_identities(mut self, identities: Vec<CertifiedKey>) -> Self {
+            for identity in &identities {
+                self.certs
+                    .extend(identity.cert.iter().cloned().map(|cert| ("client", cert)));
+            }
+            self.client_cert_resolver(move |issuers| {
+                if issuers.is_empty() {
+                    return identities.first().cloned();
+                }
+                identities
+                    .iter()
+                    .find(|identity| {
+                        identity.cert.iter().any(|cert| {
+                            cert_issuer(&cert.0)
+                                .map(|issuer| issuers.contains(&issuer))
+                                .unwrap_or(false)
+                        })
+                    })
+                    .cloned()
+            })
+        }
+
+        /// largest tls record payload sent, up to 16 KB. smaller records reduce memory of
+        /// constrained peers, default of 16 KB suits throughput
+        pub fn max_fragment_size(mut self, size: usize) -> Result<Self, IoError> {
+            self.config.mtu = Some(check_fragment_size(size)?);
+            Ok(self)
+        }
+
+        /// builder from `<PREFIX>_CA_CERT`, `<PREFIX>_CLIENT_CERT`, `<PREFIX>_CLIENT_KEY`
+        /// and `<PREFIX>_INSECURE`, each certificate is either path of pem file or inline pem
+        pub fn from_env(prefix: &str) -> Result<Self, IoError> {
+            let env = TlsEnv::from_env(prefix)?;
+            let mut builder = Self::new();
+            if let Some(ca_cert) = &env.ca_cert {
+                builder = builder.load_ca_cert_from_bytes(ca_cert)?;
+            }
+            if let Some((cert, key)) = &env.client {
+                builder = builder.load_client_certs_from_bytes(cert, key)?;
+            }
+            if env.insecure {
+                builder = builder.danger_accept_invalid_certs();
+            }
+            Ok(builder)
+        }
 
+        /// trust server only if its certificate matches one of `fingerprints`,
+        /// instead of verifying it with roots. safer than `danger_accept_invalid_certs` for self
+        /// signed certificate, domain is not verified since pinned certificate identifies server
+        pub fn with_pinned_sha256_fingerprints(mut self, fingerprints: &[Fingerprint]) -> Self {
+            self.set_verifier(Arc::new(PinnedVerifier(fingerprints.to_vec())));
+            self.insecure = false;
             self
         }
 
+        /// accept any server certificate, for development only.
+        /// built connector and every tls connector made from it are tagged insecure,
+        /// and `AllDomainConnector` refuses them unless `allow_insecure` is set
+        pub fn danger_accept_invalid_certs(mut self) -> Self {
+            log::warn!("tls certificate verification is disabled, connector is insecure");
+            self.set_verifier(Arc::new(NoCertificateVerification {}));
+            self.insecure = true;
+            self
+        }
+
+        fn set_verifier(&mut self, verifier: Arc<dyn ServerCertVerifier>) {
+            self.config
+                .dangerous()
+                .set_certificate_verifier(verifier.clone());
+            self.verifier = Some(verifier);
+        }
+
+        #[deprecated(note = "use danger_accept_invalid_certs")]
+        pub fn no_cert_verification(self) -> Self {
+            self.danger_accept_invalid_certs()
+        }
+
+        /// true if certificate verification is disabled
+        pub fn is_insecure(&self) -> bool {
+            self.insecure
+        }
+
+        /// monitor expiry of loaded client and ca certificates, labeled `client` and `ca`
+        pub fn expiry_monitor(&self, warn_before: Duration) -> Result<CertExpiryMonitor, IoError> {
+            self.certs.iter().try_fold(
+                CertExpiryMonitor::new(warn_before),
+                |monitor, (label, cert)| monitor.add_der(*label, &cert.0),
+            )
+        }
+
         pub fn build(self) -> TlsConnector {
-            self.0.into()
+            TlsConnector::with_verifier(self.config, self.verifier, self.insecure)
+        }
+
+        pub fn build_domain(self, domain: impl Into<String>) -> TlsDomainConnector {
+            TlsDomainConnector::new(self.build(), domain.into())
+        }
+
+        pub fn build_anonymous(self) -> TlsAnonymousConnector {
+            TlsAnonymousConnector::from(self.build())
         }
     }
 
@@ -284,32 +1654,176 @@ mod builder {
             Self(ServerConfig::new(NoClientAuth::new()))
         }
 
-        /// create builder with client authentication
-        /// must pass CA root
-        pub fn new_client_authenticate<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
-            let root_store = load_root_ca(path)?;
+        /// create builder with client authentication
+        /// must pass CA root
+        pub fn new_client_authenticate<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+            let root_store = load_root_ca(path)?;
+
+            Ok(Self(ServerConfig::new(AllowAnyAuthenticatedClient::new(
+                root_store,
+            ))))
+        }
+
+        /// create builder with client authentication, trusting `ca`
+        pub fn new_client_authenticate_cert(ca: &Certificate) -> Result<Self, IoError> {
+            let mut root_store = RootCertStore::empty();
+            root_store
+                .add(ca)
+                .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid ca crt"))?;
+
+            Ok(Self(ServerConfig::new(AllowAnyAuthenticatedClient::new(
+                root_store,
+            ))))
+        }
+
+        pub fn load_server_certs<P: AsRef<Path>>(
+            mut self,
+            cert_path: P,
+            key_path: P,
+        ) -> Result<Self, IoError> {
+            let server_crt = load_certs(cert_path)?;
+            let mut server_keys = load_keys(key_path)?;
+            self.0
+                .set_single_cert(server_crt, server_keys.remove(0))
+                .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid cert"))?;
+
+            Ok(self)
+        }
+
+        pub fn set_server_cert(
+            mut self,
+            server_certs: Vec<Certificate>,
+            key: PrivateKey,
+        ) -> Result<Self, IoError> {
+            self.0
+                .set_single_cert(server_certs, key)
+                .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid cert"))?;
+            Ok(self)
+        }
+
+        /// select certificate by server name client sent, before handshake completes.
+        /// returning none rejects handshake. replaces certificate set by `load_server_certs`
+        pub fn sni_resolver<F>(mut self, resolver: F) -> Self
+        where
+            F: Fn(Option<&str>) -> Option<CertifiedKey> + Send + Sync + 'static,
+        {
+            self.0.cert_resolver = Arc::new(SniResolver(resolver));
+            self
+        }
+
+        /// issue session tickets encrypted with keys of `provider`, so clients resume
+        /// on any server sharing them. `lifetime` is hint sent to clients
+        pub fn session_tickets(
+            mut self,
+            provider: Arc<dyn TicketKeyProvider>,
+            lifetime: Duration,
+        ) -> Self {
+            self.0.ticketer = Arc::new(ProviderTicketer::new(provider, lifetime));
+            self
+        }
+
+        /// largest tls record payload sent, up to 16 KB
+        pub fn max_fragment_size(mut self, size: usize) -> Result<Self, IoError> {
+            self.0.mtu = Some(check_fragment_size(size)?);
+            Ok(self)
+        }
+
+        pub fn build(self) -> TlsAcceptor {
+            TlsAcceptor::from(Arc::new(self.0))
+        }
+    }
+
+    const MAX_FRAGMENT_SIZE: usize = 16 * 1024;
+
+    fn check_fragment_size(size: usize) -> Result<usize, IoError> {
+        if size == 0 || size > MAX_FRAGMENT_SIZE {
+            Err(IoError::new(
+                ErrorKind::InvalidInput,
+                format!("fragment size must be 1 to {} bytes", MAX_FRAGMENT_SIZE),
+            ))
+        } else {
+            Ok(size)
+        }
+    }
+
+    struct ClientKeyResolver(CertifiedKey);
+
+    impl ResolvesClientCert for ClientKeyResolver {
+        fn resolve(
+            &self,
+            _acceptable_issuers: &[&[u8]],
+            _sigschemes: &[SignatureScheme],
+        ) -> Option<CertifiedKey> {
+            Some(self.0.clone())
+        }
+
+        fn has_certs(&self) -> bool {
+            true
+        }
+    }
+
+    struct HintResolver<F>(F);
 
-            Ok(Self(ServerConfig::new(AllowAnyAuthenticatedClient::new(
-                root_store,
-            ))))
+    impl<F> ResolvesClientCert for HintResolver<F>
+    where
+        F: Fn(&[&[u8]]) -> Option<CertifiedKey> + Send + Sync,
+    {
+        fn resolve(
+            &self,
+            acceptable_issuers: &[&[u8]],
+            _sigschemes: &[SignatureScheme],
+        ) -> Option<CertifiedKey> {
+            let key = (self.0)(acceptable_issuers);
+            if key.is_none() {
+                log::debug!("no client certificate for issuers requested by server");
+            }
+            key
         }
 
-        pub fn load_server_certs<P: AsRef<Path>>(
-            mut self,
-            cert_path: P,
-            key_path: P,
-        ) -> Result<Self, IoError> {
-            let server_crt = load_certs(cert_path)?;
-            let mut server_keys = load_keys(key_path)?;
-            self.0
-                .set_single_cert(server_crt, server_keys.remove(0))
-                .map_err(|_| IoError::new(ErrorKind::InvalidInput, "invalid cert"))?;
+        fn has_certs(&self) -> bool {
+            true
+        }
+    }
 
-            Ok(self)
+    struct SniResolver<F>(F);
+
+    impl<F> ResolvesServerCert for SniResolver<F>
+    where
+        F: Fn(Option<&str>) -> Option<CertifiedKey> + Send + Sync,
+    {
+        fn resolve(&self, client_hello: ClientHello) -> Option<CertifiedKey> {
+            let server_name = client_hello.server_name().map(|name| {
+                let name: &str = name.into();
+                name
+            });
+            let key = (self.0)(server_name);
+            if key.is_none() {
+                log::debug!("no certificate for server name: {:?}", server_name);
+            }
+            key
         }
+    }
 
-        pub fn build(self) -> TlsAcceptor {
-            TlsAcceptor::from(Arc::new(self.0))
+    struct PinnedVerifier(Vec<Fingerprint>);
+
+    impl ServerCertVerifier for PinnedVerifier {
+        fn verify_server_cert(
+            &self,
+            _roots: &RootCertStore,
+            presented_certs: &[Certificate],
+            _dns_name: DNSNameRef<'_>,
+            _ocsp: &[u8],
+        ) -> Result<ServerCertVerified, TLSError> {
+            let leaf = presented_certs
+                .first()
+                .ok_or(TLSError::NoCertificatesPresented)?;
+            if self.0.iter().any(|fingerprint| fingerprint.matches(leaf)) {
+                Ok(ServerCertVerified::assertion())
+            } else {
+                Err(TLSError::General(
+                    "certificate doesn't match pinned fingerprints".to_owned(),
+                ))
+            }
         }
     }
 
@@ -329,6 +1843,56 @@ mod builder {
     }
 }
 
+#[cfg(feature = "config")]
+mod config {
+    use std::convert::TryFrom;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+
+    use crate::net::ConnectorConfig;
+    use crate::net::TransportType;
+
+    use super::AllDomainConnector;
+    use super::ConnectorBuilder;
+
+    impl TryFrom<ConnectorConfig> for AllDomainConnector {
+        type Error = IoError;
+
+        fn try_from(config: ConnectorConfig) -> Result<Self, Self::Error> {
+            let invalid = |message: &str| IoError::new(ErrorKind::InvalidInput, message.to_owned());
+            let tcp = config.tcp_connector();
+            match config.transport {
+                TransportType::Tcp => Ok(Self::Tcp(tcp)),
+                TransportType::Unix => Ok(Self::new_unix()),
+                TransportType::Tls => {
+                    let tls = config.tls;
+                    let mut builder = ConnectorBuilder::new();
+                    if let Some(ca_cert) = &tls.ca_cert {
+                        builder = builder.load_ca_cert_from_bytes(&ca_cert.load()?)?;
+                    }
+                    match (&tls.client_cert, &tls.client_key) {
+                        (Some(cert), Some(key)) => {
+                            builder = builder
+                                .load_client_certs_from_bytes(&cert.load()?, &key.load()?)?;
+                        }
+                        (None, None) => {}
+                        _ => {
+                            return Err(invalid("client_cert and client_key must be set together"))
+                        }
+                    }
+                    if tls.insecure {
+                        builder = builder.danger_accept_invalid_certs();
+                    }
+                    Ok(match tls.domain {
+                        Some(domain) => Self::TlsDomain(builder.build_domain(domain).with_tcp(tcp)),
+                        None => Self::TlsAnonymous(builder.build_anonymous().with_tcp(tcp)),
+                    })
+                }
+            }
+        }
+    }
+}
+
 pub use stream::AllTcpStream;
 
 mod stream {
@@ -339,12 +1903,30 @@ mod stream {
 
     use super::DefaultClientTlsStream;
     use super::TcpStream;
+    #[cfg(feature = "zero_copy")]
+    use futures_lite::AsyncWriteExt;
     use futures_lite::{AsyncRead, AsyncWrite};
     use pin_project::pin_project;
 
+    #[cfg(feature = "zero_copy")]
+    use crate::file_slice::AsyncFileSlice;
+    use crate::instrument::stream_read;
+    use crate::instrument::stream_write;
+    use crate::net::unix::UnixStream;
+    use crate::net::PollReady;
+    use crate::net::TransportKind;
+    #[cfg(feature = "zero_copy")]
+    use crate::zero_copy::{
+        copy_slice_to, copy_slice_to_with_option, SendFileError, ZeroCopyOption, ZeroCopyWrite,
+    };
+
+    /// plain tcp, unix socket or tls stream, more may be added
     #[pin_project(project = EnumProj)]
+    #[allow(clippy::large_enum_variant)]
+    #[non_exhaustive]
     pub enum AllTcpStream {
         Tcp(#[pin] TcpStream),
+        Unix(#[pin] UnixStream),
         Tls(#[pin] DefaultClientTlsStream),
     }
 
@@ -353,9 +1935,78 @@ mod stream {
             Self::Tcp(stream)
         }
 
+        pub fn unix(stream: UnixStream) -> Self {
+            Self::Unix(stream)
+        }
+
         pub fn tls(stream: DefaultClientTlsStream) -> Self {
             Self::Tls(stream)
         }
+
+        pub fn transport_kind(&self) -> TransportKind {
+            match self {
+                Self::Tcp(_) => TransportKind::Tcp,
+                Self::Unix(_) => TransportKind::Unix,
+                Self::Tls(_) => TransportKind::Tls,
+            }
+        }
+
+        pub fn is_tls(&self) -> bool {
+            matches!(self, Self::Tls(_))
+        }
+
+        /// copy next bytes into `buf` without consuming them, such as to sniff protocol.
+        /// only plain tcp can peek its socket
+        pub async fn peek(&self, buf: &mut [u8]) -> io::Result<usize> {
+            match self {
+                Self::Tcp(stream) => stream.peek(buf).await,
+                _ => Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "peek is only supported on plain tcp",
+                )),
+            }
+        }
+
+        /// tls stream, or self back if it is plain tcp
+        #[allow(clippy::result_large_err)]
+        pub fn into_tls(self) -> Result<DefaultClientTlsStream, Self> {
+            match self {
+                Self::Tls(stream) => Ok(stream),
+                stream => Err(stream),
+            }
+        }
+
+        /// plain tcp stream, or self back if it is tls
+        #[allow(clippy::result_large_err)]
+        pub fn into_tcp(self) -> Result<TcpStream, Self> {
+            match self {
+                Self::Tcp(stream) => Ok(stream),
+                stream => Err(stream),
+            }
+        }
+    }
+
+    /// read readiness of tls is not supported, since session may hold decrypted data
+    /// which isn't visible from here while socket has none. tls writes follow socket
+    impl PollReady for AllTcpStream {
+        fn poll_read_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self {
+                Self::Tcp(stream) => stream.poll_read_ready(cx),
+                Self::Unix(stream) => stream.poll_read_ready(cx),
+                Self::Tls(_) => Poll::Ready(Err(io::Error::new(
+                    io::ErrorKind::Unsupported,
+                    "read readiness of tls stream is not known, read instead",
+                ))),
+            }
+        }
+
+        fn poll_write_ready(&self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+            match self {
+                Self::Tcp(stream) => stream.poll_write_ready(cx),
+                Self::Unix(stream) => stream.poll_write_ready(cx),
+                Self::Tls(stream) => stream.get_ref().poll_write_ready(cx),
+            }
+        }
     }
 
     impl AsyncRead for AllTcpStream {
@@ -364,10 +2015,15 @@ mod stream {
             cx: &mut Context<'_>,
             buf: &mut [u8],
         ) -> Poll<io::Result<usize>> {
-            match self.project() {
-                EnumProj::Tcp(stream) => stream.poll_read(cx, buf),
-                EnumProj::Tls(stream) => stream.poll_read(cx, buf),
+            let (kind, result) = match self.project() {
+                EnumProj::Tcp(stream) => ("tcp", stream.poll_read(cx, buf)),
+                EnumProj::Unix(stream) => ("unix", stream.poll_read(cx, buf)),
+                EnumProj::Tls(stream) => ("tls", stream.poll_read(cx, buf)),
+            };
+            if let Poll::Ready(Ok(len)) = &result {
+                stream_read(kind, *len);
             }
+            result
         }
     }
 
@@ -377,15 +2033,21 @@ mod stream {
             cx: &mut Context,
             buf: &[u8],
         ) -> Poll<Result<usize, io::Error>> {
-            match self.project() {
-                EnumProj::Tcp(stream) => stream.poll_write(cx, buf),
-                EnumProj::Tls(stream) => stream.poll_write(cx, buf),
+            let (kind, result) = match self.project() {
+                EnumProj::Tcp(stream) => ("tcp", stream.poll_write(cx, buf)),
+                EnumProj::Unix(stream) => ("unix", stream.poll_write(cx, buf)),
+                EnumProj::Tls(stream) => ("tls", stream.poll_write(cx, buf)),
+            };
+            if let Poll::Ready(Ok(len)) = &result {
+                stream_write(kind, *len);
             }
+            result
         }
 
         fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
             match self.project() {
                 EnumProj::Tcp(stream) => stream.poll_flush(cx),
+                EnumProj::Unix(stream) => stream.poll_flush(cx),
                 EnumProj::Tls(stream) => stream.poll_flush(cx),
             }
         }
@@ -393,10 +2055,48 @@ mod stream {
         fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), io::Error>> {
             match self.project() {
                 EnumProj::Tcp(stream) => stream.poll_close(cx),
+                EnumProj::Unix(stream) => stream.poll_close(cx),
                 EnumProj::Tls(stream) => stream.poll_close(cx),
             }
         }
     }
+
+    /// tls stream can't use sendfile, so it falls back to copy thru userspace
+    #[cfg(feature = "zero_copy")]
+    #[async_trait::async_trait]
+    impl ZeroCopyWrite for AllTcpStream {
+        async fn zero_copy_write_with_option(
+            &mut self,
+            source: &AsyncFileSlice,
+            option: &ZeroCopyOption,
+        ) -> Result<usize, SendFileError> {
+            match self {
+                Self::Tcp(stream) => stream.zero_copy_write_with_option(source, option).await,
+                Self::Unix(stream) => stream.zero_copy_write_with_option(source, option).await,
+                Self::Tls(stream) => copy_slice_to_with_option(stream, source, option).await,
+            }
+        }
+
+        async fn write_framed(
+            &mut self,
+            header: &[u8],
+            slice: AsyncFileSlice,
+            trailer: &[u8],
+        ) -> Result<usize, SendFileError> {
+            match self {
+                Self::Tcp(stream) => stream.write_framed(header, slice, trailer).await,
+                Self::Unix(stream) => stream.write_framed(header, slice, trailer).await,
+                Self::Tls(stream) => {
+                    // tls record boundaries are up to tls, so just write in order
+                    stream.write_all(header).await?;
+                    let len = copy_slice_to(stream, &slice).await?;
+                    stream.write_all(trailer).await?;
+                    stream.flush().await?;
+                    Ok(header.len() + len + trailer.len())
+                }
+            }
+        }
+    }
 }
 
 #[cfg(test)]
@@ -404,27 +2104,41 @@ mod test {
 
     use std::io::Error as IoError;
     use std::net::SocketAddr;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
     use std::time;
 
     use bytes::BufMut;
     use bytes::Bytes;
     use bytes::BytesMut;
     use fluvio_async_tls::TlsAcceptor;
-    use fluvio_async_tls::TlsConnector;
     use futures_lite::future::zip;
     use futures_lite::stream::StreamExt;
+    use futures_lite::{AsyncReadExt, AsyncWriteExt};
     use futures_util::sink::SinkExt;
     use log::debug;
     use tokio_util::codec::BytesCodec;
     use tokio_util::codec::Framed;
     use tokio_util::compat::FuturesAsyncReadCompatExt;
 
+    use crate::net::write_ready;
+    use crate::net::TransportKind;
+    use fluvio_future::net::duplex;
     use fluvio_future::net::TcpListener;
     use fluvio_future::net::TcpStream;
     use fluvio_future::test_async;
     use fluvio_future::timer::sleep;
 
-    use super::{AcceptorBuilder, AllTcpStream, ConnectorBuilder};
+    use crate::net::ConnectorError;
+    use crate::net::DuplexStream;
+    use crate::net::TcpDomainConnector;
+
+    use super::{
+        generate_ca, generate_self_signed, load_certified_key, load_certs, load_keys,
+        AcceptorBuilder, AllTcpStream, CertifiedKey, ConnectOpts, ConnectorBuilder, Fingerprint,
+        TlsConnector, TlsOverTransportConnector,
+    };
 
     const CA_PATH: &'static str = "certs/certs/ca.crt";
     const ITER: u16 = 10;
@@ -441,7 +2155,9 @@ mod test {
             AcceptorBuilder::new_no_client_authentication()
                 .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")?
                 .build(),
-            ConnectorBuilder::new().no_cert_verification().build(),
+            ConnectorBuilder::new()
+                .danger_accept_invalid_certs()
+                .build(),
         )
         .await
         .expect("no client cert test failed");
@@ -552,4 +2268,723 @@ mod test {
 
         Ok(())
     }
+
+    #[test_async]
+    async fn test_tls_readiness() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8916".parse::<SocketAddr>().expect("parse");
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")?
+            .build();
+        let connector = ConnectorBuilder::new()
+            .danger_accept_invalid_certs()
+            .build();
+        let listener = TcpListener::bind(&addr).await?;
+        let server = async {
+            let stream = listener.incoming().next().await.expect("stream")?;
+            let mut stream = acceptor.accept(stream).await?;
+            stream.write_all(b"ping").await?;
+            stream.flush().await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client = async {
+            let stream = TcpStream::connect(&addr).await?;
+            let mut stream = AllTcpStream::tls(connector.connect("localhost", stream).await?);
+            // tls writes follow socket
+            write_ready(&stream).await?;
+            let mut received = [0; 4];
+            stream.read_exact(&mut received).await?;
+            Ok(received) as Result<[u8; 4], IoError>
+        };
+        let (served, received) = zip(server, client).await;
+        served?;
+        assert_eq!(&received?, b"ping");
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_tls_duplex() -> Result<(), IoError> {
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")?
+            .build();
+        let connector = ConnectorBuilder::new()
+            .danger_accept_invalid_certs()
+            .build();
+        let (client_stream, server_stream) = duplex(1024);
+
+        let server_ft = async {
+            let mut tls_stream = acceptor.accept(server_stream).await?;
+            let mut buf = [0; 4];
+            tls_stream.read_exact(&mut buf).await?;
+            tls_stream.write_all(&buf).await?;
+            tls_stream.flush().await?;
+            Ok(()) as Result<(), IoError>
+        };
+
+        let client_ft = async {
+            let mut tls_stream = connector.connect("localhost", client_stream).await?;
+            tls_stream.write_all(b"ping").await?;
+            tls_stream.flush().await?;
+            let mut buf = [0; 4];
+            tls_stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (client_result, server_result) = zip(client_ft, server_ft).await;
+        client_result?;
+        server_result
+    }
+
+    #[test]
+    fn test_expiry_monitor() -> Result<(), IoError> {
+        let monitor = ConnectorBuilder::new()
+            .load_ca_cert(CA_PATH)?
+            .load_client_certs("certs/certs/client.crt", "certs/certs/client.key")?
+            .expiry_monitor(time::Duration::from_secs(30 * 24 * 3600))?;
+        let statuses = monitor.check();
+        let labels: Vec<&str> = statuses
+            .iter()
+            .map(|status| status.label.as_str())
+            .collect();
+        assert_eq!(labels, vec!["ca", "client"]);
+        // test certificates have expired
+        assert!(statuses.iter().all(|status| status.is_expired()));
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_sni_routing() -> Result<(), IoError> {
+        let key = load_certified_key("certs/certs/server.crt", "certs/certs/server.key")?;
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .sni_resolver(move |server_name| match server_name {
+                Some("localhost") => Some(key.clone()),
+                _ => None,
+            })
+            .build();
+        let connector = ConnectorBuilder::new()
+            .danger_accept_invalid_certs()
+            .build();
+
+        for (domain, accepted) in [("localhost", true), ("unknown.example", false)] {
+            let (client_stream, server_stream) = duplex(16 * 1024);
+            let (client_result, server_result) = zip(
+                connector.connect(domain, client_stream),
+                acceptor.accept(server_stream),
+            )
+            .await;
+            assert_eq!(client_result.is_ok(), accepted, "domain: {}", domain);
+            assert_eq!(server_result.is_ok(), accepted, "domain: {}", domain);
+        }
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_transport_kind() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8898".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let stream = AllTcpStream::tcp(TcpStream::connect(&addr).await?);
+        drop(listener);
+
+        assert_eq!(stream.transport_kind(), TransportKind::Tcp);
+        assert!(!stream.is_tls());
+        let stream = stream.into_tls().expect_err("not tls");
+        assert!(stream.into_tcp().is_ok());
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_peek() -> Result<(), IoError> {
+        let addr = "127.0.0.1:8904".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let mut client = TcpStream::connect(&addr).await?;
+        let server = listener.incoming().next().await.expect("stream")?;
+        client.write_all(&[0x16, 0x03, 0x01, 0x00]).await?;
+
+        let stream = AllTcpStream::tcp(server);
+        let mut buf = [0; 3];
+        assert_eq!(stream.peek(&mut buf).await?, 3);
+        assert_eq!(buf, [0x16, 0x03, 0x01]);
+        // nothing is consumed, socket itself still has all bytes
+        let mut server = stream.into_tcp().ok().expect("not buffered");
+        let mut received = [0; 4];
+        server.read_exact(&mut received).await?;
+        assert_eq!(received, [0x16, 0x03, 0x01, 0x00]);
+        Ok(())
+    }
+
+    /// transport handing out one prepared in memory stream
+    struct MemoryTransport(std::sync::Mutex<Option<DuplexStream>>);
+
+    #[async_trait::async_trait]
+    impl TcpDomainConnector for MemoryTransport {
+        type WrapperStream = DuplexStream;
+
+        async fn connect(
+            &self,
+            _addr: &str,
+        ) -> Result<(Self::WrapperStream, std::os::unix::io::RawFd), ConnectorError> {
+            let stream = self.0.lock().unwrap().take();
+            Ok((stream.expect("one connect"), 0))
+        }
+    }
+
+    #[test_async]
+    async fn test_tls_over_transport() -> Result<(), IoError> {
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")?
+            .build();
+        let (client_stream, server_stream) = crate::net::duplex(16 * 1024);
+        let connector = TlsOverTransportConnector::new(
+            MemoryTransport(std::sync::Mutex::new(Some(client_stream))),
+            ConnectorBuilder::new()
+                .danger_accept_invalid_certs()
+                .build(),
+        )
+        .domain("localhost");
+
+        let server_ft = async {
+            let mut tls_stream = acceptor.accept(server_stream).await?;
+            tls_stream.write_all(b"ping").await?;
+            tls_stream.flush().await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client_ft = async {
+            let (mut tls_stream, _) = connector.connect("memory").await?;
+            let mut buf = [0; 4];
+            tls_stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (server_result, client_result) = zip(server_ft, client_ft).await;
+        client_result?;
+        server_result
+    }
+
+    async fn handshake(acceptor: TlsAcceptor, connector: TlsConnector) -> Result<(), IoError> {
+        let (client_stream, server_stream) = crate::net::duplex(16 * 1024);
+        let (server_result, client_result) = zip(acceptor.accept(server_stream), async {
+            connector.connect("localhost", client_stream).await
+        })
+        .await;
+        client_result?;
+        server_result?;
+        Ok(())
+    }
+
+    async fn handshake_with_opts(
+        acceptor: TlsAcceptor,
+        connector: &TlsConnector,
+        opts: &ConnectOpts,
+    ) -> Result<(), IoError> {
+        let (client_stream, server_stream) = crate::net::duplex(16 * 1024);
+        let (server_result, client_result) = zip(acceptor.accept(server_stream), async {
+            connector
+                .connect_with_opts("localhost", client_stream, opts)
+                .await
+        })
+        .await;
+        client_result?;
+        server_result?;
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_connect_with_opts() -> Result<(), IoError> {
+        let ca = generate_ca()?;
+        let server = ca.issue_cert("other.example")?;
+        let acceptor = || -> Result<TlsAcceptor, IoError> {
+            Ok(AcceptorBuilder::new_no_client_authentication()
+                .set_server_cert(vec![server.cert.clone()], server.key.clone())?
+                .build())
+        };
+        let connector = ConnectorBuilder::new().add_ca_cert(ca.cert())?.build();
+
+        assert!(handshake(acceptor()?, connector.clone()).await.is_err());
+        let opts = ConnectOpts::new()
+            .verify_hostname(false)
+            .sni(false)
+            .alpn(vec![b"h2".to_vec()]);
+        handshake_with_opts(acceptor()?, &connector, &opts).await?;
+        // connector built for options is reused, also by clones
+        handshake_with_opts(acceptor()?, &connector.clone(), &opts).await?;
+        assert_eq!(connector.connectors_with_opts(), 1);
+        // connector itself is unchanged
+        assert!(handshake(acceptor()?, connector).await.is_err());
+
+        // chain is still verified
+        let connector = ConnectorBuilder::new()
+            .add_ca_cert(generate_ca()?.cert())?
+            .build();
+        assert!(handshake_with_opts(acceptor()?, &connector, &opts)
+            .await
+            .is_err());
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_max_fragment_size() -> Result<(), IoError> {
+        let server = generate_self_signed(&["localhost"])?;
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .set_server_cert(vec![server.cert.clone()], server.key)?
+            .max_fragment_size(512)?
+            .build();
+        let connector = ConnectorBuilder::new()
+            .add_ca_cert(&server.cert)?
+            .max_fragment_size(512)?
+            .build();
+
+        let (client_stream, server_stream) = crate::net::duplex(64 * 1024);
+        let message = vec![7; 4000];
+        let (server_result, client_result) = zip(
+            async {
+                let mut stream = acceptor.accept(server_stream).await?;
+                stream.write_all(&message).await?;
+                stream.flush().await
+            },
+            async {
+                let mut stream = connector.connect("localhost", client_stream).await?;
+                let mut received = vec![0; message.len()];
+                stream.read_exact(&mut received).await?;
+                Ok(received) as Result<Vec<u8>, IoError>
+            },
+        )
+        .await;
+        server_result?;
+        assert_eq!(client_result?, message);
+
+        assert!(ConnectorBuilder::new().max_fragment_size(0).is_err());
+        assert!(AcceptorBuilder::new_no_client_authentication()
+            .max_fragment_size(32 * 1024)
+            .is_err());
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_client_identities() -> Result<(), IoError> {
+        use crate::net::cert_issuer;
+
+        let ca = generate_ca()?;
+        let client = ca.issue_cert("client")?;
+        let generated = CertifiedKey::new(
+            vec![client.cert],
+            Arc::new(rustls::sign::any_supported_type(&client.key).expect("key")),
+        );
+        // identity issued by ca of fixture files
+        let fixture = CertifiedKey::new(
+            load_certs("certs/certs/client.crt")?,
+            Arc::new(
+                rustls::sign::any_supported_type(&load_keys("certs/certs/client.key")?[0])
+                    .expect("key"),
+            ),
+        );
+        let server = ca.issue_cert("localhost")?;
+        let acceptor = AcceptorBuilder::new_client_authenticate_cert(ca.cert())?
+            .set_server_cert(vec![server.cert], server.key)?
+            .build();
+
+        // identity issued by ca of server is selected, though it is not first
+        let connector = ConnectorBuilder::new()
+            .add_ca_cert(ca.cert())?
+            .client_identities(vec![fixture, generated])
+            .build();
+        handshake(acceptor.clone(), connector).await?;
+
+        let hints = Arc::new(std::sync::Mutex::new(vec![]));
+        let seen = hints.clone();
+        let connector = ConnectorBuilder::new()
+            .add_ca_cert(ca.cert())?
+            .client_cert_resolver(move |issuers| {
+                *seen.lock().unwrap() = issuers.iter().map(|issuer| issuer.to_vec()).collect();
+                None
+            })
+            .build();
+        assert!(handshake(acceptor, connector).await.is_err());
+        let expected = cert_issuer(&ca.issue_cert("other")?.cert.0)?.to_vec();
+        assert_eq!(*hints.lock().unwrap(), vec![expected]);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_generated_certs() -> Result<(), IoError> {
+        let ca = generate_ca()?;
+        let server = ca.issue_cert("localhost")?;
+        let client = ca.issue_cert("client")?;
+        let acceptor = AcceptorBuilder::new_client_authenticate_cert(ca.cert())?
+            .set_server_cert(vec![server.cert], server.key)?
+            .build();
+        let connector = ConnectorBuilder::new()
+            .add_ca_cert(ca.cert())?
+            .set_client_cert(vec![client.cert], client.key)?
+            .build();
+        handshake(acceptor, connector).await?;
+
+        // self signed certificate is trusted directly
+        let server = generate_self_signed(&["localhost", "127.0.0.1"])?;
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .set_server_cert(vec![server.cert.clone()], server.key)?
+            .build();
+        let connector = ConnectorBuilder::new().add_ca_cert(&server.cert)?.build();
+        handshake(acceptor, connector).await?;
+
+        // certificate of other authority is rejected
+        let other = generate_ca()?.issue_cert("localhost")?;
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .set_server_cert(vec![other.cert], other.key)?
+            .build();
+        let connector = ConnectorBuilder::new().add_ca_cert(ca.cert())?.build();
+        assert!(handshake(acceptor, connector).await.is_err());
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_pinned_fingerprints() -> Result<(), IoError> {
+        let server = generate_self_signed(&["localhost"])?;
+        let other = generate_self_signed(&["localhost"])?;
+        let acceptor = || -> Result<TlsAcceptor, IoError> {
+            Ok(AcceptorBuilder::new_no_client_authentication()
+                .set_server_cert(vec![server.cert.clone()], server.key.clone())?
+                .build())
+        };
+        let pinned = |fingerprints: &[Fingerprint]| {
+            ConnectorBuilder::new()
+                .with_pinned_sha256_fingerprints(fingerprints)
+                .build()
+        };
+
+        let cert_pin = Fingerprint::of_cert(&server.cert);
+        let spki_pin = Fingerprint::of_spki(&server.cert)?;
+        assert_ne!(spki_pin, Fingerprint::of_spki(&other.cert)?);
+        handshake(
+            acceptor()?,
+            pinned(&[Fingerprint::of_cert(&other.cert), cert_pin]),
+        )
+        .await?;
+        handshake(acceptor()?, pinned(&[spki_pin])).await?;
+        assert!(
+            handshake(acceptor()?, pinned(&[Fingerprint::of_cert(&other.cert)]))
+                .await
+                .is_err()
+        );
+        Ok(())
+    }
+
+    /// signs with key from file, counting signatures as hsm would
+    struct CountingKey(Box<dyn super::SigningKey>, Arc<AtomicUsize>);
+
+    impl super::SigningKey for CountingKey {
+        fn choose_scheme(
+            &self,
+            offered: &[super::SignatureScheme],
+        ) -> Option<Box<dyn super::Signer>> {
+            self.1.fetch_add(1, Ordering::SeqCst);
+            self.0.choose_scheme(offered)
+        }
+
+        fn algorithm(&self) -> super::SignatureAlgorithm {
+            self.0.algorithm()
+        }
+    }
+
+    /// accept any client certificate, test certificates have expired
+    struct AnyClient;
+
+    impl rustls::ClientCertVerifier for AnyClient {
+        fn client_auth_root_subjects(
+            &self,
+            _sni: Option<&webpki::DNSName>,
+        ) -> Option<rustls::DistinguishedNames> {
+            Some(vec![])
+        }
+
+        fn verify_client_cert(
+            &self,
+            _presented_certs: &[rustls::Certificate],
+            _sni: Option<&webpki::DNSName>,
+        ) -> Result<rustls::ClientCertVerified, rustls::TLSError> {
+            Ok(rustls::ClientCertVerified::assertion())
+        }
+    }
+
+    #[test_async]
+    async fn test_client_signing_key() -> Result<(), IoError> {
+        let mut config = rustls::ServerConfig::new(Arc::new(AnyClient));
+        config
+            .set_single_cert(
+                load_certs("certs/certs/server.crt")?,
+                load_keys("certs/certs/server.key")?.remove(0),
+            )
+            .expect("server cert");
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let signatures = Arc::new(AtomicUsize::new(0));
+        let key = rustls::sign::any_supported_type(&load_keys("certs/certs/client.key")?[0])
+            .expect("key");
+        let connector = ConnectorBuilder::new()
+            .danger_accept_invalid_certs()
+            .client_signing_key(
+                load_certs("certs/certs/client.crt")?,
+                Box::new(CountingKey(key, signatures.clone())),
+            )
+            .build();
+
+        let (client_stream, server_stream) = crate::net::duplex(16 * 1024);
+        let server_ft = async {
+            let mut tls_stream = acceptor.accept(server_stream).await?;
+            tls_stream.write_all(b"ping").await?;
+            tls_stream.flush().await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client_ft = async {
+            let mut tls_stream = connector.connect("localhost", client_stream).await?;
+            let mut buf = [0; 4];
+            tls_stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(()) as Result<(), IoError>
+        };
+        let (server_result, client_result) = zip(server_ft, client_ft).await;
+        client_result?;
+        server_result?;
+        assert_eq!(signatures.load(Ordering::SeqCst), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn test_connector_from_env() -> Result<(), IoError> {
+        std::env::set_var("TLS_FROM_ENV_TEST_CA_CERT", CA_PATH);
+        std::env::set_var("TLS_FROM_ENV_TEST_CLIENT_CERT", "certs/certs/client.crt");
+        std::env::set_var("TLS_FROM_ENV_TEST_CLIENT_KEY", "certs/certs/client.key");
+        let builder = ConnectorBuilder::from_env("TLS_FROM_ENV_TEST")?;
+        let monitor = builder.expiry_monitor(time::Duration::from_secs(1))?;
+        assert_eq!(monitor.check().len(), 2);
+
+        std::env::set_var("TLS_FROM_ENV_TEST_INSECURE", "maybe");
+        assert!(ConnectorBuilder::from_env("TLS_FROM_ENV_TEST").is_err());
+        Ok(())
+    }
+
+    #[cfg(feature = "config")]
+    #[test_async]
+    async fn test_connector_config() -> Result<(), IoError> {
+        use std::convert::TryFrom;
+
+        use crate::net::ConnectorConfig;
+
+        use super::AllDomainConnector;
+
+        let config: ConnectorConfig = serde_json::from_str(
+            r#"{
+                "transport": "tls",
+                "tls": {
+                    "domain": "localhost",
+                    "ca_cert": { "path": "certs/certs/ca.crt" },
+                    "insecure": true
+                },
+                "connect_timeout_ms": 1000
+            }"#,
+        )
+        .expect("parse");
+        let connector = AllDomainConnector::try_from(config)?;
+        assert!(matches!(&connector, AllDomainConnector::TlsDomain(tls) if tls.is_insecure()));
+        // insecure connector is refused before connecting
+        let err = connector
+            .connect("127.0.0.1:8901")
+            .await
+            .err()
+            .expect("insecure");
+        assert!(matches!(err.inner(), ConnectorError::InsecureNotAllowed));
+        let connector = connector.allow_insecure();
+
+        let addr = "127.0.0.1:8901".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")?
+            .build();
+        let server_ft = async {
+            let stream = listener.incoming().next().await.expect("stream")?;
+            let mut tls_stream = acceptor.accept(stream).await?;
+            tls_stream.write_all(b"ping").await?;
+            tls_stream.flush().await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client_ft = async {
+            let (mut stream, _) = connector.connect("127.0.0.1:8901").await?;
+            assert!(stream.is_tls());
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(()) as Result<(), IoError>
+        };
+        let (server_result, client_result) = zip(server_ft, client_ft).await;
+        client_result?;
+        server_result?;
+
+        // without domain, host of address is server name
+        let anonymous: ConnectorConfig =
+            serde_json::from_str(r#"{"transport": "tls", "tls": {"insecure": true}}"#)
+                .expect("parse");
+        let connector = AllDomainConnector::try_from(anonymous)?.allow_insecure();
+        assert!(matches!(&connector, AllDomainConnector::TlsAnonymous(_)));
+        let addr = "127.0.0.1:8917".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let acceptor = AcceptorBuilder::new_no_client_authentication()
+            .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")?
+            .build();
+        let server_ft = async {
+            let stream = listener.incoming().next().await.expect("stream")?;
+            let mut tls_stream = acceptor.accept(stream).await?;
+            tls_stream.write_all(b"ping").await?;
+            tls_stream.flush().await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client_ft = async {
+            let (mut stream, _) = connector.connect("localhost:8917").await?;
+            assert!(stream.is_tls());
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(()) as Result<(), IoError>
+        };
+        let (server_result, client_result) = zip(server_ft, client_ft).await;
+        client_result?;
+        server_result?;
+
+        let unix: ConnectorConfig =
+            serde_json::from_str(r#"{"transport": "unix"}"#).expect("parse");
+        let connector = AllDomainConnector::try_from(unix)?;
+        assert!(matches!(&connector, AllDomainConnector::Unix(_)));
+        let path =
+            std::env::temp_dir().join(format!("fluvio-future-config-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let listener = crate::net::unix::UnixListener::bind(&path)?;
+        let server_ft = async {
+            let mut stream = listener.incoming().next().await.expect("stream")?;
+            stream.write_all(b"ping").await?;
+            Ok(()) as Result<(), IoError>
+        };
+        let client_ft = async {
+            let (mut stream, _) = connector.connect(path.to_str().expect("path")).await?;
+            assert_eq!(stream.transport_kind(), TransportKind::Unix);
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+            Ok(()) as Result<(), IoError>
+        };
+        let (server_result, client_result) = zip(server_ft, client_ft).await;
+        std::fs::remove_file(&path)?;
+        client_result?;
+        server_result?;
+
+        let partial: ConnectorConfig = serde_json::from_str(
+            r#"{"transport": "tls", "tls": {"client_cert": {"pem": "cert"}}}"#,
+        )
+        .expect("parse");
+        assert!(AllDomainConnector::try_from(partial).is_err());
+
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_dual_mode_acceptor() -> Result<(), IoError> {
+        use crate::net::TcpDomainAcceptor;
+
+        use super::DualModeAcceptor;
+
+        let addr = "127.0.0.1:8905".parse::<SocketAddr>().expect("parse");
+        let listener = TcpListener::bind(&addr).await?;
+        let acceptor = DualModeAcceptor::new(
+            AcceptorBuilder::new_no_client_authentication()
+                .load_server_certs("certs/certs/server.crt", "certs/certs/server.key")?
+                .build(),
+        );
+        let connector = ConnectorBuilder::new()
+            .danger_accept_invalid_certs()
+            .build();
+
+        let server_ft = async {
+            for expect_tls in [false, true] {
+                let stream = listener.incoming().next().await.expect("stream")?;
+                let mut stream = acceptor.accept(stream).await?;
+                assert_eq!(stream.is_tls(), expect_tls);
+                let mut buf = [0; 4];
+                stream.read_exact(&mut buf).await?;
+                stream.write_all(&buf).await?;
+                stream.flush().await?;
+            }
+            Ok(()) as Result<(), IoError>
+        };
+        let client_ft = async {
+            let mut plain = TcpStream::connect(&addr).await?;
+            plain.write_all(b"ping").await?;
+            let mut buf = [0; 4];
+            plain.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"ping");
+
+            let tcp = TcpStream::connect(&addr).await?;
+            let mut tls = connector.connect("localhost", tcp).await?;
+            tls.write_all(b"pong").await?;
+            tls.flush().await?;
+            tls.read_exact(&mut buf).await?;
+            assert_eq!(&buf, b"pong");
+            Ok(()) as Result<(), IoError>
+        };
+        let (server_result, client_result) = zip(server_ft, client_ft).await;
+        client_result?;
+        server_result?;
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_insecure_refused() -> Result<(), IoError> {
+        use crate::net::ConnectorError;
+        use crate::net::TcpDomainConnector;
+
+        use super::AllDomainConnector;
+        use super::TlsAnonymousConnector;
+        use super::TlsDomainConnector;
+
+        let builder = ConnectorBuilder::new().danger_accept_invalid_certs();
+        assert!(builder.is_insecure());
+        let connector = AllDomainConnector::new_tls_domain(builder.build_domain("localhost"));
+        let err = connector
+            .connect("127.0.0.1:1")
+            .await
+            .err()
+            .expect("insecure");
+        assert!(matches!(err.inner(), ConnectorError::InsecureNotAllowed));
+        // allowed connector gets to connect, which fails since nothing listens
+        let err = connector
+            .allow_insecure()
+            .connect("127.0.0.1:1")
+            .await
+            .err()
+            .expect("connect");
+        assert!(!matches!(err.inner(), ConnectorError::InsecureNotAllowed));
+
+        // tag is carried by connector, so connectors made from it directly are refused too
+        let connector = ConnectorBuilder::new()
+            .danger_accept_invalid_certs()
+            .build();
+        assert!(connector.is_insecure());
+        let connectors = [
+            AllDomainConnector::new_tls_domain(TlsDomainConnector::new(
+                connector,
+                "localhost".to_owned(),
+            )),
+            AllDomainConnector::new_tls_anonymous(TlsAnonymousConnector::from(
+                ConnectorBuilder::new()
+                    .danger_accept_invalid_certs()
+                    .build(),
+            )),
+        ];
+        for connector in connectors.iter() {
+            let err = connector
+                .connect("127.0.0.1:1")
+                .await
+                .err()
+                .expect("insecure");
+            assert!(matches!(err.inner(), ConnectorError::InsecureNotAllowed));
+        }
+        Ok(())
+    }
 }
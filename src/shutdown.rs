@@ -0,0 +1,307 @@
+//! graceful shutdown path for services: stop accepting, signal running tasks and wait for
+//! tracked resources until deadline, reporting those which didn't stop in time.
+//!
+//! accept loops watch `Manager::accept_token`, tasks watch `Manager::token` and hold
+//! `Tracked` from `Manager::track` while they run
+use std::collections::BTreeMap;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+use futures_lite::future::or;
+use log::{debug, warn};
+
+use crate::sync::{WakerKey, WakerSet};
+use crate::timer::sleep;
+
+struct TokenState {
+    cancelled: bool,
+    waiters: WakerSet,
+}
+
+/// cancellation shared by clones, cancelling wakes every task waiting on it
+#[derive(Clone)]
+pub struct CancellationToken {
+    state: Arc<Mutex<TokenState>>,
+}
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// registration of waiting task, removed if it stops waiting
+struct Waiter<'a, S> {
+    state: &'a Mutex<S>,
+    waiters: fn(&mut S) -> &mut WakerSet,
+    key: Option<WakerKey>,
+}
+
+impl<S> Drop for Waiter<'_, S> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            (self.waiters)(&mut self.state.lock().unwrap()).remove(key);
+        }
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TokenState {
+                cancelled: false,
+                waiters: WakerSet::new(),
+            })),
+        }
+    }
+
+    pub fn cancel(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.cancelled = true;
+        state.waiters.wake_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.state.lock().unwrap().cancelled
+    }
+
+    /// wait until token is cancelled
+    pub async fn cancelled(&self) {
+        let mut waiter = Waiter {
+            state: &self.state,
+            waiters: |state: &mut TokenState| &mut state.waiters,
+            key: None,
+        };
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if state.cancelled {
+                waiter.key = None;
+                Poll::Ready(())
+            } else {
+                state
+                    .waiters
+                    .register_or_update(&mut waiter.key, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+struct TrackerState {
+    next_id: u64,
+    active: BTreeMap<u64, String>,
+    waiters: WakerSet,
+}
+
+/// named resources which are still running, such as connections or background tasks
+#[derive(Clone)]
+pub struct Tracker {
+    state: Arc<Mutex<TrackerState>>,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Tracker {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(TrackerState {
+                next_id: 0,
+                active: BTreeMap::new(),
+                waiters: WakerSet::new(),
+            })),
+        }
+    }
+
+    /// resource is tracked until returned guard is dropped
+    pub fn track(&self, name: impl Into<String>) -> Tracked {
+        let mut state = self.state.lock().unwrap();
+        let id = state.next_id;
+        state.next_id += 1;
+        state.active.insert(id, name.into());
+        Tracked {
+            state: self.state.clone(),
+            id,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().active.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// names of resources still tracked, in order they were tracked
+    pub fn active(&self) -> Vec<String> {
+        self.state
+            .lock()
+            .unwrap()
+            .active
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    /// wait until no resources are tracked
+    pub async fn wait(&self) {
+        let mut waiter = Waiter {
+            state: &self.state,
+            waiters: |state: &mut TrackerState| &mut state.waiters,
+            key: None,
+        };
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if state.active.is_empty() {
+                waiter.key = None;
+                Poll::Ready(())
+            } else {
+                state
+                    .waiters
+                    .register_or_update(&mut waiter.key, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+/// tracked resource, which stops being tracked when dropped
+pub struct Tracked {
+    state: Arc<Mutex<TrackerState>>,
+    id: u64,
+}
+
+impl Drop for Tracked {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.active.remove(&self.id);
+        if state.active.is_empty() {
+            state.waiters.wake_all();
+        }
+    }
+}
+
+/// outcome of `Manager::shutdown`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ShutdownReport {
+    pub elapsed: Duration,
+    /// resources which were still running at deadline
+    pub pending: Vec<String>,
+}
+
+impl ShutdownReport {
+    /// every resource stopped before deadline
+    pub fn is_clean(&self) -> bool {
+        self.pending.is_empty()
+    }
+}
+
+/// shutdown of service: token for accept loops, token for running tasks and tracker
+/// of resources to wait for
+#[derive(Default)]
+pub struct Manager {
+    accepting: CancellationToken,
+    running: CancellationToken,
+    tracker: Tracker,
+}
+
+impl Manager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// cancelled first, accept loops should stop taking new connections
+    pub fn accept_token(&self) -> CancellationToken {
+        self.accepting.clone()
+    }
+
+    /// cancelled once accepts stop, running tasks should finish their work
+    pub fn token(&self) -> CancellationToken {
+        self.running.clone()
+    }
+
+    pub fn tracker(&self) -> &Tracker {
+        &self.tracker
+    }
+
+    pub fn track(&self, name: impl Into<String>) -> Tracked {
+        self.tracker.track(name)
+    }
+
+    pub fn is_shutting_down(&self) -> bool {
+        self.accepting.is_cancelled()
+    }
+
+    /// stop accepting, signal tasks and wait up to `deadline` for tracked resources
+    pub async fn shutdown(&self, deadline: Duration) -> ShutdownReport {
+        let start = Instant::now();
+        debug!("shutdown: stopping accepts");
+        self.accepting.cancel();
+        debug!(
+            "shutdown: signaling {} tracked resources",
+            self.tracker.len()
+        );
+        self.running.cancel();
+        or(self.tracker.wait(), sleep(deadline)).await;
+        let report = ShutdownReport {
+            elapsed: start.elapsed(),
+            pending: self.tracker.active(),
+        };
+        if report.is_clean() {
+            debug!("shutdown: completed in {:?}", report.elapsed);
+        } else {
+            warn!(
+                "shutdown: deadline {:?} passed, still running: {:?}",
+                deadline, report.pending
+            );
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::future::zip;
+
+    use crate::test_async;
+
+    use super::Manager;
+
+    #[test_async]
+    async fn test_shutdown() -> Result<(), IoError> {
+        let manager = Manager::new();
+        let worker = manager.track("worker");
+        let stuck = manager.track("stuck");
+        let token = manager.token();
+        let accept_token = manager.accept_token();
+
+        // worker stops once signaled, stuck one holds on past deadline
+        let (report, ()) = zip(manager.shutdown(Duration::from_millis(50)), async move {
+            token.cancelled().await;
+            drop(worker);
+        })
+        .await;
+        assert!(accept_token.is_cancelled());
+        assert!(manager.is_shutting_down());
+        assert_eq!(report.pending, vec!["stuck".to_owned()]);
+        assert!(!report.is_clean());
+
+        drop(stuck);
+        let report = manager.shutdown(Duration::from_secs(5)).await;
+        assert!(report.is_clean());
+        assert!(report.elapsed < Duration::from_secs(5));
+        Ok(())
+    }
+}
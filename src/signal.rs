@@ -0,0 +1,128 @@
+//! unix signals as streams on crate's reactor, for graceful shutdown on `SIGTERM` or reload on
+//! `SIGHUP`, such as reloading certificates.
+//!
+//! signal handler only writes to pipe watched by reactor, so any executor can wait for signals
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Write;
+use std::os::unix::net::UnixStream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use async_io::Async;
+use futures_lite::{AsyncRead, Stream, StreamExt};
+use signal_hook_registry::SigId;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignalKind {
+    /// `SIGINT`, sent by ctrl-c
+    Interrupt,
+    /// `SIGTERM`, sent by service managers to stop process
+    Terminate,
+    /// `SIGHUP`, commonly used to reload configuration
+    Hangup,
+    /// other signal by number
+    Raw(i32),
+}
+
+impl SignalKind {
+    pub fn as_raw(&self) -> i32 {
+        match self {
+            Self::Interrupt => libc::SIGINT,
+            Self::Terminate => libc::SIGTERM,
+            Self::Hangup => libc::SIGHUP,
+            Self::Raw(signal) => *signal,
+        }
+    }
+}
+
+/// deliveries of signal, those arriving while stream isn't polled are coalesced.
+/// handler is removed when dropped
+pub struct Signals {
+    kind: SignalKind,
+    receiver: Async<UnixStream>,
+    id: SigId,
+}
+
+impl Stream for Signals {
+    type Item = SignalKind;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        let mut buf = [0; 64];
+        match Pin::new(&mut this.receiver).poll_read(cx, &mut buf) {
+            Poll::Ready(Ok(0)) | Poll::Ready(Err(_)) => Poll::Ready(None),
+            Poll::Ready(Ok(_)) => Poll::Ready(Some(this.kind)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl Drop for Signals {
+    fn drop(&mut self) {
+        signal_hook_registry::unregister(self.id);
+    }
+}
+
+/// signals reserved for system, registering handler for them panics
+const FORBIDDEN: [i32; 5] = [
+    libc::SIGKILL,
+    libc::SIGSTOP,
+    libc::SIGILL,
+    libc::SIGFPE,
+    libc::SIGSEGV,
+];
+
+/// stream of deliveries of `kind`. fails for signals which can't be handled, such as `SIGKILL`
+pub fn on(kind: SignalKind) -> Result<Signals, IoError> {
+    let signal = kind.as_raw();
+    if FORBIDDEN.contains(&signal) {
+        return Err(IoError::new(
+            ErrorKind::InvalidInput,
+            format!("signal {} can't be handled", signal),
+        ));
+    }
+    let (receiver, sender) = UnixStream::pair()?;
+    sender.set_nonblocking(true)?;
+    let receiver = Async::new(receiver)?;
+    // write is async signal safe, full pipe means delivery is already pending
+    let id = unsafe {
+        signal_hook_registry::register(signal, move || {
+            let _ = (&sender).write(&[1]);
+        })
+    }?;
+    Ok(Signals { kind, receiver, id })
+}
+
+/// wait for ctrl-c
+pub async fn ctrl_c() -> Result<(), IoError> {
+    let mut signals = on(SignalKind::Interrupt)?;
+    signals.next().await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+
+    use futures_lite::StreamExt;
+
+    use crate::test_async;
+
+    use super::on;
+    use super::SignalKind;
+
+    #[test_async]
+    async fn test_signal() -> Result<(), IoError> {
+        assert!(on(SignalKind::Raw(libc::SIGKILL)).is_err());
+
+        let mut hangups = on(SignalKind::Hangup)?;
+        // coalesced into one delivery
+        for _ in 0..3 {
+            assert_eq!(unsafe { libc::raise(libc::SIGHUP) }, 0);
+        }
+        assert_eq!(hangups.next().await, Some(SignalKind::Hangup));
+        Ok(())
+    }
+}
@@ -0,0 +1,287 @@
+//! `Sink` over `AsyncWrite` with bounded in flight bytes.
+//!
+//! items are queued until written to stream. once queued bytes reach budget, `poll_ready` drives
+//! writes and returns pending until stream catches up, so producer is slowed down instead of
+//! buffering without limit in front of slow stream.
+//!
+//! small items are copied together into buffers from `BufPool`, so they are written with few
+//! writes and without allocation for each batch
+use std::collections::VecDeque;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use bytes::Buf;
+use bytes::Bytes;
+use futures_lite::AsyncWrite;
+use futures_sink::Sink;
+use log::trace;
+
+use crate::buf::BufPool;
+use crate::buf::PooledBuf;
+
+/// default limit of bytes queued but not written
+pub const DEFAULT_MAX_IN_FLIGHT: usize = 1024 * 1024;
+
+enum Queued {
+    Item(Bytes),
+    /// small items copied together, `written` bytes of it are already written
+    Batch {
+        buf: PooledBuf,
+        written: usize,
+    },
+}
+
+impl Queued {
+    fn remaining(&self) -> &[u8] {
+        match self {
+            Self::Item(item) => item,
+            Self::Batch { buf, written } => &buf[*written..],
+        }
+    }
+
+    fn advance(&mut self, len: usize) {
+        match self {
+            Self::Item(item) => item.advance(len),
+            Self::Batch { written, .. } => *written += len,
+        }
+    }
+}
+
+pub struct StreamSink<S> {
+    stream: S,
+    queue: VecDeque<Queued>,
+    in_flight: usize,
+    max_in_flight: usize,
+    /// items smaller than this are batched
+    batch_size: usize,
+}
+
+impl<S> StreamSink<S> {
+    pub fn new(stream: S) -> Self {
+        Self::with_max_in_flight(stream, DEFAULT_MAX_IN_FLIGHT)
+    }
+
+    pub fn with_max_in_flight(stream: S, max_in_flight: usize) -> Self {
+        Self {
+            stream,
+            queue: VecDeque::new(),
+            in_flight: 0,
+            max_in_flight: max_in_flight.max(1),
+            batch_size: crate::Runtime::current().write_buffer_size(),
+        }
+    }
+
+    /// bytes queued but not yet written to stream
+    pub fn in_flight(&self) -> usize {
+        self.in_flight
+    }
+
+    pub fn max_in_flight(&self) -> usize {
+        self.max_in_flight
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.stream
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.stream
+    }
+
+    /// return inner stream, unwritten bytes are dropped
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S> StreamSink<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    /// write queued items until at most `target` bytes remain
+    fn poll_write_until(
+        &mut self,
+        cx: &mut Context<'_>,
+        target: usize,
+    ) -> Poll<Result<(), IoError>> {
+        while self.in_flight > target {
+            futures_lite::ready!(crate::budget::poll_budget(cx));
+            let front = match self.queue.front_mut() {
+                Some(front) => front,
+                None => break,
+            };
+            let len =
+                futures_lite::ready!(Pin::new(&mut self.stream).poll_write(cx, front.remaining()))?;
+            if len == 0 {
+                return Poll::Ready(Err(IoError::from(ErrorKind::WriteZero)));
+            }
+            trace!("sink written: {}, in flight: {}", len, self.in_flight);
+            front.advance(len);
+            if front.remaining().is_empty() {
+                self.queue.pop_front();
+            }
+            self.in_flight -= len;
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<S> Sink<Bytes> for StreamSink<S>
+where
+    S: AsyncWrite + Unpin,
+{
+    type Error = IoError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        let target = this.max_in_flight - 1;
+        this.poll_write_until(cx, target)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Bytes) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        if item.is_empty() {
+            return Ok(());
+        }
+        this.in_flight += item.len();
+        if item.len() >= this.batch_size {
+            this.queue.push_back(Queued::Item(item));
+            return Ok(());
+        }
+        match this.queue.back_mut() {
+            Some(Queued::Batch { buf, .. }) if buf.len() + item.len() <= buf.capacity() => {
+                buf.extend_from_slice(&item);
+            }
+            _ => {
+                let mut buf = BufPool::global().get(this.batch_size);
+                buf.extend_from_slice(&item);
+                this.queue.push_back(Queued::Batch { buf, written: 0 });
+            }
+        }
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        futures_lite::ready!(this.poll_write_until(cx, 0))?;
+        Pin::new(&mut this.stream).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        futures_lite::ready!(this.poll_write_until(cx, 0))?;
+        Pin::new(&mut this.stream).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::pin::Pin;
+    use std::task::{Context, Poll};
+
+    use bytes::Bytes;
+    use futures_lite::AsyncWrite;
+    use futures_util::sink::SinkExt;
+    use futures_util::task::noop_waker_ref;
+
+    use crate::test_async;
+
+    use super::Sink;
+    use super::StreamSink;
+
+    /// writer which accepts up to `capacity` bytes until drained
+    #[derive(Default)]
+    struct SlowWriter {
+        written: Vec<u8>,
+        capacity: usize,
+        writes: usize,
+    }
+
+    impl AsyncWrite for SlowWriter {
+        fn poll_write(
+            self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &[u8],
+        ) -> Poll<Result<usize, IoError>> {
+            let this = self.get_mut();
+            if this.capacity == 0 {
+                return Poll::Pending;
+            }
+            let len = buf.len().min(this.capacity);
+            this.capacity -= len;
+            this.writes += 1;
+            this.written.extend_from_slice(&buf[..len]);
+            Poll::Ready(Ok(len))
+        }
+
+        fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            Poll::Ready(Ok(()))
+        }
+
+        fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[test]
+    fn test_sink_backpressure() {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut sink = StreamSink::with_max_in_flight(SlowWriter::default(), 10);
+
+        assert!(Pin::new(&mut sink).poll_ready(&mut cx).is_ready());
+        Pin::new(&mut sink)
+            .start_send(Bytes::from_static(b"0123456789"))
+            .expect("send");
+        assert_eq!(sink.in_flight(), 10);
+
+        // stream is not taking anything, budget is used up
+        assert!(Pin::new(&mut sink).poll_ready(&mut cx).is_pending());
+
+        sink.get_mut().capacity = 4;
+        assert!(Pin::new(&mut sink).poll_ready(&mut cx).is_ready());
+        assert_eq!(sink.in_flight(), 6);
+        assert_eq!(sink.get_ref().written, b"0123");
+
+        sink.get_mut().capacity = 100;
+        assert!(Pin::new(&mut sink).poll_flush(&mut cx).is_ready());
+        assert_eq!(sink.in_flight(), 0);
+        assert_eq!(sink.get_ref().written, b"0123456789");
+    }
+
+    #[test]
+    fn test_sink_batches_small_items() {
+        let mut cx = Context::from_waker(noop_waker_ref());
+        let mut sink = StreamSink::new(SlowWriter::default());
+        for i in 0..100u8 {
+            assert!(Pin::new(&mut sink).poll_ready(&mut cx).is_ready());
+            Pin::new(&mut sink)
+                .start_send(Bytes::from(vec![i; 10]))
+                .expect("send");
+        }
+        assert_eq!(sink.in_flight(), 1000);
+
+        sink.get_mut().capacity = 10000;
+        assert!(Pin::new(&mut sink).poll_flush(&mut cx).is_ready());
+        assert_eq!(sink.in_flight(), 0);
+        assert_eq!(sink.get_ref().writes, 1);
+        assert_eq!(sink.get_ref().written.len(), 1000);
+        assert_eq!(sink.get_ref().written[999], 99);
+    }
+
+    #[test_async]
+    async fn test_sink_send_all() -> Result<(), IoError> {
+        let mut sink = StreamSink::with_max_in_flight(vec![], 3);
+        for i in 0..10u8 {
+            sink.send(Bytes::from(vec![i; 2])).await?;
+        }
+        sink.close().await?;
+        let written = sink.into_inner();
+        assert_eq!(written.len(), 20);
+        assert_eq!(written[19], 9);
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+
+use super::WakerKey;
+use super::WakerSet;
+
+struct PhaseState {
+    parties: usize,
+    arrived: usize,
+    phase: u64,
+    waiters: WakerSet,
+}
+
+impl PhaseState {
+    fn new(parties: usize) -> Self {
+        Self {
+            parties,
+            arrived: 0,
+            phase: 0,
+            waiters: WakerSet::new(),
+        }
+    }
+
+    /// start next phase once every party arrived
+    fn advance_if_complete(&mut self) -> bool {
+        if self.parties > 0 && self.arrived >= self.parties {
+            self.arrived = 0;
+            self.phase += 1;
+            self.waiters.wake_all();
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// arrival at phase, withdrawn if waiter is dropped before phase completes
+struct Arrival<'a> {
+    state: &'a Mutex<PhaseState>,
+    phase: u64,
+    key: Option<WakerKey>,
+}
+
+impl Drop for Arrival<'_> {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        if state.phase == self.phase {
+            state.arrived -= 1;
+            if let Some(key) = self.key.take() {
+                state.waiters.remove(key);
+            }
+        }
+    }
+}
+
+/// arrive and wait for phase to complete, true for party which completed it
+async fn arrive_and_wait(state: &Mutex<PhaseState>) -> (u64, bool) {
+    let mut arrival = {
+        let mut locked = state.lock().unwrap();
+        locked.arrived += 1;
+        let phase = locked.phase;
+        if locked.advance_if_complete() {
+            return (phase, true);
+        }
+        Arrival {
+            state,
+            phase,
+            key: None,
+        }
+    };
+    poll_fn(|cx| {
+        let mut locked = state.lock().unwrap();
+        if locked.phase != arrival.phase {
+            Poll::Ready((arrival.phase, false))
+        } else {
+            locked
+                .waiters
+                .register_or_update(&mut arrival.key, cx.waker());
+            Poll::Pending
+        }
+    })
+    .await
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BarrierWaitResult {
+    is_leader: bool,
+}
+
+impl BarrierWaitResult {
+    /// true for exactly one of parties at each use of barrier, which arrived last
+    pub fn is_leader(&self) -> bool {
+        self.is_leader
+    }
+}
+
+/// wait until fixed number of tasks arrive, such as subsystems which all have to be
+/// initialized before serving. barrier can be reused, task dropping its wait withdraws
+#[derive(Clone)]
+pub struct Barrier {
+    state: Arc<Mutex<PhaseState>>,
+}
+
+impl Barrier {
+    pub fn new(parties: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PhaseState::new(parties.max(1)))),
+        }
+    }
+
+    pub async fn wait(&self) -> BarrierWaitResult {
+        let (_, is_leader) = arrive_and_wait(&self.state).await;
+        BarrierWaitResult { is_leader }
+    }
+}
+
+/// barrier whose parties register and leave while it is in use. phase completes once
+/// every registered party arrived, so leaving party no longer holds others up
+#[derive(Clone)]
+pub struct Phaser {
+    state: Arc<Mutex<PhaseState>>,
+}
+
+impl Default for Phaser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Phaser {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(PhaseState::new(0))),
+        }
+    }
+
+    /// add party, which takes part from current phase
+    pub fn register(&self) -> PhaserParty {
+        self.state.lock().unwrap().parties += 1;
+        PhaserParty {
+            state: self.state.clone(),
+        }
+    }
+
+    pub fn phase(&self) -> u64 {
+        self.state.lock().unwrap().phase
+    }
+
+    pub fn parties(&self) -> usize {
+        self.state.lock().unwrap().parties
+    }
+}
+
+/// registered party of `Phaser`, dropping it deregisters
+pub struct PhaserParty {
+    state: Arc<Mutex<PhaseState>>,
+}
+
+impl PhaserParty {
+    /// wait for all parties to arrive, returns phase which completed
+    pub async fn arrive_and_wait(&self) -> u64 {
+        arrive_and_wait(&self.state).await.0
+    }
+}
+
+impl Drop for PhaserParty {
+    fn drop(&mut self) {
+        let mut state = self.state.lock().unwrap();
+        state.parties -= 1;
+        state.advance_if_complete();
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::future::{or, zip};
+    use futures_util::future::join_all;
+
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::Barrier;
+    use super::Phaser;
+
+    #[test_async]
+    async fn test_barrier() -> Result<(), IoError> {
+        let barrier = Barrier::new(3);
+        for _ in 0..2 {
+            let results = join_all((0..3).map(|_| barrier.wait())).await;
+            assert_eq!(
+                results.iter().filter(|result| result.is_leader()).count(),
+                1
+            );
+        }
+
+        // dropped waits are withdrawn, so they don't complete phase with later arrival
+        let timed_out = or(
+            async {
+                zip(barrier.wait(), barrier.wait()).await;
+                false
+            },
+            async {
+                sleep(Duration::from_millis(20)).await;
+                true
+            },
+        )
+        .await;
+        assert!(timed_out);
+        let results = join_all((0..3).map(|_| barrier.wait())).await;
+        assert_eq!(
+            results.iter().filter(|result| result.is_leader()).count(),
+            1
+        );
+
+        let phaser = Phaser::new();
+        let first = phaser.register();
+        let second = phaser.register();
+        let (a, b) = zip(first.arrive_and_wait(), second.arrive_and_wait()).await;
+        assert_eq!((a, b), (0, 0));
+        // leaving party completes phase others wait for
+        let (completed, ()) = zip(first.arrive_and_wait(), async {
+            sleep(Duration::from_millis(10)).await;
+            drop(second);
+        })
+        .await;
+        assert_eq!(completed, 1);
+        assert_eq!(phaser.phase(), 2);
+        assert_eq!(phaser.parties(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,14 @@
+//! async synchronization primitives which don't depend on executor
+mod barrier;
+mod rwlock;
+mod sharded;
+mod waker_set;
+mod work_queue;
+
+pub mod oneshot;
+
+pub use barrier::*;
+pub use rwlock::*;
+pub use sharded::*;
+pub use waker_set::*;
+pub use work_queue::*;
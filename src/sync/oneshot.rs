@@ -0,0 +1,194 @@
+//! single value channel where each side observes drop of other side, so pending
+//! response entry can be removed as soon as requester gives up
+use std::future::poll_fn;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+
+use thiserror::Error;
+
+/// sender was dropped without sending
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+#[error("oneshot sender dropped")]
+pub struct Canceled;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Error)]
+pub enum TryRecvError {
+    #[error("oneshot value not sent yet")]
+    Empty,
+    #[error("oneshot sender dropped")]
+    Canceled,
+}
+
+struct Inner<T> {
+    value: Option<T>,
+    sender_dropped: bool,
+    receiver_dropped: bool,
+    receiver_waker: Option<Waker>,
+    sender_waker: Option<Waker>,
+}
+
+pub fn channel<T>() -> (Sender<T>, Receiver<T>) {
+    let inner = Arc::new(Mutex::new(Inner {
+        value: None,
+        sender_dropped: false,
+        receiver_dropped: false,
+        receiver_waker: None,
+        sender_waker: None,
+    }));
+    (
+        Sender {
+            inner: inner.clone(),
+        },
+        Receiver { inner },
+    )
+}
+
+pub struct Sender<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+impl<T> Sender<T> {
+    /// value is given back if receiver is gone
+    pub fn send(self, value: T) -> Result<(), T> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.receiver_dropped {
+            return Err(value);
+        }
+        inner.value = Some(value);
+        if let Some(waker) = inner.receiver_waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// receiver was dropped or closed, so value would never be received
+    pub fn is_closed(&self) -> bool {
+        self.inner.lock().unwrap().receiver_dropped
+    }
+
+    pub fn poll_closed(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        let mut inner = self.inner.lock().unwrap();
+        if inner.receiver_dropped {
+            Poll::Ready(())
+        } else {
+            inner.sender_waker = Some(cx.waker().clone());
+            Poll::Pending
+        }
+    }
+
+    /// wait until receiver is dropped or closed
+    pub async fn closed(&mut self) {
+        poll_fn(|cx| self.poll_closed(cx)).await
+    }
+}
+
+impl<T> Drop for Sender<T> {
+    fn drop(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.sender_dropped = true;
+        if let Some(waker) = inner.receiver_waker.take() {
+            waker.wake();
+        }
+    }
+}
+
+/// future of value, fails with `Canceled` if sender is dropped without sending
+pub struct Receiver<T> {
+    inner: Arc<Mutex<Inner<T>>>,
+}
+
+impl<T> Receiver<T> {
+    /// refuse value, sender sees channel as closed. value already sent can still be received
+    pub fn close(&mut self) {
+        let mut inner = self.inner.lock().unwrap();
+        inner.receiver_dropped = true;
+        if let Some(waker) = inner.sender_waker.take() {
+            waker.wake();
+        }
+    }
+
+    pub fn try_recv(&mut self) -> Result<T, TryRecvError> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.value.take() {
+            Some(value) => Ok(value),
+            None if inner.sender_dropped => Err(TryRecvError::Canceled),
+            None => Err(TryRecvError::Empty),
+        }
+    }
+
+    /// sender was dropped, with or without sending
+    pub fn is_terminated(&self) -> bool {
+        self.inner.lock().unwrap().sender_dropped
+    }
+}
+
+impl<T> Future for Receiver<T> {
+    type Output = Result<T, Canceled>;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let mut inner = self.inner.lock().unwrap();
+        match inner.value.take() {
+            Some(value) => Poll::Ready(Ok(value)),
+            None if inner.sender_dropped => Poll::Ready(Err(Canceled)),
+            None => {
+                inner.receiver_waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+impl<T> Drop for Receiver<T> {
+    fn drop(&mut self) {
+        self.close();
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::future::zip;
+
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::channel;
+    use super::Canceled;
+    use super::TryRecvError;
+
+    #[test_async]
+    async fn test_oneshot() -> Result<(), IoError> {
+        let (sender, mut receiver) = channel();
+        assert_eq!(receiver.try_recv(), Err(TryRecvError::Empty));
+        let (sent, received) = zip(
+            async {
+                sleep(Duration::from_millis(10)).await;
+                sender.send(1)
+            },
+            receiver,
+        )
+        .await;
+        assert_eq!(sent, Ok(()));
+        assert_eq!(received, Ok(1));
+
+        let (sender, receiver) = channel::<u32>();
+        drop(sender);
+        assert_eq!(receiver.await, Err(Canceled));
+
+        // sender notices receiver giving up
+        let (mut sender, receiver) = channel::<u32>();
+        zip(sender.closed(), async {
+            sleep(Duration::from_millis(10)).await;
+            drop(receiver);
+        })
+        .await;
+        assert!(sender.is_closed());
+        assert_eq!(sender.send(2), Err(2));
+        Ok(())
+    }
+}
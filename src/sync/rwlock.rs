@@ -0,0 +1,471 @@
+use std::cell::UnsafeCell;
+use std::future::poll_fn;
+use std::ops::{Deref, DerefMut};
+use std::sync::Mutex;
+use std::task::{Context, Poll};
+
+use super::WakerKey;
+use super::WakerSet;
+
+/// who goes first when readers and writers are both waiting
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Fairness {
+    /// new readers wait while writer waits, so writer isn't starved by stream of readers
+    #[default]
+    WriterPriority,
+    /// readers enter whenever there is no writer, for most throughput of reads
+    ReaderPriority,
+}
+
+#[derive(Default)]
+struct State {
+    readers: usize,
+    writer: bool,
+    upgradeable: bool,
+    /// writers and upgrades waiting
+    waiting_writers: usize,
+    waiters: WakerSet,
+}
+
+/// async reader writer lock, with upgradeable read for "read mostly, occasionally swap".
+/// upgradeable read shares lock with readers but excludes other upgradeable reads,
+/// so it can become write without others changing value in between
+pub struct RwLock<T: ?Sized> {
+    fairness: Fairness,
+    state: Mutex<State>,
+    value: UnsafeCell<T>,
+}
+
+unsafe impl<T: ?Sized + Send> Send for RwLock<T> {}
+unsafe impl<T: ?Sized + Send + Sync> Sync for RwLock<T> {}
+
+impl<T> RwLock<T> {
+    pub fn new(value: T) -> Self {
+        Self::with_fairness(value, Fairness::default())
+    }
+
+    pub fn with_fairness(value: T, fairness: Fairness) -> Self {
+        Self {
+            fairness,
+            state: Mutex::new(State::default()),
+            value: UnsafeCell::new(value),
+        }
+    }
+
+    pub fn into_inner(self) -> T {
+        self.value.into_inner()
+    }
+}
+
+/// registration of waiting reader, removed if it stops waiting
+struct Waiter<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    key: Option<WakerKey>,
+}
+
+impl<T: ?Sized> Drop for Waiter<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            self.lock.state.lock().unwrap().waiters.remove(key);
+        }
+    }
+}
+
+/// waiter counted in `waiting_writers` until it acquires or is dropped
+struct WaitingWriter<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+    key: Option<WakerKey>,
+    counted: bool,
+    /// holds upgradeable read until it is upgraded
+    upgrading: bool,
+}
+
+impl<T: ?Sized> WaitingWriter<'_, T> {
+    fn count(&mut self, state: &mut State) {
+        if !self.counted {
+            state.waiting_writers += 1;
+            self.counted = true;
+        }
+    }
+
+    /// acquired, nothing left to release
+    fn acquired(&mut self, state: &mut State) {
+        if let Some(key) = self.key.take() {
+            state.waiters.remove(key);
+        }
+        if self.counted {
+            state.waiting_writers -= 1;
+            self.counted = false;
+        }
+        self.upgrading = false;
+    }
+}
+
+impl<T: ?Sized> Drop for WaitingWriter<'_, T> {
+    fn drop(&mut self) {
+        if self.counted || self.upgrading || self.key.is_some() {
+            let mut state = self.lock.state.lock().unwrap();
+            if let Some(key) = self.key.take() {
+                state.waiters.remove(key);
+            }
+            if self.counted || self.upgrading {
+                if self.counted {
+                    state.waiting_writers -= 1;
+                }
+                if self.upgrading {
+                    // upgrade was cancelled
+                    state.upgradeable = false;
+                }
+                // readers held off by this writer can enter
+                self.lock.wake_all(&mut state);
+            }
+        }
+    }
+}
+
+impl<T: ?Sized> RwLock<T> {
+    pub fn fairness(&self) -> Fairness {
+        self.fairness
+    }
+
+    pub fn get_mut(&mut self) -> &mut T {
+        self.value.get_mut()
+    }
+
+    fn wake_all(&self, state: &mut State) {
+        state.waiters.wake_all();
+    }
+
+    fn readers_may_enter(&self, state: &State) -> bool {
+        !state.writer && (self.fairness == Fairness::ReaderPriority || state.waiting_writers == 0)
+    }
+
+    fn poll_acquire(
+        &self,
+        cx: &mut Context<'_>,
+        waiter: &mut Waiter<'_, T>,
+        acquire: impl Fn(&Self, &mut State) -> bool,
+    ) -> Poll<()> {
+        let mut state = self.state.lock().unwrap();
+        if acquire(self, &mut state) {
+            if let Some(key) = waiter.key.take() {
+                state.waiters.remove(key);
+            }
+            Poll::Ready(())
+        } else {
+            state
+                .waiters
+                .register_or_update(&mut waiter.key, cx.waker());
+            Poll::Pending
+        }
+    }
+
+    fn acquire_read(&self, state: &mut State) -> bool {
+        if self.readers_may_enter(state) {
+            state.readers += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn acquire_upgradeable(&self, state: &mut State) -> bool {
+        if self.readers_may_enter(state) && !state.upgradeable {
+            state.upgradeable = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn acquire_write(&self, state: &mut State) -> bool {
+        if !state.writer && !state.upgradeable && state.readers == 0 {
+            state.writer = true;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub async fn read(&self) -> RwLockReadGuard<'_, T> {
+        let mut waiter = Waiter {
+            lock: self,
+            key: None,
+        };
+        poll_fn(|cx| self.poll_acquire(cx, &mut waiter, Self::acquire_read)).await;
+        RwLockReadGuard { lock: self }
+    }
+
+    pub fn try_read(&self) -> Option<RwLockReadGuard<'_, T>> {
+        let mut state = self.state.lock().unwrap();
+        if self.acquire_read(&mut state) {
+            Some(RwLockReadGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    pub async fn upgradeable_read(&self) -> RwLockUpgradeableReadGuard<'_, T> {
+        let mut waiter = Waiter {
+            lock: self,
+            key: None,
+        };
+        poll_fn(|cx| self.poll_acquire(cx, &mut waiter, Self::acquire_upgradeable)).await;
+        RwLockUpgradeableReadGuard { lock: self }
+    }
+
+    pub fn try_upgradeable_read(&self) -> Option<RwLockUpgradeableReadGuard<'_, T>> {
+        let mut state = self.state.lock().unwrap();
+        if self.acquire_upgradeable(&mut state) {
+            Some(RwLockUpgradeableReadGuard { lock: self })
+        } else {
+            None
+        }
+    }
+
+    pub async fn write(&self) -> RwLockWriteGuard<'_, T> {
+        let mut waiting = WaitingWriter {
+            lock: self,
+            key: None,
+            counted: false,
+            upgrading: false,
+        };
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if self.acquire_write(&mut state) {
+                waiting.acquired(&mut state);
+                Poll::Ready(())
+            } else {
+                waiting.count(&mut state);
+                state
+                    .waiters
+                    .register_or_update(&mut waiting.key, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await;
+        RwLockWriteGuard { lock: self }
+    }
+
+    pub fn try_write(&self) -> Option<RwLockWriteGuard<'_, T>> {
+        let mut state = self.state.lock().unwrap();
+        if self.acquire_write(&mut state) {
+            Some(RwLockWriteGuard { lock: self })
+        } else {
+            None
+        }
+    }
+}
+
+pub struct RwLockReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Send for RwLockReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RwLockReadGuard<'_, T> {}
+
+impl<T: ?Sized> Deref for RwLockReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockReadGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut state = self.lock.state.lock().unwrap();
+        state.readers -= 1;
+        if state.readers == 0 {
+            self.lock.wake_all(&mut state);
+        }
+    }
+}
+
+/// shares lock with readers, and can be upgraded to write once they are gone
+pub struct RwLockUpgradeableReadGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Sync> Send for RwLockUpgradeableReadGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RwLockUpgradeableReadGuard<'_, T> {}
+
+impl<'a, T: ?Sized> RwLockUpgradeableReadGuard<'a, T> {
+    /// wait for readers to leave. new readers wait meanwhile with writer priority
+    pub async fn upgrade(self) -> RwLockWriteGuard<'a, T> {
+        let lock = self.lock;
+        // upgradeable flag is handed over to waiter, so it is released if upgrade is cancelled
+        std::mem::forget(self);
+        let mut waiting = WaitingWriter {
+            lock,
+            key: None,
+            counted: false,
+            upgrading: true,
+        };
+        poll_fn(|cx| {
+            let mut state = lock.state.lock().unwrap();
+            if state.readers == 0 {
+                state.upgradeable = false;
+                state.writer = true;
+                waiting.acquired(&mut state);
+                Poll::Ready(())
+            } else {
+                waiting.count(&mut state);
+                state
+                    .waiters
+                    .register_or_update(&mut waiting.key, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await;
+        RwLockWriteGuard { lock }
+    }
+
+    /// upgrade if there are no readers now
+    pub fn try_upgrade(self) -> Result<RwLockWriteGuard<'a, T>, Self> {
+        let mut state = self.lock.state.lock().unwrap();
+        if state.readers == 0 {
+            state.upgradeable = false;
+            state.writer = true;
+            drop(state);
+            let lock = self.lock;
+            std::mem::forget(self);
+            Ok(RwLockWriteGuard { lock })
+        } else {
+            drop(state);
+            Err(self)
+        }
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockUpgradeableReadGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockUpgradeableReadGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut state = self.lock.state.lock().unwrap();
+        state.upgradeable = false;
+        self.lock.wake_all(&mut state);
+    }
+}
+
+pub struct RwLockWriteGuard<'a, T: ?Sized> {
+    lock: &'a RwLock<T>,
+}
+
+unsafe impl<T: ?Sized + Send + Sync> Send for RwLockWriteGuard<'_, T> {}
+unsafe impl<T: ?Sized + Sync> Sync for RwLockWriteGuard<'_, T> {}
+
+impl<'a, T: ?Sized> RwLockWriteGuard<'a, T> {
+    /// keep reading without letting other writer in
+    pub fn downgrade(self) -> RwLockReadGuard<'a, T> {
+        let lock = self.lock;
+        std::mem::forget(self);
+        let mut state = lock.state.lock().unwrap();
+        state.writer = false;
+        state.readers += 1;
+        lock.wake_all(&mut state);
+        RwLockReadGuard { lock }
+    }
+}
+
+impl<T: ?Sized> Deref for RwLockWriteGuard<'_, T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        unsafe { &*self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> DerefMut for RwLockWriteGuard<'_, T> {
+    fn deref_mut(&mut self) -> &mut T {
+        unsafe { &mut *self.lock.value.get() }
+    }
+}
+
+impl<T: ?Sized> Drop for RwLockWriteGuard<'_, T> {
+    fn drop(&mut self) {
+        let mut state = self.lock.state.lock().unwrap();
+        state.writer = false;
+        self.lock.wake_all(&mut state);
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::future::{poll_once, zip};
+
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::Fairness;
+    use super::RwLock;
+
+    #[test_async]
+    async fn test_rwlock() -> Result<(), IoError> {
+        let lock = RwLock::new(1);
+        let first = lock.read().await;
+        let second = lock.read().await;
+        assert!(lock.try_write().is_none());
+
+        // upgradeable read shares lock with readers, but not with other upgradeable read
+        let upgradeable = lock.upgradeable_read().await;
+        assert!(lock.try_upgradeable_read().is_none());
+        assert_eq!(*upgradeable + *first + *second, 3);
+
+        // pending upgrade holds off new readers
+        let (mut write, ()) = zip(upgradeable.upgrade(), async {
+            sleep(Duration::from_millis(10)).await;
+            assert!(lock.try_read().is_none());
+            drop(first);
+            drop(second);
+        })
+        .await;
+        *write = 2;
+        let read = write.downgrade();
+        assert_eq!(*read, 2);
+        assert!(lock.try_read().is_some());
+        drop(read);
+
+        // readers keep entering while writer waits with reader priority
+        let lock = RwLock::with_fairness(0, Fairness::ReaderPriority);
+        let reader = lock.read().await;
+        let (mut write, ()) = zip(lock.write(), async {
+            sleep(Duration::from_millis(10)).await;
+            assert!(lock.try_read().is_some());
+            drop(reader);
+        })
+        .await;
+        *write += 1;
+        drop(write);
+        assert_eq!(lock.into_inner(), 1);
+
+        // waiter registers once however often it is polled, and leaves when dropped
+        let lock = RwLock::new(0);
+        let write = lock.write().await;
+        let mut read = Box::pin(lock.read());
+        let mut upgradeable = Box::pin(lock.upgradeable_read());
+        let mut writer = Box::pin(lock.write());
+        for _ in 0..3 {
+            assert!(poll_once(&mut read).await.is_none());
+            assert!(poll_once(&mut upgradeable).await.is_none());
+            assert!(poll_once(&mut writer).await.is_none());
+        }
+        assert_eq!(lock.state.lock().unwrap().waiters.len(), 3);
+        drop(read);
+        drop(upgradeable);
+        drop(writer);
+        assert!(lock.state.lock().unwrap().waiters.is_empty());
+        drop(write);
+        Ok(())
+    }
+}
@@ -0,0 +1,190 @@
+use std::borrow::Borrow;
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::hash::{BuildHasher, Hash};
+use std::sync::Mutex;
+use std::sync::MutexGuard;
+
+/// shards by default, enough that tasks on all cores rarely contend
+const DEFAULT_SHARDS: usize = 64;
+
+/// concurrent map split in shards, each behind own lock, such as for per connection
+/// state keyed by fd or peer address. locks are never held across await, so map can be
+/// used from any task. each operation locks one shard; iteration is by snapshot,
+/// taken one shard at a time so it isn't point in time across shards
+pub struct ShardedMap<K, V, S = RandomState> {
+    hasher: S,
+    shards: Box<[Mutex<HashMap<K, V>>]>,
+}
+
+impl<K: Eq + Hash, V> Default for ShardedMap<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: Eq + Hash, V> ShardedMap<K, V> {
+    pub fn new() -> Self {
+        Self::with_shards(DEFAULT_SHARDS)
+    }
+
+    pub fn with_shards(shards: usize) -> Self {
+        Self::with_shards_and_hasher(shards, RandomState::new())
+    }
+}
+
+impl<K: Eq + Hash, V, S: BuildHasher> ShardedMap<K, V, S> {
+    pub fn with_shards_and_hasher(shards: usize, hasher: S) -> Self {
+        let shards = (0..shards.max(1))
+            .map(|_| Mutex::new(HashMap::new()))
+            .collect();
+        Self { hasher, shards }
+    }
+
+    fn shard<Q>(&self, key: &Q) -> MutexGuard<'_, HashMap<K, V>>
+    where
+        K: Borrow<Q>,
+        Q: Hash + ?Sized,
+    {
+        let index = self.hasher.hash_one(key) as usize % self.shards.len();
+        self.shards[index].lock().unwrap()
+    }
+
+    /// returns previous value
+    pub fn insert(&self, key: K, value: V) -> Option<V> {
+        let mut shard = self.shard(&key);
+        shard.insert(key, value)
+    }
+
+    pub fn remove<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).remove(key)
+    }
+
+    pub fn contains_key<Q>(&self, key: &Q) -> bool
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).contains_key(key)
+    }
+
+    pub fn get<Q>(&self, key: &Q) -> Option<V>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+        V: Clone,
+    {
+        self.shard(key).get(key).cloned()
+    }
+
+    /// run `f` on value while its shard is locked, `f` must not block
+    pub fn with<Q, R>(&self, key: &Q, f: impl FnOnce(&mut V) -> R) -> Option<R>
+    where
+        K: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shard(key).get_mut(key).map(f)
+    }
+
+    /// run `f` on value, inserting one from `default` first if key is missing
+    pub fn with_or_insert<R>(
+        &self,
+        key: K,
+        default: impl FnOnce() -> V,
+        f: impl FnOnce(&mut V) -> R,
+    ) -> R {
+        let mut shard = self.shard(&key);
+        f(shard.entry(key).or_insert_with(default))
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards
+            .iter()
+            .map(|shard| shard.lock().unwrap().len())
+            .sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards
+            .iter()
+            .all(|shard| shard.lock().unwrap().is_empty())
+    }
+
+    /// keep entries for which `f` is true
+    pub fn retain(&self, mut f: impl FnMut(&K, &mut V) -> bool) {
+        for shard in self.shards.iter() {
+            shard.lock().unwrap().retain(|key, value| f(key, value));
+        }
+    }
+
+    /// copy of entries, which can be iterated while map changes
+    pub fn snapshot(&self) -> Vec<(K, V)>
+    where
+        K: Clone,
+        V: Clone,
+    {
+        let mut entries = vec![];
+        for shard in self.shards.iter() {
+            let shard = shard.lock().unwrap();
+            entries.extend(
+                shard
+                    .iter()
+                    .map(|(key, value)| (key.clone(), value.clone())),
+            );
+        }
+        entries
+    }
+
+    pub fn clear(&self) {
+        for shard in self.shards.iter() {
+            shard.lock().unwrap().clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use std::thread;
+
+    use super::ShardedMap;
+
+    #[test]
+    fn test_sharded_map() {
+        let map: Arc<ShardedMap<SocketAddr, u64>> = Arc::new(ShardedMap::with_shards(4));
+        let threads: Vec<_> = (0..4)
+            .map(|t| {
+                let map = map.clone();
+                thread::spawn(move || {
+                    for port in 0..100u16 {
+                        let addr: SocketAddr = ([127, 0, 0, 1], port).into();
+                        map.with_or_insert(addr, || 0, |count| *count += t + 1);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().expect("thread");
+        }
+        assert_eq!(map.len(), 100);
+        let addr: SocketAddr = ([127, 0, 0, 1], 7).into();
+        assert_eq!(map.get(&addr), Some(10));
+        assert_eq!(map.with(&addr, |count| *count * 2), Some(20));
+
+        map.retain(|addr, _| addr.port() % 2 == 0);
+        let mut snapshot = map.snapshot();
+        snapshot.sort();
+        assert_eq!(snapshot.len(), 50);
+        assert_eq!(snapshot[1].0.port(), 2);
+        assert_eq!(map.remove(&snapshot[0].0), Some(10));
+        assert!(!map.contains_key(&snapshot[0].0));
+        map.clear();
+        assert!(map.is_empty());
+    }
+}
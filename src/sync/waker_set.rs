@@ -0,0 +1,152 @@
+use std::collections::BTreeMap;
+use std::task::Waker;
+
+/// registration in `WakerSet`, kept by future while it waits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct WakerKey(u64);
+
+/// wakers of futures waiting for same event, woken in order they registered.
+/// set isn't locked by itself, it is meant to be kept in state behind lock of primitive.
+///
+/// future registers once when it first returns pending, `update` its waker on later polls,
+/// and `remove` it when dropped before being woken. if `remove` returns false, future was
+/// already woken by `wake_one` and should pass the wake to next waiter, or it is lost
+#[derive(Debug, Default)]
+pub struct WakerSet {
+    next_key: u64,
+    /// ordered by key, so first entry registered first
+    wakers: BTreeMap<u64, Waker>,
+}
+
+impl WakerSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register(&mut self, waker: &Waker) -> WakerKey {
+        let key = self.next_key;
+        self.next_key += 1;
+        self.wakers.insert(key, waker.clone());
+        WakerKey(key)
+    }
+
+    /// replace waker if registration is still there, false if it was woken.
+    /// waker isn't cloned if it would wake same task
+    pub fn update(&mut self, key: WakerKey, waker: &Waker) -> bool {
+        match self.wakers.get_mut(&key.0) {
+            Some(current) => {
+                if !current.will_wake(waker) {
+                    *current = waker.clone();
+                }
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// register, or update if there is key already
+    pub fn register_or_update(&mut self, key: &mut Option<WakerKey>, waker: &Waker) {
+        match key {
+            Some(existing) if self.update(*existing, waker) => {}
+            _ => *key = Some(self.register(waker)),
+        }
+    }
+
+    /// true if registration was still there, false if it was woken
+    pub fn remove(&mut self, key: WakerKey) -> bool {
+        self.wakers.remove(&key.0).is_some()
+    }
+
+    /// wake waiter registered first, false if none
+    pub fn wake_one(&mut self) -> bool {
+        let first = self.wakers.keys().next().copied();
+        match first.and_then(|key| self.wakers.remove(&key)) {
+            Some(waker) => {
+                waker.wake();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// wake all waiters, returns how many
+    pub fn wake_all(&mut self) -> usize {
+        let wakers = std::mem::take(&mut self.wakers);
+        let count = wakers.len();
+        for waker in wakers.into_values() {
+            waker.wake();
+        }
+        count
+    }
+
+    pub fn len(&self) -> usize {
+        self.wakers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.wakers.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::sync::Mutex;
+    use std::task::{Wake, Waker};
+
+    use super::WakerSet;
+
+    /// records order in which wakers are woken
+    struct Recorder {
+        id: usize,
+        woken: Arc<Mutex<Vec<usize>>>,
+        count: AtomicUsize,
+    }
+
+    impl Wake for Recorder {
+        fn wake(self: Arc<Self>) {
+            self.count.fetch_add(1, Ordering::SeqCst);
+            self.woken.lock().unwrap().push(self.id);
+        }
+    }
+
+    #[test]
+    fn test_waker_set() {
+        let woken = Arc::new(Mutex::new(vec![]));
+        let wakers: Vec<Waker> = (0..4)
+            .map(|id| {
+                Waker::from(Arc::new(Recorder {
+                    id,
+                    woken: woken.clone(),
+                    count: AtomicUsize::new(0),
+                }))
+            })
+            .collect();
+
+        let mut set = WakerSet::new();
+        let keys: Vec<_> = wakers.iter().map(|waker| set.register(waker)).collect();
+        assert_eq!(set.len(), 4);
+        // cancelled waiter is skipped
+        assert!(set.remove(keys[1]));
+        assert!(set.wake_one());
+        assert!(set.wake_one());
+        assert_eq!(*woken.lock().unwrap(), vec![0, 2]);
+        // woken registration is gone
+        assert!(!set.update(keys[0], &wakers[0]));
+        assert!(!set.remove(keys[0]));
+
+        let mut key = Some(keys[3]);
+        set.register_or_update(&mut key, &wakers[3]);
+        assert_eq!(key, Some(keys[3]));
+        let mut key = Some(keys[0]);
+        set.register_or_update(&mut key, &wakers[0]);
+        assert_ne!(key, Some(keys[0]));
+
+        assert_eq!(set.wake_all(), 2);
+        assert!(set.is_empty());
+        assert!(!set.wake_one());
+        assert_eq!(*woken.lock().unwrap(), vec![0, 2, 3, 0]);
+    }
+}
@@ -0,0 +1,252 @@
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::poll_fn;
+use std::sync::{Arc, Mutex};
+use std::task::Poll;
+
+use super::WakerKey;
+use super::WakerSet;
+
+/// queue was closed, item is given back
+#[derive(Debug, PartialEq, Eq)]
+pub struct Closed<T>(pub T);
+
+impl<T> fmt::Display for Closed<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "work queue closed")
+    }
+}
+
+impl<T: fmt::Debug> std::error::Error for Closed<T> {}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum TryPushError<T> {
+    Full(T),
+    Closed(T),
+}
+
+struct State<T> {
+    items: VecDeque<T>,
+    closed: bool,
+    pushers: WakerSet,
+    poppers: WakerSet,
+}
+
+/// bounded queue for many producers and consumers, such as accepted connections
+/// dispatched to pool of handler tasks. waiting consumers get items in order they
+/// started waiting, and blocked producers are admitted in order too.
+/// once closed, pushes fail while consumers still drain queued items
+pub struct WorkQueue<T> {
+    capacity: usize,
+    state: Arc<Mutex<State<T>>>,
+}
+
+impl<T> Clone for WorkQueue<T> {
+    fn clone(&self) -> Self {
+        Self {
+            capacity: self.capacity,
+            state: self.state.clone(),
+        }
+    }
+}
+
+/// registration of pending push or pop, passed on to next waiter if it is dropped
+/// after being woken
+struct Waiter<'a, T> {
+    state: &'a Mutex<State<T>>,
+    key: Option<WakerKey>,
+    push: bool,
+}
+
+impl<T> Drop for Waiter<'_, T> {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            let mut state = self.state.lock().unwrap();
+            let set = if self.push {
+                &mut state.pushers
+            } else {
+                &mut state.poppers
+            };
+            if !set.remove(key) {
+                set.wake_one();
+            }
+        }
+    }
+}
+
+impl<T> WorkQueue<T> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            state: Arc::new(Mutex::new(State {
+                items: VecDeque::new(),
+                closed: false,
+                pushers: WakerSet::new(),
+                poppers: WakerSet::new(),
+            })),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// wait for room in queue
+    pub async fn push(&self, item: T) -> Result<(), Closed<T>> {
+        let mut item = Some(item);
+        let mut waiter = Waiter {
+            state: &self.state,
+            key: None,
+            push: true,
+        };
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if state.closed || state.items.len() < self.capacity {
+                if let Some(key) = waiter.key.take() {
+                    state.pushers.remove(key);
+                }
+                let item = item.take().expect("pushed once");
+                if state.closed {
+                    return Poll::Ready(Err(Closed(item)));
+                }
+                state.items.push_back(item);
+                state.poppers.wake_one();
+                Poll::Ready(Ok(()))
+            } else {
+                state
+                    .pushers
+                    .register_or_update(&mut waiter.key, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub fn try_push(&self, item: T) -> Result<(), TryPushError<T>> {
+        let mut state = self.state.lock().unwrap();
+        if state.closed {
+            Err(TryPushError::Closed(item))
+        } else if state.items.len() >= self.capacity {
+            Err(TryPushError::Full(item))
+        } else {
+            state.items.push_back(item);
+            state.poppers.wake_one();
+            Ok(())
+        }
+    }
+
+    /// wait for item, none once queue is closed and drained
+    pub async fn pop(&self) -> Option<T> {
+        let mut waiter = Waiter {
+            state: &self.state,
+            key: None,
+            push: false,
+        };
+        poll_fn(|cx| {
+            let mut state = self.state.lock().unwrap();
+            if let Some(item) = state.items.pop_front() {
+                if let Some(key) = waiter.key.take() {
+                    state.poppers.remove(key);
+                }
+                state.pushers.wake_one();
+                Poll::Ready(Some(item))
+            } else if state.closed {
+                waiter.key = None;
+                Poll::Ready(None)
+            } else {
+                state
+                    .poppers
+                    .register_or_update(&mut waiter.key, cx.waker());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+
+    pub fn try_pop(&self) -> Option<T> {
+        let mut state = self.state.lock().unwrap();
+        let item = state.items.pop_front();
+        if item.is_some() {
+            state.pushers.wake_one();
+        }
+        item
+    }
+
+    /// refuse new items, consumers get remaining ones and then none
+    pub fn close(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.closed = true;
+        state.pushers.wake_all();
+        state.poppers.wake_all();
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.state.lock().unwrap().closed
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::time::Duration;
+
+    use futures_lite::future::zip;
+    use futures_util::future::join_all;
+
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::Closed;
+    use super::TryPushError;
+    use super::WorkQueue;
+
+    #[test_async]
+    async fn test_work_queue() -> Result<(), IoError> {
+        let queue = WorkQueue::new(2);
+        queue.push(1).await.expect("push");
+        queue.push(2).await.expect("push");
+        assert_eq!(queue.try_push(3), Err(TryPushError::Full(3)));
+
+        // blocked producer is admitted once consumer makes room
+        let (pushed, popped) = zip(queue.push(3), async {
+            sleep(Duration::from_millis(10)).await;
+            queue.pop().await
+        })
+        .await;
+        assert_eq!(pushed, Ok(()));
+        assert_eq!(popped, Some(1));
+
+        // consumers share items, and drain queue after close
+        let consumers = (0..3).map(|_| {
+            let queue = queue.clone();
+            async move {
+                let mut items = vec![];
+                while let Some(item) = queue.pop().await {
+                    items.push(item);
+                }
+                items
+            }
+        });
+        let (results, ()) = zip(join_all(consumers), async {
+            for item in 4..10 {
+                queue.push(item).await.expect("push");
+            }
+            queue.close();
+        })
+        .await;
+        let mut items: Vec<i32> = results.concat();
+        items.sort_unstable();
+        assert_eq!(items, vec![2, 3, 4, 5, 6, 7, 8, 9]);
+        assert_eq!(queue.push(10).await, Err(Closed(10)));
+        Ok(())
+    }
+}
@@ -8,6 +8,13 @@ use log::trace;
 
 use crate::timer::sleep;
 
+pub use crate::budget::*;
+
+pub use executor::*;
+
+pub mod context;
+mod executor;
+
 /// run future and wait forever
 /// this is typically used in the server
 pub fn run<F>(spawn_closure: F)
@@ -34,13 +41,15 @@ where
     });
 }
 
+/// spawn on async-std, spawned task inherits `context::TaskContext` of current task.
+/// to follow executor set by `set_executor`, use `spawn_task`
 pub fn spawn<F, T>(future: F) -> JoinHandle<T>
 where
     F: Future<Output = T> + 'static + Send,
     T: Send + 'static,
 {
     trace!("spawning future");
-    task::spawn(future)
+    task::spawn(context::inherit(future))
 }
 
 #[cfg(feature = "task_unstable")]
@@ -50,7 +59,7 @@ where
     T: Send + 'static,
 {
     trace!("spawning blocking");
-    task::spawn_blocking(future)
+    task::spawn_blocking(context::inherit_blocking(future))
 }
 
 /// same as async async std block on
@@ -62,6 +71,7 @@ where
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod basic_test {
 
     use std::io::Error;
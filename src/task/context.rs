@@ -0,0 +1,166 @@
+//! values attached to task, such as request id, visible to everything task runs.
+//!
+//! context is set for future by `TaskContext::scope`, and `crate::task::spawn` captures context
+//! of spawning task so child sees same values. connect spans record request id and other values,
+//! so connect attempt can be correlated with request that triggered it
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+/// key of request id, see `TaskContext::request_id`
+pub const REQUEST_ID: &str = "request_id";
+
+thread_local! {
+    static CURRENT: RefCell<Option<Arc<TaskContext>>> = const { RefCell::new(None) };
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskContext {
+    values: BTreeMap<String, String>,
+}
+
+impl TaskContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// context of current task, if any
+    pub fn current() -> Option<Arc<TaskContext>> {
+        CURRENT.with(|current| current.borrow().clone())
+    }
+
+    /// set value, replacing previous value of key
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn with_request_id(self, request_id: impl Into<String>) -> Self {
+        self.with(REQUEST_ID, request_id)
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
+
+    pub fn request_id(&self) -> Option<&str> {
+        self.get(REQUEST_ID)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.values
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// run `future` with this context, replacing context of enclosing scope.
+    /// to add values, start from `TaskContext::current()`
+    pub fn scope<F: Future>(self, future: F) -> Scoped<F> {
+        Scoped {
+            context: Some(Arc::new(self)),
+            inner: Box::pin(future),
+        }
+    }
+}
+
+impl fmt::Display for TaskContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (key, value)) in self.iter().enumerate() {
+            if index > 0 {
+                write!(f, ",")?;
+            }
+            write!(f, "{}={}", key, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// run `future` with context of current task, used when spawning
+pub(crate) fn inherit<F: Future>(future: F) -> Scoped<F> {
+    Scoped {
+        context: TaskContext::current(),
+        inner: Box::pin(future),
+    }
+}
+
+/// run `f` with context of current task, on other thread
+#[cfg(feature = "task_unstable")]
+pub(crate) fn inherit_blocking<F, T>(f: F) -> impl FnOnce() -> T
+where
+    F: FnOnce() -> T,
+{
+    let context = TaskContext::current();
+    move || {
+        let _restore = Restore(CURRENT.with(|current| current.replace(context)));
+        f()
+    }
+}
+
+/// future running with context set, see `TaskContext::scope`
+pub struct Scoped<F> {
+    context: Option<Arc<TaskContext>>,
+    inner: Pin<Box<F>>,
+}
+
+/// restores context of enclosing scope, even if polled future panics
+struct Restore(Option<Arc<TaskContext>>);
+
+impl Drop for Restore {
+    fn drop(&mut self) {
+        let previous = self.0.take();
+        CURRENT.with(|current| *current.borrow_mut() = previous);
+    }
+}
+
+impl<F: Future> Future for Scoped<F> {
+    type Output = F::Output;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+        let this = self.get_mut();
+        let context = this.context.clone();
+        let _restore = Restore(CURRENT.with(|current| current.replace(context)));
+        this.inner.as_mut().poll(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error;
+
+    use crate::task::spawn;
+    use crate::test_async;
+
+    use super::TaskContext;
+
+    #[test_async]
+    async fn test_context_spawn() -> Result<(), Error> {
+        assert!(TaskContext::current().is_none());
+        let context = TaskContext::new()
+            .with_request_id("req-1")
+            .with("client", "c1");
+        assert_eq!(context.to_string(), "client=c1,request_id=req-1");
+
+        let child = context
+            .scope(async {
+                assert_eq!(
+                    TaskContext::current().expect("context").request_id(),
+                    Some("req-1")
+                );
+                spawn(async {
+                    TaskContext::current()
+                        .and_then(|context| context.get("client").map(String::from))
+                })
+                .await
+            })
+            .await;
+        assert_eq!(child.as_deref(), Some("c1"));
+        // restored once scope is left
+        assert!(TaskContext::current().is_none());
+        Ok(())
+    }
+}
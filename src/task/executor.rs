@@ -0,0 +1,251 @@
+//! executor for tasks spawned by crate itself, such as accept loops and certificate checks.
+//!
+//! tasks run on async-std unless other executor is set by `set_executor`, so embedders with
+//! own runtime can keep crate's background work on it
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
+use std::task::{Context, Poll, Waker};
+
+use super::context;
+
+pub type BoxFuture = Pin<Box<dyn Future<Output = ()> + Send + 'static>>;
+
+pub trait Executor: Send + Sync {
+    /// run future to completion in background
+    fn spawn_boxed(&self, future: BoxFuture);
+}
+
+pub type SharedExecutor = Arc<dyn Executor>;
+
+/// default executor
+#[derive(Debug, Clone, Default)]
+pub struct AsyncStdExecutor {}
+
+impl Executor for AsyncStdExecutor {
+    fn spawn_boxed(&self, future: BoxFuture) {
+        // dropping handle detaches task
+        async_std::task::spawn(future);
+    }
+}
+
+static EXECUTOR: RwLock<Option<SharedExecutor>> = RwLock::new(None);
+
+/// replace executor of tasks spawned by `spawn_task`
+pub fn set_executor(executor: SharedExecutor) {
+    *EXECUTOR.write().unwrap() = Some(executor);
+}
+
+/// executor used by `spawn_task`
+pub fn executor() -> SharedExecutor {
+    match EXECUTOR.read().unwrap().as_ref() {
+        Some(executor) => executor.clone(),
+        None => Arc::new(AsyncStdExecutor::default()),
+    }
+}
+
+struct State<T> {
+    output: Option<T>,
+    finished: bool,
+    cancelled: bool,
+    /// waker of task handle
+    waiter: Option<Waker>,
+    /// waker of running future, to drop it once cancelled
+    runner: Option<Waker>,
+}
+
+type Shared<T> = Arc<Mutex<State<T>>>;
+
+fn finish<T>(shared: &Shared<T>, output: Option<T>) {
+    let waiter = {
+        let mut state = shared.lock().unwrap();
+        if output.is_some() {
+            state.output = output;
+        }
+        state.finished = true;
+        state.waiter.take()
+    };
+    if let Some(waiter) = waiter {
+        waiter.wake();
+    }
+}
+
+/// future given to executor, output is passed to task handle
+struct Runner<F: Future> {
+    future: Option<Pin<Box<F>>>,
+    shared: Shared<F::Output>,
+}
+
+impl<F: Future> Future for Runner<F> {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = self.get_mut();
+        {
+            let mut state = this.shared.lock().unwrap();
+            if state.cancelled {
+                drop(state);
+                this.future.take();
+                finish(&this.shared, None);
+                return Poll::Ready(());
+            }
+            state.runner = Some(cx.waker().clone());
+        }
+        let future = match this.future.as_mut() {
+            Some(future) => future,
+            None => return Poll::Ready(()),
+        };
+        match future.as_mut().poll(cx) {
+            Poll::Ready(output) => {
+                this.future.take();
+                finish(&this.shared, Some(output));
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<F: Future> Drop for Runner<F> {
+    /// executor may drop task without finishing it, such as on shutdown
+    fn drop(&mut self) {
+        if self.future.take().is_some() {
+            finish(&self.shared, None);
+        }
+    }
+}
+
+/// handle of task spawned by `spawn_task`, task keeps running if handle is dropped
+pub struct Task<T> {
+    shared: Shared<T>,
+}
+
+impl<T> Task<T> {
+    /// stop task without waiting for it to be dropped, such as from `Drop`
+    pub fn abort(&self) {
+        let runner = {
+            let mut state = self.shared.lock().unwrap();
+            state.cancelled = true;
+            state.runner.take()
+        };
+        if let Some(runner) = runner {
+            runner.wake();
+        }
+    }
+
+    /// stop task, output is returned if it finished before
+    pub async fn cancel(self) -> Option<T> {
+        self.abort();
+        futures_lite::future::poll_fn(|cx| {
+            let mut state = self.shared.lock().unwrap();
+            if state.finished {
+                Poll::Ready(state.output.take())
+            } else {
+                state.waiter = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        })
+        .await
+    }
+}
+
+impl<T> Future for Task<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.shared.lock().unwrap();
+        if let Some(output) = state.output.take() {
+            return Poll::Ready(output);
+        }
+        if state.finished {
+            panic!("task was dropped by executor before finishing");
+        }
+        state.waiter = Some(cx.waker().clone());
+        Poll::Pending
+    }
+}
+
+/// spawn on executor set by `set_executor`. like `spawn`, task inherits context of current task
+pub fn spawn_task<F, T>(future: F) -> Task<T>
+where
+    F: Future<Output = T> + Send + 'static,
+    T: Send + 'static,
+{
+    let shared = Arc::new(Mutex::new(State {
+        output: None,
+        finished: false,
+        cancelled: false,
+        waiter: None,
+        runner: None,
+    }));
+    executor().spawn_boxed(Box::pin(Runner {
+        future: Some(Box::pin(context::inherit(future))),
+        shared: shared.clone(),
+    }));
+    Task { shared }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::set_executor;
+    use super::spawn_task;
+    use super::AsyncStdExecutor;
+    use super::BoxFuture;
+    use super::Executor;
+
+    static SPAWNED: AtomicUsize = AtomicUsize::new(0);
+
+    struct CountingExecutor(AsyncStdExecutor);
+
+    impl Executor for CountingExecutor {
+        fn spawn_boxed(&self, future: BoxFuture) {
+            SPAWNED.fetch_add(1, Ordering::SeqCst);
+            self.0.spawn_boxed(future)
+        }
+    }
+
+    #[test_async]
+    async fn test_set_executor() -> Result<(), Error> {
+        set_executor(Arc::new(CountingExecutor(AsyncStdExecutor::default())));
+        let before = SPAWNED.load(Ordering::SeqCst);
+        assert_eq!(spawn_task(async { 1 + 1 }).await, 2);
+        assert!(SPAWNED.load(Ordering::SeqCst) > before);
+
+        let forever = spawn_task(async {
+            sleep(Duration::from_secs(3600)).await;
+        });
+        assert!(forever.cancel().await.is_none());
+
+        // aborted task is dropped by its runner
+        struct Flag(Arc<AtomicBool>);
+        impl Drop for Flag {
+            fn drop(&mut self) {
+                self.0.store(true, Ordering::SeqCst);
+            }
+        }
+        let dropped = Arc::new(AtomicBool::new(false));
+        let flag = Flag(dropped.clone());
+        let aborted = spawn_task(async move {
+            let _flag = flag;
+            sleep(Duration::from_secs(3600)).await;
+        });
+        sleep(Duration::from_millis(10)).await;
+        aborted.abort();
+        sleep(Duration::from_millis(10)).await;
+        assert!(dropped.load(Ordering::SeqCst));
+        Ok(())
+    }
+}
@@ -10,7 +10,21 @@ macro_rules! assert_async_block {
     }};
 }
 
+#[cfg(feature = "timer")]
+mod deterministic;
+#[cfg(feature = "timer")]
+pub use deterministic::*;
+#[cfg(all(unix, feature = "net", feature = "timer"))]
+mod faulty;
+#[cfg(all(unix, feature = "net", feature = "timer"))]
+pub use faulty::*;
+#[cfg(all(unix, feature = "net"))]
+mod record;
+#[cfg(all(unix, feature = "net"))]
+pub use record::*;
+
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod test {
 
     use std::io::Error;
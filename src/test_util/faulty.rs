@@ -0,0 +1,263 @@
+//! connector and stream injecting faults, for testing reconnect and error handling
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::RawFd;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures_lite::future::Future;
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use crate::net::ConnectorError;
+use crate::net::TcpDomainConnector;
+use crate::timer::sleep;
+use crate::timer::Sleeper;
+
+/// faults to inject, default is no fault
+#[derive(Debug, Clone, Default)]
+pub struct FaultConfig {
+    /// delay before connecting
+    pub connect_delay: Option<Duration>,
+    /// fail after connection is made, as if tls handshake failed
+    pub fail_handshake: bool,
+    /// delay before each read
+    pub read_delay: Option<Duration>,
+    /// return at most this many bytes per read
+    pub max_read: Option<usize>,
+    /// reset connection once this many bytes are read and written
+    pub reset_after: Option<usize>,
+}
+
+/// connector injecting faults into inner connector and its streams
+pub struct FaultyConnector<C> {
+    inner: C,
+    config: FaultConfig,
+}
+
+impl<C> FaultyConnector<C> {
+    pub fn new(inner: C, config: FaultConfig) -> Self {
+        Self { inner, config }
+    }
+
+    pub fn config_mut(&mut self) -> &mut FaultConfig {
+        &mut self.config
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for FaultyConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = FaultyStream<C::WrapperStream>;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        if let Some(delay) = self.config.connect_delay {
+            debug!("delaying connect to: {} by {:?}", domain, delay);
+            sleep(delay).await;
+        }
+        let (stream, fd) = self.inner.connect(domain).await?;
+        if self.config.fail_handshake {
+            debug!("injecting handshake failure to: {}", domain);
+            return Err(ConnectorError::tls_handshake(
+                None,
+                "injected handshake failure",
+            ));
+        }
+        Ok((FaultyStream::new(stream, self.config.clone()), fd))
+    }
+}
+
+pub struct FaultyStream<S> {
+    inner: S,
+    config: FaultConfig,
+    transferred: usize,
+    delay: Option<Sleeper>,
+}
+
+impl<S> FaultyStream<S> {
+    pub fn new(inner: S, config: FaultConfig) -> Self {
+        Self {
+            inner,
+            config,
+            transferred: 0,
+            delay: None,
+        }
+    }
+
+    pub fn get_ref(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn get_mut(&mut self) -> &mut S {
+        &mut self.inner
+    }
+
+    /// bytes read and written so far
+    pub fn transferred(&self) -> usize {
+        self.transferred
+    }
+
+    /// how many bytes can be transferred before reset, error if already reset
+    fn remaining(&self) -> Result<usize, IoError> {
+        match self.config.reset_after {
+            Some(limit) if self.transferred >= limit => {
+                debug!("injecting reset after: {} bytes", self.transferred);
+                Err(IoError::new(
+                    ErrorKind::ConnectionReset,
+                    "injected connection reset",
+                ))
+            }
+            Some(limit) => Ok(limit - self.transferred),
+            None => Ok(usize::MAX),
+        }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for FaultyStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        if let Some(read_delay) = self.config.read_delay {
+            let delay = self.delay.get_or_insert_with(|| sleep(read_delay));
+            futures_lite::ready!(Pin::new(delay).poll(cx));
+        }
+
+        let remaining = self.remaining()?;
+        let len = buf
+            .len()
+            .min(self.config.max_read.unwrap_or(usize::MAX))
+            .min(remaining);
+        let result = Pin::new(&mut self.inner).poll_read(cx, &mut buf[..len]);
+        if let Poll::Ready(result) = &result {
+            self.delay = None;
+            if let Ok(read) = result {
+                self.transferred += read;
+            }
+        }
+        result
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for FaultyStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let remaining = self.remaining()?;
+        let len = buf.len().min(remaining);
+        let result = Pin::new(&mut self.inner).poll_write(cx, &buf[..len]);
+        if let Poll::Ready(Ok(written)) = &result {
+            self.transferred += written;
+        }
+        result
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use futures_lite::future::zip;
+    use futures_lite::io::Cursor;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+    use futures_lite::StreamExt;
+
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::TcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::FaultConfig;
+    use super::FaultyConnector;
+    use super::FaultyStream;
+
+    #[test_async]
+    async fn test_faulty_stream() -> Result<(), IoError> {
+        let config = FaultConfig {
+            read_delay: Some(Duration::from_millis(10)),
+            max_read: Some(3),
+            reset_after: Some(8),
+            ..Default::default()
+        };
+        let mut stream = FaultyStream::new(Cursor::new(b"0123456789".to_vec()), config);
+
+        let start = Instant::now();
+        let mut buf = [0; 10];
+        assert_eq!(stream.read(&mut buf).await?, 3);
+        assert_eq!(stream.read(&mut buf).await?, 3);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // only 2 bytes left before reset
+        assert_eq!(stream.read(&mut buf).await?, 2);
+        let err = stream.read(&mut buf).await.expect_err("reset");
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        let err = stream.write_all(b"a").await.expect_err("reset");
+        assert_eq!(err.kind(), ErrorKind::ConnectionReset);
+        assert_eq!(stream.transferred(), 8);
+        Ok(())
+    }
+
+    const FAULTY_ADDR: &str = "127.0.0.1:8894";
+
+    #[test_async]
+    async fn test_faulty_connector() -> Result<(), IoError> {
+        let server = async {
+            let listener = TcpListener::bind(FAULTY_ADDR).await?;
+            let mut incoming = listener.incoming();
+            for _ in 0..2u16 {
+                let mut stream = incoming.next().await.expect("client should connect")?;
+                let _ = stream.write_all(b"hello").await;
+            }
+            Ok(()) as Result<(), IoError>
+        };
+
+        let client = async {
+            sleep(Duration::from_millis(100)).await;
+            let mut connector = FaultyConnector::new(
+                DefaultTcpDomainConnector::new(),
+                FaultConfig {
+                    fail_handshake: true,
+                    ..Default::default()
+                },
+            );
+            let err = connector
+                .connect(FAULTY_ADDR)
+                .await
+                .err()
+                .expect("handshake should fail");
+            assert_eq!(err.kind(), ErrorKind::ConnectionRefused);
+
+            connector.config_mut().fail_handshake = false;
+            connector.config_mut().max_read = Some(1);
+            let (mut stream, _fd) = connector.connect(FAULTY_ADDR).await?;
+            let mut buf = [0; 5];
+            assert_eq!(stream.read(&mut buf).await?, 1);
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (client_result, server_result) = zip(client, server).await;
+        client_result?;
+        server_result
+    }
+}
@@ -0,0 +1,368 @@
+//! record traffic of connections to capture file and replay it back without server.
+//!
+//! capture is sequence of records: kind (`C` connect, `R` read from peer, `W` written to peer),
+//! connection id and data, with id and length as big endian u32
+use std::collections::HashMap;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::io::Read;
+use std::io::Write;
+use std::os::unix::io::RawFd;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::AtomicU32;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::task::{Context, Poll, Waker};
+
+use async_trait::async_trait;
+use futures_lite::{AsyncRead, AsyncWrite};
+use log::debug;
+
+use crate::net::ConnectorError;
+use crate::net::TcpDomainConnector;
+
+const CONNECT: u8 = b'C';
+const READ: u8 = b'R';
+const WRITE: u8 = b'W';
+
+struct Capture {
+    writer: BufWriter<File>,
+}
+
+impl Capture {
+    fn record(&mut self, kind: u8, id: u32, data: &[u8]) -> Result<(), IoError> {
+        self.writer.write_all(&[kind])?;
+        self.writer.write_all(&id.to_be_bytes())?;
+        self.writer.write_all(&(data.len() as u32).to_be_bytes())?;
+        self.writer.write_all(data)?;
+        self.writer.flush()
+    }
+}
+
+/// connector recording all bytes exchanged by its connections to capture file
+pub struct RecordingConnector<C> {
+    inner: C,
+    capture: Arc<Mutex<Capture>>,
+    next_id: AtomicU32,
+}
+
+impl<C> RecordingConnector<C> {
+    /// record to file at `path`, existing file is truncated
+    pub fn new<P: AsRef<Path>>(inner: C, path: P) -> Result<Self, IoError> {
+        let file = File::create(path)?;
+        Ok(Self {
+            inner,
+            capture: Arc::new(Mutex::new(Capture {
+                writer: BufWriter::new(file),
+            })),
+            next_id: AtomicU32::new(0),
+        })
+    }
+}
+
+#[async_trait]
+impl<C> TcpDomainConnector for RecordingConnector<C>
+where
+    C: TcpDomainConnector + Send + Sync,
+{
+    type WrapperStream = RecordingStream<C::WrapperStream>;
+
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        let (stream, fd) = self.inner.connect(domain).await?;
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        debug!("recording connection: {} to: {}", id, domain);
+        self.capture
+            .lock()
+            .unwrap()
+            .record(CONNECT, id, domain.as_bytes())?;
+        Ok((
+            RecordingStream {
+                inner: stream,
+                id,
+                capture: self.capture.clone(),
+            },
+            fd,
+        ))
+    }
+}
+
+/// stream recording what is read and written thru it
+pub struct RecordingStream<S> {
+    inner: S,
+    id: u32,
+    capture: Arc<Mutex<Capture>>,
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for RecordingStream<S> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let len = futures_lite::ready!(Pin::new(&mut self.inner).poll_read(cx, buf))?;
+        if len > 0 {
+            self.capture
+                .lock()
+                .unwrap()
+                .record(READ, self.id, &buf[..len])?;
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for RecordingStream<S> {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let len = futures_lite::ready!(Pin::new(&mut self.inner).poll_write(cx, buf))?;
+        if len > 0 {
+            self.capture
+                .lock()
+                .unwrap()
+                .record(WRITE, self.id, &buf[..len])?;
+        }
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Pin::new(&mut self.inner).poll_close(cx)
+    }
+}
+
+struct Record {
+    kind: u8,
+    data: Vec<u8>,
+    pos: usize,
+}
+
+/// connector serving connections from capture file, in order they were recorded
+pub struct ReplayConnector {
+    connections: Mutex<VecDeque<VecDeque<Record>>>,
+}
+
+impl ReplayConnector {
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, IoError> {
+        let mut reader = BufReader::new(File::open(path)?);
+        let mut connections: Vec<VecDeque<Record>> = vec![];
+        let mut ids: HashMap<u32, usize> = HashMap::new();
+        let mut header = [0; 9];
+        loop {
+            match reader.read_exact(&mut header) {
+                Ok(()) => {}
+                Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+                Err(err) => return Err(err),
+            }
+            let kind = header[0];
+            let id = u32::from_be_bytes([header[1], header[2], header[3], header[4]]);
+            let len = u32::from_be_bytes([header[5], header[6], header[7], header[8]]);
+            let mut data = vec![0; len as usize];
+            reader.read_exact(&mut data)?;
+
+            match kind {
+                CONNECT => {
+                    ids.insert(id, connections.len());
+                    connections.push(VecDeque::new());
+                }
+                READ | WRITE => {
+                    let index = *ids.get(&id).ok_or_else(|| {
+                        IoError::new(ErrorKind::InvalidData, "record before connect")
+                    })?;
+                    connections[index].push_back(Record { kind, data, pos: 0 });
+                }
+                _ => {
+                    return Err(IoError::new(
+                        ErrorKind::InvalidData,
+                        format!("unknown record kind: {}", kind),
+                    ))
+                }
+            }
+        }
+        Ok(Self {
+            connections: Mutex::new(connections.into()),
+        })
+    }
+
+    /// connections not yet replayed
+    pub fn remaining(&self) -> usize {
+        self.connections.lock().unwrap().len()
+    }
+}
+
+#[async_trait]
+impl TcpDomainConnector for ReplayConnector {
+    type WrapperStream = ReplayStream;
+
+    /// fd is -1 since there is no socket
+    async fn connect(&self, domain: &str) -> Result<(Self::WrapperStream, RawFd), ConnectorError> {
+        let records = self
+            .connections
+            .lock()
+            .unwrap()
+            .pop_front()
+            .ok_or_else(|| {
+                IoError::new(ErrorKind::ConnectionRefused, "no recorded connection left")
+            })?;
+        debug!("replaying connection to: {}", domain);
+        Ok((
+            ReplayStream {
+                records,
+                read_waker: None,
+            },
+            -1,
+        ))
+    }
+}
+
+/// stream serving recorded reads. writes must match recorded writes,
+/// read waits while recorded write preceding it has not been made
+pub struct ReplayStream {
+    records: VecDeque<Record>,
+    read_waker: Option<Waker>,
+}
+
+impl AsyncRead for ReplayStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let this = &mut *self;
+        let record = match this.records.front_mut() {
+            None => return Poll::Ready(Ok(0)),
+            Some(record) if record.kind == WRITE => {
+                this.read_waker = Some(cx.waker().clone());
+                return Poll::Pending;
+            }
+            Some(record) => record,
+        };
+        let len = buf.len().min(record.data.len() - record.pos);
+        buf[..len].copy_from_slice(&record.data[record.pos..record.pos + len]);
+        record.pos += len;
+        if record.pos == record.data.len() {
+            this.records.pop_front();
+        }
+        Poll::Ready(Ok(len))
+    }
+}
+
+impl AsyncWrite for ReplayStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<Result<usize, IoError>> {
+        let index = match self.records.iter().position(|record| record.kind == WRITE) {
+            Some(index) => index,
+            None => {
+                return Poll::Ready(Err(IoError::new(
+                    ErrorKind::InvalidData,
+                    "write not in recording",
+                )))
+            }
+        };
+        let record = &mut self.records[index];
+        let len = buf.len().min(record.data.len() - record.pos);
+        if buf[..len] != record.data[record.pos..record.pos + len] {
+            return Poll::Ready(Err(IoError::new(
+                ErrorKind::InvalidData,
+                "write does not match recording",
+            )));
+        }
+        record.pos += len;
+        if record.pos == record.data.len() {
+            self.records.remove(index);
+            if let Some(waker) = self.read_waker.take() {
+                waker.wake();
+            }
+        }
+        Poll::Ready(Ok(len))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Result<(), IoError>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+
+    use std::env::temp_dir;
+    use std::io::Error as IoError;
+    use std::io::ErrorKind;
+    use std::time::Duration;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::AsyncWriteExt;
+    use futures_lite::StreamExt;
+
+    use crate::net::DefaultTcpDomainConnector;
+    use crate::net::TcpDomainConnector;
+    use crate::net::TcpListener;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::RecordingConnector;
+    use super::ReplayConnector;
+
+    const RECORD_ADDR: &str = "127.0.0.1:8895";
+
+    #[test_async]
+    async fn test_record_replay() -> Result<(), IoError> {
+        let capture = temp_dir().join("record_replay.capture");
+
+        let server = async {
+            let listener = TcpListener::bind(RECORD_ADDR).await?;
+            let mut incoming = listener.incoming();
+            let mut stream = incoming.next().await.expect("client should connect")?;
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            stream.write_all(b"pong").await?;
+            Ok(()) as Result<(), IoError>
+        };
+
+        let client = async {
+            sleep(Duration::from_millis(100)).await;
+            let connector = RecordingConnector::new(DefaultTcpDomainConnector::new(), &capture)?;
+            let (mut stream, _fd) = connector.connect(RECORD_ADDR).await?;
+            stream.write_all(b"ping").await?;
+            let mut buf = [0; 4];
+            stream.read_exact(&mut buf).await?;
+            Ok(()) as Result<(), IoError>
+        };
+
+        let (client_result, server_result) = zip(client, server).await;
+        client_result?;
+        server_result?;
+
+        let replay = ReplayConnector::open(&capture)?;
+        assert_eq!(replay.remaining(), 1);
+        let (mut stream, _fd) = replay.connect(RECORD_ADDR).await?;
+        stream.write_all(b"ping").await?;
+        let mut buf = [0; 4];
+        stream.read_exact(&mut buf).await?;
+        assert_eq!(&buf, b"pong");
+        assert_eq!(stream.read(&mut buf).await?, 0);
+
+        let err = stream.write_all(b"ping").await.expect_err("not recorded");
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(replay.connect(RECORD_ADDR).await.is_err());
+        Ok(())
+    }
+}
@@ -1,33 +1,72 @@
 pub use inner::*;
+#[cfg(any(test, feature = "fixture"))]
+pub use mock::*;
 
 mod inner {
 
     use std::pin::Pin;
     use std::task::{Context, Poll};
     use std::time::Duration;
+    use std::time::Instant;
 
     use async_io::Timer;
     use futures_lite::future::Future;
 
     use pin_project::pin_project;
 
-    /// same as `after` but return () to make it compatible as previous
+    /// same as `after` but return () to make it compatible as previous.
+    /// follows mock clock if it is paused on current thread
     pub fn sleep(duration: Duration) -> Sleeper {
-        Sleeper(after(duration))
+        #[cfg(any(test, feature = "fixture"))]
+        if let Some(now) = super::mock::mock_now() {
+            return Sleeper(Delay::Mock(super::mock::MockSleep::new(now + duration)));
+        }
+        Sleeper(Delay::Timer(after(duration)))
+    }
+
+    /// same as `sleep` but until `deadline`, such as one computed from `now`
+    pub fn sleep_until(deadline: Instant) -> Sleeper {
+        #[cfg(any(test, feature = "fixture"))]
+        if let Some(deadline) = super::mock::mock_deadline(deadline) {
+            return Sleeper(Delay::Mock(super::mock::MockSleep::new(deadline)));
+        }
+        Sleeper(Delay::Timer(Timer::at(deadline)))
+    }
+
+    /// current time, moved only by `advance` while mock clock is paused on current thread
+    pub fn now() -> Instant {
+        #[cfg(any(test, feature = "fixture"))]
+        if let Some(now) = super::mock::mock_instant() {
+            return now;
+        }
+        Instant::now()
     }
 
     #[pin_project]
-    pub struct Sleeper(#[pin] Timer);
+    pub struct Sleeper(#[pin] Delay);
+
+    #[pin_project(project = DelayProj)]
+    enum Delay {
+        Timer(#[pin] Timer),
+        /// deadline on mock clock
+        #[cfg(any(test, feature = "fixture"))]
+        Mock(super::mock::MockSleep),
+    }
 
     impl Future for Sleeper {
         type Output = ();
 
         fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
-            let this = self.project();
-            if let Poll::Ready(_) = this.0.poll(cx) {
-                Poll::Ready(())
-            } else {
-                Poll::Pending
+            match self.project().0.project() {
+                DelayProj::Timer(timer) => {
+                    if let Poll::Ready(_) = timer.poll(cx) {
+                        Poll::Ready(())
+                    } else {
+                        Poll::Pending
+                    }
+                }
+                #[cfg(any(test, feature = "fixture"))]
+                DelayProj::Mock(sleep) => sleep.poll(cx),
             }
         }
     }
@@ -51,6 +90,193 @@ mod inner {
     }
 }
 
+/// mock clock for testing timeouts and backoff without waiting in real time.
+///
+/// clock is per thread, so it only affects sleeps created and polled on thread calling `pause`,
+/// such as test running with `test_async`. timeouts of crate, such as of `net`, sleep thru it.
+/// `after` always uses real time
+#[cfg(any(test, feature = "fixture"))]
+mod mock {
+
+    use std::cell::{Cell, RefCell};
+    use std::collections::BTreeMap;
+    use std::task::{Context, Poll, Waker};
+    use std::time::Duration;
+    use std::time::Instant;
+
+    use log::trace;
+
+    struct MockClock {
+        /// real time when clock was paused
+        start: Instant,
+        now: Duration,
+        /// sleeps waiting for clock by their key, with their deadline
+        wakers: BTreeMap<u64, (Duration, Waker)>,
+    }
+
+    impl MockClock {
+        fn wake_all(&mut self) {
+            for (_, waker) in std::mem::take(&mut self.wakers).into_values() {
+                waker.wake();
+            }
+        }
+    }
+
+    thread_local! {
+        static CLOCK: RefCell<Option<MockClock>> = const { RefCell::new(None) };
+        /// keys of sleeps, not reset by `pause` so sleep from previous pause can't remove
+        /// registration of another one
+        static NEXT_KEY: Cell<u64> = const { Cell::new(0) };
+    }
+
+    /// pause time on current thread, sleeps created after this only complete thru `advance`
+    pub fn pause() {
+        CLOCK.with(|clock| {
+            let mut clock = clock.borrow_mut();
+            if clock.is_none() {
+                *clock = Some(MockClock {
+                    start: Instant::now(),
+                    now: Duration::ZERO,
+                    wakers: BTreeMap::new(),
+                });
+            }
+        });
+    }
+
+    /// resume real time, pending mock sleeps complete immediately
+    pub fn resume() {
+        let clock = CLOCK.with(|clock| clock.borrow_mut().take());
+        if let Some(mut clock) = clock {
+            clock.wake_all();
+        }
+    }
+
+    pub fn is_paused() -> bool {
+        CLOCK.with(|clock| clock.borrow().is_some())
+    }
+
+    /// move mock clock forward, waking sleeps whose deadline has passed
+    ///
+    /// # Panics
+    ///
+    /// if clock is not paused on current thread
+    pub fn advance(duration: Duration) {
+        let wakers = CLOCK.with(|clock| {
+            let mut clock = clock.borrow_mut();
+            let clock = clock.as_mut().expect("clock must be paused to advance");
+            clock.now += duration;
+            trace!("mock clock advanced to: {:?}", clock.now);
+            let now = clock.now;
+            let (expired, waiting): (BTreeMap<_, _>, BTreeMap<_, _>) =
+                std::mem::take(&mut clock.wakers)
+                    .into_iter()
+                    .partition(|(_, (deadline, _))| *deadline <= now);
+            clock.wakers = waiting;
+            expired
+        });
+        for (_, waker) in wakers.into_values() {
+            waker.wake();
+        }
+    }
+
+    /// time elapsed on mock clock since pause, none if not paused
+    pub(crate) fn mock_now() -> Option<Duration> {
+        CLOCK.with(|clock| clock.borrow().as_ref().map(|clock| clock.now))
+    }
+
+    /// `now` as instant, none if not paused
+    pub(crate) fn mock_instant() -> Option<Instant> {
+        CLOCK.with(|clock| clock.borrow().as_ref().map(|clock| clock.start + clock.now))
+    }
+
+    /// `deadline` as time on mock clock, none if not paused
+    pub(crate) fn mock_deadline(deadline: Instant) -> Option<Duration> {
+        CLOCK.with(|clock| {
+            clock
+                .borrow()
+                .as_ref()
+                .map(|clock| deadline.saturating_duration_since(clock.start))
+        })
+    }
+
+    /// earliest deadline of sleeps waiting for mock clock
+    pub(crate) fn next_deadline() -> Option<Duration> {
+        CLOCK.with(|clock| {
+            clock
+                .borrow()
+                .as_ref()
+                .and_then(|clock| clock.wakers.values().map(|(deadline, _)| *deadline).min())
+        })
+    }
+
+    #[cfg(test)]
+    pub(crate) fn waiting_sleeps() -> usize {
+        CLOCK.with(|clock| {
+            clock
+                .borrow()
+                .as_ref()
+                .map_or(0, |clock| clock.wakers.len())
+        })
+    }
+
+    /// sleep on mock clock, registered once while it waits and removed when dropped
+    pub(crate) struct MockSleep {
+        deadline: Duration,
+        key: Option<u64>,
+    }
+
+    impl MockSleep {
+        pub(crate) fn new(deadline: Duration) -> Self {
+            Self {
+                deadline,
+                key: None,
+            }
+        }
+
+        pub(crate) fn poll(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+            CLOCK.with(|clock| match clock.borrow_mut().as_mut() {
+                Some(clock) if clock.now < self.deadline => {
+                    match self.key.and_then(|key| clock.wakers.get_mut(&key)) {
+                        Some((_, waker)) => {
+                            if !waker.will_wake(cx.waker()) {
+                                *waker = cx.waker().clone();
+                            }
+                        }
+                        None => {
+                            let key = NEXT_KEY.with(|next| next.replace(next.get() + 1));
+                            clock
+                                .wakers
+                                .insert(key, (self.deadline, cx.waker().clone()));
+                            self.key = Some(key);
+                        }
+                    }
+                    Poll::Pending
+                }
+                Some(clock) => {
+                    if let Some(key) = self.key.take() {
+                        clock.wakers.remove(&key);
+                    }
+                    Poll::Ready(())
+                }
+                None => Poll::Ready(()),
+            })
+        }
+    }
+
+    impl Drop for MockSleep {
+        fn drop(&mut self) {
+            if let Some(key) = self.key.take() {
+                // clock may be gone already if thread is exiting
+                let _ = CLOCK.try_with(|clock| {
+                    if let Some(clock) = clock.borrow_mut().as_mut() {
+                        clock.wakers.remove(&key);
+                    }
+                });
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod test {
 
@@ -60,8 +286,11 @@ mod test {
     use log::debug;
     use tokio::select;
 
+    use futures_lite::future::{poll_once, yield_now, zip};
+
     use fluvio_future::test_async;
     use fluvio_future::timer::sleep;
+    use fluvio_future::timer::{advance, pause, resume};
 
     /// test timer loop
     #[test_async]
@@ -90,4 +319,54 @@ mod test {
 
         Ok(())
     }
+
+    #[test_async]
+    async fn test_mock_clock() -> Result<(), ()> {
+        let time_now = Instant::now();
+        pause();
+
+        let sleeper = sleep(Duration::from_secs(60));
+        let clock = async {
+            yield_now().await;
+            advance(Duration::from_secs(30));
+            yield_now().await;
+            advance(Duration::from_secs(30));
+        };
+        zip(sleeper, clock).await;
+        resume();
+
+        assert!(time_now.elapsed() < Duration::from_secs(1));
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_mock_sleep_dropped() -> Result<(), ()> {
+        // clock of this crate, not of `fluvio_future` used by other tests
+        use super::mock::waiting_sleeps;
+        use super::{advance, next_deadline, pause, resume, sleep};
+
+        pause();
+        let mut short = sleep(Duration::from_secs(10));
+        let mut long = sleep(Duration::from_secs(20));
+        // polled again and again, but registered once
+        for _ in 0..3 {
+            assert!(poll_once(&mut short).await.is_none());
+            assert!(poll_once(&mut long).await.is_none());
+        }
+        assert_eq!(waiting_sleeps(), 2);
+        assert_eq!(next_deadline(), Some(Duration::from_secs(10)));
+
+        drop(short);
+        assert_eq!(waiting_sleeps(), 1);
+        assert_eq!(next_deadline(), Some(Duration::from_secs(20)));
+
+        // only expired sleeps are woken
+        advance(Duration::from_secs(15));
+        assert_eq!(waiting_sleeps(), 1);
+        advance(Duration::from_secs(5));
+        assert_eq!(next_deadline(), None);
+        assert!(poll_once(&mut long).await.is_some());
+        resume();
+        Ok(())
+    }
 }
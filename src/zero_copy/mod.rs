@@ -0,0 +1,908 @@
+use std::io::Error as IoError;
+use std::io::ErrorKind;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::io::RawFd;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use thiserror::Error;
+
+use async_io::Async;
+use async_trait::async_trait;
+use futures_lite::AsyncWrite;
+use futures_lite::AsyncWriteExt;
+use nix::errno::Errno;
+#[allow(unused)]
+use nix::libc::off_t;
+use nix::sys::sendfile::sendfile;
+use nix::sys::uio::pread;
+use nix::unistd::close;
+use nix::unistd::dup;
+use nix::unistd::write;
+use nix::Error as NixError;
+
+use log::debug;
+use log::trace;
+
+use crate::task::consume_budget;
+use crate::task::spawn_blocking;
+use crate::task::yield_now;
+
+use crate::buf::BufPool;
+use crate::file_slice::AsyncFileSlice;
+
+#[cfg(target_os = "linux")]
+pub mod socket;
+
+#[derive(Error, Debug)]
+pub enum SendFileError {
+    #[error("IO error: {source}")]
+    IoError {
+        #[from]
+        source: IoError,
+    },
+    #[error("Nix error: {source}")]
+    NixError {
+        #[from]
+        source: NixError,
+    },
+    #[error("transfer cancelled after {transferred} bytes")]
+    Cancelled { transferred: u64 },
+}
+
+/// callback receiving total bytes sent so far
+pub type ZeroCopyProgress = Arc<dyn Fn(u64) + Send + Sync>;
+
+/// default size of each transfer, between chunks other tasks get chance to run
+pub const DEFAULT_CHUNK_SIZE: u64 = 1024 * 1024;
+
+/// handle to stop zero copy transfer in progress.
+/// transfer is stopped at next chunk boundary
+#[derive(Clone, Default, Debug)]
+pub struct ZeroCopyCancel(Arc<AtomicBool>);
+
+impl ZeroCopyCancel {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
+/// options for zero copy transfer
+#[derive(Clone)]
+pub struct ZeroCopyOption {
+    pub chunk_size: u64,
+    pub progress: Option<ZeroCopyProgress>,
+    pub cancel: Option<ZeroCopyCancel>,
+}
+
+impl Default for ZeroCopyOption {
+    fn default() -> Self {
+        Self {
+            chunk_size: DEFAULT_CHUNK_SIZE,
+            progress: None,
+            cancel: None,
+        }
+    }
+}
+
+impl ZeroCopyOption {
+    fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .map(|cancel| cancel.is_cancelled())
+            .unwrap_or(false)
+    }
+
+    fn report(&self, total_transferred: u64) {
+        if let Some(progress) = &self.progress {
+            progress(total_transferred);
+        }
+    }
+}
+
+/// zero copy write
+#[async_trait]
+pub trait ZeroCopyWrite {
+    async fn zero_copy_write(&mut self, source: &AsyncFileSlice) -> Result<usize, SendFileError>
+    where
+        Self: Send,
+    {
+        self.zero_copy_write_with_option(source, &ZeroCopyOption::default())
+            .await
+    }
+
+    /// transfer in chunks, reporting progress and checking for cancellation between chunks
+    async fn zero_copy_write_with_option(
+        &mut self,
+        source: &AsyncFileSlice,
+        option: &ZeroCopyOption,
+    ) -> Result<usize, SendFileError>;
+
+    /// write protocol frame wrapping file payload: header, slice and trailer.
+    /// on linux, socket is corked so frame is not split into partial segments.
+    /// return total bytes written
+    async fn write_framed(
+        &mut self,
+        header: &[u8],
+        slice: AsyncFileSlice,
+        trailer: &[u8],
+    ) -> Result<usize, SendFileError>;
+}
+
+#[async_trait]
+impl<T> ZeroCopyWrite for T
+where
+    T: AsRawFd + Send,
+{
+    async fn zero_copy_write_with_option(
+        &mut self,
+        source: &AsyncFileSlice,
+        option: &ZeroCopyOption,
+    ) -> Result<usize, SendFileError> {
+        let size = source.len();
+        let target_fd = self.as_raw_fd();
+        let source_fd = source.fd();
+        let chunk_size = option.chunk_size.max(1);
+
+        let mut total_transferred: u64 = 0;
+        let mut current_offset = source.position();
+        // registered only when target socket is full
+        let mut writable: Option<Async<DupFd>> = None;
+
+        while total_transferred < size {
+            if option.is_cancelled() {
+                debug!(
+                    "zero copy cancelled after: {} out of {}",
+                    total_transferred, size
+                );
+                return Err(SendFileError::Cancelled {
+                    transferred: total_transferred,
+                });
+            }
+
+            let to_be_transfer = (size - total_transferred).min(chunk_size);
+            let offset = current_offset;
+
+            let sent = spawn_blocking(move || {
+                sendfile_chunk(target_fd, source_fd, offset, to_be_transfer)
+            })
+            .await?;
+
+            let (len, would_block) = match sent {
+                ChunkSent::Complete(len) => (len, false),
+                ChunkSent::WouldBlock(len) => (len, true),
+            };
+
+            total_transferred += len;
+            current_offset += len;
+            if len > 0 {
+                option.report(total_transferred);
+            }
+
+            if len == 0 && !would_block {
+                debug!(
+                    "source ended after: {} bytes out of {}",
+                    total_transferred, size
+                );
+                return Err(source_ended(total_transferred, size));
+            }
+
+            if would_block {
+                trace!(
+                    "target fd: {} is full after: {} bytes, waiting for writable",
+                    target_fd,
+                    total_transferred
+                );
+                let target = match writable.take() {
+                    Some(target) => target,
+                    None => Async::new(DupFd::new(target_fd)?)?,
+                };
+                target.writable().await?;
+                writable = Some(target);
+            } else if total_transferred < size {
+                debug!(
+                    "current transferred: {} less than total: {}, continuing",
+                    total_transferred, size
+                );
+                yield_now().await;
+            }
+        }
+
+        Ok(total_transferred as usize)
+    }
+
+    async fn write_framed(
+        &mut self,
+        header: &[u8],
+        slice: AsyncFileSlice,
+        trailer: &[u8],
+    ) -> Result<usize, SendFileError> {
+        let target_fd = self.as_raw_fd();
+        let mut writable: Option<Async<DupFd>> = None;
+
+        // cork is only for tcp, other sockets are written as is
+        #[cfg(target_os = "linux")]
+        let _cork = match socket::CorkGuard::new(target_fd) {
+            Ok(cork) => Some(cork),
+            Err(err) => {
+                debug!("fd: {} can't be corked: {}", target_fd, err);
+                None
+            }
+        };
+
+        write_all_fd(target_fd, header, &mut writable).await?;
+        let len = self.zero_copy_write(&slice).await?;
+        write_all_fd(target_fd, trailer, &mut writable).await?;
+
+        Ok(header.len() + len + trailer.len())
+    }
+}
+
+/// slice reaches past end of file, so transfer can't be completed
+fn source_ended(transferred: u64, size: u64) -> SendFileError {
+    IoError::new(
+        ErrorKind::UnexpectedEof,
+        format!("source ended after {} bytes out of {}", transferred, size),
+    )
+    .into()
+}
+
+/// write all of buffer to non blocking fd, waiting on reactor when it is full
+async fn write_all_fd(
+    target_fd: RawFd,
+    mut buf: &[u8],
+    writable: &mut Option<Async<DupFd>>,
+) -> Result<(), SendFileError> {
+    while !buf.is_empty() {
+        match write(target_fd, buf) {
+            Ok(0) => return Err(IoError::from(ErrorKind::WriteZero).into()),
+            Ok(len) => {
+                trace!("fd: {} written: {} bytes", target_fd, len);
+                buf = &buf[len..];
+            }
+            Err(NixError::Sys(Errno::EINTR)) => continue,
+            Err(NixError::Sys(Errno::EAGAIN)) => {
+                let target = match writable.take() {
+                    Some(target) => target,
+                    None => Async::new(DupFd::new(target_fd)?)?,
+                };
+                target.writable().await?;
+                *writable = Some(target);
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+    Ok(())
+}
+
+/// duplicate of target fd so it can be registered with reactor without taking over original
+struct DupFd(RawFd);
+
+impl DupFd {
+    fn new(fd: RawFd) -> Result<Self, NixError> {
+        Ok(Self(dup(fd)?))
+    }
+}
+
+impl AsRawFd for DupFd {
+    fn as_raw_fd(&self) -> RawFd {
+        self.0
+    }
+}
+
+impl Drop for DupFd {
+    fn drop(&mut self) {
+        let _ = close(self.0);
+    }
+}
+
+/// outcome of sending one chunk
+enum ChunkSent {
+    /// chunk was sent, or less if source ended
+    Complete(u64),
+    /// target can't accept more now, count is what was sent before that
+    WouldBlock(u64),
+}
+
+/// send `len` bytes from source starting at `offset`.
+/// short sends and interrupts are retried, stops early if target would block
+#[cfg(target_os = "linux")]
+fn sendfile_chunk(
+    target_fd: RawFd,
+    source_fd: RawFd,
+    offset: u64,
+    len: u64,
+) -> Result<ChunkSent, SendFileError> {
+    let mut total_transferred: u64 = 0;
+    let mut current_offset = offset as off_t;
+
+    while total_transferred < len {
+        let to_be_transfer = (len - total_transferred) as usize;
+
+        trace!(
+            "trying: zero copy source fd: {} offset: {} len: {}, target: fd{}",
+            source_fd,
+            current_offset,
+            to_be_transfer,
+            target_fd
+        );
+
+        // sendfile advances offset by bytes sent
+        match sendfile(
+            target_fd,
+            source_fd,
+            Some(&mut current_offset),
+            to_be_transfer,
+        ) {
+            Ok(0) => break,
+            Ok(sent) => {
+                trace!(
+                    "actual: zero copy bytes transferred: {} out of {}",
+                    sent,
+                    len
+                );
+                total_transferred += sent as u64;
+            }
+            Err(NixError::Sys(Errno::EINTR)) => {
+                trace!("EINTR, retrying");
+                continue;
+            }
+            Err(NixError::Sys(Errno::EAGAIN)) => {
+                return Ok(ChunkSent::WouldBlock(total_transferred));
+            }
+            Err(err) => {
+                log::error!("error sendfile: {}", err);
+                return Err(err.into());
+            }
+        }
+    }
+
+    Ok(ChunkSent::Complete(total_transferred))
+}
+
+/// send `len` bytes from source starting at `offset`.
+/// short sends and interrupts are retried, stops early if target would block
+#[cfg(target_os = "macos")]
+fn sendfile_chunk(
+    target_fd: RawFd,
+    source_fd: RawFd,
+    offset: u64,
+    len: u64,
+) -> Result<ChunkSent, SendFileError> {
+    let mut total_transferred: u64 = 0;
+    let mut current_offset = offset;
+
+    while total_transferred < len {
+        let to_be_transfer = (len - total_transferred) as i64;
+
+        trace!(
+            "mac zero copy source fd: {} offset: {} len: {}, target: fd{}",
+            source_fd,
+            current_offset,
+            to_be_transfer,
+            target_fd
+        );
+
+        // on error, mac still reports bytes sent before it
+        let (res, sent) = sendfile(
+            source_fd,
+            target_fd,
+            current_offset as i64,
+            Some(to_be_transfer),
+            None,
+            None,
+        );
+
+        trace!("mac zero copy bytes transferred: {}", sent);
+        total_transferred += sent as u64;
+        current_offset += sent as u64;
+        match res {
+            Ok(_) => {
+                if sent == 0 {
+                    break;
+                }
+            }
+            Err(NixError::Sys(Errno::EINTR)) => {
+                trace!("EINTR, retrying");
+                continue;
+            }
+            Err(NixError::Sys(Errno::EAGAIN)) => {
+                return Ok(ChunkSent::WouldBlock(total_transferred));
+            }
+            Err(err) => {
+                log::error!("error sendfile: {}", err);
+                return Err(err.into());
+            }
+        }
+    }
+
+    Ok(ChunkSent::Complete(total_transferred))
+}
+
+/// userspace fallback for streams which can't do sendfile, such as TLS streams.
+/// file is read in chunks and written to the stream, so encryption happens on the way.
+pub async fn copy_slice_to<W>(
+    writer: &mut W,
+    source: &AsyncFileSlice,
+) -> Result<usize, SendFileError>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    copy_slice_to_with_option(writer, source, &ZeroCopyOption::default()).await
+}
+
+/// same as `copy_slice_to`, progress and cancellation are checked for each buffer copied
+pub async fn copy_slice_to_with_option<W>(
+    writer: &mut W,
+    source: &AsyncFileSlice,
+    option: &ZeroCopyOption,
+) -> Result<usize, SendFileError>
+where
+    W: AsyncWrite + Unpin + Send,
+{
+    let size = source.len();
+    let source_fd = source.fd();
+    let mut current_offset = source.position();
+    let mut total_transferred: u64 = 0;
+    let chunk_size = crate::Runtime::current().file_chunk_size();
+    let mut buffer = BufPool::global().get_zeroed(chunk_size);
+
+    while total_transferred < size {
+        if option.is_cancelled() {
+            debug!(
+                "copy cancelled after: {} out of {}",
+                total_transferred, size
+            );
+            return Err(SendFileError::Cancelled {
+                transferred: total_transferred,
+            });
+        }
+
+        let to_be_read = (size - total_transferred).min(buffer.len() as u64) as usize;
+        let offset = current_offset as off_t;
+
+        trace!(
+            "copy source fd: {} offset: {} len: {}",
+            source_fd,
+            offset,
+            to_be_read
+        );
+
+        let (read_buffer, result) = spawn_blocking(move || {
+            let result = pread(source_fd, &mut buffer[0..to_be_read], offset);
+            (buffer, result)
+        })
+        .await;
+        buffer = read_buffer;
+
+        let len = match result {
+            Ok(0) => {
+                debug!(
+                    "file ended after: {} bytes out of {}",
+                    total_transferred, size
+                );
+                return Err(source_ended(total_transferred, size));
+            }
+            Ok(len) => len,
+            Err(err) => {
+                log::error!("error reading file slice: {}", err);
+                return Err(err.into());
+            }
+        };
+
+        if let Err(err) = writer.write_all(&buffer[0..len]).await {
+            return Err(err.into());
+        }
+
+        total_transferred += len as u64;
+        current_offset += len as u64;
+        option.report(total_transferred);
+        // writer may never be pending, such as fast local socket
+        consume_budget().await;
+    }
+
+    writer.flush().await?;
+    Ok(total_transferred as usize)
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::net::SocketAddr;
+    use std::time;
+
+    use futures_lite::future::zip;
+    use futures_util::stream::StreamExt;
+    use log::debug;
+
+    use crate::fs::util as file_util;
+    use crate::fs::AsyncFileExtension;
+    use crate::net::TcpListener;
+    use crate::net::TcpStream;
+    use crate::test_async;
+    use crate::timer::sleep;
+    use crate::zero_copy::ZeroCopyWrite;
+    use futures_lite::AsyncReadExt;
+
+    use super::copy_slice_to;
+    use super::copy_slice_to_with_option;
+    use super::SendFileError;
+    use super::ZeroCopyCancel;
+    use super::ZeroCopyOption;
+
+    const CONST_TEST_ADDR: &str = "127.0.0.1:9999";
+    const ZERO_COPY_PORT: u16 = 8888;
+
+    #[test_async]
+    async fn test_zero_copy_from_fs_to_socket() -> Result<(), SendFileError> {
+        // spawn tcp client and check contents
+        let server = async {
+            #[allow(unused_mut)]
+            let mut listener = TcpListener::bind(CONST_TEST_ADDR).await?;
+
+            debug!("server: listening");
+            let mut incoming = listener.incoming();
+            if let Some(stream) = incoming.next().await {
+                debug!("server: got connection. waiting");
+                let mut tcp_stream = stream?;
+                let mut buf = [0; 30];
+                let len = tcp_stream.read(&mut buf).await?;
+                assert_eq!(len, 30);
+            } else {
+                panic!("client should connect");
+            }
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let client = async {
+            let file = file_util::open("test-data/apirequest.bin").await?;
+            sleep(time::Duration::from_millis(100)).await;
+            let addr = CONST_TEST_ADDR.parse::<SocketAddr>().expect("parse");
+            debug!("client: file loaded");
+            let mut stream = TcpStream::connect(&addr).await?;
+            debug!("client: connected to server");
+            let f_slice = file.as_slice(0, None).await?;
+            debug!("client: send back file using zero copy");
+            stream.zero_copy_write(&f_slice).await?;
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        // read file and zero copy to tcp stream
+
+        let _ = zip(client, server).await;
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_zero_copy_large_size() -> Result<(), SendFileError> {
+        const MAX_BYTES: usize = 300000;
+
+        use futures_lite::AsyncWriteExt;
+        use std::env::temp_dir;
+
+        const TEST_ITERATION: u16 = 20;
+
+        async fn init_file() {
+            let temp_file = temp_dir().join("async_large");
+            debug!("temp file: {:#?}", temp_file);
+            let mut file = file_util::create(temp_file.clone())
+                .await
+                .expect("file creation");
+
+            let bytes: Vec<u8> = vec![0; 1000];
+            for _ in 0..300 {
+                file.write_all(&bytes).await.expect("writing");
+            }
+
+            file.sync_all().await.expect("flushing");
+            drop(file);
+            debug!("finish creating large test file");
+        }
+
+        // spawn tcp client and check contents
+        let server = async {
+            init_file().await;
+
+            let temp_file = temp_dir().join("async_large");
+            // do zero copy
+            let file = file_util::open(&temp_file).await.expect("re opening");
+            let f_slice = file.as_slice(0, None).await.expect("filed opening");
+            assert_eq!(f_slice.len(), MAX_BYTES as u64);
+
+            let listener = TcpListener::bind(format!("127.0.0.1:{}", ZERO_COPY_PORT))
+                .await
+                .expect("failed bind");
+
+            debug!("server: listening");
+            let mut incoming = listener.incoming();
+
+            for i in 0..TEST_ITERATION {
+                if let Some(stream) = incoming.next().await {
+                    debug!("server {} got connection. waiting", i);
+                    let mut tcp_stream = stream?;
+
+                    debug!(
+                        "server {}, send back file using zero copy: {:#?}",
+                        i,
+                        f_slice.len()
+                    );
+                    tcp_stream
+                        .zero_copy_write(&f_slice)
+                        .await
+                        .expect("file slice");
+                } else {
+                    panic!("client should connect");
+                }
+            }
+
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let client = async {
+            sleep(time::Duration::from_millis(100)).await;
+            let addr = format!("127.0.0.1:{}", ZERO_COPY_PORT)
+                .parse::<SocketAddr>()
+                .expect("parse");
+            debug!("client: file loaded");
+
+            for i in 0..TEST_ITERATION {
+                debug!("client: Test loop: {}", i);
+                let mut stream = TcpStream::connect(&addr).await?;
+                debug!("client: {} connected trying to read", i);
+                let mut buffer = Vec::with_capacity(MAX_BYTES);
+                stream
+                    .read_exact(&mut buffer)
+                    .await
+                    .expect("no more buffer");
+                debug!("client: {} test success", i);
+
+                // sleep 10 milliseconds between request to keep tcp connection otherwise it may lead to EPIPE error
+                //
+                sleep(time::Duration::from_millis(10)).await;
+            }
+
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        // read file and zero copy to tcp stream
+        let _ = zip(client, server).await;
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_copy_slice_to_writer() -> Result<(), SendFileError> {
+        let file = file_util::open("test-data/apirequest.bin").await?;
+        let f_slice = file.as_slice(2, None).await?;
+
+        let mut output: Vec<u8> = vec![];
+        let len = copy_slice_to(&mut output, &f_slice).await?;
+        assert_eq!(len, 28);
+        assert_eq!(output.len(), 28);
+
+        let mut expected = vec![];
+        let mut std_file = std::fs::File::open("test-data/apirequest.bin")?;
+        std::io::Read::read_to_end(&mut std_file, &mut expected)?;
+        assert_eq!(output, expected[2..]);
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_slice_past_end_of_file() -> Result<(), SendFileError> {
+        let temp_file = create_test_file("async_short", 100).await;
+        let file = file_util::open(&temp_file).await?;
+        // slice is longer than file
+        let f_slice = file.raw_slice(0, 200);
+
+        let mut output: Vec<u8> = vec![];
+        let err = copy_slice_to(&mut output, &f_slice)
+            .await
+            .expect_err("file is short");
+        assert!(
+            matches!(err, SendFileError::IoError { ref source } if source.kind() == std::io::ErrorKind::UnexpectedEof)
+        );
+        assert_eq!(output.len(), 100);
+
+        let (mut target, mut peer) = std::os::unix::net::UnixStream::pair()?;
+        let err = target
+            .zero_copy_write(&f_slice)
+            .await
+            .expect_err("file is short");
+        assert!(
+            matches!(err, SendFileError::IoError { ref source } if source.kind() == std::io::ErrorKind::UnexpectedEof)
+        );
+        drop(target);
+        let mut received = vec![];
+        std::io::Read::read_to_end(&mut peer, &mut received)?;
+        assert_eq!(received.len(), 100);
+        Ok(())
+    }
+
+    async fn create_test_file(name: &str, len: usize) -> std::path::PathBuf {
+        use futures_lite::AsyncWriteExt;
+
+        let temp_file = std::env::temp_dir().join(name);
+        let mut file = file_util::create(temp_file.clone())
+            .await
+            .expect("file creation");
+        let bytes: Vec<u8> = (0..len).map(|i| (i % 256) as u8).collect();
+        file.write_all(&bytes).await.expect("writing");
+        file.sync_all().await.expect("flushing");
+        temp_file
+    }
+
+    #[test_async]
+    async fn test_zero_copy_chunked_progress() -> Result<(), SendFileError> {
+        use std::sync::Arc;
+        use std::sync::Mutex;
+
+        const CHUNK_PORT: u16 = 8889;
+        const FILE_LEN: usize = 200000;
+
+        let temp_file = create_test_file("async_chunked", FILE_LEN).await;
+
+        let server = async {
+            let file = file_util::open(&temp_file).await.expect("re opening");
+            let f_slice = file.as_slice(0, None).await.expect("slice");
+
+            let listener = TcpListener::bind(format!("127.0.0.1:{}", CHUNK_PORT))
+                .await
+                .expect("failed bind");
+            let mut incoming = listener.incoming();
+            let mut tcp_stream = incoming.next().await.expect("client should connect")?;
+
+            let reported: Arc<Mutex<Vec<u64>>> = Arc::new(Mutex::new(vec![]));
+            let progress_reported = reported.clone();
+            let option = ZeroCopyOption {
+                chunk_size: 64 * 1024,
+                progress: Some(Arc::new(move |sent| {
+                    progress_reported.lock().unwrap().push(sent)
+                })),
+                cancel: None,
+            };
+            let len = tcp_stream
+                .zero_copy_write_with_option(&f_slice, &option)
+                .await
+                .expect("zero copy");
+            assert_eq!(len, FILE_LEN);
+
+            let reported = reported.lock().unwrap();
+            assert_eq!(*reported, vec![65536, 131072, 196608, FILE_LEN as u64]);
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let client = async {
+            sleep(time::Duration::from_millis(100)).await;
+            let mut stream = TcpStream::connect(format!("127.0.0.1:{}", CHUNK_PORT)).await?;
+            let mut buffer = vec![];
+            stream.read_to_end(&mut buffer).await?;
+            assert_eq!(buffer.len(), FILE_LEN);
+            assert_eq!(buffer[FILE_LEN - 1], ((FILE_LEN - 1) % 256) as u8);
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let (client_result, server_result) = zip(client, server).await;
+        client_result?;
+        server_result
+    }
+
+    #[test_async]
+    async fn test_copy_slice_cancel() -> Result<(), SendFileError> {
+        use std::sync::Arc;
+
+        let temp_file = create_test_file("async_copy_cancel", 200000).await;
+        let file = file_util::open(&temp_file).await?;
+        let f_slice = file.as_slice(0, None).await?;
+
+        let cancel = ZeroCopyCancel::new();
+        let progress_cancel = cancel.clone();
+        let option = ZeroCopyOption {
+            progress: Some(Arc::new(move |_| progress_cancel.cancel())),
+            cancel: Some(cancel),
+            ..Default::default()
+        };
+
+        let mut output: Vec<u8> = vec![];
+        match copy_slice_to_with_option(&mut output, &f_slice, &option).await {
+            Err(SendFileError::Cancelled { transferred }) => {
+                assert_eq!(transferred, output.len() as u64);
+                assert!(transferred < f_slice.len());
+            }
+            other => panic!("expected cancel, got: {:?}", other),
+        }
+        Ok(())
+    }
+
+    #[test_async]
+    async fn test_zero_copy_slow_reader() -> Result<(), SendFileError> {
+        const SLOW_PORT: u16 = 8890;
+        const FILE_LEN: usize = 4 * 1024 * 1024;
+
+        let temp_file = create_test_file("async_slow_reader", FILE_LEN).await;
+
+        let server = async {
+            let file = file_util::open(&temp_file).await.expect("re opening");
+            let f_slice = file.as_slice(0, None).await.expect("slice");
+
+            let listener = TcpListener::bind(format!("127.0.0.1:{}", SLOW_PORT))
+                .await
+                .expect("failed bind");
+            let mut incoming = listener.incoming();
+            let mut tcp_stream = incoming.next().await.expect("client should connect")?;
+
+            // socket buffer will fill up long before whole file is sent
+            let len = tcp_stream.zero_copy_write(&f_slice).await?;
+            assert_eq!(len, FILE_LEN);
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let client = async {
+            sleep(time::Duration::from_millis(100)).await;
+            let mut stream = TcpStream::connect(format!("127.0.0.1:{}", SLOW_PORT)).await?;
+            let mut received = Vec::with_capacity(FILE_LEN);
+            let mut buf = vec![0; 256 * 1024];
+            loop {
+                let len = stream.read(&mut buf).await?;
+                if len == 0 {
+                    break;
+                }
+                received.extend_from_slice(&buf[0..len]);
+                sleep(time::Duration::from_millis(2)).await;
+            }
+            assert_eq!(received.len(), FILE_LEN);
+            for (i, b) in received.iter().enumerate() {
+                assert_eq!(*b, (i % 256) as u8, "mismatch at: {}", i);
+            }
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let (client_result, server_result) = zip(client, server).await;
+        server_result?;
+        client_result
+    }
+
+    #[test_async]
+    async fn test_zero_copy_write_framed() -> Result<(), SendFileError> {
+        const FRAMED_PORT: u16 = 8892;
+
+        let server = async {
+            let file = file_util::open("test-data/apirequest.bin").await?;
+            let f_slice = file.as_slice(0, None).await?;
+
+            let listener = TcpListener::bind(format!("127.0.0.1:{}", FRAMED_PORT))
+                .await
+                .expect("failed bind");
+            let mut incoming = listener.incoming();
+            let mut tcp_stream = incoming.next().await.expect("client should connect")?;
+
+            let len = tcp_stream.write_framed(b"HEAD", f_slice, b"TAIL").await?;
+            assert_eq!(len, 38);
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let client = async {
+            sleep(time::Duration::from_millis(100)).await;
+            let mut stream = TcpStream::connect(format!("127.0.0.1:{}", FRAMED_PORT)).await?;
+            let mut received = vec![];
+            stream.read_to_end(&mut received).await?;
+
+            let mut expected = b"HEAD".to_vec();
+            let mut std_file = std::fs::File::open("test-data/apirequest.bin")?;
+            std::io::Read::read_to_end(&mut std_file, &mut expected)?;
+            expected.extend_from_slice(b"TAIL");
+            assert_eq!(received, expected);
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let (client_result, server_result) = zip(client, server).await;
+        server_result?;
+        client_result
+    }
+}
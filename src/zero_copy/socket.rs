@@ -0,0 +1,332 @@
+//! linux only send options: TCP_CORK and MSG_ZEROCOPY
+use std::collections::VecDeque;
+use std::mem;
+use std::os::unix::io::RawFd;
+use std::time::Duration;
+
+use async_io::Async;
+use futures_lite::future::or;
+use log::debug;
+use log::trace;
+use nix::errno::Errno;
+use nix::libc;
+use nix::Error as NixError;
+
+use crate::timer::sleep;
+
+use super::DupFd;
+use super::SendFileError;
+
+// not exposed by libc for all targets
+const SO_ZEROCOPY: libc::c_int = 60;
+const SO_EE_ORIGIN_ZEROCOPY: u8 = 5;
+const SO_EE_CODE_ZEROCOPY_COPIED: u8 = 1;
+
+/// how long to wait before checking completions again when socket has other events
+const COMPLETION_RECHECK: Duration = Duration::from_millis(1);
+
+fn set_int_option(
+    fd: RawFd,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> Result<(), NixError> {
+    let res = unsafe {
+        libc::setsockopt(
+            fd,
+            level,
+            name,
+            &value as *const libc::c_int as *const libc::c_void,
+            mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    Errno::result(res).map(drop)
+}
+
+fn get_int_option(fd: RawFd, level: libc::c_int, name: libc::c_int) -> Result<i32, NixError> {
+    let mut value: libc::c_int = 0;
+    let mut len = mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let res = unsafe {
+        libc::getsockopt(
+            fd,
+            level,
+            name,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    Errno::result(res).map(|_| value)
+}
+
+/// set or clear TCP_CORK.
+/// while corked, partial frames are held back until uncorked
+pub fn set_cork(fd: RawFd, cork: bool) -> Result<(), NixError> {
+    set_int_option(fd, libc::IPPROTO_TCP, libc::TCP_CORK, cork as libc::c_int)
+}
+
+pub fn is_corked(fd: RawFd) -> Result<bool, NixError> {
+    get_int_option(fd, libc::IPPROTO_TCP, libc::TCP_CORK).map(|value| value != 0)
+}
+
+/// keep socket corked while alive, so header and payload written in between go out together
+pub struct CorkGuard(RawFd);
+
+impl CorkGuard {
+    pub fn new(fd: RawFd) -> Result<Self, NixError> {
+        set_cork(fd, true)?;
+        Ok(Self(fd))
+    }
+}
+
+impl Drop for CorkGuard {
+    fn drop(&mut self) {
+        if let Err(err) = set_cork(self.0, false) {
+            debug!("failed to uncork fd: {}, {}", self.0, err);
+        }
+    }
+}
+
+/// buffer can be released only when kernel has acknowledged last send using it
+struct PendingBuffer {
+    last_id: u32,
+    _buffer: Vec<u8>,
+}
+
+/// sender using MSG_ZEROCOPY, the kernel reads directly from user buffers.
+/// buffers are held until completion is reported on socket error queue
+pub struct MsgZeroCopySender {
+    target: Async<DupFd>,
+    next_id: u32,
+    pending: VecDeque<PendingBuffer>,
+    copied: u64,
+}
+
+impl MsgZeroCopySender {
+    /// enable SO_ZEROCOPY on socket. fails if kernel doesn't support it
+    pub fn new(fd: RawFd) -> Result<Self, SendFileError> {
+        set_int_option(fd, libc::SOL_SOCKET, SO_ZEROCOPY, 1)?;
+        Ok(Self {
+            target: Async::new(DupFd::new(fd)?)?,
+            next_id: 0,
+            pending: VecDeque::new(),
+            copied: 0,
+        })
+    }
+
+    /// number of buffers not yet released by kernel
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// number of sends where kernel fell back to copying, ex: loopback
+    pub fn copied(&self) -> u64 {
+        self.copied
+    }
+
+    /// send all of buffer. buffer is kept until kernel is done with it
+    pub async fn send(&mut self, buffer: Vec<u8>) -> Result<usize, SendFileError> {
+        let fd = self.target.get_ref().0;
+        let mut sent = 0;
+        let mut last_id = None;
+
+        while sent < buffer.len() {
+            let remaining = &buffer[sent..];
+            let res = unsafe {
+                libc::send(
+                    fd,
+                    remaining.as_ptr() as *const libc::c_void,
+                    remaining.len(),
+                    libc::MSG_ZEROCOPY,
+                )
+            };
+            match Errno::result(res) {
+                Ok(len) => {
+                    trace!("msg zero copy sent: {}, id: {}", len, self.next_id);
+                    sent += len as usize;
+                    last_id = Some(self.next_id);
+                    self.next_id = self.next_id.wrapping_add(1);
+                }
+                Err(NixError::Sys(Errno::EINTR)) => continue,
+                Err(NixError::Sys(Errno::EAGAIN)) => {
+                    self.reap()?;
+                    self.target.writable().await?;
+                }
+                Err(NixError::Sys(Errno::ENOBUFS)) => {
+                    // out of option memory, wait for earlier sends to complete
+                    debug!("ENOBUFS, waiting for completions");
+                    self.wait_completion().await?;
+                }
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        if let Some(last_id) = last_id {
+            self.pending.push_back(PendingBuffer {
+                last_id,
+                _buffer: buffer,
+            });
+        }
+        self.reap()?;
+        Ok(sent)
+    }
+
+    /// wait until all buffers are released by kernel
+    pub async fn flush(&mut self) -> Result<(), SendFileError> {
+        self.reap()?;
+        while !self.pending.is_empty() {
+            self.wait_completion().await?;
+        }
+        Ok(())
+    }
+
+    async fn wait_completion(&mut self) -> Result<(), SendFileError> {
+        let before = self.pending.len();
+        // error queue is signaled as readable, but so is incoming data
+        or(
+            async {
+                let _ = self.target.readable().await;
+            },
+            sleep(COMPLETION_RECHECK),
+        )
+        .await;
+        self.reap()?;
+        if self.pending.len() == before {
+            sleep(COMPLETION_RECHECK).await;
+        }
+        Ok(())
+    }
+
+    /// read completions from error queue without blocking
+    fn reap(&mut self) -> Result<(), SendFileError> {
+        let fd = self.target.get_ref().0;
+        loop {
+            match read_completion(fd)? {
+                Some((lo, hi, copied)) => {
+                    trace!("msg zero copy completed: {}..={}", lo, hi);
+                    if copied {
+                        self.copied += 1;
+                    }
+                    while let Some(front) = self.pending.front() {
+                        if ids_completed(front.last_id, hi) {
+                            self.pending.pop_front();
+                        } else {
+                            break;
+                        }
+                    }
+                }
+                None => return Ok(()),
+            }
+        }
+    }
+}
+
+/// true if id is at or before hi, accounting for wrap around
+fn ids_completed(id: u32, hi: u32) -> bool {
+    hi.wrapping_sub(id) < u32::MAX / 2
+}
+
+/// read one completion range from error queue
+fn read_completion(fd: RawFd) -> Result<Option<(u32, u32, bool)>, NixError> {
+    loop {
+        let mut control = [0u8; 128];
+        let mut msg: libc::msghdr = unsafe { mem::zeroed() };
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = control.len() as _;
+
+        let res = unsafe { libc::recvmsg(fd, &mut msg, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT) };
+        match Errno::result(res) {
+            Ok(_) => {}
+            Err(NixError::Sys(Errno::EAGAIN)) => return Ok(None),
+            Err(NixError::Sys(Errno::EINTR)) => continue,
+            Err(err) => return Err(err),
+        }
+
+        let mut cmsg = unsafe { libc::CMSG_FIRSTHDR(&msg) };
+        while !cmsg.is_null() {
+            let header = unsafe { &*cmsg };
+            let is_recverr = (header.cmsg_level == libc::SOL_IP
+                && header.cmsg_type == libc::IP_RECVERR)
+                || (header.cmsg_level == libc::SOL_IPV6 && header.cmsg_type == libc::IPV6_RECVERR);
+            if is_recverr {
+                let err = unsafe {
+                    std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::sock_extended_err)
+                };
+                if err.ee_errno == 0 && err.ee_origin == SO_EE_ORIGIN_ZEROCOPY {
+                    let copied = err.ee_code & SO_EE_CODE_ZEROCOPY_COPIED != 0;
+                    return Ok(Some((err.ee_info, err.ee_data, copied)));
+                }
+            }
+            cmsg = unsafe { libc::CMSG_NXTHDR(&msg, cmsg) };
+        }
+
+        // not a zero copy notification, skip it
+        trace!("skipping error queue message");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+
+    use std::os::unix::io::AsRawFd;
+    use std::time;
+
+    use futures_lite::future::zip;
+    use futures_lite::AsyncReadExt;
+    use futures_lite::StreamExt;
+
+    use crate::net::TcpListener;
+    use crate::net::TcpStream;
+    use crate::test_async;
+    use crate::timer::sleep;
+
+    use super::is_corked;
+    use super::CorkGuard;
+    use super::MsgZeroCopySender;
+    use super::SendFileError;
+
+    const MSG_ZERO_COPY_PORT: u16 = 8891;
+
+    #[test_async]
+    async fn test_msg_zero_copy_send() -> Result<(), SendFileError> {
+        const MESSAGES: usize = 16;
+        const MESSAGE_LEN: usize = 64 * 1024;
+
+        let server = async {
+            let listener = TcpListener::bind(format!("127.0.0.1:{}", MSG_ZERO_COPY_PORT))
+                .await
+                .expect("failed bind");
+            let mut incoming = listener.incoming();
+            let stream = incoming.next().await.expect("client should connect")?;
+
+            {
+                let _cork = CorkGuard::new(stream.as_raw_fd())?;
+                assert!(is_corked(stream.as_raw_fd())?);
+            }
+            assert!(!is_corked(stream.as_raw_fd())?);
+
+            let mut sender = MsgZeroCopySender::new(stream.as_raw_fd())?;
+            for i in 0..MESSAGES {
+                let len = sender.send(vec![i as u8; MESSAGE_LEN]).await?;
+                assert_eq!(len, MESSAGE_LEN);
+            }
+            sender.flush().await?;
+            assert_eq!(sender.pending(), 0);
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let client = async {
+            sleep(time::Duration::from_millis(100)).await;
+            let mut stream =
+                TcpStream::connect(format!("127.0.0.1:{}", MSG_ZERO_COPY_PORT)).await?;
+            let mut buffer = vec![0; MESSAGES * MESSAGE_LEN];
+            stream.read_exact(&mut buffer).await?;
+            assert_eq!(buffer[0], 0);
+            assert_eq!(buffer[MESSAGES * MESSAGE_LEN - 1], (MESSAGES - 1) as u8);
+            Ok(()) as Result<(), SendFileError>
+        };
+
+        let (client_result, server_result) = zip(client, server).await;
+        server_result?;
+        client_result
+    }
+}